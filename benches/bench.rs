@@ -28,9 +28,10 @@ impl demultiplex::StreamConstructor for NullStreamConstructor {
         match req {
             demultiplex::FilterRequest::ByPid(0) => NullFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
             demultiplex::FilterRequest::ByPid(_) => NullFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
-            demultiplex::FilterRequest::ByStream(StreamType::H264, pmt_section, stream_info) => NullElementaryStreamConsumer::construct(pmt_section, stream_info),
-            demultiplex::FilterRequest::ByStream(_stype, _pmt_section, _stream_info) => NullFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
+            demultiplex::FilterRequest::ByStream { stream_type: StreamType::H264, pmt: pmt_section, stream_info, .. } => NullElementaryStreamConsumer::construct(pmt_section, stream_info),
+            demultiplex::FilterRequest::ByStream { .. } => NullFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
             demultiplex::FilterRequest::Pmt{pid, program_number} => NullFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
+            demultiplex::FilterRequest::Nit{pid: _} => NullFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
         }
     }
 }
@@ -56,7 +57,7 @@ fn mpeg2ts_reader(c: &mut Criterion) {
     let l = f.metadata().unwrap().len() as usize;
     let size = l.min(188*200_000);
     let mut buf = vec![0; size];
-    f.read(&mut buf[..]).unwrap();
+    f.read_exact(&mut buf[..]).unwrap();
     let mut ctx = NullDemuxContext::new(NullStreamConstructor);
     let mut demux = demultiplex::Demultiplex::new(&mut ctx);
     c.bench("parse", Benchmark::new("parse", move |b| {
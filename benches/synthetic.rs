@@ -0,0 +1,279 @@
+//! Benchmarks `Demultiplex::push()` using a synthetic, multi-program Transport Stream generated in
+//! memory, so that (unlike `bench.rs`) no sample media file is required.
+//!
+//! The `mmap` benchmark writes a larger stream to a temporary file and passes the whole
+//! memory-mapped file to a single `push()` call, as an application processing recordings might.
+
+#[macro_use]
+extern crate criterion;
+#[macro_use]
+extern crate mpeg2ts_reader;
+extern crate memmap2;
+
+use criterion::{Criterion,Benchmark,Throughput};
+use mpeg2ts_reader::demultiplex;
+use mpeg2ts_reader::pes;
+use mpeg2ts_reader::psi;
+use mpeg2ts_reader::StreamType;
+
+const PACKET_SIZE: usize = 188;
+const PROGRAM_COUNT: u16 = 4;
+/// approximate size of the generated stream
+const STREAM_SIZE: usize = 4 * 1024 * 1024;
+/// approximate size of the stream written to a file for the `mmap` benchmark
+const MMAP_STREAM_SIZE: usize = 100 * 1024 * 1024;
+/// number of packets between repetitions of the PAT and PMTs
+const PSI_INTERVAL: usize = 200;
+const VIDEO_PES_PACKETS: usize = 20;
+const AUDIO_PES_PACKETS: usize = 2;
+
+fn pmt_pid(program_number: u16) -> u16 { 0x100 + program_number }
+fn video_pid(program_number: u16) -> u16 { 0x200 + program_number }
+fn audio_pid(program_number: u16) -> u16 { 0x300 + program_number }
+
+/// Builds a complete section-syntax section (including CRC) from the given `table_id`,
+/// `table_id_extension` and the bytes following the `last_section_number` field.
+fn make_section(table_id: u8, table_id_extension: u16, body: &[u8]) -> Vec<u8> {
+    let section_length = 5 + body.len() + 4;
+    let mut sect = vec![
+        table_id,
+        0b1011_0000 | (section_length >> 8) as u8,
+        section_length as u8,
+        (table_id_extension >> 8) as u8,
+        table_id_extension as u8,
+        0b1100_0001,  // version 0, current_next_indicator
+        0,  // section_number
+        0,  // last_section_number
+    ];
+    sect.extend_from_slice(body);
+    let crc = psi::mpeg2_crc32(&sect[..]);
+    sect.extend_from_slice(&[(crc >> 24) as u8, (crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+    sect
+}
+
+struct StreamBuilder {
+    size: usize,
+    buf: Vec<u8>,
+    cc: Vec<u8>,
+}
+impl StreamBuilder {
+    fn new(size: usize) -> StreamBuilder {
+        StreamBuilder {
+            size,
+            buf: Vec::with_capacity(size + PACKET_SIZE * 64),
+            cc: vec![0; 0x2000],
+        }
+    }
+
+    /// Appends a packet with the given payload, which must be at most 184 bytes, filling any
+    /// remainder of the payload with `0xff`.
+    fn packet(&mut self, pid: u16, pusi: bool, payload: &[u8]) {
+        let cc = self.cc[pid as usize];
+        self.cc[pid as usize] = (cc + 1) & 0xf;
+        self.buf.push(0x47);
+        self.buf.push(if pusi { 0b0100_0000 } else { 0 } | (pid >> 8) as u8);
+        self.buf.push(pid as u8);
+        self.buf.push(0b0001_0000 | cc);
+        self.buf.extend_from_slice(payload);
+        let len = self.buf.len();
+        self.buf.resize(len + PACKET_SIZE - 4 - payload.len(), 0xff);
+    }
+
+    fn section(&mut self, pid: u16, sect: &[u8]) {
+        let mut payload = vec![0];  // pointer_field
+        payload.extend_from_slice(sect);
+        self.packet(pid, true, &payload[..]);
+    }
+
+    fn psi(&mut self) {
+        let mut pat = vec![];
+        for program_number in 1..=PROGRAM_COUNT {
+            let pid = pmt_pid(program_number);
+            pat.extend_from_slice(&[
+                (program_number >> 8) as u8,
+                program_number as u8,
+                0b1110_0000 | (pid >> 8) as u8,
+                pid as u8,
+            ]);
+        }
+        let pat = make_section(0x00, 1, &pat[..]);
+        self.section(0, &pat[..]);
+        for program_number in 1..=PROGRAM_COUNT {
+            let pcr_pid = video_pid(program_number);
+            let mut pmt = vec![
+                0b1110_0000 | (pcr_pid >> 8) as u8,
+                pcr_pid as u8,
+                0b1111_0000,  // program_info_length
+                0,
+            ];
+            for &(stream_type, pid) in &[(StreamType::H264, video_pid(program_number)), (StreamType::Adts, audio_pid(program_number))] {
+                pmt.extend_from_slice(&[
+                    u8::from(stream_type),
+                    0b1110_0000 | (pid >> 8) as u8,
+                    pid as u8,
+                    0b1111_0000,  // ES_info_length
+                    0,
+                ]);
+            }
+            let pmt = make_section(0x02, program_number, &pmt[..]);
+            self.section(pmt_pid(program_number), &pmt[..]);
+        }
+    }
+
+    /// Appends a PES packet spanning exactly `packet_count` TS packets.  If `bounded` is false,
+    /// then `PES_packet_length` is given as `0`, as is usual for video streams.
+    fn pes(&mut self, pid: u16, stream_id: u8, packet_count: usize, bounded: bool) {
+        let total = packet_count * (PACKET_SIZE - 4);
+        let pes_packet_length = if bounded { total - 6 } else { 0 };
+        let mut pes = vec![
+            0, 0, 1, stream_id,
+            (pes_packet_length >> 8) as u8,
+            pes_packet_length as u8,
+            0b1000_0000,
+            0b1000_0000,  // PTS_DTS_flags
+            5,  // PES_header_data_length
+            0b0010_0001, 0, 1, 0, 1,  // PTS
+        ];
+        let mut val = 0u8;
+        while pes.len() < total {
+            pes.push(val);
+            val = val.wrapping_add(1);
+        }
+        for (i, chunk) in pes.chunks(PACKET_SIZE - 4).enumerate() {
+            self.packet(pid, i == 0, chunk);
+        }
+    }
+
+    fn build(mut self) -> Vec<u8> {
+        let mut since_psi = PSI_INTERVAL;
+        while self.buf.len() < self.size {
+            if since_psi >= PSI_INTERVAL {
+                self.psi();
+                since_psi = 0;
+            }
+            for program_number in 1..=PROGRAM_COUNT {
+                self.pes(video_pid(program_number), 0xe0, VIDEO_PES_PACKETS, false);
+                self.pes(audio_pid(program_number), 0xc0, AUDIO_PES_PACKETS, true);
+                since_psi += VIDEO_PES_PACKETS + AUDIO_PES_PACKETS;
+            }
+        }
+        self.buf
+    }
+}
+
+packet_filter_switch!{
+    NullFilterSwitch<NullDemuxContext> {
+        Pat: demultiplex::PatPacketFilter<NullDemuxContext>,
+        Pmt: demultiplex::PmtPacketFilter<NullDemuxContext>,
+        Null: demultiplex::NullPacketFilter<NullDemuxContext>,
+    }
+}
+demux_context!(NullDemuxContext, NullStreamConstructor);
+
+/// Handles the PAT and PMTs, but ignores all elementary streams
+pub struct NullStreamConstructor;
+impl demultiplex::StreamConstructor for NullStreamConstructor {
+    type F = NullFilterSwitch;
+
+    fn construct(&mut self, req: demultiplex::FilterRequest) -> Self::F {
+        match req {
+            demultiplex::FilterRequest::ByPid(0) => NullFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
+            demultiplex::FilterRequest::ByPid(_) => NullFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
+            demultiplex::FilterRequest::ByStream { .. } => NullFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
+            demultiplex::FilterRequest::Pmt{pid, program_number} => NullFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
+            demultiplex::FilterRequest::Nit{pid: _} => NullFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
+        }
+    }
+}
+
+packet_filter_switch!{
+    PesFilterSwitch<PesDemuxContext> {
+        Pat: demultiplex::PatPacketFilter<PesDemuxContext>,
+        Pmt: demultiplex::PmtPacketFilter<PesDemuxContext>,
+        Null: demultiplex::NullPacketFilter<PesDemuxContext>,
+        Pes: pes::PesPacketFilter<PesDemuxContext,CountingElementaryStreamConsumer>,
+    }
+}
+demux_context!(PesDemuxContext, PesStreamConstructor);
+
+/// Reassembles PES packets from every elementary stream
+pub struct PesStreamConstructor;
+impl demultiplex::StreamConstructor for PesStreamConstructor {
+    type F = PesFilterSwitch;
+
+    fn construct(&mut self, req: demultiplex::FilterRequest) -> Self::F {
+        match req {
+            demultiplex::FilterRequest::ByPid(0) => PesFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
+            demultiplex::FilterRequest::ByPid(_) => PesFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
+            demultiplex::FilterRequest::ByStream { .. } => PesFilterSwitch::Pes(pes::PesPacketFilter::new(CountingElementaryStreamConsumer::default())),
+            demultiplex::FilterRequest::Pmt{pid, program_number} => PesFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
+            demultiplex::FilterRequest::Nit{pid: _} => PesFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
+        }
+    }
+}
+
+/// Inspects each PES header and counts payload bytes, so that the work of reassembly can't be
+/// optimised away
+#[derive(Default)]
+pub struct CountingElementaryStreamConsumer {
+    packets: usize,
+    bytes: usize,
+}
+impl pes::ElementaryStreamConsumer for CountingElementaryStreamConsumer {
+    fn start_stream(&mut self) { }
+    fn begin_packet(&mut self, header: pes::PesHeader) {
+        self.packets += 1;
+        if let pes::PesContents::Parsed(Some(parsed)) = header.contents() {
+            criterion::black_box(parsed.pts_dts());
+            self.bytes += parsed.payload().len();
+        }
+    }
+    fn continue_packet(&mut self, data: &[u8]) {
+        self.bytes += data.len();
+    }
+    fn end_packet(&mut self) { }
+    fn continuity_error(&mut self) { }
+}
+
+fn synthetic(c: &mut Criterion) {
+    let buf = StreamBuilder::new(STREAM_SIZE).build();
+    let size = buf.len();
+    c.bench("synthetic", Benchmark::new("null", move |b| {
+        b.iter(|| {
+            let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+            let mut demux = demultiplex::Demultiplex::new(&mut ctx);
+            demux.push(&mut ctx, &buf[..]);
+        } );
+    }).throughput(Throughput::Bytes(size as u32)));
+
+    let buf = StreamBuilder::new(STREAM_SIZE).build();
+    c.bench("synthetic", Benchmark::new("pes", move |b| {
+        b.iter(|| {
+            let mut ctx = PesDemuxContext::new(PesStreamConstructor);
+            let mut demux = demultiplex::Demultiplex::new(&mut ctx);
+            demux.push(&mut ctx, &buf[..]);
+        } );
+    }).throughput(Throughput::Bytes(size as u32)));
+}
+
+fn mmap(c: &mut Criterion) {
+    let path = std::env::temp_dir().join(format!("mpeg2ts-reader-bench-{}.ts", std::process::id()));
+    let buf = StreamBuilder::new(MMAP_STREAM_SIZE).build();
+    let size = buf.len();
+    std::fs::write(&path, &buf[..]).unwrap();
+    drop(buf);
+    let file = std::fs::File::open(&path).unwrap();
+    let map = unsafe { memmap2::Mmap::map(&file) }.unwrap();
+    // the mapping remains valid once the file is unlinked
+    std::fs::remove_file(&path).unwrap();
+    c.bench("synthetic", Benchmark::new("mmap", move |b| {
+        b.iter(|| {
+            let mut ctx = PesDemuxContext::new(PesStreamConstructor);
+            let mut demux = demultiplex::Demultiplex::new(&mut ctx);
+            demux.push(&mut ctx, &map[..]);
+        } );
+    }).sample_size(10).throughput(Throughput::Bytes(size as u32)));
+}
+
+criterion_group!(benches, synthetic, mmap);
+criterion_main!(benches);
@@ -31,9 +31,10 @@ impl demultiplex::StreamConstructor for PcrDumpStreamConstructor {
             demultiplex::FilterRequest::ByPid(0) => PcrDumpFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
             demultiplex::FilterRequest::Pmt{pid, program_number} => PcrDumpFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
 
-            demultiplex::FilterRequest::ByStream(_, pmt_section, stream_info) => PcrDumpFilterSwitch::Pcr(PcrPacketFilter::construct(pmt_section, stream_info)),
+            demultiplex::FilterRequest::ByStream { pmt: pmt_section, stream_info, .. } => PcrDumpFilterSwitch::Pcr(PcrPacketFilter::construct(pmt_section, stream_info)),
 
             demultiplex::FilterRequest::ByPid(_) => PcrDumpFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
+            demultiplex::FilterRequest::Nit{pid: _} => PcrDumpFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
         }
     }
 }
@@ -42,6 +43,12 @@ impl demultiplex::StreamConstructor for PcrDumpStreamConstructor {
 pub struct PcrPacketFilter<Ctx: DemuxContext> {
     phantom: marker::PhantomData<Ctx>,
 }
+impl<Ctx: DemuxContext> Default for PcrPacketFilter<Ctx> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Ctx: DemuxContext> PcrPacketFilter<Ctx> {
     pub fn construct(_pmt: &demultiplex::PmtSection, _stream_info: &demultiplex::StreamInfo) -> PcrPacketFilter<Ctx> {
         Self::new()
@@ -67,7 +74,7 @@ impl<Ctx: DemuxContext> PacketFilter for PcrPacketFilter<Ctx> {
 fn main() {
     // open input file named on command line,
     let name = env::args().nth(1).unwrap();
-    let mut f = File::open(&name).expect(&format!("file not found: {}", &name));
+    let mut f = File::open(&name).unwrap_or_else(|_| panic!("file not found: {}", &name));
 
     // create the context object that stores the state of the transport stream demultiplexing
     // process
@@ -81,7 +88,7 @@ fn main() {
     loop {
         match f.read(&mut buf[..]).expect("read failed") {
             0 => break ,
-            n => demux.push(&mut ctx, &buf[0..n]),
+            n => { demux.push(&mut ctx, &buf[0..n]); },
         }
     }
 }
@@ -54,12 +54,12 @@ impl demultiplex::StreamConstructor for DumpStreamConstructor {
                 DumpFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
             // This match-arm installs our application-specific handling for each H264 stream
             // discovered within the transport stream,
-            demultiplex::FilterRequest::ByStream(StreamType::H264, pmt_section, stream_info) =>
+            demultiplex::FilterRequest::ByStream { stream_type: StreamType::H264, pmt: pmt_section, stream_info, .. } =>
                 PtsDumpElementaryStreamConsumer::construct(pmt_section, stream_info),
             // We need to have a match-arm to specify how to handle any other StreamType values
             // that might be present; we answer with NullPacketFilter so that anything other than
             // H264 (handled above) is ignored,
-            demultiplex::FilterRequest::ByStream(_stype, _pmt_section, _stream_info) =>
+            demultiplex::FilterRequest::ByStream { .. } =>
                 DumpFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
             // The 'Program Map Table' defines the sub-streams for a particular program within the
             // Transport Stream (it is common for Transport Streams to contain only one program).
@@ -67,6 +67,10 @@ impl demultiplex::StreamConstructor for DumpStreamConstructor {
             // logic if required,
             demultiplex::FilterRequest::Pmt{pid, program_number} =>
                 DumpFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
+            // The 'Network Information Table' is announced in the PAT, but this application has
+            // no interest in its contents, so it is ignored,
+            demultiplex::FilterRequest::Nit{pid: _} =>
+                DumpFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
         }
     }
 }
@@ -138,7 +142,7 @@ impl pes::ElementaryStreamConsumer for PtsDumpElementaryStreamConsumer {
 fn main() {
     // open input file named on command line,
     let name = env::args().nth(1).unwrap();
-    let mut f = File::open(&name).expect(&format!("file not found: {}", &name));
+    let mut f = File::open(&name).unwrap_or_else(|_| panic!("file not found: {}", &name));
 
     // create the context object that stores the state of the transport stream demultiplexing
     // process
@@ -152,7 +156,7 @@ fn main() {
     loop {
         match f.read(&mut buf[..]).expect("read failed") {
             0 => break ,
-            n => demux.push(&mut ctx, &buf[0..n]),
+            n => { demux.push(&mut ctx, &buf[0..n]); },
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,65 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+#[macro_use]
+extern crate mpeg2ts_reader;
+
+use mpeg2ts_reader::demultiplex;
+use mpeg2ts_reader::pes;
+
+packet_filter_switch!{
+    FuzzFilterSwitch<FuzzDemuxContext> {
+        Pat: demultiplex::PatPacketFilter<FuzzDemuxContext>,
+        Pmt: demultiplex::PmtPacketFilter<FuzzDemuxContext>,
+        Null: demultiplex::NullPacketFilter<FuzzDemuxContext>,
+        Pes: pes::PesPacketFilter<FuzzDemuxContext,FuzzElementaryStreamConsumer>,
+    }
+}
+demux_context!(FuzzDemuxContext, FuzzStreamConstructor);
+
+pub struct FuzzStreamConstructor;
+impl demultiplex::StreamConstructor for FuzzStreamConstructor {
+    type F = FuzzFilterSwitch;
+
+    fn construct(&mut self, req: demultiplex::FilterRequest) -> Self::F {
+        match req {
+            demultiplex::FilterRequest::ByPid(0) => FuzzFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
+            demultiplex::FilterRequest::ByPid(_) => FuzzFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
+            demultiplex::FilterRequest::ByStream { pmt: pmt_section, stream_info, .. } => {
+                // exercise the descriptor parsing code
+                for d in pmt_section.descriptors() {
+                    format!("{:?}", d);
+                }
+                for d in stream_info.descriptors() {
+                    format!("{:?}", d);
+                }
+                FuzzFilterSwitch::Pes(pes::PesPacketFilter::new(FuzzElementaryStreamConsumer))
+            },
+            demultiplex::FilterRequest::Pmt{pid, program_number} => FuzzFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
+            demultiplex::FilterRequest::Nit{pid: _} => FuzzFilterSwitch::Null(demultiplex::NullPacketFilter::new()),
+        }
+    }
+}
+
+pub struct FuzzElementaryStreamConsumer;
+impl pes::ElementaryStreamConsumer for FuzzElementaryStreamConsumer {
+    fn start_stream(&mut self) { }
+    fn begin_packet(&mut self, header: pes::PesHeader) {
+        // exercise the PES header parsing code
+        if let pes::PesContents::Parsed(Some(parsed)) = header.contents() {
+            let _ = parsed.pts_dts();
+            let _ = parsed.escr();
+            let _ = parsed.es_rate();
+            let _ = parsed.dsm_trick_mode();
+            let _ = parsed.payload();
+        }
+    }
+    fn continue_packet(&mut self, _data: &[u8]) { }
+    fn end_packet(&mut self) { }
+    fn continuity_error(&mut self) { }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut ctx = FuzzDemuxContext::new(FuzzStreamConstructor);
+    let mut demux = demultiplex::Demultiplex::new(&mut ctx);
+    demux.push(&mut ctx, data);
+});
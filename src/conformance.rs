@@ -0,0 +1,394 @@
+//! Checks on a Transport Stream's conformance to some of the rules of _ISO/IEC 13818-1_ and the
+//! measurement guidelines of _ETSI TR 101 290_, for quality-control applications.
+//!
+//! The checks are made by `Demultiplex` as packets are pushed, once enabled with
+//! [`DemultiplexBuilder::conformance_checks()`](../demultiplex/struct.DemultiplexBuilder.html#method.conformance_checks),
+//! and any violations found are accumulated in a [`ConformanceReport`](struct.ConformanceReport.html).
+//! The rules checked are,
+//!
+//!  - packets should not have the `transport_error_indicator` set
+//!  - the `continuity_counter` of each PID should increase by one with each packet carrying
+//!    payload, except where the `discontinuity_indicator` is set
+//!  - the PAT should be repeated at least every 0.5 seconds
+//!  - PCR values should be repeated at least every 0.1 seconds on each PID carrying them
+//!  - the `PCR_PID` given in a PMT should not be one of the reserved PID values, and unless it
+//!    is `0x1FFF` (meaning the program has no PCR), a PCR should appear on that PID within 0.1
+//!    seconds of the PMT
+//!  - the `transport_scrambling_control` field should not have the reserved value `0b01`, and
+//!    should indicate 'not scrambled' for packets without payload, null packets, and packets
+//!    carrying the PAT or a PMT
+//!
+//! Time is measured using the PCR values of the first PID seen carrying a PCR, so the PAT
+//! repetition and missing PCR checks only take effect in streams which carry PCRs.
+
+use packet;
+use pcr::{PCR_HZ, PCR_WRAP};
+use demultiplex::{ContinuityStatus, ProgramMap};
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// The maximum interval between copies of the PAT
+const PAT_INTERVAL: u64 = PCR_HZ / 2;
+/// The maximum interval between PCR values on a single PID
+const PCR_INTERVAL: u64 = PCR_HZ / 10;
+
+/// The rule broken by a [`Violation`](struct.Violation.html).
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum ViolationKind {
+    TransportError,
+    ContinuityError,
+    PatRepetition,
+    PcrRepetition,
+    ReservedPcrPid,
+    MissingPcr,
+    ScramblingControl,
+}
+
+/// A single breach of the rules, found at the packet with the given PID, at the given byte
+/// offset within the stream (see
+/// [`Demultiplex::stream_offset()`](../demultiplex/struct.Demultiplex.html#method.stream_offset)).
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub struct Violation {
+    pub pid: u16,
+    pub offset: u64,
+    pub kind: ViolationKind,
+    pub description: String,
+}
+
+/// Accumulates the [`Violation`](struct.Violation.html)s found in a Transport Stream.
+#[derive(Debug,Default,Clone)]
+pub struct ConformanceReport {
+    violations: Vec<Violation>,
+}
+impl ConformanceReport {
+    pub fn new() -> ConformanceReport {
+        ConformanceReport::default()
+    }
+
+    /// The violations found so far, in the order they occurred.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// Removes and returns the violations found so far, so that an application monitoring a
+    /// long-running stream need not accumulate them without limit.
+    pub fn take_violations(&mut self) -> Vec<Violation> {
+        std::mem::take(&mut self.violations)
+    }
+
+    fn add(&mut self, pid: u16, offset: u64, kind: ViolationKind, description: String) {
+        warn!("PID {}: {}", pid, description);
+        self.violations.push(Violation { pid, offset, kind, description });
+    }
+}
+
+/// The state needed to make the checks which span many packets, together with the report of
+/// the violations found.
+pub(crate) struct ConformanceChecker {
+    report: ConformanceReport,
+    /// the PID carrying the PAT, which is `0` unless the stream has been remapped
+    pat_pid: u16,
+    /// the PID whose PCR values are used to measure elapsed time
+    clock_pid: Option<u16>,
+    now: Option<u64>,
+    last_pat: Option<u64>,
+    pat_late: bool,
+    last_pcr: HashMap<u16, u64>,
+    /// `(program_number, pcr_pid)` pairs already reported as invalid
+    bad_pcr_pids: HashSet<(u16, u16)>,
+    /// `(program_number, pcr_pid)` pairs for which no PCR has yet been seen, with the time the
+    /// wait began (or `None` if the time was not yet known)
+    awaiting_pcr: HashMap<(u16, u16), Option<u64>>,
+}
+impl ConformanceChecker {
+    pub fn new(pat_pid: u16) -> ConformanceChecker {
+        ConformanceChecker {
+            report: ConformanceReport::new(),
+            pat_pid,
+            clock_pid: None,
+            now: None,
+            last_pat: None,
+            pat_late: false,
+            last_pcr: HashMap::new(),
+            bad_pcr_pids: HashSet::new(),
+            awaiting_pcr: HashMap::new(),
+        }
+    }
+
+    /// Forgets the timing and history of the current stream, so that a new stream is not judged
+    /// by it.  The violations already found remain in the report.
+    pub fn clear(&mut self) {
+        self.clock_pid = None;
+        self.now = None;
+        self.last_pat = None;
+        self.pat_late = false;
+        self.last_pcr.clear();
+        self.bad_pcr_pids.clear();
+        self.awaiting_pcr.clear();
+    }
+
+    pub fn report(&self) -> &ConformanceReport {
+        &self.report
+    }
+    pub fn report_mut(&mut self) -> &mut ConformanceReport {
+        &mut self.report
+    }
+
+    pub fn transport_error(&mut self, pk: &packet::Packet, offset: u64) {
+        self.report.add(pk.pid(), offset, ViolationKind::TransportError, "transport_error_indicator is set".to_string());
+    }
+
+    /// Checks a packet which has passed through the given `ContinuityWatch`, in the context of
+    /// the programs currently known (if the context keeps track of them).
+    pub fn packet(&mut self, pk: &packet::Packet, offset: u64, status: ContinuityStatus, programs: Option<&ProgramMap>) {
+        let pid = pk.pid();
+        let af = pk.adaptation_field();
+        let discontinuity_indicator = af.as_ref().is_some_and(|af| af.discontinuity_indicator());
+        if status == ContinuityStatus::Discontinuity && !discontinuity_indicator {
+            self.report.add(pid, offset, ViolationKind::ContinuityError, format!("unexpected continuity_counter value {}", pk.continuity_counter().count()));
+        }
+        self.scrambling(pk, offset, programs);
+        if pid == self.pat_pid && pk.payload_unit_start_indicator() {
+            self.last_pat = self.now;
+            self.pat_late = false;
+        }
+        if let Some(pcr) = af.and_then(|af| af.pcr().ok()) {
+            self.pcr(pid, offset, u64::from(pcr), discontinuity_indicator);
+        }
+    }
+
+    fn scrambling(&mut self, pk: &packet::Packet, offset: u64, programs: Option<&ProgramMap>) {
+        let value = match pk.transport_scrambling_control() {
+            packet::TransportScramblingControl::NotScrambled => return,
+            packet::TransportScramblingControl::Undefined(v) => v,
+        };
+        let pid = pk.pid();
+        let problem = if value == 0b01 {
+            "reserved transport_scrambling_control value 0b01"
+        } else if !pk.has_payload() {
+            "transport_scrambling_control indicates a packet without payload is scrambled"
+        } else if pid == packet::NULL_PID {
+            "transport_scrambling_control indicates a null packet is scrambled"
+        } else if pid == self.pat_pid || programs.is_some_and(|map| map.programs().any(|p| p.pmt_pid == pid)) {
+            "transport_scrambling_control indicates a PAT or PMT packet is scrambled"
+        } else {
+            return;
+        };
+        self.report.add(pid, offset, ViolationKind::ScramblingControl, problem.to_string());
+    }
+
+    fn pcr(&mut self, pid: u16, offset: u64, pcr: u64, discontinuity_indicator: bool) {
+        self.awaiting_pcr.retain(|&(_, pcr_pid), _| pcr_pid != pid);
+        if let Some(last) = self.last_pcr.insert(pid, pcr) {
+            let elapsed = (pcr + PCR_WRAP - last) % PCR_WRAP;
+            if !discontinuity_indicator && elapsed > PCR_INTERVAL {
+                self.report.add(pid, offset, ViolationKind::PcrRepetition, format!("{}ms since the previous PCR", elapsed * 1000 / PCR_HZ));
+            }
+        }
+        if self.clock_pid.is_none() {
+            self.clock_pid = Some(pid);
+        }
+        if self.clock_pid != Some(pid) {
+            return;
+        }
+        if discontinuity_indicator {
+            // the time since the last PAT can't be known across the discontinuity
+            self.last_pat = None;
+        }
+        self.now = Some(pcr);
+        self.check_awaiting_pcr(pcr, offset);
+        match self.last_pat {
+            None => self.last_pat = Some(pcr),
+            Some(last_pat) => {
+                let elapsed = (pcr + PCR_WRAP - last_pat) % PCR_WRAP;
+                if elapsed > PAT_INTERVAL && !self.pat_late {
+                    self.pat_late = true;
+                    self.report.add(self.pat_pid, offset, ViolationKind::PatRepetition, format!("no PAT for {}ms", elapsed * 1000 / PCR_HZ));
+                }
+            },
+        }
+    }
+
+    /// Reports the programs still waiting for their first PCR more than `PCR_INTERVAL` after
+    /// their PMT was seen.
+    fn check_awaiting_pcr(&mut self, now: u64, offset: u64) {
+        let mut missing = vec!();
+        for (&key, since) in self.awaiting_pcr.iter_mut() {
+            match *since {
+                None => *since = Some(now),
+                Some(since) => if (now + PCR_WRAP - since) % PCR_WRAP > PCR_INTERVAL {
+                    missing.push(key);
+                },
+            }
+        }
+        for (program_number, pcr_pid) in missing {
+            self.awaiting_pcr.remove(&(program_number, pcr_pid));
+            self.bad_pcr_pids.insert((program_number, pcr_pid));
+            self.report.add(pcr_pid, offset, ViolationKind::MissingPcr, format!("no PCR on the PCR_PID of program {}", program_number));
+        }
+    }
+
+    /// Checks the programs most recently announced by the PAT and PMTs.
+    pub fn programs(&mut self, programs: &ProgramMap, offset: u64) {
+        let mut awaiting_pcr = HashMap::new();
+        for program in programs.programs() {
+            if let Some(pcr_pid) = program.pcr_pid {
+                let key = (program.program_number, pcr_pid);
+                // PIDs 0x0000 to 0x000f are reserved for the PAT, CAT, TSDT and so on
+                if pcr_pid < 0x10 {
+                    if self.bad_pcr_pids.insert(key) {
+                        self.report.add(program.pmt_pid, offset, ViolationKind::ReservedPcrPid, format!("PMT of program {} gives reserved PCR_PID {}", program.program_number, pcr_pid));
+                    }
+                } else if pcr_pid != packet::NULL_PID && !self.last_pcr.contains_key(&pcr_pid) && !self.bad_pcr_pids.contains(&key) {
+                    let since = self.awaiting_pcr.get(&key).cloned().unwrap_or(self.now);
+                    awaiting_pcr.insert(key, since);
+                }
+            }
+        }
+        self.awaiting_pcr = awaiting_pcr;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use demultiplex;
+    use test_util;
+    use test_util::{NullDemuxContext, NullStreamConstructor};
+
+    #[test]
+    fn violations() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        assert!(demultiplex::Demultiplex::new(&mut ctx).conformance_report().is_none());
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .conformance_checks(true)
+            .build(&mut ctx);
+
+        let mut buf = vec!();
+        buf.extend(test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01])));
+        // PCR_PID of 0x0001, which is reserved for the CAT
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe0, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00])));
+        buf.extend(test_util::pcr_packet(0x201, 0, 0, false));
+        // continuity_counter skips a value,
+        buf.extend(test_util::packet(0x201, 2));
+        let mut scrambled = test_util::packet(0x300, 0);
+        scrambled[3] |= 0b0100_0000;  // reserved transport_scrambling_control value
+        buf.extend(scrambled);
+        let mut errored = test_util::packet(0x302, 0);
+        errored[1] |= 0b1000_0000;  // transport_error_indicator
+        buf.extend(errored);
+        // 600ms later, which is too long since the last PCR and since the last PAT,
+        buf.extend(test_util::pcr_packet(0x201, 2, 600 * 27_000, false));
+        deplex.push(&mut ctx, &buf[..]);
+
+        let report = deplex.conformance_report().unwrap();
+        let found: Vec<_> = report.violations().iter().map(|v| (v.pid, v.kind)).collect();
+        assert_eq!(found, vec!(
+            (0x101, ViolationKind::ReservedPcrPid),
+            (0x201, ViolationKind::ContinuityError),
+            (0x300, ViolationKind::ScramblingControl),
+            (0x302, ViolationKind::TransportError),
+            (0x201, ViolationKind::PcrRepetition),
+            (0, ViolationKind::PatRepetition),
+        ));
+        assert_eq!(report.violations()[0].offset, packet::PACKET_SIZE as u64);
+        assert_eq!(report.violations()[5].offset, 6 * packet::PACKET_SIZE as u64);
+        assert_eq!(deplex.conformance_report_mut().unwrap().take_violations().len(), 6);
+
+        // timely repetitions of the PAT and PCR are fine
+        let mut buf = vec!();
+        buf.extend(test_util::section_packets(0, 1, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01])));
+        buf.extend(test_util::pcr_packet(0x201, 2, 650 * 27_000, false));
+        buf.extend(test_util::pcr_packet(0x201, 2, 700 * 27_000, false));
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(deplex.conformance_report().unwrap().violations(), &[][..]);
+    }
+
+    #[test]
+    fn missing_pcr() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .conformance_checks(true)
+            .build(&mut ctx);
+
+        let mut buf = vec!();
+        buf.extend(test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01, 0, 2, 0xe1, 0x02, 0, 3, 0xe1, 0x03])));
+        // PCR_PID of 0x201, which carries PCRs
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00])));
+        // PCR_PID of 0x202, which never does
+        buf.extend(test_util::section_packets(0x102, 0, &test_util::section(0x02, 2, 0, true, &[0xe2, 0x02, 0xf0, 0x00, 0x1b, 0xe2, 0x02, 0xf0, 0x00])));
+        // PCR_PID of 0x1fff, meaning that the program has no PCR
+        buf.extend(test_util::section_packets(0x103, 0, &test_util::section(0x02, 3, 0, true, &[0xff, 0xff, 0xf0, 0x00, 0x1b, 0xe2, 0x03, 0xf0, 0x00])));
+        for i in 0..5u32 {
+            buf.extend(test_util::pcr_packet(0x201, 0, u64::from(i) * 40 * 27_000, false));
+        }
+        deplex.push(&mut ctx, &buf[..]);
+
+        let found: Vec<_> = deplex.conformance_report().unwrap().violations().iter().map(|v| (v.pid, v.kind)).collect();
+        assert_eq!(found, vec!((0x202, ViolationKind::MissingPcr)));
+        // reported once, however long the PCR stays missing
+        let mut buf = vec!();
+        for i in 5..10u32 {
+            buf.extend(test_util::pcr_packet(0x201, 0, u64::from(i) * 40 * 27_000, false));
+        }
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(deplex.conformance_report().unwrap().violations().len(), 1);
+    }
+
+    #[test]
+    fn remapped_pat() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .pat_pid(0x20)
+            .conformance_checks(true)
+            .build(&mut ctx);
+
+        // a PAT every 400ms and a PCR every 80ms, for a second
+        let mut buf = vec!();
+        for i in 0..13 {
+            if i % 5 == 0 {
+                buf.extend(test_util::section_packets(0x20, i / 5, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01])));
+            }
+            buf.extend(test_util::pcr_packet(0x201, 0, u64::from(i) * 80 * 27_000, false));
+        }
+        deplex.push(&mut ctx, &buf[..]);
+        // the PAT is repeated in time on its remapped PID
+        assert_eq!(deplex.conformance_report().unwrap().violations(), &[][..]);
+
+        let mut scrambled = test_util::section_packets(0x20, 3, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        scrambled[3] |= 0b1000_0000;  // scrambled with the 'even' key
+        deplex.push(&mut ctx, &scrambled[..]);
+        let found: Vec<_> = deplex.conformance_report().unwrap().violations().iter().map(|v| (v.pid, v.kind)).collect();
+        assert_eq!(found, vec!((0x20, ViolationKind::ScramblingControl)));
+    }
+
+    #[test]
+    fn reset() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .conformance_checks(true)
+            .build(&mut ctx);
+        let mut buf = vec!();
+        buf.extend(test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01])));
+        buf.extend(test_util::pcr_packet(0x201, 0, 0, false));
+        buf.extend(test_util::packet(0x202, 0));
+        buf.extend(test_util::pcr_packet(0x201, 0, 1000 * 27_000, false));
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(deplex.conformance_report().unwrap().violations().len(), 2);
+
+        // following a channel change, the new stream's clock and continuity_counter values are
+        // unrelated to those of the old
+        deplex.reset(&mut ctx);
+        let mut buf = vec!();
+        buf.extend(test_util::section_packets(0, 5, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01])));
+        buf.extend(test_util::pcr_packet(0x201, 7, 90_000 * 27_000, false));
+        buf.extend(test_util::packet(0x202, 9));
+        deplex.push(&mut ctx, &buf[..]);
+        // only the violations found before the reset are reported
+        let found: Vec<_> = deplex.conformance_report().unwrap().violations().iter().map(|v| (v.pid, v.kind)).collect();
+        assert_eq!(found, vec!(
+            (0x201, ViolationKind::PcrRepetition),
+            (0, ViolationKind::PatRepetition),
+        ));
+    }
+}
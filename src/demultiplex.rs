@@ -1,12 +1,19 @@
 use std::collections::HashSet;
-use std::fmt;
+use std::collections::HashMap;
+use std::collections::BTreeMap;
 use packet;
 use psi;
-use descriptor;
 use std;
 use fixedbitset;
 use StreamType;
+use conformance;
+use pcr;
+use descriptor;
+use descriptor::dvb::{StreamIdentifierDescriptor, SubtitlingDescriptor, TeletextDescriptor};
 use std::marker;
+use std::ops;
+pub use psi::pat::PatSection;
+pub use psi::pmt::{PmtSection, StreamInfo, StreamInfoIter};
 
 // TODO: Pid = u16;
 
@@ -14,11 +21,44 @@ pub trait PacketFilter {
     type Ctx: DemuxContext;
 
     fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet);
+
+    /// If `true`, `Demultiplex` will not pass this filter any packet which has no payload (see
+    /// [`Packet::has_payload()`](../packet/struct.Packet.html#method.has_payload)), saving work
+    /// for filters that would ignore such packets anyway.  Filters which look at the adaptation
+    /// field, for example to extract the PCR, should leave this as the default, `false`.
+    fn needs_payload(&self) -> bool {
+        false
+    }
+}
+
+/// A packet filter behind dynamic dispatch, as an alternative to the enum generated by
+/// [`packet_filter_switch!()`](../macro.packet_filter_switch.html), for applications which can't
+/// enumerate every filter type at compile time.  Each packet then costs a virtual call, in
+/// exchange for being able to mix filter types freely.  See
+/// [`BoxedStreamConstructor`](struct.BoxedStreamConstructor.html).
+pub type BoxedPacketFilter<Ctx> = Box<dyn PacketFilter<Ctx=Ctx>>;
+
+impl<F: PacketFilter + ?Sized> PacketFilter for Box<F> {
+    type Ctx = F::Ctx;
+
+    fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet) {
+        (**self).consume(ctx, pk)
+    }
+
+    fn needs_payload(&self) -> bool {
+        (**self).needs_payload()
+    }
 }
 
 pub struct NullPacketFilter<Ctx: DemuxContext> {
     phantom: marker::PhantomData<Ctx>,
 }
+impl<Ctx: DemuxContext> Default for NullPacketFilter<Ctx> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Ctx: DemuxContext> NullPacketFilter<Ctx> {
     pub fn construct(_pmt: &PmtSection, _stream_info: &StreamInfo) -> NullPacketFilter<Ctx> {
         Self::new()
@@ -36,12 +76,169 @@ impl<Ctx: DemuxContext> PacketFilter for NullPacketFilter<Ctx> {
     }
 }
 
+/// Receives the raw bytes of the packets passed to a [`RawPacketFilter`](struct.RawPacketFilter.html).
+///
+/// Implemented for any `FnMut(&[u8])` closure, and for `Vec<u8>`, which has each packet appended.
+pub trait RawPacketSink {
+    /// Called with the complete 188 bytes of each packet, including header and adaptation field.
+    fn packet(&mut self, data: &[u8]);
+}
+impl<F: FnMut(&[u8])> RawPacketSink for F {
+    fn packet(&mut self, data: &[u8]) {
+        self(data)
+    }
+}
+impl RawPacketSink for Vec<u8> {
+    fn packet(&mut self, data: &[u8]) {
+        self.extend_from_slice(data);
+    }
+}
+
+/// A `PacketFilter` which passes every packet it receives, unchanged, to the given
+/// `RawPacketSink`, e.g. to record the packets of selected PIDs without any further parsing.
+pub struct RawPacketFilter<Ctx: DemuxContext, S: RawPacketSink> {
+    sink: S,
+    phantom: marker::PhantomData<Ctx>,
+}
+impl<Ctx: DemuxContext, S: RawPacketSink> RawPacketFilter<Ctx, S> {
+    pub fn new(sink: S) -> RawPacketFilter<Ctx, S> {
+        RawPacketFilter {
+            sink,
+            phantom: marker::PhantomData,
+        }
+    }
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+}
+impl<Ctx: DemuxContext, S: RawPacketSink> PacketFilter for RawPacketFilter<Ctx, S> {
+    type Ctx = Ctx;
+    fn consume(&mut self, _ctx: &mut Self::Ctx, pk: packet::Packet) {
+        self.sink.packet(pk.buffer());
+    }
+}
+
+/// A `PacketFilter` which passes every packet it receives to both of the given filters, in turn,
+/// allowing for example the packets of a PID to be recorded by a `RawPacketFilter` while also
+/// being reassembled by a `PesPacketFilter`.
+///
+/// Nested `TeePacketFilter`s can be used to feed more than two filters.
+pub struct TeePacketFilter<A, B>
+where
+    A: PacketFilter,
+    B: PacketFilter<Ctx=A::Ctx>,
+{
+    first: A,
+    second: B,
+}
+impl<A, B> TeePacketFilter<A, B>
+where
+    A: PacketFilter,
+    B: PacketFilter<Ctx=A::Ctx>,
+{
+    pub fn new(first: A, second: B) -> TeePacketFilter<A, B> {
+        TeePacketFilter {
+            first,
+            second,
+        }
+    }
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+    pub fn first_mut(&mut self) -> &mut A {
+        &mut self.first
+    }
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+    pub fn second_mut(&mut self) -> &mut B {
+        &mut self.second
+    }
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+impl<A, B> PacketFilter for TeePacketFilter<A, B>
+where
+    A: PacketFilter,
+    B: PacketFilter<Ctx=A::Ctx>,
+{
+    type Ctx = A::Ctx;
+    fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet) {
+        if pk.has_payload() || !self.first.needs_payload() {
+            self.first.consume(ctx, pk.clone());
+        }
+        if pk.has_payload() || !self.second.needs_payload() {
+            self.second.consume(ctx, pk);
+        }
+    }
+    fn needs_payload(&self) -> bool {
+        self.first.needs_payload() && self.second.needs_payload()
+    }
+}
+
+/// A `PacketFilter` which passes only the first `limit` packets it receives on to the wrapped
+/// filter, and then requests its own removal from the `Demultiplex` through the context's
+/// `FilterChangeset`, e.g. to inspect just the start of each stream.
+///
+/// Note that, once removed, further packets on the PID will cause the `StreamConstructor` to be
+/// asked for a new filter with `FilterRequest::ByPid`, unless the `Demultiplex` was built with
+/// [`auto_construct_filters(false)`](struct.DemultiplexBuilder.html#method.auto_construct_filters).
+pub struct LimitPacketFilter<F: PacketFilter> {
+    inner: F,
+    remaining: u64,
+    removal_requested: bool,
+}
+impl<F: PacketFilter> LimitPacketFilter<F> {
+    pub fn new(inner: F, limit: u64) -> LimitPacketFilter<F> {
+        LimitPacketFilter {
+            inner,
+            remaining: limit,
+            removal_requested: false,
+        }
+    }
+    /// The number of packets still to be passed to the wrapped filter.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+    pub fn inner_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+impl<F: PacketFilter> PacketFilter for LimitPacketFilter<F> {
+    type Ctx = F::Ctx;
+    fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet) {
+        let pid = pk.pid();
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            self.inner.consume(ctx, pk);
+        }
+        if self.remaining == 0 && !self.removal_requested {
+            ctx.filter_changeset().remove(pid);
+            self.removal_requested = true;
+        }
+    }
+    fn needs_payload(&self) -> bool {
+        self.inner.needs_payload()
+    }
+}
+
 /// Creates the boilerplate needed for a filter-implementation-specific `DemuxContext`.
 ///
 /// This macro takes two arguments; the name for the new type, and the name of an existing
 /// implementation of `PacketFilter`.  It then..
 ///
-/// 1. creates a struct with the given name, wrapping an instance of `FilterChangeset`
+/// 1. creates a struct with the given name, wrapping an instance of `FilterChangeset` and a
+///    `ProgramMap`
 /// 2. provides an implementation of `default()` for that struct
 /// 3. provides an implementation of `DemuxContext`
 ///
@@ -85,12 +282,20 @@ macro_rules! demux_context {
         pub struct $name {
             changeset: $crate::demultiplex::FilterChangeset<<$ctor as $crate::demultiplex::StreamConstructor>::F>,
             constructor: $ctor,
+            program_map: $crate::demultiplex::ProgramMap,
+            psi_stats: $crate::demultiplex::PsiStatsMap,
+            packet_offset: Option<u64>,
+            filtered_pids: $crate::demultiplex::FilteredPids,
         }
         impl $name {
             pub fn new(constructor: $ctor) -> Self {
                 $name {
                     changeset: $crate::demultiplex::FilterChangeset::new(),
                     constructor,
+                    program_map: $crate::demultiplex::ProgramMap::new(),
+                    psi_stats: $crate::demultiplex::PsiStatsMap::new(),
+                    packet_offset: None,
+                    filtered_pids: $crate::demultiplex::FilteredPids::new(),
                 }
             }
         }
@@ -104,6 +309,30 @@ macro_rules! demux_context {
             fn filter_constructor(&mut self) -> &mut $ctor {
                 &mut self.constructor
             }
+            fn program_map(&self) -> Option<&$crate::demultiplex::ProgramMap> {
+                Some(&self.program_map)
+            }
+            fn program_map_mut(&mut self) -> Option<&mut $crate::demultiplex::ProgramMap> {
+                Some(&mut self.program_map)
+            }
+            fn psi_stats_map(&self) -> Option<&$crate::demultiplex::PsiStatsMap> {
+                Some(&self.psi_stats)
+            }
+            fn psi_stats_map_mut(&mut self) -> Option<&mut $crate::demultiplex::PsiStatsMap> {
+                Some(&mut self.psi_stats)
+            }
+            fn set_packet_offset(&mut self, offset: u64) {
+                self.packet_offset = Some(offset);
+            }
+            fn packet_offset(&self) -> Option<u64> {
+                self.packet_offset
+            }
+            fn filtered_pids(&self) -> Option<&$crate::demultiplex::FilteredPids> {
+                Some(&self.filtered_pids)
+            }
+            fn filtered_pids_mut(&mut self) -> Option<&mut $crate::demultiplex::FilteredPids> {
+                Some(&mut self.filtered_pids)
+            }
         }
     };
 }
@@ -134,12 +363,76 @@ macro_rules! packet_filter_switch {
 
                 }
             }
+            #[inline(always)]
+            fn needs_payload(&self) -> bool {
+                match self {
+                    $( &$name::$case_name(ref f) => f.needs_payload(), )*
+                }
+            }
+        }
+    }
+}
+/// A read-only record of the PIDs for which a `Demultiplex` currently has a filter, available to
+/// filters via
+/// [`DemuxContext::filtered_pids()`](trait.DemuxContext.html#method.filtered_pids).
+///
+/// The set is brought up to date before each packet is dispatched, so it does not include
+/// changes still pending in the context's `FilterChangeset`.  A filter can therefore check, for
+/// example, whether another filter has already claimed a shared ECM PID before requesting one
+/// itself.
+#[derive(Debug,Clone,PartialEq)]
+pub struct FilteredPids {
+    pids: fixedbitset::FixedBitSet,
+}
+impl Default for FilteredPids {
+    fn default() -> Self {
+        FilteredPids {
+            pids: fixedbitset::FixedBitSet::with_capacity(0x2000),
+        }
+    }
+}
+impl FilteredPids {
+    pub fn new() -> FilteredPids {
+        FilteredPids::default()
+    }
+
+    pub fn contains(&self, pid: u16) -> bool {
+        self.pids.contains(pid as usize)
+    }
+
+    /// The PIDs in the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item=u16> + '_ {
+        self.pids.ones().map(|pid| pid as u16)
+    }
+
+    fn set_from<F: PacketFilter>(&mut self, filters: &Filters<F>) {
+        self.pids.clear();
+        for pid in filters.pids() {
+            self.pids.insert(pid as usize);
         }
     }
 }
+
+/// The set of filters in use by a `Demultiplex`, keyed by PID.
+///
+/// By default, filters are held in a `Vec` indexed by PID, giving the fastest lookup, but
+/// needing a slot for every PID value up to the highest one in use.  Enabling the crate's
+/// `sparse-filters` feature instead holds them in a `Vec` sorted by PID, so that memory use is
+/// proportional to the number of filters (at the cost of a binary search per lookup), which may
+/// suit memory-constrained applications handling only a few PIDs.
 pub struct Filters<F: PacketFilter> {
-    filters_by_pid: Vec<Option<F>>
+    #[cfg(not(feature = "sparse-filters"))]
+    filters_by_pid: Vec<Option<F>>,
+    #[cfg(feature = "sparse-filters")]
+    filters_by_pid: Vec<(u16, F)>,
+}
+impl<F: PacketFilter> Default for Filters<F> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
+
+#[cfg(not(feature = "sparse-filters"))]
 impl<F: PacketFilter> Filters<F> {
     pub fn new() -> Filters<F> {
         Filters {
@@ -170,17 +463,73 @@ impl<F: PacketFilter> Filters<F> {
         self.filters_by_pid[pid as usize] = Some(filter);
     }
 
-    pub fn remove(&mut self, pid: u16) {
+    /// Removes the filter for the given PID, returning it if one was present.
+    pub fn remove(&mut self, pid: u16) -> Option<F> {
         if (pid as usize) < self.filters_by_pid.len() {
-            self.filters_by_pid[pid as usize] = None;
+            self.filters_by_pid[pid as usize].take()
+        } else {
+            None
         }
     }
 
+    /// Removes all filters.
+    pub fn clear(&mut self) {
+        self.filters_by_pid.clear();
+    }
+
     pub fn pids(&self) -> Vec<u16> {
         self.filters_by_pid.iter().enumerate().filter_map(|(i, e)| { if e.is_some() { Some(i as u16) } else { None } } ).collect()
     }
 }
 
+#[cfg(feature = "sparse-filters")]
+impl<F: PacketFilter> Filters<F> {
+    pub fn new() -> Filters<F> {
+        Filters {
+            filters_by_pid: vec!(),
+        }
+    }
+
+    fn index(&self, pid: u16) -> Result<usize, usize> {
+        self.filters_by_pid.binary_search_by_key(&pid, |&(p, _)| p)
+    }
+
+    pub fn contains(&self, pid: u16) -> bool {
+        self.index(pid).is_ok()
+    }
+
+    pub fn get(&mut self, pid: u16) -> Option<&mut F> {
+        match self.index(pid) {
+            Ok(i) => Some(&mut self.filters_by_pid[i].1),
+            Err(_) => None,
+        }
+    }
+
+    pub fn insert(&mut self, pid: u16, filter: F) {
+        match self.index(pid) {
+            Ok(i) => self.filters_by_pid[i].1 = filter,
+            Err(i) => self.filters_by_pid.insert(i, (pid, filter)),
+        }
+    }
+
+    /// Removes the filter for the given PID, returning it if one was present.
+    pub fn remove(&mut self, pid: u16) -> Option<F> {
+        match self.index(pid) {
+            Ok(i) => Some(self.filters_by_pid.remove(i).1),
+            Err(_) => None,
+        }
+    }
+
+    /// Removes all filters.
+    pub fn clear(&mut self) {
+        self.filters_by_pid.clear();
+    }
+
+    pub fn pids(&self) -> Vec<u16> {
+        self.filters_by_pid.iter().map(|&(pid, _)| pid).collect()
+    }
+}
+
 
 // A filter can't change the map of filters-by-pid that it is itself owned by while the filter is
 // running, so this changeset protocol allows a filter to specify any filter updates required so
@@ -194,7 +543,7 @@ impl<F: PacketFilter> FilterChange<F> {
     fn apply(self, filters: &mut Filters<F>) {
         match self {
             FilterChange::Insert(pid, filter) => filters.insert(pid, filter),
-            FilterChange::Remove(pid) => filters.remove(pid),
+            FilterChange::Remove(pid) => { filters.remove(pid); },
         };
     }
 }
@@ -211,6 +560,12 @@ impl<F: PacketFilter> std::fmt::Debug for FilterChange<F> {
 pub struct FilterChangeset<F: PacketFilter> {
     updates: Vec<FilterChange<F>>
 }
+impl<F: PacketFilter> Default for FilterChangeset<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<F: PacketFilter> FilterChangeset<F> {
     pub fn new() -> FilterChangeset<F> {
         FilterChangeset { updates: Vec::new() }
@@ -245,18 +600,156 @@ impl<F: PacketFilter> std::iter::IntoIterator for FilterChangeset<F> {
 
 pub enum FilterRequest<'a, 'buf: 'a> {
     ByPid(u16),
-    ByStream(StreamType, &'a PmtSection<'buf>, &'a StreamInfo<'buf>),
+    /// requests a filter for an elementary stream announced in the PMT of the given program
+    ByStream {
+        program_number: u16,
+        stream_type: StreamType,
+        pmt: &'a PmtSection<'buf>,
+        stream_info: &'a StreamInfo<'buf>,
+    },
     Pmt{pid: u16, program_number: u16},
+    /// requests a filter for the `network_PID` announced by the PAT entry with
+    /// `program_number` 0, which carries the Network Information Table rather than a PMT
+    Nit{pid: u16},
 }
 
-// TODO: would be nice to have an impl of this trait for `Fn(FilterRequest)->F`, but that ends up
-// not being usable without having additional type-parameters over several parts of the API.
 pub trait StreamConstructor {
     type F: PacketFilter;
 
     fn construct(&mut self, req: FilterRequest) -> Self::F;
 }
 
+/// Adapts a function or closure, `FnMut(FilterRequest) -> F`, into a `StreamConstructor`,
+/// avoiding the need to define a new type implementing that trait.
+///
+/// Since the type of the constructor must be named in the
+/// [`demux_context!()`](../macro.demux_context.html) invocation, use either a function pointer
+/// type (as below), or a boxed closure like `Box<dyn FnMut(FilterRequest) -> MyFilterSwitch>`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate mpeg2ts_reader;
+/// # use mpeg2ts_reader::demultiplex::{self, FilterRequest, FnStreamConstructor};
+/// # fn main() {
+/// packet_filter_switch!{
+///     MyFilterSwitch<MyDemuxContext> {
+///         Pat: demultiplex::PatPacketFilter<MyDemuxContext>,
+///         Pmt: demultiplex::PmtPacketFilter<MyDemuxContext>,
+///         Nul: demultiplex::NullPacketFilter<MyDemuxContext>,
+///     }
+/// };
+/// type MyStreamConstructor = FnStreamConstructor<MyFilterSwitch, fn(FilterRequest) -> MyFilterSwitch>;
+/// demux_context!(MyDemuxContext, MyStreamConstructor);
+///
+/// fn construct(req: FilterRequest) -> MyFilterSwitch {
+///     match req {
+///         FilterRequest::ByPid(0) => MyFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
+///         FilterRequest::Pmt{pid, program_number} => MyFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
+///         _ => MyFilterSwitch::Nul(demultiplex::NullPacketFilter::new()),
+///     }
+/// }
+///
+/// let mut ctx = MyDemuxContext::new(FnStreamConstructor::new(construct));
+/// let mut demux = demultiplex::Demultiplex::new(&mut ctx);
+/// # }
+/// ```
+pub struct FnStreamConstructor<F, Fun> {
+    fun: Fun,
+    phantom: marker::PhantomData<fn() -> F>,
+}
+impl<F, Fun> FnStreamConstructor<F, Fun>
+    where
+        F: PacketFilter,
+        Fun: FnMut(FilterRequest) -> F
+{
+    pub fn new(fun: Fun) -> FnStreamConstructor<F, Fun> {
+        FnStreamConstructor {
+            fun,
+            phantom: marker::PhantomData,
+        }
+    }
+}
+impl<F, Fun> StreamConstructor for FnStreamConstructor<F, Fun>
+    where
+        F: PacketFilter,
+        Fun: FnMut(FilterRequest) -> F
+{
+    type F = F;
+
+    fn construct(&mut self, req: FilterRequest) -> Self::F {
+        (self.fun)(req)
+    }
+}
+
+/// A `StreamConstructor` producing [`BoxedPacketFilter`](type.BoxedPacketFilter.html) values,
+/// so that filters of any type can be chosen at runtime.
+///
+/// Each request is first given to the wrapped function.  If that returns `None`, the constructor
+/// falls back to a `PatPacketFilter` for PID 0, a `PmtPacketFilter` for each program's PMT, and a
+/// `NullPacketFilter` for anything else, so the function need only handle the streams it is
+/// interested in.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate mpeg2ts_reader;
+/// # use mpeg2ts_reader::demultiplex::{self, BoxedPacketFilter, BoxedStreamConstructor, FilterRequest};
+/// # use mpeg2ts_reader::StreamType;
+/// # fn main() {
+/// demux_context!(MyDemuxContext, BoxedStreamConstructor<MyDemuxContext>);
+///
+/// let mut ctx = MyDemuxContext::new(BoxedStreamConstructor::new(|req| match req {
+///     FilterRequest::ByStream { stream_type: StreamType::H264, .. } => {
+///         let filter: BoxedPacketFilter<MyDemuxContext> = Box::new(demultiplex::RawPacketFilter::new(|data: &[u8]| {
+///             // ...
+///         }));
+///         Some(filter)
+///     },
+///     _ => None,
+/// }));
+/// let mut demux = demultiplex::Demultiplex::new(&mut ctx);
+/// # }
+/// ```
+pub struct BoxedStreamConstructor<Ctx: DemuxContext> {
+    fun: BoxedConstructorFn<Ctx>,
+}
+type BoxedConstructorFn<Ctx> = Box<dyn FnMut(FilterRequest) -> Option<BoxedPacketFilter<Ctx>>>;
+impl<Ctx: DemuxContext + 'static> BoxedStreamConstructor<Ctx> {
+    pub fn new<Fun>(fun: Fun) -> BoxedStreamConstructor<Ctx>
+        where
+            Fun: FnMut(FilterRequest) -> Option<BoxedPacketFilter<Ctx>> + 'static
+    {
+        BoxedStreamConstructor {
+            fun: Box::new(fun),
+        }
+    }
+}
+impl<Ctx: DemuxContext + 'static> StreamConstructor for BoxedStreamConstructor<Ctx> {
+    type F = BoxedPacketFilter<Ctx>;
+
+    fn construct(&mut self, req: FilterRequest) -> Self::F {
+        let is_pat = matches!(req, FilterRequest::ByPid(0));
+        let pmt = match req {
+            FilterRequest::Pmt { pid, program_number } => Some((pid, program_number)),
+            _ => None,
+        };
+        if let Some(filter) = (self.fun)(req) {
+            return filter;
+        }
+        if is_pat {
+            Box::new(PatPacketFilter::new())
+        } else if let Some((pid, program_number)) = pmt {
+            Box::new(PmtPacketFilter::new(pid, program_number))
+        } else {
+            Box::new(NullPacketFilter::new())
+        }
+    }
+}
+
+/// Processes the PMT of a single program, requesting a filter for each elementary stream it
+/// lists, and removing the filters of streams dropped from a later version of the table.
+///
+/// Should a (malformed) PMT list the same `elementary_PID` more than once, the first entry is
+/// used and any later entries for that PID are ignored, with a warning logged.
 pub struct PmtProcessor<Ctx: DemuxContext> {
     pid: u16,
     program_number: u16,
@@ -276,18 +769,29 @@ impl<Ctx: DemuxContext> PmtProcessor<Ctx> {
         }
     }
 
-    fn new_table(&mut self, ctx: &mut Ctx, header: &psi::SectionCommonHeader, table_syntax_header: &psi::TableSyntaxHeader, sect: &PmtSection) {
-        if 0x02 != header.table_id {
-            println!("[PMT pid:{} program:{}] Expected PMT to have table id 0x2, but got {:#x}", self.pid, self.program_number, header.table_id);
-            return;
+    fn new_table(&mut self, ctx: &mut Ctx, table_syntax_header: &psi::TableSyntaxHeader, sect: &PmtSection) {
+        if let Some(old_version) = self.current_version {
+            if old_version != table_syntax_header.version() {
+                ctx.table_version_changed(TableKind::Pmt { program_number: self.program_number }, old_version, table_syntax_header.version());
+            }
         }
-        // pass the table_id value this far!
+        ctx.pmt_updated(self.program_number, sect);
         let mut pids_seen = HashSet::new();
+        let mut streams = vec!();
         for stream_info in sect.streams() {
-            println!("[PMT pid:{} program:{}] new entry PID {}", self.pid, self.program_number, stream_info.elementary_pid());
-            let pes_packet_consumer = ctx.filter_constructor().construct(FilterRequest::ByStream(stream_info.stream_type(), &sect, &stream_info));
+            if !pids_seen.insert(stream_info.elementary_pid()) {
+                warn!("[PMT pid:{} program:{}] ignoring repeated entry for PID {}", self.pid, self.program_number, stream_info.elementary_pid());
+                continue;
+            }
+            debug!("[PMT pid:{} program:{}] new entry PID {}", self.pid, self.program_number, stream_info.elementary_pid());
+            streams.push(StreamEntry::from_stream_info(&stream_info));
+            let pes_packet_consumer = ctx.filter_constructor().construct(FilterRequest::ByStream {
+                program_number: self.program_number,
+                stream_type: stream_info.stream_type(),
+                pmt: sect,
+                stream_info: &stream_info,
+            });
             ctx.filter_changeset().insert(stream_info.elementary_pid(), pes_packet_consumer);
-            pids_seen.insert(stream_info.elementary_pid());
             self.filters_registered.insert(stream_info.elementary_pid() as usize);
         }
         // remove filters for descriptors we've seen before that are not present in this updated
@@ -298,6 +802,18 @@ impl<Ctx: DemuxContext> PmtProcessor<Ctx> {
                 self.filters_registered.set(pid, false);
             }
         }
+        let snapshot = PmtSnapshot {
+            pid: self.pid,
+            version: table_syntax_header.version(),
+            pcr_pid: sect.pcr_pid(),
+            streams,
+        };
+        if let Some(sink) = ctx.event_sink() {
+            sink.on_pmt(self.program_number, &snapshot);
+        }
+        if let Some(map) = ctx.program_map_mut() {
+            map.update_program(self.program_number, self.pid, snapshot.version, snapshot.pcr_pid, snapshot.streams);
+        }
         self.current_version = Some(table_syntax_header.version());
     }
 }
@@ -305,138 +821,49 @@ impl<Ctx: DemuxContext> PmtProcessor<Ctx> {
 impl<Ctx: DemuxContext> psi::WholeSectionSyntaxPayloadParser for PmtProcessor<Ctx> {
     type Context = Ctx;
 
-    fn section<'a>(&mut self, ctx: &mut Self::Context, header: &psi::SectionCommonHeader, table_syntax_header: &psi::TableSyntaxHeader, data: &'a [u8]) {
-        let start = psi::SectionCommonHeader::SIZE+psi::TableSyntaxHeader::SIZE;
-        let end = data.len() - 4;  // remove CRC bytes
-        self.new_table(ctx, header, table_syntax_header, &PmtSection::new(&data[start..end]));
-    }
-}
-
-pub struct StreamInfo<'buf> {
-    data: &'buf[u8],
-}
-
-impl<'buf> StreamInfo<'buf> {
-    const HEADER_SIZE: usize = 5;
-
-    fn from_bytes(data: &'buf[u8]) -> Option<(StreamInfo<'buf>, usize)> {
-        if data.len() < Self::HEADER_SIZE {
-            println!("only {} bytes remaining for stream info, at least {} required {:?}", data.len(), Self::HEADER_SIZE, data);
-            return None;
+    fn section(&mut self, ctx: &mut Self::Context, header: &psi::SectionCommonHeader, table_syntax_header: &psi::TableSyntaxHeader, data: &[u8]) {
+        if let Some(stats) = ctx.psi_stats_map_mut() {
+            stats.crc_ok(self.pid);
         }
-        let result = StreamInfo {
-            data,
-        };
-
-        let descriptor_end = Self::HEADER_SIZE + result.es_info_length() as usize;
-        if descriptor_end > data.len() {
-            print!("PMT section of size {} is not large enough to contain es_info_length of {}", data.len(), result.es_info_length());
-            return None;
+        if header.table_id != PmtSection::TABLE_ID {
+            debug!("[PMT pid:{} program:{}] ignoring section with table_id {:#x}", self.pid, self.program_number, header.table_id);
+            ctx.unexpected_table(self.pid, TableKind::Pmt { program_number: self.program_number }, header, table_syntax_header, data);
+            return;
         }
-        Some((result, descriptor_end))
-    }
-
-    pub fn stream_type(&self) -> StreamType {
-        self.data[0].into()
-    }
-    pub fn reserved1(&self) -> u8 {
-        self.data[1] >> 5
-    }
-    pub fn elementary_pid(&self) -> u16 {
-       u16::from(self.data[1] & 0b00011111) << 8 | u16::from(self.data[2])
-    }
-    pub fn reserved2(&self) -> u8 {
-        self.data[3] >> 4
-    }
-    pub fn es_info_length(&self) -> u16 {
-        u16::from(self.data[3] & 0b00001111) << 8 | u16::from(self.data[4])
-    }
-
-    pub fn descriptors(&self) -> descriptor::DescriptorIter {
-        let descriptor_end = Self::HEADER_SIZE + self.es_info_length() as usize;
-        descriptor::DescriptorIter::new(&self.data[Self::HEADER_SIZE..descriptor_end])
-    }
-}
-impl<'buf> fmt::Debug for StreamInfo<'buf> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        f.debug_struct("StreamInfo")
-            .field("stream_type", &self.stream_type())
-            .field("elementry_pid", &self.elementary_pid())
-            .field("es_info_length", &self.es_info_length())
-            .finish()
-    }
-}
-
-#[derive(Debug)]
-pub struct PmtSection<'buf> {
-    data: &'buf[u8],
-}
-
-impl<'buf> PmtSection<'buf> {
-    fn new(data: &'buf[u8]) -> PmtSection<'buf> {
-        PmtSection {
-            data,
+        ctx.raw_section(self.pid, TableKind::Pmt { program_number: self.program_number }, data);
+        if table_syntax_header.current_next_indicator() == psi::CurrentNext::Next {
+            debug!("[PMT pid:{} program:{}] ignoring section version {} which is not yet applicable", self.pid, self.program_number, table_syntax_header.version());
+            ctx.next_section(self.pid, TableKind::Pmt { program_number: self.program_number }, table_syntax_header, data);
+            return;
         }
-    }
-}
-
-impl<'buf> PmtSection<'buf> {
-    const HEADER_SIZE: usize = 4;
-
-    pub fn reserved1(&self) -> u8 {
-        self.data[0] >> 5
-    }
-    pub fn pcr_pid(&self) -> u16 {
-        u16::from(self.data[0] & 0b00011111) << 8 | u16::from(self.data[1])
-    }
-    pub fn reserved2(&self) -> u8 {
-        self.data[2] >> 4
-    }
-    pub fn program_info_length(&self) -> u16 {
-        u16::from(self.data[2] & 0b00001111) << 8 | u16::from(self.data[3])
-    }
-    pub fn descriptors(&self) -> descriptor::DescriptorIter {
-        let descriptor_end = Self::HEADER_SIZE + self.program_info_length() as usize;
-        let descriptor_data = &self.data[Self::HEADER_SIZE..descriptor_end];
-        descriptor::DescriptorIter::new(descriptor_data)
-    }
-    pub fn streams(&self) -> StreamInfoIter {
-        let descriptor_end = Self::HEADER_SIZE + self.program_info_length() as usize;
-        if descriptor_end > self.data.len() {
-            panic!("program_info_length={} extends beyond end of PMT section (section_length={})", self.program_info_length(), self.data.len());
+        let start = psi::SectionCommonHeader::SIZE+psi::TableSyntaxHeader::SIZE;
+        if data.len() < start + PmtSection::HEADER_SIZE + 4 {
+            warn!("PMT section of size {} is too small to hold the PMT header and CRC", data.len());
+            return;
+        }
+        if let Some(stats) = ctx.psi_stats_map_mut() {
+            stats.set_version(self.pid, table_syntax_header.version());
+        }
+        let end = data.len() - 4;  // remove CRC bytes
+        self.new_table(ctx, table_syntax_header, &PmtSection::new(&data[start..end]));
+        if let Some(map) = ctx.program_map_mut() {
+            map.set_pmt_section(self.program_number, data);
         }
-        StreamInfoIter::new(&self.data[descriptor_end..])
     }
-}
-pub struct StreamInfoIter<'buf> {
-    buf: &'buf[u8],
-}
-impl<'buf> StreamInfoIter<'buf> {
-   fn new(buf: &'buf[u8]) -> StreamInfoIter<'buf> {
-       StreamInfoIter { buf }
-   }
-}
-impl<'buf> Iterator for StreamInfoIter<'buf> {
-    type Item = StreamInfo<'buf>;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.buf.is_empty() {
-            return None;
-        }
-        if let Some((stream_info, info_len)) = StreamInfo::from_bytes(self.buf) {
-            self.buf = &self.buf[info_len..];
-            Some(stream_info)
-        } else {
-            None
+    fn crc_error(&mut self, ctx: &mut Self::Context, _header: &psi::SectionCommonHeader, _table_syntax_header: &psi::TableSyntaxHeader) {
+        if let Some(stats) = ctx.psi_stats_map_mut() {
+            stats.crc_fail(self.pid);
         }
     }
 }
 
 // ---- PAT ----
 
-pub struct PmtPacketFilter<Ctx: DemuxContext + 'static> {
-    pmt_section_packet_consumer: psi::SectionPacketConsumer<
-        psi::SectionSyntaxSectionProcessor<
+type PmtSectionPacketConsumer<Ctx> = psi::SectionPacketConsumer<
+    psi::SectionSyntaxSectionProcessor<
+        PsiStatsSectionSyntaxPayloadParser<
+            Ctx,
             psi::DedupSectionSyntaxPayloadParser<
                 psi::BufferSectionSyntaxParser<
                     psi::CrcCheckWholeSectionSyntaxPayloadParser<
@@ -445,7 +872,11 @@ pub struct PmtPacketFilter<Ctx: DemuxContext + 'static> {
                 >
             >
         >
-    >,
+    >
+>;
+
+pub struct PmtPacketFilter<Ctx: DemuxContext + 'static> {
+    pmt_section_packet_consumer: PmtSectionPacketConsumer<Ctx>,
 }
 impl<Ctx: DemuxContext> PmtPacketFilter<Ctx> {
     pub fn new(pid: u16, program_number: u16) -> PmtPacketFilter<Ctx> {
@@ -453,10 +884,13 @@ impl<Ctx: DemuxContext> PmtPacketFilter<Ctx> {
         PmtPacketFilter {
             pmt_section_packet_consumer: psi::SectionPacketConsumer::new(
                 psi::SectionSyntaxSectionProcessor::new(
-                    psi::DedupSectionSyntaxPayloadParser::new(
-                        psi::BufferSectionSyntaxParser::new(
-                            psi::CrcCheckWholeSectionSyntaxPayloadParser::new(
-                                pmt_proc
+                    PsiStatsSectionSyntaxPayloadParser::new(
+                        pid,
+                        psi::DedupSectionSyntaxPayloadParser::new(
+                            psi::BufferSectionSyntaxParser::new(
+                                psi::CrcCheckWholeSectionSyntaxPayloadParser::new(
+                                    pmt_proc
+                                )
                             )
                         )
                     )
@@ -471,33 +905,67 @@ impl<Ctx: DemuxContext> PacketFilter for PmtPacketFilter<Ctx> {
     fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet) {
         self.pmt_section_packet_consumer.consume(ctx, pk);
     }
+    fn needs_payload(&self) -> bool {
+        true
+    }
 }
 
 pub struct PatProcessor<Ctx: DemuxContext> {
+    pid: u16,
     current_version: Option<u8>,
     filters_registered: fixedbitset::FixedBitSet,
     phantom: marker::PhantomData<Ctx>,
 }
 
+impl<Ctx: DemuxContext> Default for PatProcessor<Ctx> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Ctx: DemuxContext> PatProcessor<Ctx> {
+    /// Creates a processor for the PAT carried on PID 0, as required by _ISO/IEC 13818-1_.
     pub fn new() -> PatProcessor<Ctx> {
+        Self::with_pid(0)
+    }
+
+    /// Creates a processor for a PAT which has been remapped to the given PID, which is then
+    /// reported to the `DemuxContext` hooks and used for the PAT's `psi_stats()`.
+    pub fn with_pid(pid: u16) -> PatProcessor<Ctx> {
         PatProcessor {
+            pid,
             current_version: None,
             filters_registered: fixedbitset::FixedBitSet::with_capacity(0x2000),
             phantom: marker::PhantomData,
         }
     }
 
-    fn new_table(&mut self, ctx: &mut Ctx, header: &psi::SectionCommonHeader, table_syntax_header: &psi::TableSyntaxHeader, sect: &PatSection) {
-        if 0x00 != header.table_id {
-            println!("Expected PAT to have table id 0x0, but got {:#x}", header.table_id);
-            return;
+    fn new_table(&mut self, ctx: &mut Ctx, table_syntax_header: &psi::TableSyntaxHeader, sect: &PatSection) {
+        if let Some(old_version) = self.current_version {
+            if old_version != table_syntax_header.version() {
+                ctx.table_version_changed(TableKind::Pat, old_version, table_syntax_header.version());
+            }
         }
         let mut pids_seen = HashSet::new();
+        let mut programs_seen = HashSet::new();
+        let mut program_numbers = vec!();
+        let mut entries = vec!();
         // add or update filters for descriptors we've not seen before,
         for desc in sect.programs() {
-            println!("new table for pid {}, program {}", desc.pid(), desc.program_number());
-            let filter = ctx.filter_constructor().construct(FilterRequest::Pmt {pid: desc.pid(), program_number: desc.program_number() });
+            entries.push(PatEntry { program_number: desc.program_number(), pid: desc.pid() });
+            let filter = if desc.program_number() == 0 {
+                debug!("new table for pid {}, network information", desc.pid());
+                ctx.filter_constructor().construct(FilterRequest::Nit { pid: desc.pid() })
+            } else {
+                debug!("new table for pid {}, program {}", desc.pid(), desc.program_number());
+                if let Some(map) = ctx.program_map_mut() {
+                    map.insert_program(desc.program_number(), desc.pid());
+                }
+                if programs_seen.insert(desc.program_number()) {
+                    program_numbers.push(desc.program_number());
+                }
+                ctx.filter_constructor().construct(FilterRequest::Pmt { pid: desc.pid(), program_number: desc.program_number() })
+            };
             ctx.filter_changeset().insert(desc.pid(), filter);
             pids_seen.insert(desc.pid());
             self.filters_registered.insert(desc.pid() as usize);
@@ -510,6 +978,20 @@ impl<Ctx: DemuxContext> PatProcessor<Ctx> {
                 self.filters_registered.set(pid, false);
             }
         }
+        if let Some(map) = ctx.program_map_mut() {
+            map.retain_programs(&programs_seen);
+            map.transport_stream_id = Some(table_syntax_header.id());
+        }
+        if let Some(sink) = ctx.event_sink() {
+            sink.on_pat(&PatSnapshot {
+                transport_stream_id: table_syntax_header.id(),
+                version: table_syntax_header.version(),
+                programs: entries,
+            });
+        }
+        if self.current_version.is_none() {
+            ctx.pat_ready(&program_numbers);
+        }
 
         self.current_version = Some(table_syntax_header.version());
     }
@@ -519,63 +1001,465 @@ impl<Ctx: DemuxContext> PatProcessor<Ctx> {
 impl<Ctx: DemuxContext> psi::WholeSectionSyntaxPayloadParser for PatProcessor<Ctx> {
     type Context = Ctx;
 
-    fn section<'a>(&mut self, ctx: &mut Self::Context, header: &psi::SectionCommonHeader, table_syntax_header: &psi::TableSyntaxHeader, data: &'a [u8]) {
+    fn section(&mut self, ctx: &mut Self::Context, header: &psi::SectionCommonHeader, table_syntax_header: &psi::TableSyntaxHeader, data: &[u8]) {
+        if let Some(stats) = ctx.psi_stats_map_mut() {
+            stats.crc_ok(self.pid);
+        }
+        if header.table_id != PatSection::TABLE_ID {
+            debug!("[PAT] ignoring section with table_id {:#x}", header.table_id);
+            ctx.unexpected_table(self.pid, TableKind::Pat, header, table_syntax_header, data);
+            return;
+        }
+        ctx.raw_section(self.pid, TableKind::Pat, data);
+        if table_syntax_header.current_next_indicator() == psi::CurrentNext::Next {
+            debug!("[PAT] ignoring section version {} which is not yet applicable", table_syntax_header.version());
+            ctx.next_section(self.pid, TableKind::Pat, table_syntax_header, data);
+            return;
+        }
         let start = psi::SectionCommonHeader::SIZE+psi::TableSyntaxHeader::SIZE;
+        if data.len() < start + 4 {
+            warn!("PAT section of size {} is too small to hold the CRC", data.len());
+            return;
+        }
+        // BufferSectionSyntaxParser limits `data` to the extent given by section_length, so any
+        // stuffing bytes following the section in the TS packet are never seen here, and will not
+        // be misinterpreted as program entries
+        if let Some(stats) = ctx.psi_stats_map_mut() {
+            stats.set_version(self.pid, table_syntax_header.version());
+        }
         let end = data.len() - 4;  // remove CRC bytes
-        self.new_table(ctx, header, table_syntax_header, &PatSection::new(&data[start..end]));
+        self.new_table(ctx, table_syntax_header, &PatSection::new(&data[start..end]));
+        if let Some(map) = ctx.program_map_mut() {
+            map.set_pat_section(data);
+        }
+    }
+
+    fn crc_error(&mut self, ctx: &mut Self::Context, _header: &psi::SectionCommonHeader, _table_syntax_header: &psi::TableSyntaxHeader) {
+        if let Some(stats) = ctx.psi_stats_map_mut() {
+            stats.crc_fail(self.pid);
+        }
+    }
+}
+
+// ---- program map ----
+
+/// An owned summary of one elementary stream announced in a PMT, held within a
+/// [`ProgramMap`](struct.ProgramMap.html).
+#[derive(Debug,Clone,PartialEq)]
+pub struct StreamEntry {
+    pub elementary_pid: u16,
+    pub stream_type: StreamType,
+    /// The language code from the first `ISO_639_language_descriptor` for this stream, if any
+    pub language: Option<String>,
+    /// A copy of the descriptor data from this stream's `es_info` block
+    pub es_info: Vec<u8>,
+}
+impl StreamEntry {
+    fn from_stream_info(stream_info: &StreamInfo) -> StreamEntry {
+        let language = stream_info.language()
+            .map(|code| String::from_utf8_lossy(&code[..]).into_owned());
+        StreamEntry {
+            elementary_pid: stream_info.elementary_pid(),
+            stream_type: stream_info.stream_type(),
+            language,
+            es_info: stream_info.es_info_bytes().to_vec(),
+        }
+    }
+
+    /// The `component_tag` from this stream's `stream_identifier_descriptor`, if it has one.
+    pub fn component_tag(&self) -> Option<u8> {
+        descriptor::DescriptorIter::new(&self.es_info)
+            .filter_map(|desc| match desc {
+                Ok(descriptor::Descriptor::UserPrivate { tag: StreamIdentifierDescriptor::TAG, payload }) => {
+                    StreamIdentifierDescriptor::new(payload).ok()
+                },
+                _ => None,
+            })
+            .map(|desc| desc.component_tag())
+            .next()
     }
 }
 
-#[derive(Clone,Debug)]
-struct ProgramDescriptor<'buf> {
-    data: &'buf[u8],
+/// An owned summary of one program announced in the PAT, held within a
+/// [`ProgramMap`](struct.ProgramMap.html).
+///
+/// The `pmt_version`, `pcr_pid` and `streams` fields are only populated once the program's PMT
+/// has been received.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ProgramEntry {
+    pub program_number: u16,
+    pub pmt_pid: u16,
+    pub pmt_version: Option<u8>,
+    pub pcr_pid: Option<u16>,
+    pub streams: Vec<StreamEntry>,
+}
+
+/// A subtitle service discovered in the `es_info` of a PMT stream entry; see
+/// [`ProgramMap::subtitle_tracks()`](struct.ProgramMap.html#method.subtitle_tracks).
+#[derive(Debug,Clone,PartialEq)]
+pub struct SubtitleTrack {
+    pub program_number: u16,
+    /// The PID of the elementary stream carrying the subtitles
+    pub pid: u16,
+    /// The _ISO 639-2_ language code given for this subtitle service
+    pub language: String,
+    pub kind: SubtitleKind,
+}
+
+/// Distinguishes DVB subtitles from Teletext subtitles, giving the page identifiers needed to
+/// decode each.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum SubtitleKind {
+    /// DVB subtitles (_EN 300 743_), announced by a `subtitling_descriptor`
+    Dvb {
+        subtitling_type: u8,
+        composition_page_id: u16,
+        ancillary_page_id: u16,
+    },
+    /// A Teletext subtitle page, announced by a `teletext_descriptor`
+    Teletext {
+        teletext_type: u8,
+        magazine_number: u8,
+        page_number: u8,
+    },
+}
+
+/// Accumulates owned copies of the programs and streams discovered while processing PAT and PMT
+/// tables, so that the current state of the transport stream can be inspected at any time (the
+/// borrowed `PatSection` / `PmtSection` values only exist while a table is being processed).
+///
+/// Programs are removed when they disappear from an updated PAT, and a program's streams are
+/// replaced whenever an updated PMT is processed, mirroring the filter changes the demultiplexer
+/// makes.  Available via [`DemuxContext::program_map()`](trait.DemuxContext.html#method.program_map).
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct ProgramMap {
+    programs: BTreeMap<u16, ProgramEntry>,
+    transport_stream_id: Option<u16>,
+    pat_section: Option<Vec<u8>>,
+    pmt_sections: BTreeMap<u16, Vec<u8>>,
+    /// keyed by PCR PID, since several programs may share one
+    pcr_bitrates: BTreeMap<u16, f64>,
 }
+impl ProgramMap {
+    pub fn new() -> ProgramMap {
+        ProgramMap::default()
+    }
+
+    /// The programs currently announced, ordered by `program_number`.
+    pub fn programs(&self) -> impl Iterator<Item=&ProgramEntry> {
+        self.programs.values()
+    }
+
+    pub fn program(&self, program_number: u16) -> Option<&ProgramEntry> {
+        self.programs.get(&program_number)
+    }
+
+    /// Every elementary stream of every program, paired with the `program_number` of the program
+    /// it belongs to.  Ordered by `program_number`, and then in the order the PMT lists the
+    /// streams.  Programs whose PMT has not yet been received contribute no streams.
+    pub fn all_streams(&self) -> impl Iterator<Item=(u16, &StreamEntry)> {
+        self.programs.values()
+            .flat_map(|program| program.streams.iter().map(move |stream| (program.program_number, stream)))
+    }
+
+    /// The `transport_stream_id` given by the most recent version of the PAT (in the
+    /// `table_id_extension` field of its header), identifying this Transport Stream among the
+    /// others within a network.  Returns `None` until the first PAT has been received.
+    pub fn transport_stream_id(&self) -> Option<u16> {
+        self.transport_stream_id
+    }
+
+    /// A copy of the most recent version of the PAT section to pass its CRC check, exactly as
+    /// received (from the `table_id` up to and including the `CRC_32`), for applications which
+    /// need to retransmit it.  Only the most recent section is retained, so for a PAT split
+    /// across several sections, this is whichever section was received last.
+    pub fn latest_pat_bytes(&self) -> Option<&[u8]> {
+        self.pat_section.as_ref().map(|s| &s[..])
+    }
+
+    /// As for `latest_pat_bytes()`, but giving the PMT section of the given program.  Returns
+    /// `None` if the program is unknown or its PMT has not yet been received.
+    pub fn latest_pmt_bytes(&self, program_number: u16) -> Option<&[u8]> {
+        self.pmt_sections.get(&program_number).map(|s| &s[..])
+    }
+
+    /// The PID carrying the PCR for the given program, taken from the most recent version of the
+    /// program's PMT.  Returns `None` if the program is unknown, if its PMT has not yet been
+    /// received, or if the PMT indicates that the program has no PCR (by giving the null PID).
+    pub fn pcr_pid(&self, program_number: u16) -> Option<u16> {
+        self.program(program_number)
+            .and_then(|p| p.pcr_pid)
+            .filter(|&pid| pid != packet::NULL_PID)
+    }
+
+    /// The Transport Stream bitrate, in bits per second, measured over the most recent interval
+    /// between PCRs on the given program's PCR PID (see
+    /// [`pcr::PcrBitrate`](../pcr/struct.PcrBitrate.html)).  Since every packet transmitted in
+    /// the interval is counted, this is the rate of the whole multiplex as timed by this program's
+    /// clock, rather than the rate of this program's streams alone.
+    ///
+    /// Returns `None` unless enabled by
+    /// [`DemultiplexBuilder::program_bitrate()`](struct.DemultiplexBuilder.html#method.program_bitrate),
+    /// and until two PCRs have been received for the program.
+    pub fn program_bitrate(&self, program_number: u16) -> Option<f64> {
+        self.pcr_pid(program_number)
+            .and_then(|pid| self.pcr_bitrates.get(&pid).cloned())
+    }
+
+    /// As for [`PmtSection::has_dedicated_pcr_pid()`](struct.PmtSection.html#method.has_dedicated_pcr_pid),
+    /// using the most recent version of the given program's PMT.  Returns `None` if the program
+    /// is unknown or its PMT has not yet been received.
+    pub fn has_dedicated_pcr_pid(&self, program_number: u16) -> Option<bool> {
+        let program = self.program(program_number)?;
+        let pcr_pid = program.pcr_pid?;
+        Some(pcr_pid != packet::NULL_PID && !program.streams.iter().any(|s| s.elementary_pid == pcr_pid))
+    }
+
+    /// The PID of the elementary stream of the given program whose `stream_identifier_descriptor`
+    /// gives the `component_tag` value, as used by the `ComponentDescriptor` entries of the EIT
+    /// and SDT to refer to a particular stream.  Returns `None` if the program is unknown, its PMT
+    /// has not yet been received, or none of its streams has the tag.
+    pub fn pid_for_component_tag(&self, program_number: u16, component_tag: u8) -> Option<u16> {
+        self.program(program_number)?
+            .streams.iter()
+            .find(|stream| stream.component_tag() == Some(component_tag))
+            .map(|stream| stream.elementary_pid)
+    }
+
+    /// The DVB and Teletext subtitle services announced in the PMTs received so far, ordered by
+    /// `program_number`, and then in the order the PMT lists them.  Teletext pages which do not
+    /// carry subtitles are not included.
+    pub fn subtitle_tracks(&self) -> Vec<SubtitleTrack> {
+        let mut tracks = vec!();
+        for program in self.programs() {
+            for stream in &program.streams {
+                for desc in descriptor::DescriptorIter::new(&stream.es_info) {
+                    let (tag, payload) = match desc {
+                        Ok(descriptor::Descriptor::UserPrivate { tag, payload }) => (tag, payload),
+                        _ => continue,
+                    };
+                    let track = |language_code: [u8; 3], kind| SubtitleTrack {
+                        program_number: program.program_number,
+                        pid: stream.elementary_pid,
+                        language: String::from_utf8_lossy(&language_code[..]).into_owned(),
+                        kind,
+                    };
+                    match tag {
+                        SubtitlingDescriptor::TAG => {
+                            if let Ok(desc) = SubtitlingDescriptor::new(payload) {
+                                tracks.extend(desc.subtitles().map(|s| track(s.language_code, SubtitleKind::Dvb {
+                                    subtitling_type: s.subtitling_type,
+                                    composition_page_id: s.composition_page_id,
+                                    ancillary_page_id: s.ancillary_page_id,
+                                })));
+                            }
+                        },
+                        TeletextDescriptor::TAG => {
+                            if let Ok(desc) = TeletextDescriptor::new(payload) {
+                                tracks.extend(desc.pages().filter(|p| p.is_subtitle()).map(|p| track(p.language_code, SubtitleKind::Teletext {
+                                    teletext_type: p.teletext_type,
+                                    magazine_number: p.magazine_number,
+                                    page_number: p.page_number,
+                                })));
+                            }
+                        },
+                        _ => (),
+                    }
+                }
+            }
+        }
+        tracks
+    }
 
-impl<'buf> ProgramDescriptor<'buf> {
-    /// panics if fewer than 4 bytes are provided
-    pub fn from_bytes(data: &'buf[u8]) -> ProgramDescriptor<'buf> {
-        ProgramDescriptor {
-            data,
+    fn insert_program(&mut self, program_number: u16, pmt_pid: u16) {
+        let entry = self.programs.entry(program_number).or_insert_with(|| ProgramEntry {
+            program_number,
+            pmt_pid,
+            pmt_version: None,
+            pcr_pid: None,
+            streams: vec!(),
+        });
+        if entry.pmt_pid != pmt_pid {
+            // the PMT moved, so details from the old PID no longer apply
+            entry.pmt_pid = pmt_pid;
+            entry.pmt_version = None;
+            entry.pcr_pid = None;
+            entry.streams.clear();
+            self.pmt_sections.remove(&program_number);
         }
     }
 
-    pub fn program_number(&self) -> u16 {
-        (u16::from(self.data[0]) << 8) | u16::from(self.data[1])
+    fn clear(&mut self) {
+        self.programs.clear();
+        self.transport_stream_id = None;
+        self.pat_section = None;
+        self.pmt_sections.clear();
+        self.pcr_bitrates.clear();
+    }
+
+    fn set_pcr_bitrate(&mut self, pcr_pid: u16, bitrate: f64) {
+        self.pcr_bitrates.insert(pcr_pid, bitrate);
+    }
+
+    fn retain_programs(&mut self, program_numbers: &HashSet<u16>) {
+        self.programs.retain(|program_number, _| program_numbers.contains(program_number));
+        self.pmt_sections.retain(|program_number, _| program_numbers.contains(program_number));
+    }
+
+    fn set_pat_section(&mut self, data: &[u8]) {
+        let buf = self.pat_section.get_or_insert_with(Vec::new);
+        buf.clear();
+        buf.extend_from_slice(data);
+    }
+
+    fn set_pmt_section(&mut self, program_number: u16, data: &[u8]) {
+        if self.programs.contains_key(&program_number) {
+            let buf = self.pmt_sections.entry(program_number).or_default();
+            buf.clear();
+            buf.extend_from_slice(data);
+        }
+    }
 
+    fn update_program(&mut self, program_number: u16, pmt_pid: u16, pmt_version: u8, pcr_pid: u16, streams: Vec<StreamEntry>) {
+        self.insert_program(program_number, pmt_pid);
+        let entry = self.programs.get_mut(&program_number).unwrap();
+        entry.pmt_version = Some(pmt_version);
+        entry.pcr_pid = Some(pcr_pid);
+        entry.streams = streams;
     }
+}
 
-    pub fn pid(&self) -> u16 {
-        (u16::from(self.data[2]) & 0b00011111) << 8 | u16::from(self.data[3])
+// ---- PSI stats ----
+
+/// Counters for the PSI sections seen on a single PID, held within a
+/// [`PsiStatsMap`](struct.PsiStatsMap.html).
+///
+/// Repeated copies of a section with an unchanged `version_number` are counted in
+/// `sections_seen()`, but are then discarded without having their CRC checked, so `crc_ok()` and
+/// `crc_fail()` only count the sections which were actually checked.
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct PsiStats {
+    version: Option<u8>,
+    crc_ok: u64,
+    crc_fail: u64,
+    sections_seen: u64,
+}
+impl PsiStats {
+    /// The `version_number` of the last section on this PID to be processed as the current
+    /// version of its table, if any.  Sections which pass their CRC check but are then ignored
+    /// (being not yet applicable, or having an unexpected `table_id`) do not change this value.
+    pub fn version(&self) -> Option<u8> {
+        self.version
+    }
+    pub fn crc_ok(&self) -> u64 {
+        self.crc_ok
+    }
+    pub fn crc_fail(&self) -> u64 {
+        self.crc_fail
+    }
+    /// The number of sections started on this PID, including duplicates.
+    pub fn sections_seen(&self) -> u64 {
+        self.sections_seen
     }
 }
 
-#[derive(Clone,Debug)]
-pub struct PatSection<'buf> {
-    data: &'buf[u8],
+/// Accumulates [`PsiStats`](struct.PsiStats.html) for each PID carrying PAT or PMT sections.
+/// Available via [`DemuxContext::psi_stats_map()`](trait.DemuxContext.html#method.psi_stats_map).
+///
+/// Comparing the values periodically allows an application to tell which tables are stable, and
+/// which are changing version or suffering corruption.
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct PsiStatsMap {
+    stats: BTreeMap<u16, PsiStats>,
 }
-impl<'buf> PatSection<'buf> {
-    fn new(data: &'buf[u8]) -> PatSection<'buf> {
-        PatSection {
-            data,
+impl PsiStatsMap {
+    pub fn new() -> PsiStatsMap {
+        PsiStatsMap::default()
+    }
+
+    pub fn get(&self, pid: u16) -> Option<&PsiStats> {
+        self.stats.get(&pid)
+    }
+
+    /// Produces `(pid, stats)` for each PID on which at least one section has been seen, ordered
+    /// by PID.
+    pub fn pids(&self) -> impl Iterator<Item=(u16, &PsiStats)> {
+        self.stats.iter().map(|(&pid, stats)| (pid, stats))
+    }
+
+    /// Sets all counters back to zero.  The last-seen versions are retained.
+    pub fn reset(&mut self) {
+        for stats in self.stats.values_mut() {
+            stats.crc_ok = 0;
+            stats.crc_fail = 0;
+            stats.sections_seen = 0;
         }
     }
-    fn programs(&self) -> ProgramIter {
-        ProgramIter { buf: &self.data[..] }
+
+    /// Forgets all PIDs, including their last-seen versions.
+    fn clear(&mut self) {
+        self.stats.clear();
+    }
+
+    fn entry(&mut self, pid: u16) -> &mut PsiStats {
+        self.stats.entry(pid).or_default()
+    }
+    fn section_seen(&mut self, pid: u16) {
+        self.entry(pid).sections_seen += 1;
+    }
+    fn crc_ok(&mut self, pid: u16) {
+        self.entry(pid).crc_ok += 1;
     }
+    fn set_version(&mut self, pid: u16, version: u8) {
+        self.entry(pid).version = Some(version);
+    }
+    fn crc_fail(&mut self, pid: u16) {
+        self.entry(pid).crc_fail += 1;
+    }
+}
+
+/// Counts every section passed through to the wrapped parser in the context's
+/// [`PsiStatsMap`](struct.PsiStatsMap.html), before any deduplication takes place.
+pub struct PsiStatsSectionSyntaxPayloadParser<Ctx, SSPP>
+where
+    Ctx: DemuxContext,
+    SSPP: psi::SectionSyntaxPayloadParser<Context=Ctx>
+{
+    pid: u16,
+    inner: SSPP,
 }
-struct ProgramIter<'buf> {
-    buf: &'buf[u8],
+impl<Ctx, SSPP> PsiStatsSectionSyntaxPayloadParser<Ctx, SSPP>
+where
+    Ctx: DemuxContext,
+    SSPP: psi::SectionSyntaxPayloadParser<Context=Ctx>
+{
+    pub fn new(pid: u16, inner: SSPP) -> PsiStatsSectionSyntaxPayloadParser<Ctx, SSPP> {
+        PsiStatsSectionSyntaxPayloadParser {
+            pid,
+            inner,
+        }
+    }
 }
-impl<'buf> Iterator for ProgramIter<'buf> {
-    type Item = ProgramDescriptor<'buf>;
+impl<Ctx, SSPP> psi::SectionSyntaxPayloadParser for PsiStatsSectionSyntaxPayloadParser<Ctx, SSPP>
+where
+    Ctx: DemuxContext,
+    SSPP: psi::SectionSyntaxPayloadParser<Context=Ctx>
+{
+    type Context = Ctx;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.buf.is_empty() {
-            return None;
+    fn start_syntax_section(&mut self, ctx: &mut Self::Context, header: &psi::SectionCommonHeader, table_syntax_header: &psi::TableSyntaxHeader, data: &[u8]) {
+        if let Some(stats) = ctx.psi_stats_map_mut() {
+            stats.section_seen(self.pid);
         }
-        let (head, tail) = self.buf.split_at(4);
-        self.buf = tail;
-        Some(ProgramDescriptor::from_bytes(head))
+        self.inner.start_syntax_section(ctx, header, table_syntax_header, data);
+    }
+
+    fn continue_syntax_section(&mut self, ctx: &mut Self::Context, data: &[u8]) {
+        self.inner.continue_syntax_section(ctx, data);
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
     }
 }
 
@@ -586,11 +1470,20 @@ impl<'buf> Iterator for ProgramIter<'buf> {
 /// order to have diagnostic logging for packets within the Transport Stream that were not
 /// announced in the PAT or PMT tables.
 ///
+/// If the context provides an [`EventSink`](trait.EventSink.html), the PID is reported to
+/// `on_unhandled_pid()` instead of being logged.
+///
 /// If you do not want those diagnostic messages, use `NullPacketFilter` as the default instead.
 pub struct UnhandledPid<Ctx: DemuxContext> {
     pid_seen: bool,
     phantom: marker::PhantomData<Ctx>,
 }
+impl<Ctx: DemuxContext> Default for UnhandledPid<Ctx> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Ctx: DemuxContext> UnhandledPid<Ctx> {
     pub fn new() -> UnhandledPid<Ctx> {
         UnhandledPid {
@@ -601,25 +1494,276 @@ impl<Ctx: DemuxContext> UnhandledPid<Ctx> {
 }
 impl<Ctx: DemuxContext> PacketFilter for UnhandledPid<Ctx> {
     type Ctx = Ctx;
-    fn consume(&mut self, _ctx: &mut Self::Ctx, pk: packet::Packet) {
+    fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet) {
         if !self.pid_seen {
-            println!("unhandled pid {}", pk.pid());
+            match ctx.event_sink() {
+                Some(sink) => sink.on_unhandled_pid(pk.pid()),
+                None => warn!("unhandled pid {}", pk.pid()),
+            }
             self.pid_seen = true;
         }
     }
 }
 
+/// Identifies the kind of PSI table passed to
+/// [`DemuxContext::table_version_changed()`](trait.DemuxContext.html#method.table_version_changed).
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum TableKind {
+    Pat,
+    Pmt { program_number: u16 },
+}
+
+/// An owned copy of the content of a PAT, passed to
+/// [`EventSink::on_pat()`](trait.EventSink.html#method.on_pat).
+#[derive(Debug,Clone,PartialEq)]
+pub struct PatSnapshot {
+    pub transport_stream_id: u16,
+    pub version: u8,
+    pub programs: Vec<PatEntry>,
+}
+
+/// A single program announced in a [`PatSnapshot`](struct.PatSnapshot.html).  A
+/// `program_number` of `0` indicates that `pid` is the `network_PID`, rather than the PID of a PMT.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct PatEntry {
+    pub program_number: u16,
+    pub pid: u16,
+}
+
+/// An owned copy of the content of a PMT, passed to
+/// [`EventSink::on_pmt()`](trait.EventSink.html#method.on_pmt).
+#[derive(Debug,Clone,PartialEq)]
+pub struct PmtSnapshot {
+    pub pid: u16,
+    pub version: u8,
+    pub pcr_pid: u16,
+    pub streams: Vec<StreamEntry>,
+}
+
+/// Receives notifications of changes to the structure of the Transport Stream, as an alternative
+/// to implementing `PacketFilter` or `StreamConstructor` for applications that only need to know
+/// which programs and streams are present.
+///
+/// Each method has a default implementation which does nothing, so implementations need only
+/// provide the methods for the events they are interested in.  Events are delivered to the sink
+/// returned by [`DemuxContext::event_sink()`](trait.DemuxContext.html#method.event_sink); the
+/// simplest way to get started is with an [`EventDemuxContext`](struct.EventDemuxContext.html).
+pub trait EventSink {
+    /// Called when the first PAT is processed, and again each time its `version_number` changes.
+    fn on_pat(&mut self, _pat: &PatSnapshot) {
+    }
+
+    /// Called when the first PMT for the given program is processed, and again each time its
+    /// `version_number` changes.
+    fn on_pmt(&mut self, _program_number: u16, _pmt: &PmtSnapshot) {
+    }
+
+    /// Called the first time a packet is seen on a PID which was not announced by the PAT or any
+    /// PMT, if [`UnhandledPid`](struct.UnhandledPid.html) is the filter for that PID.
+    fn on_unhandled_pid(&mut self, _pid: u16) {
+    }
+}
+
 pub trait DemuxContext: Sized {
     type F: PacketFilter<Ctx=Self>;
     type Ctor: StreamConstructor<F=Self::F>;
 
     fn filter_changeset(&mut self) -> &mut FilterChangeset<Self::F>;
     fn filter_constructor(&mut self) -> &mut Self::Ctor;
+
+    /// The programs and streams announced by the PAT and PMT tables processed so far, or `None`
+    /// if the context does not keep track of them.
+    ///
+    /// The default implementation returns `None`, while the type created by
+    /// [`demux_context!()`](../macro.demux_context.html) keeps the map up to date.
+    fn program_map(&self) -> Option<&ProgramMap> {
+        None
+    }
+
+    /// Used by the PAT and PMT processing to update the map returned by `program_map()`.  The
+    /// default implementation returns `None`, in which case the map is not maintained.
+    fn program_map_mut(&mut self) -> Option<&mut ProgramMap> {
+        None
+    }
+
+    /// Shorthand for `program_map().all_streams()`, giving no streams if there is no
+    /// `program_map()`.
+    fn all_streams(&self) -> impl Iterator<Item=(u16, &StreamEntry)> {
+        self.program_map().into_iter().flat_map(|map| map.all_streams())
+    }
+
+    /// Shorthand for `program_map().pcr_pid(program_number)`.
+    fn pcr_pid(&self, program_number: u16) -> Option<u16> {
+        self.program_map()?.pcr_pid(program_number)
+    }
+
+    /// Shorthand for `program_map().transport_stream_id()`.
+    fn transport_stream_id(&self) -> Option<u16> {
+        self.program_map()?.transport_stream_id()
+    }
+
+    /// Shorthand for `program_map().program_bitrate(program_number)`.
+    fn program_bitrate(&self, program_number: u16) -> Option<f64> {
+        self.program_map()?.program_bitrate(program_number)
+    }
+
+    /// Shorthand for `program_map().pid_for_component_tag(program_number, component_tag)`.
+    fn pid_for_component_tag(&self, program_number: u16, component_tag: u8) -> Option<u16> {
+        self.program_map()?.pid_for_component_tag(program_number, component_tag)
+    }
+
+    /// Shorthand for `program_map().subtitle_tracks()`, giving no tracks if there is no
+    /// `program_map()`.
+    fn subtitle_tracks(&self) -> Vec<SubtitleTrack> {
+        self.program_map().map_or_else(Vec::new, |map| map.subtitle_tracks())
+    }
+
+    /// Shorthand for `program_map().latest_pat_bytes()`.
+    fn latest_pat_bytes(&self) -> Option<&[u8]> {
+        self.program_map()?.latest_pat_bytes()
+    }
+
+    /// Shorthand for `program_map().latest_pmt_bytes(program_number)`.
+    fn latest_pmt_bytes(&self, program_number: u16) -> Option<&[u8]> {
+        self.program_map()?.latest_pmt_bytes(program_number)
+    }
+
+    /// Statistics on the PSI sections processed so far, by PID, or `None` if the context does not
+    /// keep track of them.
+    ///
+    /// The default implementation returns `None`, while the type created by
+    /// [`demux_context!()`](../macro.demux_context.html) keeps the statistics up to date.
+    fn psi_stats_map(&self) -> Option<&PsiStatsMap> {
+        None
+    }
+
+    /// Used by the PAT and PMT processing to update the statistics returned by
+    /// `psi_stats_map()`.  The default implementation returns `None`, in which case no
+    /// statistics are kept.
+    fn psi_stats_map_mut(&mut self) -> Option<&mut PsiStatsMap> {
+        None
+    }
+
+    /// Shorthand for `psi_stats_map().get(pid)`.
+    fn psi_stats(&self, pid: u16) -> Option<&PsiStats> {
+        self.psi_stats_map()?.get(pid)
+    }
+
+    /// Called when a PAT or PMT is received with a `version_number` different to the version of
+    /// that table which was processed previously.  Not called for the first instance of a table.
+    ///
+    /// The default implementation does nothing.  Note that the type created by
+    /// [`demux_context!()`](../macro.demux_context.html) uses this default, so implement
+    /// `DemuxContext` directly to observe these changes.
+    fn table_version_changed(&mut self, _table: TableKind, _old_version: u8, _new_version: u8) {
+    }
+
+    /// Called for each new version of a program's PMT, before filters are requested from the
+    /// `StreamConstructor` for any of its streams.  The program-level descriptors (for example a
+    /// `CA_descriptor` giving the PID of the ECM stream) are available from `pmt.descriptors()`,
+    /// so that the application can prepare for the streams which follow.
+    ///
+    /// The default implementation does nothing.
+    fn pmt_updated(&mut self, _program_number: u16, _pmt: &PmtSection) {
+    }
+
+    /// Called when a section on the PID of the PAT or of a PMT has a `table_id` other than that
+    /// of the expected table.  Some multiplexers carry private tables on the same PID as the PMT,
+    /// and these are otherwise ignored; this hook allows the application to notice them, or to
+    /// parse them itself.  `data` is the whole section, which has passed its CRC check.
+    ///
+    /// The default implementation does nothing.
+    fn unexpected_table(&mut self, _pid: u16, _expected: TableKind, _header: &psi::SectionCommonHeader, _table_syntax_header: &psi::TableSyntaxHeader, _data: &[u8]) {
+    }
+
+    /// Called with the complete bytes of each PAT or PMT section as received (from the `table_id`
+    /// up to and including the `CRC_32`), after the CRC has been checked, and before the section
+    /// is parsed.  This allows sections to be stored verbatim, for example so that they can later
+    /// be retransmitted without being re-encoded.
+    ///
+    /// Repeated copies of a section with an unchanged `version_number` are discarded before this
+    /// point, so are not passed to this method.
+    ///
+    /// The default implementation does nothing.
+    fn raw_section(&mut self, _pid: u16, _table: TableKind, _data: &[u8]) {
+    }
+
+    /// Called for each PAT or PMT section whose `current_next_indicator` is
+    /// `CurrentNext::Next`, announcing a version of the table which is not yet applicable.  Such
+    /// sections are otherwise ignored, until the same version is transmitted as the current
+    /// table, so this hook lets tools such as conformance checkers observe scheduled table
+    /// changes.  `data` is the whole section, which has passed its CRC check.
+    ///
+    /// Unlike current sections, repeated copies of a 'next' section are not discarded, so this is
+    /// called for every copy received.
+    ///
+    /// The default implementation does nothing.
+    fn next_section(&mut self, _pid: u16, _table: TableKind, _table_syntax_header: &psi::TableSyntaxHeader, _data: &[u8]) {
+    }
+
+    /// Called once, when the first valid PAT has been processed, with the `program_number` of
+    /// each program it announces (in the order they appear in the PAT, and excluding any entry
+    /// for the NIT).  By this point the programs are present in `program_map()`, and filters for
+    /// their PMTs have been requested, so an application which waits for the PAT before setting
+    /// up its own processing need not poll for it.  The PMTs themselves will not yet have been
+    /// seen; `pmt_updated()` is called as each one arrives.
+    ///
+    /// Later versions of the PAT are reported through `table_version_changed()` instead.  After
+    /// [`Demultiplex::reset()`](struct.Demultiplex.html#method.reset), this is called again for
+    /// the next PAT.
+    ///
+    /// The default implementation does nothing.
+    fn pat_ready(&mut self, _program_numbers: &[u16]) {
+    }
+
+    /// Called by `Demultiplex` before each packet is processed, with the byte offset of the start
+    /// of that packet within the stream (see
+    /// [`Demultiplex::stream_offset()`](struct.Demultiplex.html#method.stream_offset)).  Any
+    /// section parsed, or `pmt_updated()` call made, while processing the packet can therefore be
+    /// located in the input (for example to build an index for seeking within a recording), by
+    /// consulting the value stored here.
+    ///
+    /// The default implementation does nothing.  The type created by
+    /// [`demux_context!()`](../macro.demux_context.html) stores the value, to be returned by
+    /// `packet_offset()`.
+    fn set_packet_offset(&mut self, _offset: u64) {
+    }
+
+    /// The value last given to `set_packet_offset()`, i.e. the byte offset of the packet currently
+    /// being processed.  The default implementation returns `None`.
+    fn packet_offset(&self) -> Option<u64> {
+        None
+    }
+
+    /// The PIDs for which the `Demultiplex` has a filter installed, as of the dispatch of the
+    /// current packet, or `None` if the context does not keep track of them.
+    ///
+    /// The default implementation returns `None`, while the type created by
+    /// [`demux_context!()`](../macro.demux_context.html) keeps the set up to date.
+    fn filtered_pids(&self) -> Option<&FilteredPids> {
+        None
+    }
+
+    /// Used by `Demultiplex` to update the set returned by `filtered_pids()`.  The default
+    /// implementation returns `None`, in which case the set is not maintained.
+    fn filtered_pids_mut(&mut self) -> Option<&mut FilteredPids> {
+        None
+    }
+
+    /// The [`EventSink`](trait.EventSink.html) to be notified as tables are processed, if any.
+    ///
+    /// The default implementation returns `None`, as does the type created by
+    /// [`demux_context!()`](../macro.demux_context.html).  See
+    /// [`EventDemuxContext`](struct.EventDemuxContext.html) for a context which provides one.
+    fn event_sink(&mut self) -> Option<&mut dyn EventSink> {
+        None
+    }
 }
 
-pub struct PatPacketFilter<Ctx: DemuxContext> {
-    pat_section_packet_consumer: psi::SectionPacketConsumer<
-        psi::SectionSyntaxSectionProcessor<
+type PatSectionPacketConsumer<Ctx> = psi::SectionPacketConsumer<
+    psi::SectionSyntaxSectionProcessor<
+        PsiStatsSectionSyntaxPayloadParser<
+            Ctx,
             psi::DedupSectionSyntaxPayloadParser<
                 psi::BufferSectionSyntaxParser<
                     psi::CrcCheckWholeSectionSyntaxPayloadParser<
@@ -628,17 +1772,43 @@ pub struct PatPacketFilter<Ctx: DemuxContext> {
                 >
             >
         >
-    >,
+    >
+>;
+
+pub struct PatPacketFilter<Ctx: DemuxContext> {
+    pid: u16,
+    pat_section_packet_consumer: PatSectionPacketConsumer<Ctx>,
+}
+impl<Ctx: DemuxContext> Default for PatPacketFilter<Ctx> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
+
 impl<Ctx: DemuxContext> PatPacketFilter<Ctx> {
+    /// Creates a filter for the PAT on PID 0.
+    ///
+    /// Should the filter be installed on some other PID (as `Demultiplex` does when configured
+    /// with [`DemultiplexBuilder::pat_pid()`](struct.DemultiplexBuilder.html#method.pat_pid)),
+    /// it adopts the PID of the first packet it is given, so that the PAT's events and
+    /// statistics are reported against the PID actually carrying it.
     pub fn new() -> PatPacketFilter<Ctx> {
-        let pat_proc = PatProcessor::new();
+        Self::with_pid(0)
+    }
+
+    /// Creates a filter for a PAT carried on the given PID.
+    pub fn with_pid(pid: u16) -> PatPacketFilter<Ctx> {
+        let pat_proc = PatProcessor::with_pid(pid);
         PatPacketFilter {
+            pid,
             pat_section_packet_consumer: psi::SectionPacketConsumer::new(
                 psi::SectionSyntaxSectionProcessor::new(
-                    psi::DedupSectionSyntaxPayloadParser::new(
-                        psi::BufferSectionSyntaxParser::new(
-                            psi::CrcCheckWholeSectionSyntaxPayloadParser::new(pat_proc)
+                    PsiStatsSectionSyntaxPayloadParser::new(
+                        pid,
+                        psi::DedupSectionSyntaxPayloadParser::new(
+                            psi::BufferSectionSyntaxParser::new(
+                                psi::CrcCheckWholeSectionSyntaxPayloadParser::new(pat_proc)
+                            )
                         )
                     )
                 )
@@ -650,129 +1820,1897 @@ impl<Ctx: DemuxContext> PacketFilter for PatPacketFilter<Ctx> {
     type Ctx = Ctx;
 
     fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet) {
+        if pk.pid() != self.pid {
+            *self = Self::with_pid(pk.pid());
+        }
         self.pat_section_packet_consumer.consume(ctx, pk);
     }
+    fn needs_payload(&self) -> bool {
+        true
+    }
 }
 
-pub struct Demultiplex<Ctx: DemuxContext> {
-    processor_by_pid: Filters<Ctx::F>,
+/// The result of checking a packet's `continuity_counter` with
+/// [`ContinuityWatch::check()`](struct.ContinuityWatch.html#method.check).
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum ContinuityStatus {
+    /// This is the first packet seen for the PID, or the PID is the null PID, for which the
+    /// `continuity_counter` is undefined
+    Unknown,
+    /// The `continuity_counter` has the expected value
+    Continuous,
+    /// The packet is a copy of the previous packet with this PID.  The standard permits a packet
+    /// carrying a payload to be sent twice, with only the PCR (if present) allowed to differ, and
+    /// the copy should be ignored by the decoder.
+    Duplicate,
+    /// The `continuity_counter` does not have the expected value; some packets may have been
+    /// lost.  This includes a repeated counter value where the payload differs from that of the
+    /// previous packet.
+    Discontinuity,
 }
-impl<Ctx: DemuxContext> Demultiplex<Ctx> {
-    pub fn new(ctx: &mut Ctx) -> Demultiplex<Ctx> {
-        let mut result = Demultiplex {
-            processor_by_pid: Filters::new(),
-        };
 
-        result.processor_by_pid.insert(0, ctx.filter_constructor().construct(FilterRequest::ByPid(0)));
+/// Tracks the `continuity_counter` of each PID, distinguishing the packet duplication permitted
+/// by the standard from true discontinuities.
+///
+/// In order to tell whether a packet which repeats the previous counter value is really a
+/// duplicate, a copy of the last packet seen on each PID is retained.  Only the 188 byte
+/// transport stream packet is kept and compared, whatever the configured
+/// [`PacketFormat`](../packet/enum.PacketFormat.html), since the `TP_extra_header` of an 'm2ts'
+/// packet records the arrival time of each copy, and FEC parity bytes only restate the packet.
+#[derive(Clone,Default)]
+pub struct ContinuityWatch {
+    last_packet: HashMap<u16, [u8; packet::PACKET_SIZE]>,
+}
+impl ContinuityWatch {
+    /// The position of the `program_clock_reference` field, when the adaptation field has one
+    const PCR_RANGE: ops::Range<usize> = 6..12;
 
-        result
+    pub fn new() -> ContinuityWatch {
+        ContinuityWatch {
+            last_packet: HashMap::new(),
+        }
     }
 
-    pub fn push(&mut self, ctx: &mut Ctx, buf: &[u8]) {
-        // TODO: simplify
-        let mut i=0;
-        loop {
-            let end = i+packet::PACKET_SIZE;
-            if end > buf.len() {
-                break;
-            }
-            let mut pk_buf = &buf[i..end];
-            if packet::Packet::is_sync_byte(pk_buf[0]) {
-                {
-                    let mut pk = packet::Packet::new(pk_buf);
-                    let this_pid = pk.pid();
-                    if !self.processor_by_pid.contains(this_pid) {
-                        let filter = ctx.filter_constructor().construct(FilterRequest::ByPid(this_pid));
-                        self.processor_by_pid.insert(this_pid, filter);
-                    };
-                    let this_proc = self.processor_by_pid.get(this_pid).unwrap();
-                    while ctx.filter_changeset().is_empty() {
-                        this_proc.consume(ctx, pk);
-                        i += packet::PACKET_SIZE;
-                        let end = i+packet::PACKET_SIZE;
-                        if end > buf.len() {
-                            break;
-                        }
-                        pk_buf = &buf[i..end];
-                        if !packet::Packet::is_sync_byte(pk_buf[0]) {
-                            // TODO: attempt to resynchronise
-                            return
-                        }
-                        pk = packet::Packet::new(pk_buf);
-                        if pk.pid() != this_pid {
-                            i -= packet::PACKET_SIZE;
-                            break;
-                        }
+    /// Checks the given packet against the previous packet seen with the same PID, and then
+    /// records it as the previous packet for that PID.
+    pub fn check(&mut self, pk: &packet::Packet) -> ContinuityStatus {
+        let pid = pk.pid();
+        if pid == packet::NULL_PID {
+            // the continuity_counter of null packets is undefined
+            return ContinuityStatus::Unknown;
+        }
+        let this_cc = pk.continuity_counter();
+        let status = match self.last_packet.get(&pid) {
+            None => ContinuityStatus::Unknown,
+            Some(last) => {
+                let last_cc = packet::Packet::new(&last[..]).continuity_counter();
+                // counter only increases if the packet has a payload,
+                if !pk.has_payload() {
+                    if this_cc.count() == last_cc.count() {
+                        ContinuityStatus::Continuous
+                    } else {
+                        ContinuityStatus::Discontinuity
                     }
+                } else if this_cc.follows(last_cc) {
+                    ContinuityStatus::Continuous
+                } else if this_cc.count() == last_cc.count() && Self::same_except_pcr(pk, &last[..]) {
+                    ContinuityStatus::Duplicate
+                } else {
+                    ContinuityStatus::Discontinuity
                 }
-                if !ctx.filter_changeset().is_empty() {
-                    ctx.filter_changeset().apply(&mut self.processor_by_pid);
-                }
-                debug_assert!(ctx.filter_changeset().is_empty());
-            } else {
-                // TODO: attempt to resynchronise
-                return
-            }
-            i += packet::PACKET_SIZE;
+            },
+        };
+        if status != ContinuityStatus::Duplicate {
+            let mut copy = [0u8; packet::PACKET_SIZE];
+            copy.copy_from_slice(pk.buffer());
+            self.last_packet.insert(pid, copy);
         }
+        status
     }
-}
 
-#[cfg(test)]
-mod test {
-    use data_encoding::base16;
-    use bitstream_io::{BE, BitWriter};
-    use std::io;
+    /// `true` if the packet has the same content as the given previous packet, aside from the
+    /// value of any PCR, which a duplicate packet may update.
+    fn same_except_pcr(pk: &packet::Packet, last: &[u8]) -> bool {
+        let buf = pk.buffer();
+        if pk.adaptation_field().is_some_and(|af| af.pcr().is_ok()) {
+            // the adaptation_field_length and flags are compared, so last has a PCR here too
+            buf[..Self::PCR_RANGE.start] == last[..Self::PCR_RANGE.start]
+                && buf[Self::PCR_RANGE.end..] == last[Self::PCR_RANGE.end..]
+        } else {
+            buf == last
+        }
+    }
 
-    use demultiplex;
-    use psi;
-    use psi::WholeSectionSyntaxPayloadParser;
+    /// Forgets the last packet seen on every PID, so that the next packet on each PID will be
+    /// reported as `ContinuityStatus::Unknown`.
+    pub fn clear(&mut self) {
+        self.last_packet.clear();
+    }
+}
 
-    packet_filter_switch!{
-        NullFilterSwitch<NullDemuxContext> {
-            Pat: demultiplex::PatPacketFilter<NullDemuxContext>,
-            Pmt: demultiplex::PmtPacketFilter<NullDemuxContext>,
-            Nul: demultiplex::NullPacketFilter<NullDemuxContext>,
-        }
+/// Per-PID packet counters, maintained by `Demultiplex` when enabled via
+/// [`DemultiplexBuilder::pid_stats()`](struct.DemultiplexBuilder.html#method.pid_stats).
+///
+/// The counts include every packet received, not just those passed on to the filters, so
+/// duplicate packets are counted even when the `Demultiplex` is configured to
+/// `drop_duplicate_packets()`, as are null packets with `skip_null_packets()`.  Packets with the
+/// `transport_error_indicator` set are counted unless the `Demultiplex` is configured to
+/// `skip_errored_packets()`, in which case they are discarded before being counted.
+///
+/// Bitrate can be estimated by periodically reading the packet counts, and multiplying the
+/// number of packets seen in the interval by `packet::PACKET_SIZE * 8`, before calling `reset()`
+/// to begin the next interval.
+#[derive(Clone)]
+pub struct PidStats {
+    total_packets: u64,
+    packets_by_pid: Vec<u64>,
+    continuity_errors_by_pid: Vec<u64>,
+    duplicates_by_pid: Vec<u64>,
+}
+impl Default for PidStats {
+    fn default() -> Self {
+        Self::new()
     }
-    demux_context!(NullDemuxContext, NullStreamConstructor);
+}
+impl PidStats {
+    const PID_COUNT: usize = 0x2000;
 
-    pub struct NullStreamConstructor;
-    impl demultiplex::StreamConstructor for NullStreamConstructor {
-        type F = NullFilterSwitch;
+    pub fn new() -> PidStats {
+        PidStats {
+            total_packets: 0,
+            packets_by_pid: vec![0; Self::PID_COUNT],
+            continuity_errors_by_pid: vec![0; Self::PID_COUNT],
+            duplicates_by_pid: vec![0; Self::PID_COUNT],
+        }
+    }
 
-        fn construct(&mut self, req: demultiplex::FilterRequest) -> Self::F {
-            match req {
-                demultiplex::FilterRequest::ByPid(0) => NullFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
-                demultiplex::FilterRequest::ByPid(_) => NullFilterSwitch::Nul(demultiplex::NullPacketFilter::new()),
-                demultiplex::FilterRequest::ByStream(_stype, _pmt_section, _stream_info) => NullFilterSwitch::Nul(demultiplex::NullPacketFilter::new()),
-                demultiplex::FilterRequest::Pmt{pid, program_number} => NullFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
-            }
+    fn record(&mut self, pk: &packet::Packet, status: ContinuityStatus) {
+        let pid = pk.pid() as usize;
+        self.total_packets += 1;
+        self.packets_by_pid[pid] += 1;
+        match status {
+            ContinuityStatus::Discontinuity => self.continuity_errors_by_pid[pid] += 1,
+            ContinuityStatus::Duplicate => self.duplicates_by_pid[pid] += 1,
+            ContinuityStatus::Unknown | ContinuityStatus::Continuous => (),
         }
     }
 
-    #[test]
-    fn demux_empty() {
-        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
-        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
-        deplex.push(&mut ctx, &[0x0; 0][..]);
+    /// The number of packets seen (for all PIDs) since creation, or the last call to `reset()`.
+    pub fn total_packets(&self) -> u64 {
+        self.total_packets
     }
 
-    #[test]
-    fn pat() {
-        // TODO: better
-        let buf = base16::decode(b"474000150000B00D0001C100000001E1E02D507804FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap();
-        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
-        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
-        deplex.push(&mut ctx, &buf[..]);
+    /// The number of packets seen with the given PID since creation, or the last call to `reset()`.
+    pub fn packet_count(&self, pid: u16) -> u64 {
+        self.packets_by_pid.get(pid as usize).cloned().unwrap_or(0)
     }
 
-    #[test]
-    fn pat_no_existing_program() {
-        let mut processor = demultiplex::PatProcessor::new();
-        let section = vec!(
-            // common header
-            0, 0, 0,
+    /// The number of null packets (PID `0x1fff`) seen since creation, or the last call to
+    /// `reset()`.  These are included in `total_packets()`, whether or not the `Demultiplex` is
+    /// configured to `skip_null_packets()`.
+    pub fn null_packets(&self) -> u64 {
+        self.packet_count(packet::NULL_PID)
+    }
+
+    /// The number of continuity_counter discontinuities detected for the given PID since
+    /// creation, or the last call to `reset()`.
+    pub fn continuity_errors(&self, pid: u16) -> u64 {
+        self.continuity_errors_by_pid.get(pid as usize).cloned().unwrap_or(0)
+    }
+
+    /// The total number of continuity_counter discontinuities detected, over all PIDs.
+    pub fn total_continuity_errors(&self) -> u64 {
+        self.continuity_errors_by_pid.iter().sum()
+    }
+
+    /// The number of duplicate packets (repeating both the continuity_counter and the content
+    /// of the previous packet) detected for the given PID since creation, or the last call to
+    /// `reset()`.  Duplicates are not counted as continuity errors, and are included in
+    /// `packet_count()` even if the `Demultiplex` is configured to `drop_duplicate_packets()`.
+    pub fn duplicate_packets(&self, pid: u16) -> u64 {
+        self.duplicates_by_pid.get(pid as usize).cloned().unwrap_or(0)
+    }
+
+    /// The total number of duplicate packets detected, over all PIDs.
+    pub fn total_duplicate_packets(&self) -> u64 {
+        self.duplicates_by_pid.iter().sum()
+    }
+
+    /// Produces `(pid, packet_count)` for each PID on which at least one packet has been seen.
+    pub fn pids(&self) -> impl Iterator<Item=(u16, u64)> + '_ {
+        self.packets_by_pid.iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(pid, &count)| (pid as u16, count))
+    }
+
+    /// Sets all counters back to zero.  The last-seen continuity_counter values are retained, so
+    /// that discontinuities spanning the reset are still detected.
+    pub fn reset(&mut self) {
+        self.total_packets = 0;
+        for c in self.packets_by_pid.iter_mut() {
+            *c = 0;
+        }
+        for c in self.continuity_errors_by_pid.iter_mut() {
+            *c = 0;
+        }
+        for c in self.duplicates_by_pid.iter_mut() {
+            *c = 0;
+        }
+    }
+}
+
+// ---- events ----
+
+/// The filters used by [`EventDemuxContext`](struct.EventDemuxContext.html), which process the
+/// PAT and PMTs, and otherwise discard packets.
+pub enum EventFilter<S: EventSink + 'static> {
+    Pat(PatPacketFilter<EventDemuxContext<S>>),
+    Pmt(PmtPacketFilter<EventDemuxContext<S>>),
+    Unhandled(UnhandledPid<EventDemuxContext<S>>),
+    Null(NullPacketFilter<EventDemuxContext<S>>),
+}
+impl<S: EventSink + 'static> PacketFilter for EventFilter<S> {
+    type Ctx = EventDemuxContext<S>;
+
+    fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet) {
+        match self {
+            EventFilter::Pat(f) => f.consume(ctx, pk),
+            EventFilter::Pmt(f) => f.consume(ctx, pk),
+            EventFilter::Unhandled(f) => f.consume(ctx, pk),
+            EventFilter::Null(f) => f.consume(ctx, pk),
+        }
+    }
+    fn needs_payload(&self) -> bool {
+        match self {
+            EventFilter::Pat(f) => f.needs_payload(),
+            EventFilter::Pmt(f) => f.needs_payload(),
+            EventFilter::Unhandled(f) => f.needs_payload(),
+            EventFilter::Null(f) => f.needs_payload(),
+        }
+    }
+}
+
+/// The `StreamConstructor` used by [`EventDemuxContext`](struct.EventDemuxContext.html).
+/// Elementary streams and the NIT are ignored, and PIDs not announced in the PAT or a PMT are
+/// reported to the sink by an [`UnhandledPid`](struct.UnhandledPid.html) filter.
+pub struct EventStreamConstructor<S> {
+    phantom: marker::PhantomData<S>,
+}
+impl<S: EventSink + 'static> StreamConstructor for EventStreamConstructor<S> {
+    type F = EventFilter<S>;
+
+    fn construct(&mut self, req: FilterRequest) -> Self::F {
+        match req {
+            FilterRequest::ByPid(0) => EventFilter::Pat(PatPacketFilter::new()),
+            FilterRequest::ByPid(_) => EventFilter::Unhandled(UnhandledPid::new()),
+            FilterRequest::ByStream { .. } => EventFilter::Null(NullPacketFilter::new()),
+            FilterRequest::Pmt{pid, program_number} => EventFilter::Pmt(PmtPacketFilter::new(pid, program_number)),
+            FilterRequest::Nit{..} => EventFilter::Null(NullPacketFilter::new()),
+        }
+    }
+}
+
+/// A ready-made `DemuxContext` which delivers PAT, PMT and unhandled-PID events to the given
+/// [`EventSink`](trait.EventSink.html), for applications that don't need to define their own
+/// filters.
+///
+/// ```
+/// # use mpeg2ts_reader::demultiplex::{Demultiplex, EventDemuxContext, EventSink, PmtSnapshot};
+/// struct Printer;
+/// impl EventSink for Printer {
+///     fn on_pmt(&mut self, program_number: u16, pmt: &PmtSnapshot) {
+///         println!("program {} has {} streams", program_number, pmt.streams.len());
+///     }
+/// }
+///
+/// let mut ctx = EventDemuxContext::new(Printer);
+/// let mut demux = Demultiplex::new(&mut ctx);
+/// # let buf = [0u8; 0];
+/// demux.push(&mut ctx, &buf[..]);
+/// ```
+pub struct EventDemuxContext<S: EventSink + 'static> {
+    changeset: FilterChangeset<EventFilter<S>>,
+    constructor: EventStreamConstructor<S>,
+    program_map: ProgramMap,
+    psi_stats: PsiStatsMap,
+    packet_offset: Option<u64>,
+    filtered_pids: FilteredPids,
+    sink: S,
+}
+impl<S: EventSink + 'static> EventDemuxContext<S> {
+    pub fn new(sink: S) -> EventDemuxContext<S> {
+        EventDemuxContext {
+            changeset: FilterChangeset::new(),
+            constructor: EventStreamConstructor { phantom: marker::PhantomData },
+            program_map: ProgramMap::new(),
+            psi_stats: PsiStatsMap::new(),
+            packet_offset: None,
+            filtered_pids: FilteredPids::new(),
+            sink,
+        }
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+}
+impl<S: EventSink + 'static> DemuxContext for EventDemuxContext<S> {
+    type F = EventFilter<S>;
+    type Ctor = EventStreamConstructor<S>;
+
+    fn filter_changeset(&mut self) -> &mut FilterChangeset<Self::F> {
+        &mut self.changeset
+    }
+    fn filter_constructor(&mut self) -> &mut Self::Ctor {
+        &mut self.constructor
+    }
+    fn program_map(&self) -> Option<&ProgramMap> {
+        Some(&self.program_map)
+    }
+    fn program_map_mut(&mut self) -> Option<&mut ProgramMap> {
+        Some(&mut self.program_map)
+    }
+    fn psi_stats_map(&self) -> Option<&PsiStatsMap> {
+        Some(&self.psi_stats)
+    }
+    fn psi_stats_map_mut(&mut self) -> Option<&mut PsiStatsMap> {
+        Some(&mut self.psi_stats)
+    }
+    fn set_packet_offset(&mut self, offset: u64) {
+        self.packet_offset = Some(offset);
+    }
+    fn packet_offset(&self) -> Option<u64> {
+        self.packet_offset
+    }
+    fn filtered_pids(&self) -> Option<&FilteredPids> {
+        Some(&self.filtered_pids)
+    }
+    fn filtered_pids_mut(&mut self) -> Option<&mut FilteredPids> {
+        Some(&mut self.filtered_pids)
+    }
+    fn event_sink(&mut self) -> Option<&mut dyn EventSink> {
+        Some(&mut self.sink)
+    }
+}
+
+/// Configures the options of a [`Demultiplex`](struct.Demultiplex.html) in one place, before
+/// creating it with `build()`.  `Demultiplex::new()` is equivalent to
+/// `DemultiplexBuilder::new().build()`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate mpeg2ts_reader;
+/// # use mpeg2ts_reader::demultiplex;
+/// # use mpeg2ts_reader::packet;
+/// # packet_filter_switch!{
+/// #     NullFilterSwitch<NullDemuxContext> {
+/// #         Nul: demultiplex::NullPacketFilter<NullDemuxContext>,
+/// #     }
+/// # }
+/// # demux_context!(NullDemuxContext, NullStreamConstructor);
+/// # pub struct NullStreamConstructor;
+/// # impl demultiplex::StreamConstructor for NullStreamConstructor {
+/// #     type F = NullFilterSwitch;
+/// #     fn construct(&mut self, req: demultiplex::FilterRequest) -> Self::F {
+/// #         NullFilterSwitch::Nul(demultiplex::NullPacketFilter::default())
+/// #     }
+/// # }
+/// # fn main() {
+/// let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+/// let mut demux = demultiplex::DemultiplexBuilder::new()
+///     .packet_format(packet::PacketFormat::Fec)
+///     .skip_errored_packets(true)
+///     .resync(true)
+///     .build(&mut ctx);
+/// # }
+/// ```
+pub struct DemultiplexBuilder<Ctx: DemuxContext> {
+    packet_format: packet::PacketFormat,
+    skip_errored_packets: bool,
+    skip_null_packets: bool,
+    drop_duplicate_packets: bool,
+    resync: bool,
+    auto_construct_filters: bool,
+    pat_pid: u16,
+    pid_stats: bool,
+    conformance_checks: bool,
+    program_bitrate: bool,
+    phantom: marker::PhantomData<Ctx>,
+}
+impl<Ctx: DemuxContext> Default for DemultiplexBuilder<Ctx> {
+    fn default() -> DemultiplexBuilder<Ctx> {
+        DemultiplexBuilder {
+            packet_format: packet::PacketFormat::Ts,
+            skip_errored_packets: false,
+            skip_null_packets: false,
+            drop_duplicate_packets: false,
+            resync: false,
+            auto_construct_filters: true,
+            pat_pid: 0,
+            pid_stats: false,
+            conformance_checks: false,
+            program_bitrate: false,
+            phantom: marker::PhantomData,
+        }
+    }
+}
+impl<Ctx: DemuxContext> DemultiplexBuilder<Ctx> {
+    pub fn new() -> DemultiplexBuilder<Ctx> {
+        Self::default()
+    }
+
+    /// The framing of the packets in the data given to `push()`.  Defaults to
+    /// `PacketFormat::Ts`, for plain 188 byte packets.
+    ///
+    /// This has no effect on `push_packet()`, which always takes a 188 byte packet.
+    pub fn packet_format(mut self, format: packet::PacketFormat) -> Self {
+        self.packet_format = format;
+        self
+    }
+
+    /// If `true`, packets with the `transport_error_indicator` set will be discarded without
+    /// being passed to any filter.  Since the header of such a packet cannot be trusted, they
+    /// are not counted by `pid_stats()` either.  Defaults to `false`.
+    pub fn skip_errored_packets(mut self, skip: bool) -> Self {
+        self.skip_errored_packets = skip;
+        self
+    }
+
+    /// If `true`, null packets (PID `0x1fff`) will be discarded by `push()` without being passed
+    /// to any filter, and no filter will be requested for that PID.  They are still counted by
+    /// `pid_stats()`, if enabled.  Defaults to `false`.
+    pub fn skip_null_packets(mut self, skip: bool) -> Self {
+        self.skip_null_packets = skip;
+        self
+    }
+
+    /// If `true`, a packet which exactly duplicates the previous packet with the same PID
+    /// (including its continuity_counter value) will be discarded by `push()` without being passed
+    /// to the filter for that PID, as the standard requires of a decoder.  Duplicates are still
+    /// counted by `pid_stats()`, if enabled.  Defaults to `false`.
+    pub fn drop_duplicate_packets(mut self, drop: bool) -> Self {
+        self.drop_duplicate_packets = drop;
+        self
+    }
+
+    /// If `true`, maintain per-PID packet counts, available from `Demultiplex::pid_stats()`.
+    /// Defaults to `false`.
+    pub fn pid_stats(mut self, enable: bool) -> Self {
+        self.pid_stats = enable;
+        self
+    }
+
+    /// If `true`, check the stream against the rules described in the
+    /// [`conformance`](../conformance/index.html) module, with any violations found available
+    /// from `Demultiplex::conformance_report()`.  Defaults to `false`, as checking adds to the
+    /// cost of processing each packet.
+    pub fn conformance_checks(mut self, enable: bool) -> Self {
+        self.conformance_checks = enable;
+        self
+    }
+
+    /// If `true`, measure each program's bitrate from its PCRs, with the result available from
+    /// [`DemuxContext::program_bitrate()`](trait.DemuxContext.html#method.program_bitrate), and
+    /// updated as each PCR arrives.  Defaults to `false`, as measuring adds to the cost of
+    /// processing each packet.
+    ///
+    /// Each packet between PCRs counts as 188 bytes, including errored packets, and excluding any
+    /// extra bytes of m2ts or FEC framing (see
+    /// [`PcrBitrate::packet()`](../pcr/struct.PcrBitrate.html#method.packet)).
+    pub fn program_bitrate(mut self, enable: bool) -> Self {
+        self.program_bitrate = enable;
+        self
+    }
+
+    /// If `true`, when `push()` finds data which does not begin with the sync byte where a packet
+    /// was expected, it will search forward for the next position at which packets appear to
+    /// start (the sync byte being present there, and one packet further on, if the buffer is
+    /// long enough to check), and carry on from that point.  Defaults to `false`, in which case
+    /// `push()` stops processing at the point sync is lost.
+    pub fn resync(mut self, resync: bool) -> Self {
+        self.resync = resync;
+        self
+    }
+
+    /// If `true` (the default), then on seeing a packet with a PID for which no filter is
+    /// installed, the context's `StreamConstructor` is asked for one with
+    /// `FilterRequest::ByPid`.  If `false`, such packets are instead discarded, so that only the
+    /// PIDs given filters by the PAT and PMT, or by `Demultiplex::insert_filter()`, are
+    /// processed.
+    pub fn auto_construct_filters(mut self, auto: bool) -> Self {
+        self.auto_construct_filters = auto;
+        self
+    }
+
+    /// The PID on which to expect the PAT.  Defaults to `0`, as required by _ISO/IEC 13818-1_,
+    /// but may be changed in order to process streams which have been remapped such that the
+    /// PAT appears elsewhere.
+    ///
+    /// The filter installed on this PID is still the one the `StreamConstructor` returns for
+    /// `FilterRequest::ByPid(0)`, so existing constructors need no change.
+    ///
+    /// Panics if `pid` is not less than `packet::NULL_PID`.
+    pub fn pat_pid(mut self, pid: u16) -> Self {
+        assert!(pid < packet::NULL_PID, "invalid PAT PID {:#x}", pid);
+        self.pat_pid = pid;
+        self
+    }
+
+    pub fn build(self, ctx: &mut Ctx) -> Demultiplex<Ctx> {
+        let continuity = self.pid_stats || self.conformance_checks || self.drop_duplicate_packets;
+        let mut result = Demultiplex {
+            processor_by_pid: Filters::new(),
+            pid_stats: if self.pid_stats { Some(PidStats::new()) } else { None },
+            pcr_bitrate: if self.program_bitrate { Some(pcr::PcrBitrate::new()) } else { None },
+            continuity: if continuity { Some(ContinuityWatch::new()) } else { None },
+            skip_null_packets: self.skip_null_packets,
+            drop_duplicate_packets: self.drop_duplicate_packets,
+            packet_format: self.packet_format,
+            skip_errored_packets: self.skip_errored_packets,
+            resync: self.resync,
+            auto_construct_filters: self.auto_construct_filters,
+            stream_offset: 0,
+            conformance: if self.conformance_checks { Some(conformance::ConformanceChecker::new(self.pat_pid)) } else { None },
+            filtered_pids_stale: false,
+            pat_pid: self.pat_pid,
+        };
+
+        result.insert_pat_filter(ctx);
+        result.update_filtered_pids(ctx);
+
+        result
+    }
+}
+
+pub struct Demultiplex<Ctx: DemuxContext> {
+    processor_by_pid: Filters<Ctx::F>,
+    pid_stats: Option<PidStats>,
+    continuity: Option<ContinuityWatch>,
+    skip_null_packets: bool,
+    drop_duplicate_packets: bool,
+    packet_format: packet::PacketFormat,
+    skip_errored_packets: bool,
+    resync: bool,
+    auto_construct_filters: bool,
+    stream_offset: u64,
+    conformance: Option<conformance::ConformanceChecker>,
+    pcr_bitrate: Option<pcr::PcrBitrate>,
+    /// set when filters are changed by `insert_filter()` or `remove_filter()`, which have no
+    /// access to the context's `FilteredPids`
+    filtered_pids_stale: bool,
+    pat_pid: u16,
+}
+impl<Ctx: DemuxContext> Demultiplex<Ctx> {
+    /// Creates a `Demultiplex` with the default options; use a
+    /// [`DemultiplexBuilder`](struct.DemultiplexBuilder.html) to configure others.
+    pub fn new(ctx: &mut Ctx) -> Demultiplex<Ctx> {
+        DemultiplexBuilder::new().build(ctx)
+    }
+
+    /// Returns `None` unless enabled by
+    /// [`DemultiplexBuilder::conformance_checks()`](struct.DemultiplexBuilder.html#method.conformance_checks).
+    pub fn conformance_report(&self) -> Option<&conformance::ConformanceReport> {
+        self.conformance.as_ref().map(|c| c.report())
+    }
+
+    /// Mutable access to the report, so that the violations found may be taken by the caller.
+    /// Returns `None` unless conformance checks are enabled.
+    pub fn conformance_report_mut(&mut self) -> Option<&mut conformance::ConformanceReport> {
+        self.conformance.as_mut().map(|c| c.report_mut())
+    }
+
+    /// Returns `None` unless enabled by
+    /// [`DemultiplexBuilder::pid_stats()`](struct.DemultiplexBuilder.html#method.pid_stats).
+    pub fn pid_stats(&self) -> Option<&PidStats> {
+        self.pid_stats.as_ref()
+    }
+
+    /// Mutable access to the counters, so that they may be `reset()` by the caller.  Returns
+    /// `None` unless counting is enabled.
+    pub fn pid_stats_mut(&mut self) -> Option<&mut PidStats> {
+        self.pid_stats.as_mut()
+    }
+
+    /// Discards all existing filters (along with the PAT and PMT state held within them), the
+    /// context's `ProgramMap` and `PsiStatsMap`, and any continuity_counter history, leaving the
+    /// `Demultiplex` in the same state as if it had just been created with the given context.
+    ///
+    /// This is useful when the input switches to a different Transport Stream (for example after
+    /// a channel change), so that tables from the old stream are not assumed to still apply.
+    /// The options set by the `DemultiplexBuilder` are retained, as are any counters in
+    /// `pid_stats()` and any violations already in the `conformance_report()`.
+    pub fn reset(&mut self, ctx: &mut Ctx) {
+        self.processor_by_pid.clear();
+        self.insert_pat_filter(ctx);
+        if let Some(map) = ctx.program_map_mut() {
+            map.clear();
+        }
+        if let Some(stats) = ctx.psi_stats_map_mut() {
+            stats.clear();
+        }
+        if let Some(ref mut watch) = self.continuity {
+            watch.clear();
+        }
+        if let Some(ref mut rate) = self.pcr_bitrate {
+            rate.clear();
+        }
+        if let Some(ref mut checker) = self.conformance {
+            checker.clear();
+        }
+        self.update_filtered_pids(ctx);
+    }
+
+    fn insert_pat_filter(&mut self, ctx: &mut Ctx) {
+        let filter = ctx.filter_constructor().construct(FilterRequest::ByPid(0));
+        self.processor_by_pid.insert(self.pat_pid, filter);
+    }
+
+    fn update_filtered_pids(&mut self, ctx: &mut Ctx) {
+        if let Some(pids) = ctx.filtered_pids_mut() {
+            pids.set_from(&self.processor_by_pid);
+        }
+        self.filtered_pids_stale = false;
+    }
+
+    /// The byte offset within the stream of the next data to be given to `push()` (or
+    /// `push_packet()`), i.e. the total number of bytes consumed so far, plus any offset given to
+    /// `set_stream_offset()`.  Since `push()` does not consume a trailing partial packet, the
+    /// offset stays correct when the caller passes those bytes again.
+    pub fn stream_offset(&self) -> u64 {
+        self.stream_offset
+    }
+
+    /// Sets the byte offset within the stream of the next data to be given to `push()`, for
+    /// example after seeking within a file, so that the offsets passed to
+    /// [`DemuxContext::set_packet_offset()`](trait.DemuxContext.html#method.set_packet_offset)
+    /// refer to positions in that file.  Defaults to `0`.
+    pub fn set_stream_offset(&mut self, offset: u64) {
+        self.stream_offset = offset;
+    }
+
+    /// Installs the given filter to handle packets with the given PID, replacing any filter
+    /// already present for that PID.
+    ///
+    /// This allows the application to take control of a PID from outside of the filters
+    /// themselves, between calls to `push()`.  Note that a `PmtPacketFilter` will still replace
+    /// the filters for the elementary streams it manages, if the PMT changes.
+    pub fn insert_filter(&mut self, pid: u16, filter: Ctx::F) {
+        self.processor_by_pid.insert(pid, filter);
+        self.filtered_pids_stale = true;
+    }
+
+    /// Removes the filter handling packets with the given PID, returning it if one was present.
+    ///
+    /// If further packets with this PID are pushed, the context's `StreamConstructor` will be
+    /// asked for a new filter with `FilterRequest::ByPid`, so to stop processing a PID
+    /// completely, the application may want to `insert_filter()` a `NullPacketFilter` instead.
+    pub fn remove_filter(&mut self, pid: u16) -> Option<Ctx::F> {
+        self.filtered_pids_stale = true;
+        self.processor_by_pid.remove(pid)
+    }
+
+    /// Processes a single Transport Stream packet, for applications which already receive data
+    /// one packet at a time (for example, one packet per network datagram) and so have no need
+    /// for the buffer handling performed by `push()`.  Any filter changes caused by the packet
+    /// are applied before this method returns.
+    ///
+    /// A packet which does not begin with the sync-byte is logged and discarded.
+    pub fn push_packet(&mut self, ctx: &mut Ctx, pk_buf: &[u8; packet::PACKET_SIZE]) {
+        if !packet::Packet::is_sync_byte(pk_buf[0]) {
+            warn!("discarding packet with invalid sync-byte {:#04x}", pk_buf[0]);
+            return;
+        }
+        let offset = self.stream_offset;
+        self.stream_offset += packet::PACKET_SIZE as u64;
+        ctx.set_packet_offset(offset);
+        self.process(ctx, packet::Packet::new(&pk_buf[..]), offset);
+    }
+
+    /// Processes packets which have already been framed by some other component (for example, a
+    /// network reassembler), without requiring them to be copied into a single buffer for
+    /// `push()`.  Each item must hold exactly one packet of the size this `Demultiplex` was
+    /// configured with (see [`packet_format()`](struct.DemultiplexBuilder.html#method.packet_format)),
+    /// and any filter changes caused by a packet are applied before the next is processed.
+    ///
+    /// Items of the wrong length, or which do not begin with the sync-byte, are logged and
+    /// discarded, though they still count towards `stream_offset()`.
+    pub fn push_iter<'a, I>(&mut self, ctx: &mut Ctx, packets: I)
+    where
+        I: IntoIterator<Item=&'a [u8]>
+    {
+        for pk_buf in packets {
+            if pk_buf.len() != self.packet_format.packet_size() {
+                warn!("discarding {} byte packet at offset {}, expected {} bytes", pk_buf.len(), self.stream_offset, self.packet_format.packet_size());
+                self.stream_offset += pk_buf.len() as u64;
+                continue;
+            }
+            let consumed = self.push(ctx, pk_buf);
+            self.stream_offset += (pk_buf.len() - consumed) as u64;
+        }
+    }
+
+    fn process(&mut self, ctx: &mut Ctx, pk: packet::Packet, offset: u64) {
+        if self.filtered_pids_stale {
+            self.update_filtered_pids(ctx);
+        }
+        if let Some(ref mut rate) = self.pcr_bitrate {
+            if let (Some(bitrate), Some(map)) = (rate.packet(&pk), ctx.program_map_mut()) {
+                map.set_pcr_bitrate(pk.pid(), bitrate);
+            }
+        }
+        if pk.transport_error_indicator() {
+            if let Some(ref mut checker) = self.conformance {
+                checker.transport_error(&pk, offset);
+            }
+            if self.skip_errored_packets {
+                return;
+            }
+        }
+        let this_pid = pk.pid();
+        let status = Self::record(&mut self.continuity, &mut self.pid_stats, &pk);
+        if let Some(ref mut checker) = self.conformance {
+            checker.packet(&pk, offset, status, ctx.program_map());
+        }
+        if self.skip_null_packets && this_pid == packet::NULL_PID {
+            return;
+        }
+        if self.drop_duplicate_packets && status == ContinuityStatus::Duplicate {
+            return;
+        }
+        if !self.processor_by_pid.contains(this_pid) {
+            if !self.auto_construct_filters {
+                return;
+            }
+            let filter = ctx.filter_constructor().construct(FilterRequest::ByPid(this_pid));
+            self.processor_by_pid.insert(this_pid, filter);
+            self.update_filtered_pids(ctx);
+        }
+        let filter = self.processor_by_pid.get(this_pid).unwrap();
+        if !pk.has_payload() && filter.needs_payload() {
+            return;
+        }
+        filter.consume(ctx, pk);
+        if !ctx.filter_changeset().is_empty() {
+            ctx.filter_changeset().apply(&mut self.processor_by_pid);
+            self.update_filtered_pids(ctx);
+            // the changes result from a new PAT or PMT
+            if let (Some(checker), Some(map)) = (self.conformance.as_mut(), ctx.program_map()) {
+                checker.programs(map, offset);
+            }
+        }
+    }
+
+    /// Processes each whole packet in the given buffer, which must begin at a packet boundary,
+    /// and returns the number of bytes consumed.  Any partial packet at the end of the buffer is
+    /// not consumed, so when feeding a stream in pieces, the caller should pass the remaining
+    /// bytes again at the start of the next call.  Processing stops early if a packet does not
+    /// begin with the sync byte, unless the `Demultiplex` was built with
+    /// [`resync()`](struct.DemultiplexBuilder.html#method.resync) enabled.
+    ///
+    /// The packets are borrowed from `buf` rather than copied, so a large memory-mapped file can
+    /// be passed in one call, or sliced at any multiple of `PACKET_SIZE` bytes.  No heap
+    /// allocation is performed per-packet by `push()` itself or by the PSI handling, once the
+    /// filters for the stream's PIDs have been created.  Allocation occurs only when a PSI table
+    /// changes, when a PID is seen for the first time, and where a PSI section needs to be
+    /// buffered across packets (reusing the buffer from the previous section where possible);
+    /// the application's own filters may of course allocate.
+    pub fn push(&mut self, ctx: &mut Ctx, buf: &[u8]) -> usize {
+        let stride = self.packet_format.packet_size();
+        let sync_offset = self.packet_format.sync_offset();
+        let mut i = 0;
+        while i + stride <= buf.len() {
+            let pk_buf = &buf[i..i + stride];
+            if !packet::Packet::is_sync_byte(pk_buf[sync_offset]) {
+                if !self.resync {
+                    warn!("lost sync at offset {}", self.stream_offset + i as u64);
+                    break;
+                }
+                let next = Self::find_sync(buf, i + 1, stride, sync_offset);
+                warn!("lost sync at offset {}, skipping {} bytes", self.stream_offset + i as u64, next - i);
+                i = next;
+                continue;
+            }
+            let pk = if self.packet_format == packet::PacketFormat::M2ts {
+                packet::Packet::from_m2ts(pk_buf)
+            } else {
+                packet::Packet::new(&pk_buf[..packet::PACKET_SIZE])
+            };
+            let offset = self.stream_offset + i as u64;
+            ctx.set_packet_offset(offset);
+            self.process(ctx, pk, offset);
+            i += stride;
+        }
+        self.stream_offset += i as u64;
+        i
+    }
+
+    /// Finds the first offset, from `start`, at which a packet appears to begin (having the sync
+    /// byte, as does the following packet, if the buffer extends that far).  If there is none,
+    /// returns the offset from which no further check was possible.
+    fn find_sync(buf: &[u8], start: usize, stride: usize, sync_offset: usize) -> usize {
+        let limit = buf.len().saturating_sub(sync_offset);
+        for i in start..limit {
+            if packet::Packet::is_sync_byte(buf[i + sync_offset]) {
+                let next = i + stride + sync_offset;
+                if next >= buf.len() || packet::Packet::is_sync_byte(buf[next]) {
+                    return i;
+                }
+            }
+        }
+        std::cmp::max(start, limit)
+    }
+
+    // takes the fields it needs, rather than &mut self, so that it may be called while a filter
+    // in processor_by_pid is borrowed
+    fn record(continuity: &mut Option<ContinuityWatch>, pid_stats: &mut Option<PidStats>, pk: &packet::Packet) -> ContinuityStatus {
+        let status = match *continuity {
+            Some(ref mut watch) => watch.check(pk),
+            None => ContinuityStatus::Unknown,
+        };
+        if let Some(ref mut stats) = *pid_stats {
+            stats.record(pk, status);
+        }
+        status
+    }
+}
+
+/// Returns a future which reads Transport Stream data from `reader` until end-of-file, passing it
+/// to [`Demultiplex::push()`](struct.Demultiplex.html#method.push).
+///
+/// Reads need not be packet-aligned; any partial packet at the end of one read is kept and
+/// completed by the next.  The future resolves to an error if `reader` fails, or if the
+/// `Demultiplex` can make no progress through a full buffer (having lost sync, without
+/// [`resync()`](struct.DemultiplexBuilder.html#method.resync) enabled).  Trailing bytes at the end
+/// of the stream, too few to make a whole packet, are discarded with a warning.
+///
+/// Only available with the `tokio` feature.
+#[cfg(feature = "tokio")]
+pub fn demux_stream<'a, Ctx, R>(demux: &'a mut Demultiplex<Ctx>, ctx: &'a mut Ctx, reader: R) -> DemuxStream<'a, Ctx, R>
+where
+    Ctx: DemuxContext + 'a,
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let buf = vec![0u8; demux.packet_format.packet_size() * DemuxStream::<Ctx, R>::BUFFER_PACKETS];
+    DemuxStream {
+        demux,
+        ctx,
+        reader,
+        buf,
+        len: 0,
+    }
+}
+
+/// The future returned by [`demux_stream()`](fn.demux_stream.html).
+#[cfg(feature = "tokio")]
+pub struct DemuxStream<'a, Ctx: DemuxContext + 'a, R> {
+    demux: &'a mut Demultiplex<Ctx>,
+    ctx: &'a mut Ctx,
+    reader: R,
+    buf: Vec<u8>,
+    // number of bytes at the start of buf which have been read, but not yet consumed by push()
+    len: usize,
+}
+#[cfg(feature = "tokio")]
+impl<'a, Ctx: DemuxContext + 'a, R> DemuxStream<'a, Ctx, R> {
+    const BUFFER_PACKETS: usize = 256;
+}
+#[cfg(feature = "tokio")]
+impl<'a, Ctx, R> std::future::Future for DemuxStream<'a, Ctx, R>
+where
+    Ctx: DemuxContext + 'a,
+    R: tokio::io::AsyncRead + Unpin,
+{
+    type Output = std::io::Result<()>;
+
+    fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            if this.len == this.buf.len() {
+                return std::task::Poll::Ready(Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "no packets found in full buffer (lost sync?)")));
+            }
+            let mut read_buf = tokio::io::ReadBuf::new(&mut this.buf[this.len..]);
+            match std::pin::Pin::new(&mut this.reader).poll_read(cx, &mut read_buf) {
+                std::task::Poll::Pending => return std::task::Poll::Pending,
+                std::task::Poll::Ready(Err(e)) => return std::task::Poll::Ready(Err(e)),
+                std::task::Poll::Ready(Ok(())) => (),
+            }
+            let n = read_buf.filled().len();
+            if n == 0 {
+                if this.len > 0 {
+                    warn!("discarding {} trailing bytes at end of stream", this.len);
+                }
+                return std::task::Poll::Ready(Ok(()));
+            }
+            this.len += n;
+            let consumed = this.demux.push(this.ctx, &this.buf[..this.len]);
+            this.buf.copy_within(consumed..this.len, 0);
+            this.len -= consumed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use data_encoding::base16;
+
+    use demultiplex;
+    use packet;
+    use demultiplex::DemuxContext;
+    use psi;
+    use test_util;
+    use test_util::{CountingFilter, NullDemuxContext, NullFilterSwitch, NullStreamConstructor};
+    use psi::WholeSectionSyntaxPayloadParser;
+
+    #[test]
+    fn demux_empty() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        deplex.push(&mut ctx, &[0x0; 0][..]);
+    }
+
+    #[test]
+    fn pid_stats() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        assert!(demultiplex::Demultiplex::new(&mut ctx).pid_stats().is_none());
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .pid_stats(true)
+            .build(&mut ctx);
+        let mut buf = vec!();
+        buf.extend(test_util::packet(101, 0));
+        buf.extend(test_util::packet(101, 1));
+        buf.extend(test_util::packet(102, 7));
+        buf.extend(test_util::packet(101, 3)); // discontinuity
+        deplex.push(&mut ctx, &buf[..]);
+        {
+            let stats = deplex.pid_stats().unwrap();
+            assert_eq!(stats.total_packets(), 4);
+            assert_eq!(stats.packet_count(101), 3);
+            assert_eq!(stats.packet_count(102), 1);
+            assert_eq!(stats.continuity_errors(101), 1);
+            assert_eq!(stats.continuity_errors(102), 0);
+            assert_eq!(stats.pids().collect::<Vec<_>>(), vec!((101, 3), (102, 1)));
+        }
+        deplex.pid_stats_mut().unwrap().reset();
+        deplex.push(&mut ctx, &test_util::packet(101, 4)[..]);
+        let stats = deplex.pid_stats().unwrap();
+        assert_eq!(stats.total_packets(), 1);
+        assert_eq!(stats.total_continuity_errors(), 0);
+    }
+
+    #[test]
+    fn duplicate_packets() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .pid_stats(true)
+            .drop_duplicate_packets(true)
+            .build(&mut ctx);
+        let mut changed = test_util::packet(101, 1);
+        changed[4] = 0;
+        let mut buf = vec!();
+        buf.extend(test_util::packet(101, 0));
+        buf.extend(test_util::packet(101, 1));
+        buf.extend(test_util::packet(101, 1)); // duplicate
+        buf.extend(test_util::packet(101, 2));
+        buf.extend(test_util::packet(101, 3));
+        buf.extend(changed);  // same CC as previous packet, but different payload
+        deplex.push(&mut ctx, &buf[..]);
+        let stats = deplex.pid_stats().unwrap();
+        assert_eq!(stats.packet_count(101), 6);
+        assert_eq!(stats.duplicate_packets(101), 1);
+        assert_eq!(stats.total_duplicate_packets(), 1);
+        assert_eq!(stats.continuity_errors(101), 1);
+    }
+
+    #[test]
+    fn duplicate_packets_with_pcr() {
+        use demultiplex::ContinuityStatus;
+        // PCR in an adaptation field followed by payload
+        let pcr_payload_packet = |cc, pcr| {
+            let mut buf = test_util::pcr_packet(101, cc, pcr, false);
+            buf[3] = 0b0011_0000 | cc;  // AdaptationFieldAndPayload
+            buf[4] = 7;  // adaptation_field_length
+            buf
+        };
+        let mut watch = demultiplex::ContinuityWatch::new();
+        watch.check(&packet::Packet::new(&pcr_payload_packet(0, 0)[..]));
+        // a duplicate may carry an updated PCR
+        assert_eq!(watch.check(&packet::Packet::new(&pcr_payload_packet(0, 27_000)[..])), ContinuityStatus::Duplicate);
+        let mut changed = pcr_payload_packet(0, 27_000);
+        changed[20] = 0;
+        assert_eq!(watch.check(&packet::Packet::new(&changed[..])), ContinuityStatus::Discontinuity);
+
+        // in 'm2ts' packets, the TP_extra_header of each copy differs
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .packet_format(packet::PacketFormat::M2ts)
+            .pid_stats(true)
+            .build(&mut ctx);
+        let mut buf = vec!();
+        for (arrival, pk) in vec!(test_util::packet(101, 0), test_util::packet(101, 0)).into_iter().enumerate() {
+            buf.extend_from_slice(&[0, 0, 0, arrival as u8]);
+            buf.extend(pk);
+        }
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(deplex.pid_stats().unwrap().duplicate_packets(101), 1);
+    }
+
+    #[test]
+    fn continuity_watch() {
+        use demultiplex::ContinuityStatus;
+        let mut watch = demultiplex::ContinuityWatch::new();
+        let first = test_util::packet(101, 15);
+        let next = test_util::packet(101, 0);
+        let mut af_only = test_util::packet(101, 0);
+        af_only[3] = 0b0010_0000;
+        assert_eq!(watch.check(&packet::Packet::new(&first[..])), ContinuityStatus::Unknown);
+        assert_eq!(watch.check(&packet::Packet::new(&next[..])), ContinuityStatus::Continuous);
+        assert_eq!(watch.check(&packet::Packet::new(&next[..])), ContinuityStatus::Duplicate);
+        assert_eq!(watch.check(&packet::Packet::new(&af_only[..])), ContinuityStatus::Continuous);
+        assert_eq!(watch.check(&packet::Packet::new(&first[..])), ContinuityStatus::Discontinuity);
+        let null = test_util::packet(packet::NULL_PID, 0);
+        watch.check(&packet::Packet::new(&null[..]));
+        assert_eq!(watch.check(&packet::Packet::new(&null[..])), ContinuityStatus::Unknown);
+        watch.clear();
+        assert_eq!(watch.check(&packet::Packet::new(&next[..])), ContinuityStatus::Unknown);
+    }
+
+    #[test]
+    fn needs_payload() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        let payload_count = std::rc::Rc::new(std::cell::Cell::new(0));
+        let all_count = std::rc::Rc::new(std::cell::Cell::new(0));
+        deplex.insert_filter(101, NullFilterSwitch::Cnt(CountingFilter { needs_payload: true, count: payload_count.clone() }));
+        deplex.insert_filter(102, NullFilterSwitch::Cnt(CountingFilter { needs_payload: false, count: all_count.clone() }));
+        let af_only = |pid: u16, cc: u8| {
+            let mut pk = test_util::packet(pid, cc);
+            pk[3] = 0b0010_0000 | cc;  // AdaptationFieldOnly
+            pk[4] = 183;
+            pk[5] = 0;
+            pk
+        };
+        let mut buf = vec!();
+        buf.extend(test_util::packet(101, 0));
+        buf.extend(af_only(101, 0));
+        buf.extend(test_util::packet(102, 0));
+        buf.extend(af_only(102, 0));
+        deplex.push(&mut ctx, &buf[..]);
+        let mut pk = [0u8; packet::PACKET_SIZE];
+        pk.copy_from_slice(&af_only(101, 0)[..]);
+        deplex.push_packet(&mut ctx, &pk);
+        assert_eq!(payload_count.get(), 1);
+        assert_eq!(all_count.get(), 2);
+        assert!(!packet::Packet::new(&af_only(101, 0)[..]).has_payload());
+    }
+
+    #[test]
+    fn skip_null_packets() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .pid_stats(true)
+            .skip_null_packets(true)
+            .build(&mut ctx);
+        let mut buf = vec!();
+        buf.extend(test_util::packet(101, 0));
+        buf.extend(test_util::packet(0x1fff, 0));
+        buf.extend(test_util::packet(0x1fff, 0));
+        buf.extend(test_util::packet(101, 1));
+        deplex.push(&mut ctx, &buf[..]);
+        assert!(!deplex.processor_by_pid.contains(0x1fff));
+        let stats = deplex.pid_stats().unwrap();
+        assert_eq!(stats.total_packets(), 4);
+        assert_eq!(stats.null_packets(), 2);
+        assert_eq!(stats.packet_count(101), 2);
+        assert_eq!(stats.total_continuity_errors(), 0);
+    }
+
+    #[test]
+    fn push_packet() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .pid_stats(true)
+            .build(&mut ctx);
+        let mut pat = [0xffu8; packet::PACKET_SIZE];
+        pat[..4].copy_from_slice(&[0x47, 0b0100_0000, 0x00, 0b0001_0000]);
+        let section = [
+            0x00,  // pointer_field
+            0x00, 0b1011_0000, 13, 0x00, 0x01, 0b1100_0001, 0x00, 0x00,
+            0, 1, 0xe1, 0x01,  // program 1 -> PMT PID 0x101
+        ];
+        pat[4..4+section.len()].copy_from_slice(&section);
+        let crc = psi::mpeg2_crc32(&pat[5..4+section.len()]);
+        pat[4+section.len()..4+section.len()+4].copy_from_slice(&[(crc >> 24) as u8, (crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+        deplex.push_packet(&mut ctx, &pat);
+        assert_eq!(ctx.program_map().unwrap().program(1).map(|p| p.pmt_pid), Some(0x101));
+        assert!(deplex.processor_by_pid.contains(0x101));
+
+        let mut bad = [0u8; packet::PACKET_SIZE];
+        bad[1] = 0x01;
+        deplex.push_packet(&mut ctx, &bad);
+        assert_eq!(deplex.pid_stats().unwrap().total_packets(), 1);
+    }
+
+    #[test]
+    fn push_iter() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .pid_stats(true)
+            .build(&mut ctx);
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00])));
+        let short = [0x47u8; 100];
+        let unsynced = [0u8; packet::PACKET_SIZE];
+        let pk = test_util::packet(0x201, 0);
+        let packets = buf.chunks(packet::PACKET_SIZE)
+            .chain(Some(&short[..]))
+            .chain(Some(&unsynced[..]))
+            .chain(Some(&pk[..]));
+        deplex.push_iter(&mut ctx, packets);
+        assert!(ctx.program_map().unwrap().program(1).unwrap().pmt_version.is_some());
+        // the packet for the elementary stream was handled by the filter created by the PMT
+        assert!(deplex.processor_by_pid.contains(0x201));
+        assert_eq!(deplex.pid_stats().unwrap().total_packets(), 3);
+        assert_eq!(ctx.packet_offset(), Some(3 * 188 + 100));
+        assert_eq!(deplex.stream_offset(), 4 * 188 + 100);
+    }
+
+    // yields the data in short pieces, returning Pending before each one
+    #[cfg(feature = "tokio")]
+    struct ChunkedReader {
+        data: Vec<u8>,
+        pos: usize,
+        pending: bool,
+    }
+    #[cfg(feature = "tokio")]
+    impl tokio::io::AsyncRead for ChunkedReader {
+        fn poll_read(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context, buf: &mut tokio::io::ReadBuf) -> std::task::Poll<std::io::Result<()>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return std::task::Poll::Pending;
+            }
+            let end = std::cmp::min(self.pos + std::cmp::min(100, buf.remaining()), self.data.len());
+            buf.put_slice(&self.data[self.pos..end]);
+            self.pos = end;
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn demux_stream() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .pid_stats(true)
+            .build(&mut ctx);
+        let mut data = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        data.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00])));
+        data.extend(test_util::packet(0x201, 0));
+        data.extend(&[0x47u8; 50][..]);
+        let reader = ChunkedReader { data, pos: 0, pending: false };
+        let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        rt.block_on(demultiplex::demux_stream(&mut deplex, &mut ctx, reader)).unwrap();
+        assert!(ctx.program_map().unwrap().program(1).unwrap().pmt_version.is_some());
+        assert!(deplex.processor_by_pid.contains(0x201));
+        assert_eq!(deplex.pid_stats().unwrap().total_packets(), 3);
+        // the trailing partial packet was not consumed
+        assert_eq!(deplex.stream_offset(), 3 * 188);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn demux_stream_lost_sync() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        let reader = ChunkedReader { data: vec![0u8; 300 * packet::PACKET_SIZE], pos: 0, pending: false };
+        let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let err = rt.block_on(demultiplex::demux_stream(&mut deplex, &mut ctx, reader)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn raw_packet_filter() {
+        use demultiplex::PacketFilter;
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut filter = demultiplex::RawPacketFilter::new(vec![]);
+        let mut pk1 = test_util::packet(101, 0);
+        pk1[3] = 0b0011_0000;  // AdaptationFieldAndPayload
+        pk1[4] = 1;  // adaptation_field_length
+        pk1[5] = 0b1000_0000;  // discontinuity_indicator
+        let pk2 = test_util::packet(101, 1);
+        filter.consume(&mut ctx, packet::Packet::new(&pk1[..]));
+        filter.consume(&mut ctx, packet::Packet::new(&pk2[..]));
+        let mut expected = pk1.clone();
+        expected.extend_from_slice(&pk2[..]);
+        assert_eq!(filter.sink(), &expected);
+
+        let mut count = 0;
+        {
+            let mut filter: demultiplex::RawPacketFilter<NullDemuxContext, _> = demultiplex::RawPacketFilter::new(|data: &[u8]| {
+                assert_eq!(data.len(), packet::PACKET_SIZE);
+                count += 1;
+            });
+            filter.consume(&mut ctx, packet::Packet::new(&pk1[..]));
+        }
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn limit_packet_filter() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .auto_construct_filters(false)
+            .build(&mut ctx);
+        let count = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = CountingFilter { needs_payload: true, count: count.clone() };
+        deplex.insert_filter(101, NullFilterSwitch::Lim(demultiplex::LimitPacketFilter::new(counter, 2)));
+        let buf: Vec<u8> = (0..4).flat_map(|cc| test_util::packet(101, cc)).collect();
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(count.get(), 2);
+        assert!(!deplex.processor_by_pid.contains(101));
+
+        // a limit of zero removes the filter on the first packet, without passing it on
+        let mut filter = demultiplex::LimitPacketFilter::new(CountingFilter { needs_payload: true, count: count.clone() }, 0);
+        demultiplex::PacketFilter::consume(&mut filter, &mut ctx, packet::Packet::new(&buf[..packet::PACKET_SIZE]));
+        assert_eq!(count.get(), 2);
+        assert_eq!(filter.remaining(), 0);
+        assert!(filter.inner().needs_payload);
+        assert_matches!(ctx.filter_changeset().updates.pop(), Some(demultiplex::FilterChange::Remove(101)));
+    }
+
+    #[test]
+    fn tee_packet_filter() {
+        use demultiplex::PacketFilter;
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut pids = vec!();
+        {
+            let mut filter = demultiplex::TeePacketFilter::new(
+                demultiplex::RawPacketFilter::<NullDemuxContext, _>::new(vec![]),
+                demultiplex::RawPacketFilter::new(|data: &[u8]| pids.push(packet::Packet::new(data).pid())),
+            );
+            let pk = test_util::packet(101, 0);
+            filter.consume(&mut ctx, packet::Packet::new(&pk[..]));
+            assert_eq!(filter.first().sink(), &pk);
+            let (first, _) = filter.into_inner();
+            assert_eq!(first.sink().len(), packet::PACKET_SIZE);
+        }
+        assert_eq!(pids, vec!(101));
+    }
+
+    type BoxedStreamConstructor = demultiplex::FnStreamConstructor<
+        BoxedFilterSwitch,
+        Box<dyn FnMut(demultiplex::FilterRequest) -> BoxedFilterSwitch>
+    >;
+    packet_filter_switch!{
+        BoxedFilterSwitch<BoxedDemuxContext> {
+            Pat: demultiplex::PatPacketFilter<BoxedDemuxContext>,
+            Nul: demultiplex::NullPacketFilter<BoxedDemuxContext>,
+        }
+    }
+    demux_context!(BoxedDemuxContext, BoxedStreamConstructor);
+
+    #[test]
+    fn fn_stream_constructor() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        let requested = Rc::new(RefCell::new(vec![]));
+        let requested_clone = requested.clone();
+        let ctor = demultiplex::FnStreamConstructor::new(Box::new(move |req: demultiplex::FilterRequest| {
+            match req {
+                demultiplex::FilterRequest::ByPid(0) => BoxedFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
+                demultiplex::FilterRequest::ByPid(pid) => {
+                    requested_clone.borrow_mut().push(pid);
+                    BoxedFilterSwitch::Nul(demultiplex::NullPacketFilter::new())
+                },
+                _ => BoxedFilterSwitch::Nul(demultiplex::NullPacketFilter::new()),
+            }
+        }) as Box<dyn FnMut(demultiplex::FilterRequest) -> BoxedFilterSwitch>);
+        let mut ctx = BoxedDemuxContext::new(ctor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        let mut buf = test_util::packet(101, 0);
+        buf.extend(test_util::packet(102, 0));
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(*requested.borrow(), vec![101, 102]);
+    }
+
+    demux_context!(DynDemuxContext, demultiplex::BoxedStreamConstructor<DynDemuxContext>);
+
+    #[test]
+    fn boxed_stream_constructor() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        let pids = Rc::new(RefCell::new(vec![]));
+        let pids_clone = pids.clone();
+        let ctor = demultiplex::BoxedStreamConstructor::new(move |req| match req {
+            demultiplex::FilterRequest::ByStream { stream_type: ::StreamType::H264, .. } => {
+                let pids = pids_clone.clone();
+                let filter: demultiplex::BoxedPacketFilter<DynDemuxContext> = Box::new(demultiplex::RawPacketFilter::new(move |data: &[u8]| {
+                    pids.borrow_mut().push(packet::Packet::new(data).pid())
+                }));
+                Some(filter)
+            },
+            _ => None,
+        });
+        let mut ctx = DynDemuxContext::new(ctor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00])));
+        buf.extend(test_util::packet(0x201, 0));
+        buf.extend(test_util::packet(0x202, 0));
+        buf.extend(test_util::packet(0x201, 1));
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(ctx.program_map().unwrap().program(1).unwrap().streams.len(), 1);
+        assert_eq!(*pids.borrow(), vec![0x201, 0x201]);
+    }
+
+    #[test]
+    fn filters() {
+        let mut filters = demultiplex::Filters::<NullFilterSwitch>::new();
+        assert!(!filters.contains(0x1ffe));
+        filters.insert(0x1ffe, NullFilterSwitch::Nul(demultiplex::NullPacketFilter::new()));
+        filters.insert(0x20, NullFilterSwitch::Nul(demultiplex::NullPacketFilter::new()));
+        filters.insert(0x100, NullFilterSwitch::Nul(demultiplex::NullPacketFilter::new()));
+        filters.insert(0x20, NullFilterSwitch::Pat(demultiplex::PatPacketFilter::new()));
+        assert_eq!(filters.pids(), vec![0x20, 0x100, 0x1ffe]);
+        assert!(matches!(filters.get(0x20), Some(&mut NullFilterSwitch::Pat(_))));
+        assert!(filters.get(0x21).is_none());
+        assert!(filters.remove(0x100).is_some());
+        assert!(filters.remove(0x100).is_none());
+        assert!(!filters.contains(0x100));
+        assert_eq!(filters.pids(), vec![0x20, 0x1ffe]);
+    }
+
+    #[test]
+    fn insert_and_remove_filter() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        assert!(deplex.remove_filter(101).is_none());
+        deplex.insert_filter(101, NullFilterSwitch::Nul(demultiplex::NullPacketFilter::new()));
+        assert!(deplex.processor_by_pid.contains(101));
+        assert!(matches!(deplex.remove_filter(101), Some(NullFilterSwitch::Nul(_))));
+        assert!(!deplex.processor_by_pid.contains(101));
+        assert!(deplex.remove_filter(101).is_none());
+        // a filter is requested again from the StreamConstructor if the PID is seen again
+        deplex.push(&mut ctx, &test_util::packet(101, 0)[..]);
+        assert!(deplex.processor_by_pid.contains(101));
+        // the PAT filter may be removed too
+        assert!(matches!(deplex.remove_filter(0), Some(NullFilterSwitch::Pat(_))));
+    }
+
+    #[test]
+    fn pat() {
+        // TODO: better
+        let buf = base16::decode(b"474000150000B00D0001C100000001E1E02D507804FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap();
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        deplex.push(&mut ctx, &buf[..]);
+        // the 0xff stuffing following the section must not be taken for program entries
+        assert_eq!(ctx.program_map().unwrap().programs().count(), 1);
+        assert_eq!(ctx.program_map().unwrap().program(1).map(|p| p.pmt_pid), Some(0x1e0));
+    }
+
+    #[test]
+    fn pat_spanning_packets() {
+        let program_count = 50;
+        let mut body = vec!();
+        for program_number in 1..=program_count {
+            body.extend_from_slice(&[0, program_number as u8, 0xe1, program_number as u8]);
+        }
+        let buf = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &body[..]));
+        assert_eq!(buf.len(), 2 * packet::PACKET_SIZE);
+
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(ctx.program_map().unwrap().programs().count(), program_count);
+        assert_eq!(ctx.program_map().unwrap().program(50).map(|p| p.pmt_pid), Some(0x100 + 50));
+    }
+
+    #[test]
+    fn psi_stats() {
+        let pat_packet = |version: u8, cc: u8, corrupt: bool| {
+            let mut section = test_util::section(0x00, 1, version, true, &[0, 1, 0xe1, 0x01]);
+            if corrupt {
+                section[9] ^= 0xff;
+            }
+            test_util::section_packets(0, cc, &section[..])
+        };
+        let mut buf = pat_packet(0, 0, false);
+        buf.extend(pat_packet(0, 1, false));
+        buf.extend(pat_packet(1, 2, true));
+
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        deplex.push(&mut ctx, &buf[..]);
+        {
+            let stats = ctx.psi_stats(0).unwrap();
+            assert_eq!(stats.sections_seen(), 3);
+            // the second copy of version 0 is a duplicate, so is not checked,
+            assert_eq!(stats.crc_ok(), 1);
+            assert_eq!(stats.crc_fail(), 1);
+            assert_eq!(stats.version(), Some(0));
+        }
+        assert!(ctx.psi_stats(0x101).is_none());
+        assert_eq!(ctx.psi_stats_map().unwrap().pids().count(), 1);
+
+        ctx.psi_stats_map_mut().unwrap().reset();
+        assert_eq!(ctx.psi_stats(0).unwrap().sections_seen(), 0);
+        assert_eq!(ctx.psi_stats(0).unwrap().version(), Some(0));
+    }
+
+    #[derive(Default)]
+    struct RecordingSink {
+        pats: Vec<demultiplex::PatSnapshot>,
+        pmts: Vec<(u16, demultiplex::PmtSnapshot)>,
+        unhandled: Vec<u16>,
+    }
+    impl demultiplex::EventSink for RecordingSink {
+        fn on_pat(&mut self, pat: &demultiplex::PatSnapshot) {
+            self.pats.push(pat.clone());
+        }
+        fn on_pmt(&mut self, program_number: u16, pmt: &demultiplex::PmtSnapshot) {
+            self.pmts.push((program_number, pmt.clone()));
+        }
+        fn on_unhandled_pid(&mut self, pid: u16) {
+            self.unhandled.push(pid);
+        }
+    }
+
+    #[test]
+    fn pmt_tail_shares_packet_with_next_section() {
+        let mut pmt = vec!(0xe2, 0x01, 0xf0, 0x00);
+        for i in 0..40 {
+            pmt.extend_from_slice(&[0x1b, 0xe2, 0x01 + i, 0xf0, 0x00]);
+        }
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        let pmt_v0 = test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &pmt[..]));
+        let pmt_v1 = test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 1, true, &pmt[..19]));
+        // the section (with its pointer_field) is 217 bytes, so 33 are left for the second packet
+        let tail = &pmt_v0[188 + 4..188 + 4 + 33];
+        let next_section = &pmt_v1[5..5 + 31];
+        let mut shared = test_util::packet(0x101, 1);
+        shared[1] |= 0b0100_0000;  // payload_unit_start_indicator
+        shared[4] = tail.len() as u8;  // pointer_field
+        shared[5..5 + tail.len()].copy_from_slice(tail);
+        shared[5 + tail.len()..5 + tail.len() + next_section.len()].copy_from_slice(next_section);
+        buf.extend_from_slice(&pmt_v0[..188]);
+        buf.extend(shared);
+
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        deplex.push(&mut ctx, &buf[..]);
+        // both the completed version 0 section and the new version 1 section were processed
+        let stats = ctx.psi_stats_map().unwrap().get(0x101).unwrap();
+        assert_eq!(stats.sections_seen(), 2);
+        assert_eq!(stats.crc_ok(), 2);
+        let program = ctx.program_map().unwrap().program(1).unwrap();
+        assert_eq!(program.pmt_version, Some(1));
+        assert_eq!(program.streams.len(), 3);
+    }
+
+    #[test]
+    fn pmt_spanning_packets() {
+        let mut pmt = vec!(0xe2, 0x01, 0xf0, 0x00);
+        for i in 0..40 {
+            pmt.extend_from_slice(&[0x1b, 0xe2, 0x01 + i, 0xf0, 0x00]);
+        }
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        let pmt_packets = test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &pmt[..]));
+        assert_eq!(pmt_packets.len(), 2 * 188);
+        buf.extend(pmt_packets);
+
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        deplex.push(&mut ctx, &buf[..]);
+        let program = ctx.program_map().unwrap().program(1).unwrap();
+        assert_eq!(program.pmt_version, Some(0));
+        assert_eq!(program.streams.len(), 40);
+        assert_eq!(program.streams[39].elementary_pid, 0x201 + 39);
+
+        // if the second packet of an updated PMT is lost, the partial section is discarded rather
+        // than being joined with whatever follows
+        let mut pmt_v1 = test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 1, true, &pmt[..]));
+        for pk in pmt_v1.chunks_mut(188) {
+            pk[3] = (pk[3] & 0xf0) | ((pk[3] & 0xf) + 2);
+        }
+        let mut pmt_v2 = test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 2, true, &pmt[..19]));
+        pmt_v2[3] = (pmt_v2[3] & 0xf0) | 5;
+        let mut buf = pmt_v1[..188].to_vec();
+        buf.extend_from_slice(&pmt_v2[..]);
+        deplex.push(&mut ctx, &buf[..]);
+        let program = ctx.program_map().unwrap().program(1).unwrap();
+        assert_eq!(program.pmt_version, Some(2));
+        assert_eq!(program.streams.len(), 3);
+        assert_eq!(ctx.psi_stats(0x101).unwrap().crc_fail(), 0);
+    }
+
+    #[test]
+    fn pcr_pid() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00])));
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(ctx.pcr_pid(1), Some(0x201));
+        assert_eq!(ctx.pcr_pid(2), None);
+
+        let mut pmt = test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 1, true, &[0xe2, 0x02, 0xf0, 0x00]));
+        pmt[3] |= 1;  // continuity_counter
+        deplex.push(&mut ctx, &pmt[..]);
+        assert_eq!(ctx.pcr_pid(1), Some(0x202));
+
+        // a PCR_PID of 0x1fff means that the program has no PCR
+        let mut pmt = test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 2, true, &[0xff, 0xff, 0xf0, 0x00]));
+        pmt[3] |= 2;
+        deplex.push(&mut ctx, &pmt[..]);
+        assert_eq!(ctx.program_map().unwrap().program(1).unwrap().pmt_version, Some(2));
+        assert_eq!(ctx.pcr_pid(1), None);
+    }
+
+    #[test]
+    fn filtered_pids() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        let pids = |ctx: &NullDemuxContext| ctx.filtered_pids().unwrap().iter().collect::<Vec<_>>();
+        assert_eq!(pids(&ctx), vec!(0));
+        deplex.push(&mut ctx, &test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]))[..]);
+        assert_eq!(pids(&ctx), vec!(0, 0x101));
+        assert!(ctx.filtered_pids().unwrap().contains(0x101));
+
+        // changes made from outside the filters are seen once the next packet is processed
+        deplex.remove_filter(0x101);
+        let count = std::rc::Rc::new(std::cell::Cell::new(0));
+        deplex.insert_filter(0x102, NullFilterSwitch::Cnt(CountingFilter { needs_payload: false, count }));
+        assert_eq!(pids(&ctx), vec!(0, 0x101));
+        deplex.push(&mut ctx, &test_util::packet(0x103, 0)[..]);
+        assert_eq!(pids(&ctx), vec!(0, 0x102, 0x103));
+
+        deplex.reset(&mut ctx);
+        assert_eq!(pids(&ctx), vec!(0));
+    }
+
+    #[test]
+    fn transport_stream_id() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        assert_eq!(ctx.transport_stream_id(), None);
+        deplex.push(&mut ctx, &test_util::section_packets(0, 0, &test_util::section(0x00, 0x1234, 0, true, &[0, 1, 0xe1, 0x01]))[..]);
+        assert_eq!(ctx.transport_stream_id(), Some(0x1234));
+        let mut pat = test_util::section_packets(0, 0, &test_util::section(0x00, 0x4321, 1, true, &[0, 1, 0xe1, 0x01]));
+        pat[3] |= 1;  // continuity_counter
+        deplex.push(&mut ctx, &pat[..]);
+        assert_eq!(ctx.transport_stream_id(), Some(0x4321));
+        deplex.reset(&mut ctx);
+        assert_eq!(ctx.transport_stream_id(), None);
+    }
+
+    #[test]
+    fn latest_section_bytes() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        let section = |pk: &[u8]| {
+            let len = 3 + ((usize::from(pk[6]) & 0xf) << 8 | usize::from(pk[7]));
+            pk[5..5 + len].to_vec()
+        };
+        let pat = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        let pmt = test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00]));
+        assert_eq!(ctx.latest_pat_bytes(), None);
+        deplex.push(&mut ctx, &pat[..]);
+        deplex.push(&mut ctx, &pmt[..]);
+        assert_eq!(ctx.latest_pat_bytes(), Some(&section(&pat)[..]));
+        assert_eq!(ctx.latest_pmt_bytes(1), Some(&section(&pmt)[..]));
+        assert_eq!(ctx.latest_pmt_bytes(2), None);
+
+        // a new version failing its CRC check does not replace the cached section,
+        let mut bad = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 2, true, &[0, 2, 0xe1, 0x02]));
+        bad[3] |= 1;  // continuity_counter
+        bad[15] ^= 0xff;
+        deplex.push(&mut ctx, &bad[..]);
+        assert_eq!(ctx.latest_pat_bytes(), Some(&section(&pat)[..]));
+
+        // but a valid one does, and the PMT of the program it removes is discarded
+        let mut pat = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 1, true, &[0, 2, 0xe1, 0x02]));
+        pat[3] |= 2;
+        deplex.push(&mut ctx, &pat[..]);
+        assert_eq!(ctx.latest_pat_bytes(), Some(&section(&pat)[..]));
+        assert_eq!(ctx.latest_pmt_bytes(1), None);
+
+        deplex.reset(&mut ctx);
+        assert_eq!(ctx.latest_pat_bytes(), None);
+    }
+
+    #[test]
+    fn next_pat_not_applied() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        let set_cc = |mut buf: Vec<u8>, cc: u8| { buf[3] = (buf[3] & 0xf0) | cc; buf };
+
+        deplex.push(&mut ctx, &test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]))[..]);
+        assert!(deplex.processor_by_pid.contains(0x101));
+
+        // version 1 is announced ahead of time, but must not yet be acted upon
+        let next = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 1, false, &[0, 2, 0xe1, 0x02]));
+        deplex.push(&mut ctx, &set_cc(next, 1)[..]);
+        assert!(deplex.processor_by_pid.contains(0x101));
+        assert!(!deplex.processor_by_pid.contains(0x102));
+        assert!(ctx.program_map().unwrap().program(2).is_none());
+        // the section passed its CRC check, so is counted, but its version is not yet in effect
+        assert_eq!(ctx.psi_stats(0).map(|s| s.crc_ok()), Some(2));
+        assert_eq!(ctx.psi_stats(0).and_then(|s| s.version()), Some(0));
+
+        // the same version then becomes current
+        let current = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 1, true, &[0, 2, 0xe1, 0x02]));
+        deplex.push(&mut ctx, &set_cc(current, 2)[..]);
+        assert!(!deplex.processor_by_pid.contains(0x101));
+        assert!(deplex.processor_by_pid.contains(0x102));
+        assert!(ctx.program_map().unwrap().program(2).is_some());
+        assert_eq!(ctx.psi_stats(0).map(|s| s.crc_ok()), Some(3));
+        assert_eq!(ctx.psi_stats(0).and_then(|s| s.version()), Some(1));
+    }
+
+    #[test]
+    fn next_sections_reported() {
+        use demultiplex::PacketFilter;
+        let mut ctx = VersionDemuxContext::new();
+        let mut pat_filter = demultiplex::PatPacketFilter::new();
+        let mut pmt_filter = demultiplex::PmtPacketFilter::new(0x101, 1);
+        let set_cc = |mut buf: Vec<u8>, cc: u8| { buf[3] = (buf[3] & 0xf0) | cc; buf };
+        let pmt = [0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00];
+        let pat_packets = vec!(
+            test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01])),
+            set_cc(test_util::section_packets(0, 0, &test_util::section(0x00, 1, 1, false, &[0, 2, 0xe1, 0x02])), 1),
+        );
+        for pk in pat_packets {
+            pat_filter.consume(&mut ctx, packet::Packet::new(&pk[..]));
+        }
+        let pmt_packets = vec!(
+            test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &pmt[..])),
+            set_cc(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 3, false, &pmt[..])), 1),
+            set_cc(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 3, false, &pmt[..])), 2),
+        );
+        for pk in pmt_packets {
+            pmt_filter.consume(&mut ctx, packet::Packet::new(&pk[..]));
+        }
+        assert_eq!(ctx.next_sections, vec!((0, 1), (0x101, 3), (0x101, 3)));
+        // the current tables are unaffected
+        assert!(ctx.program_map().unwrap().program(2).is_none());
+        assert_eq!(ctx.program_map().unwrap().program(1).unwrap().pmt_version, Some(0));
+        assert!(ctx.changes.is_empty());
+    }
+
+    #[test]
+    fn pat_ready() {
+        use demultiplex::PacketFilter;
+        let mut ctx = VersionDemuxContext::new();
+        let mut pat_filter = demultiplex::PatPacketFilter::new();
+        let set_cc = |mut buf: Vec<u8>, cc: u8| { buf[3] = (buf[3] & 0xf0) | cc; buf };
+        let pat_packets = vec!(
+            // a section which is not yet applicable does not count
+            test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, false, &[0, 3, 0xe1, 0x03])),
+            // program 0 is the NIT
+            set_cc(test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 2, 0xe1, 0x02, 0, 0, 0xe0, 0x10, 0, 1, 0xe1, 0x01])), 1),
+            set_cc(test_util::section_packets(0, 0, &test_util::section(0x00, 1, 1, true, &[0, 1, 0xe1, 0x01])), 2),
+        );
+        for pk in pat_packets {
+            pat_filter.consume(&mut ctx, packet::Packet::new(&pk[..]));
+        }
+        assert_eq!(ctx.pat_ready, vec!(vec!(2, 1)));
+        assert_eq!(ctx.changes, vec!((demultiplex::TableKind::Pat, 0, 1)));
+    }
+
+    #[test]
+    fn reset() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .pid_stats(true)
+            .build(&mut ctx);
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 3, true, &[0, 1, 0xe1, 0x01]));
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00])));
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(deplex.processor_by_pid.pids(), vec!(0, 0x101, 0x201));
+        assert!(ctx.program_map().unwrap().program(1).is_some());
+
+        deplex.reset(&mut ctx);
+        assert_eq!(deplex.processor_by_pid.pids(), vec!(0));
+        assert_eq!(ctx.program_map().unwrap().programs().count(), 0);
+        assert_eq!(ctx.psi_stats_map().unwrap().pids().count(), 0);
+
+        // the new stream's PAT happens to have the same version_number as the old one, and its
+        // continuity_counter does not follow on, but it must still be processed
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 2, 3, true, &[0, 2, 0xe1, 0x02]));
+        buf[3] = (buf[3] & 0xf0) | 9;
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(deplex.processor_by_pid.pids(), vec!(0, 0x102));
+        assert!(ctx.program_map().unwrap().program(2).is_some());
+        // statistics for the new PAT are not mixed with those of the old
+        assert_eq!(ctx.psi_stats(0).map(|s| s.crc_ok()), Some(1));
+        assert_eq!(deplex.pid_stats().unwrap().total_continuity_errors(), 0);
+    }
+
+    #[test]
+    fn m2ts_packets() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .packet_format(packet::PacketFormat::M2ts)
+            .pid_stats(true)
+            .build(&mut ctx);
+        let mut buf = vec!();
+        for (i, pk) in test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01])).chunks(packet::PACKET_SIZE)
+            .chain(test_util::packet(0x101, 0).chunks(packet::PACKET_SIZE))
+            .enumerate()
+        {
+            // copy_permission_indicator of 0b11, and a 30-bit arrival_time_stamp
+            buf.extend_from_slice(&[0xc0, 0, 0, i as u8]);
+            buf.extend_from_slice(pk);
+        }
+        assert_eq!(buf.len(), 2 * packet::M2TS_PACKET_SIZE);
+        deplex.push(&mut ctx, &buf[..]);
+        assert!(ctx.program_map().unwrap().program(1).is_some());
+        let stats = deplex.pid_stats().unwrap();
+        assert_eq!(stats.total_packets(), 2);
+        assert_eq!(stats.packet_count(0x101), 1);
+
+        let pk = packet::Packet::from_m2ts(&buf[packet::M2TS_PACKET_SIZE..]);
+        assert_eq!(pk.pid(), 0x101);
+        assert_eq!(pk.arrival_time_stamp(), Some(1));
+        assert_eq!(pk.copy_permission_indicator(), Some(0b11));
+        let pk = packet::Packet::new(&buf[packet::M2TS_PACKET_SIZE + 4..]);
+        assert_eq!(pk.arrival_time_stamp(), None);
+        assert_eq!(pk.copy_permission_indicator(), None);
+    }
+
+    #[test]
+    fn stream_offset() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        assert_eq!(ctx.packet_offset(), None);
+        deplex.set_stream_offset(1000);
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00])));
+        buf.extend_from_slice(&test_util::packet(0x201, 0)[..100]);
+        assert_eq!(deplex.push(&mut ctx, &buf[..]), 2 * packet::PACKET_SIZE);
+        assert!(ctx.program_map().unwrap().program(1).unwrap().pmt_version.is_some());
+        // the offset of the packet holding the PMT remains available after it was processed
+        assert_eq!(ctx.packet_offset(), Some(1000 + 188));
+        assert_eq!(deplex.stream_offset(), 1000 + 2 * 188);
+
+        // the partial packet is passed again, completed
+        let pk = test_util::packet(0x201, 0);
+        deplex.push(&mut ctx, &pk[..]);
+        assert_eq!(ctx.packet_offset(), Some(1000 + 2 * 188));
+        let mut pk_buf = [0u8; packet::PACKET_SIZE];
+        pk_buf.copy_from_slice(&test_util::packet(0x201, 1)[..]);
+        deplex.push_packet(&mut ctx, &pk_buf);
+        assert_eq!(ctx.packet_offset(), Some(1000 + 3 * 188));
+        assert_eq!(deplex.stream_offset(), 1000 + 4 * 188);
+    }
+
+    #[test]
+    fn builder() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .packet_format(packet::PacketFormat::Fec)
+            .skip_errored_packets(true)
+            .resync(true)
+            .auto_construct_filters(false)
+            .pid_stats(true)
+            .build(&mut ctx);
+        let count = std::rc::Rc::new(std::cell::Cell::new(0));
+        deplex.insert_filter(101, NullFilterSwitch::Cnt(CountingFilter { needs_payload: false, count: count.clone() }));
+        let fec = |mut pk: Vec<u8>| {
+            pk.extend_from_slice(&[0; 16]);
+            pk
+        };
+        let mut errored = test_util::packet(101, 1);
+        errored[1] |= 0b1000_0000;  // transport_error_indicator
+        let mut buf = vec!();
+        buf.extend(fec(test_util::packet(101, 0)));
+        buf.extend_from_slice(&[0; 5]);  // junk, causing loss of sync
+        buf.extend(fec(test_util::packet(102, 0)));
+        buf.extend(fec(errored));
+        buf.extend(fec(test_util::packet(101, 1)));
+        buf.extend_from_slice(&test_util::packet(101, 2)[..100]);
+        assert_eq!(deplex.push(&mut ctx, &buf[..]), buf.len() - 100);
+        assert_eq!(count.get(), 2);
+        // no filter was created for PID 102
+        assert_eq!(deplex.processor_by_pid.pids(), vec!(0, 101));
+        let stats = deplex.pid_stats().unwrap();
+        assert_eq!(stats.total_packets(), 3);
+        assert_eq!(stats.total_continuity_errors(), 0);
+
+        // without resync, processing stops where sync is lost
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .packet_format(packet::PacketFormat::Fec)
+            .build(&mut ctx);
+        assert_eq!(deplex.push(&mut ctx, &buf[..]), packet::FEC_PACKET_SIZE);
+    }
+
+    #[test]
+    fn builder_pat_pid() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .pat_pid(0x20)
+            .build(&mut ctx);
+        assert!(deplex.processor_by_pid.contains(0x20));
+        assert!(!deplex.processor_by_pid.contains(0));
+        let mut buf = test_util::section_packets(0x20, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00])));
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(ctx.program_map().unwrap().program(1).map(|p| p.pmt_pid), Some(0x101));
+        assert_eq!(ctx.program_map().unwrap().program(1).unwrap().streams.len(), 1);
+        // the PAT's statistics are recorded against the PID which carries it
+        assert_eq!(ctx.psi_stats(0x20).map(|s| s.crc_ok()), Some(1));
+        assert!(ctx.psi_stats(0).is_none());
+
+        deplex.reset(&mut ctx);
+        assert!(deplex.processor_by_pid.contains(0x20));
+    }
+
+    #[test]
+    fn remapped_pat_hooks() {
+        use demultiplex::PacketFilter;
+        let mut ctx = VersionDemuxContext::new();
+        let mut pat_filter = demultiplex::PatPacketFilter::new();
+        let pk = test_util::section_packets(0x20, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        pat_filter.consume(&mut ctx, packet::Packet::new(&pk[..]));
+        let mut pk = test_util::section_packets(0x20, 0, &test_util::section(0x00, 1, 1, false, &[0, 1, 0xe1, 0x01]));
+        pk[3] |= 1;  // continuity_counter
+        pat_filter.consume(&mut ctx, packet::Packet::new(&pk[..]));
+        assert_eq!(ctx.raw_sections.iter().map(|&(pid, _)| pid).collect::<Vec<_>>(), vec!(0x20, 0x20));
+        assert_eq!(ctx.next_sections, vec!((0x20, 1)));
+        assert_eq!(ctx.psi_stats(0x20).map(|s| s.crc_ok()), Some(2));
+    }
+
+    #[test]
+    fn event_sink() {
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 7, 3, true, &[0, 1, 0xe1, 0x01]));
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 5, true, &[
+            0xe2, 0x01,  // PCR_PID
+            0xf0, 0x00,  // program_info_length
+            0x1b, 0xe2, 0x01, 0xf0, 0x00,  // H264 stream on PID 0x201
+        ])));
+        buf.extend(test_util::packet(0x201, 0));
+        buf.extend(test_util::packet(0x300, 0));
+        buf.extend(test_util::packet(0x300, 1));
+
+        let mut ctx = demultiplex::EventDemuxContext::new(RecordingSink::default());
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        deplex.push(&mut ctx, &buf[..]);
+
+        let sink = ctx.into_sink();
+        assert_eq!(sink.pats, vec!(demultiplex::PatSnapshot {
+            transport_stream_id: 7,
+            version: 3,
+            programs: vec!(demultiplex::PatEntry { program_number: 1, pid: 0x101 }),
+        }));
+        assert_eq!(sink.pmts.len(), 1);
+        let (program_number, ref pmt) = sink.pmts[0];
+        assert_eq!(program_number, 1);
+        assert_eq!(pmt.pid, 0x101);
+        assert_eq!(pmt.version, 5);
+        assert_eq!(pmt.pcr_pid, 0x201);
+        assert_eq!(pmt.streams.len(), 1);
+        assert_eq!(pmt.streams[0].elementary_pid, 0x201);
+        assert_eq!(pmt.streams[0].stream_type, ::StreamType::H264);
+        // the stream announced in the PMT is not 'unhandled', and each PID is reported once
+        assert_eq!(sink.unhandled, vec!(0x300));
+    }
+
+    #[test]
+    fn pat_no_existing_program() {
+        let mut processor = demultiplex::PatProcessor::new();
+        let section = vec!(
+            // common header
+            0, 0, 0,
 
             // table syntax header
             0x0D, 0x00, 0b00000001, 0xC1, 0x00,
@@ -785,7 +3723,7 @@ mod test {
         let table_syntax_header = psi::TableSyntaxHeader::new(&section[psi::SectionCommonHeader::SIZE..]);
         let mut ctx = NullDemuxContext::new(NullStreamConstructor);
         processor.section(&mut ctx, &header, &table_syntax_header, &section[..]);
-        let mut changes = ctx.changeset.updates.into_iter();
+        let mut changes = std::mem::take(&mut ctx.filter_changeset().updates).into_iter();
         assert_matches!(changes.next(), Some(demultiplex::FilterChange::Insert(101, _)));
     }
 
@@ -811,7 +3749,7 @@ mod test {
             let table_syntax_header = psi::TableSyntaxHeader::new(&section[psi::SectionCommonHeader::SIZE..]);
             processor.section(&mut ctx, &header, &table_syntax_header, &section[..]);
         }
-        ctx.changeset.updates.clear();
+        ctx.filter_changeset().updates.clear();
         {
             let section = vec!(
                 // common header
@@ -828,17 +3766,247 @@ mod test {
             let table_syntax_header = psi::TableSyntaxHeader::new(&section[psi::SectionCommonHeader::SIZE..]);
             processor.section(&mut ctx, &header, &table_syntax_header, &section[..]);
         }
-        let mut changes = ctx.changeset.updates.into_iter();
+        let mut changes = std::mem::take(&mut ctx.filter_changeset().updates).into_iter();
         assert_matches!(changes.next(), Some(demultiplex::FilterChange::Remove(101,)));
     }
 
-    fn make_test_data<F>(builder: F) -> Vec<u8>
-    where
-        F: Fn(BitWriter<BE>)->Result<(), io::Error>
-    {
-        let mut data: Vec<u8> = Vec::new();
-        builder(BitWriter::<BE>::new(&mut data)).unwrap();
-        data
+    #[test]
+    fn pat_network_pid() {
+        let mut processor = demultiplex::PatProcessor::new();
+        let section = vec!(
+            // common header
+            0, 0, 0,
+
+            // table syntax header
+            0x0D, 0x00, 0b00000001, 0xC1, 0x00,
+
+            0, 0,   // program_number 0 => network_PID
+            0, 16,  // pid
+            0, 1,   // program_number
+            0, 101, // pid
+            0, 0, 0, 0  // CRC (incorrect!)
+        );
+        let header = psi::SectionCommonHeader::new(&section[..psi::SectionCommonHeader::SIZE]);
+        let table_syntax_header = psi::TableSyntaxHeader::new(&section[psi::SectionCommonHeader::SIZE..]);
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        processor.section(&mut ctx, &header, &table_syntax_header, &section[..]);
+        let mut changes = std::mem::take(&mut ctx.filter_changeset().updates).into_iter();
+        assert_matches!(changes.next(), Some(demultiplex::FilterChange::Insert(16, NullFilterSwitch::Nul(_))));
+        assert_matches!(changes.next(), Some(demultiplex::FilterChange::Insert(101, NullFilterSwitch::Pmt(_))));
+    }
+
+    #[test]
+    fn pmt_program_info_length_too_long() {
+        let mut processor = demultiplex::PmtProcessor::new(101, 1001);
+        let section = test_util::make_test_data(|mut w| {
+            // common section header,
+            w.write(8, 0x02)?;   // table_id
+            w.write_bit(true)?;  // section_syntax_indicator
+            w.write_bit(false)?; // private_indicator
+            w.write(2, 3)?;      // reserved
+            w.write(12, 18)?;    // section_length
+
+            // section syntax header,
+            w.write(16, 0)?;    // id
+            w.write(2, 3)?;     // reserved
+            w.write(5, 0)?;     // version
+            w.write(1, 1)?;     // current_next_indicator
+            w.write(8, 0)?;     // section_number
+            w.write(8, 0)?;     // last_section_number
+
+            // PMT section payload
+            w.write(3, 7)?;     // reserved
+            w.write(13, 123)?;  // pcr_pid
+            w.write(4, 15)?;    // reserved
+            w.write(12, 4095)?; // program_info_length (far too long!)
+            w.write(8, 0)?;     // stream_type
+            w.write(3, 7)?;     // reserved
+            w.write(13, 201)?;  // elementary_pid
+            w.write(4, 15)?;    // reserved
+            w.write(12, 0)?;    // es_info_length
+            w.write(32, 0)      // CRC (incorrect)
+        });
+        let header = psi::SectionCommonHeader::new(&section[..psi::SectionCommonHeader::SIZE]);
+        let table_syntax_header = psi::TableSyntaxHeader::new(&section[psi::SectionCommonHeader::SIZE..]);
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        processor.section(&mut ctx, &header, &table_syntax_header, &section[..]);
+        assert!(ctx.filter_changeset().updates.is_empty());
+    }
+
+    #[test]
+    fn stream_info_from_bytes() {
+        // too short to hold the header,
+        assert!(demultiplex::StreamInfo::from_bytes(&[0x1b, 0xe0, 0x65, 0xf0][..]).is_none());
+        // es_info_length=3, but only 2 bytes of descriptor data follow,
+        assert!(demultiplex::StreamInfo::from_bytes(&[0x1b, 0xe0, 0x65, 0xf0, 3, 0x0a, 0][..]).is_none());
+
+        let data = [
+            0x1b, 0xe0, 0x65, 0xf0, 2,  // H264 stream on PID 101, es_info_length=2
+            0x0a, 0,                    // empty ISO_639_language_descriptor
+            0x0f, 0xe0, 0x66, 0xf0, 0,  // following entry
+        ];
+        let (info, len) = demultiplex::StreamInfo::from_bytes(&data[..]).unwrap();
+        assert_eq!(len, 7);
+        assert_eq!(info.stream_type(), ::StreamType::H264);
+        assert_eq!(info.elementary_pid(), 101);
+        assert_eq!(info.descriptors().count(), 1);
+    }
+
+    packet_filter_switch!{
+        VersionFilterSwitch<VersionDemuxContext> {
+            Nul: demultiplex::NullPacketFilter<VersionDemuxContext>,
+        }
+    }
+    /// records the `(program_number, elementary_pid)` of each stream requested
+    pub struct VersionStreamConstructor {
+        streams: Vec<(u16, u16)>,
+    }
+    impl demultiplex::StreamConstructor for VersionStreamConstructor {
+        type F = VersionFilterSwitch;
+
+        fn construct(&mut self, req: demultiplex::FilterRequest) -> Self::F {
+            if let demultiplex::FilterRequest::ByStream { program_number, stream_info, .. } = req {
+                self.streams.push((program_number, stream_info.elementary_pid()));
+            }
+            VersionFilterSwitch::Nul(demultiplex::NullPacketFilter::new())
+        }
+    }
+    pub struct VersionDemuxContext {
+        changeset: demultiplex::FilterChangeset<VersionFilterSwitch>,
+        constructor: VersionStreamConstructor,
+        program_map: demultiplex::ProgramMap,
+        psi_stats: demultiplex::PsiStatsMap,
+        changes: Vec<(demultiplex::TableKind, u8, u8)>,
+        unexpected: Vec<(u16, u8)>,
+        raw_sections: Vec<(u16, Vec<u8>)>,
+        /// `(pid, version)` of each 'next' section
+        next_sections: Vec<(u16, u8)>,
+        /// the `program_numbers` given to each `pat_ready()` call
+        pat_ready: Vec<Vec<u16>>,
+        /// `(program_number, count of program-level descriptors)` for each `pmt_updated()` call
+        pmts: Vec<(u16, usize)>,
+    }
+    impl demultiplex::DemuxContext for VersionDemuxContext {
+        type F = VersionFilterSwitch;
+        type Ctor = VersionStreamConstructor;
+
+        fn filter_changeset(&mut self) -> &mut demultiplex::FilterChangeset<Self::F> {
+            &mut self.changeset
+        }
+        fn filter_constructor(&mut self) -> &mut Self::Ctor {
+            &mut self.constructor
+        }
+        fn program_map(&self) -> Option<&demultiplex::ProgramMap> {
+            Some(&self.program_map)
+        }
+        fn program_map_mut(&mut self) -> Option<&mut demultiplex::ProgramMap> {
+            Some(&mut self.program_map)
+        }
+        fn psi_stats_map(&self) -> Option<&demultiplex::PsiStatsMap> {
+            Some(&self.psi_stats)
+        }
+        fn psi_stats_map_mut(&mut self) -> Option<&mut demultiplex::PsiStatsMap> {
+            Some(&mut self.psi_stats)
+        }
+        fn table_version_changed(&mut self, table: demultiplex::TableKind, old_version: u8, new_version: u8) {
+            self.changes.push((table, old_version, new_version));
+        }
+        fn unexpected_table(&mut self, pid: u16, _expected: demultiplex::TableKind, header: &psi::SectionCommonHeader, _table_syntax_header: &psi::TableSyntaxHeader, _data: &[u8]) {
+            self.unexpected.push((pid, header.table_id));
+        }
+        fn raw_section(&mut self, pid: u16, _table: demultiplex::TableKind, data: &[u8]) {
+            self.raw_sections.push((pid, data.to_vec()));
+        }
+        fn next_section(&mut self, pid: u16, _table: demultiplex::TableKind, table_syntax_header: &psi::TableSyntaxHeader, _data: &[u8]) {
+            assert_eq!(table_syntax_header.current_next_indicator(), psi::CurrentNext::Next);
+            self.next_sections.push((pid, table_syntax_header.version()));
+        }
+        fn pat_ready(&mut self, program_numbers: &[u16]) {
+            // the programs are already in the map
+            for pn in program_numbers {
+                assert!(self.program_map.program(*pn).is_some());
+            }
+            self.pat_ready.push(program_numbers.to_vec());
+        }
+        fn pmt_updated(&mut self, program_number: u16, pmt: &demultiplex::PmtSection) {
+            // requested before any of the program's streams
+            assert!(self.constructor.streams.is_empty());
+            self.pmts.push((program_number, pmt.descriptors().count()));
+        }
+    }
+    impl VersionDemuxContext {
+        fn new() -> VersionDemuxContext {
+            VersionDemuxContext {
+                changeset: demultiplex::FilterChangeset::new(),
+                constructor: VersionStreamConstructor { streams: vec!() },
+                program_map: demultiplex::ProgramMap::new(),
+                psi_stats: demultiplex::PsiStatsMap::new(),
+                changes: vec!(),
+                unexpected: vec!(),
+                raw_sections: vec!(),
+                next_sections: vec!(),
+                pat_ready: vec!(),
+                pmts: vec!(),
+            }
+        }
+    }
+
+    #[test]
+    fn pat_version_changed() {
+        let mut ctx = VersionDemuxContext::new();
+        let mut processor = demultiplex::PatProcessor::new();
+        for &version_byte in &[0b00000111, 0b00000111, 0b00001001] {
+            let section = vec!(
+                // common header
+                0, 0, 0,
+
+                // table syntax header
+                0x0D, 0x00, version_byte, 0xC1, 0x00,
+
+                0, 1,   // program_number
+                0, 101, // pid
+                0, 0, 0, 0  // CRC (incorrect!)
+            );
+            let header = psi::SectionCommonHeader::new(&section[..psi::SectionCommonHeader::SIZE]);
+            let table_syntax_header = psi::TableSyntaxHeader::new(&section[psi::SectionCommonHeader::SIZE..]);
+            processor.section(&mut ctx, &header, &table_syntax_header, &section[..]);
+        }
+        assert_eq!(ctx.changes, vec!((demultiplex::TableKind::Pat, 3, 4)));
+    }
+
+    #[test]
+    fn pmt_pid_with_private_table() {
+        use demultiplex::PacketFilter;
+
+        let mut ctx = VersionDemuxContext::new();
+        let mut filter = demultiplex::PmtPacketFilter::new(0x101, 1);
+        // a private table with the same version_number as the PMT which follows must not cause
+        // the PMT to be discarded as a duplicate
+        let private = test_util::section_packets(0x101, 0, &test_util::section(0xc0, 1, 0, true, &[1, 2, 3]));
+        filter.consume(&mut ctx, packet::Packet::new(&private[..]));
+        assert_eq!(ctx.unexpected, vec!((0x101, 0xc0)));
+        assert_eq!(ctx.program_map().unwrap().program(1), None);
+        // the section passed its CRC check, so is counted, despite being ignored
+        assert_eq!(ctx.psi_stats(0x101).map(|s| s.crc_ok()), Some(1));
+        assert_eq!(ctx.psi_stats(0x101).and_then(|s| s.version()), None);
+
+        let mut pmt = test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[
+            0xe2, 0x01,
+            0xf0, 0x06,  // program_info_length
+            0x09, 0x04, 0x0b, 0x00, 0xe3, 0x00,  // CA_descriptor
+            0x1b, 0xe2, 0x01, 0xf0, 0x00,
+        ]));
+        pmt[3] |= 1;  // continuity_counter follows that of the previous packet
+        filter.consume(&mut ctx, packet::Packet::new(&pmt[..]));
+        assert_eq!(ctx.unexpected.len(), 1);
+        assert_eq!(ctx.pmts, vec!((1, 1)));
+        // only the PMT is delivered as a raw section, complete with its header and CRC
+        let section_length = 5 + 15 + 4;
+        assert_eq!(ctx.raw_sections, vec!((0x101, pmt[5..5 + 3 + section_length].to_vec())));
+        let program = ctx.program_map().unwrap().program(1).unwrap();
+        assert_eq!(program.pmt_version, Some(0));
+        assert_eq!(program.streams.len(), 1);
+        assert_eq!(ctx.constructor.streams, vec!((1, 0x201)));
     }
 
     #[test]
@@ -847,7 +4015,7 @@ mod test {
         let pid = 101;
         let program_number = 1001;
         let mut processor = demultiplex::PmtProcessor::new(pid, program_number);
-        let section = make_test_data(|mut w| {
+        let section = test_util::make_test_data(|mut w| {
             // common section header,
             w.write(8, 0x02)?;   // table_id
             w.write_bit(true)?;  // section_syntax_indicator
@@ -888,7 +4056,360 @@ mod test {
         let table_syntax_header = psi::TableSyntaxHeader::new(&section[psi::SectionCommonHeader::SIZE..]);
         let mut ctx = NullDemuxContext::new(NullStreamConstructor);
         processor.section(&mut ctx, &header, &table_syntax_header, &section[..]);
-        let mut changes = ctx.changeset.updates.into_iter();
+        let mut changes = std::mem::take(&mut ctx.filter_changeset().updates).into_iter();
         assert_matches!(changes.next(), Some(demultiplex::FilterChange::Insert(201,_)));
     }
+
+    #[test]
+    fn pmt_duplicate_pid() {
+        let pmt_section = |version: u8, entries: &[(u8, u16)]| {
+            let section_length = psi::TableSyntaxHeader::SIZE + 4 + entries.len() * 5 + 4;
+            let mut section = vec!(
+                0x02, 0b1011_0000 | (section_length >> 8) as u8, section_length as u8,
+                0x00, 0x01, 0b1100_0001 | version << 1, 0x00, 0x00,
+                0xe2, 0x01, 0xf0, 0x00,  // PCR_PID, program_info_length
+            );
+            for &(stream_type, pid) in entries {
+                section.extend_from_slice(&[stream_type, 0xe0 | (pid >> 8) as u8, pid as u8, 0xf0, 0x00]);
+            }
+            section.extend_from_slice(&[0, 0, 0, 0]);  // CRC (not checked here)
+            section
+        };
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut processor = demultiplex::PmtProcessor::new(0x101, 1);
+        let mut process = |ctx: &mut NullDemuxContext, section: Vec<u8>| {
+            let header = psi::SectionCommonHeader::new(&section[..psi::SectionCommonHeader::SIZE]);
+            let table_syntax_header = psi::TableSyntaxHeader::new(&section[psi::SectionCommonHeader::SIZE..]);
+            processor.section(ctx, &header, &table_syntax_header, &section[..]);
+        };
+
+        process(&mut ctx, pmt_section(0, &[(0x1b, 0x201), (0x0f, 0x201), (0x0f, 0x202)]));
+        {
+            let mut changes = ctx.filter_changeset().updates.drain(..);
+            assert_matches!(changes.next(), Some(demultiplex::FilterChange::Insert(0x201, _)));
+            assert_matches!(changes.next(), Some(demultiplex::FilterChange::Insert(0x202, _)));
+            assert!(changes.next().is_none());
+        }
+        let streams = &ctx.program_map().unwrap().program(1).unwrap().streams;
+        assert_eq!(streams.len(), 2);
+        assert_eq!(streams[0].stream_type, ::StreamType::H264);
+
+        // the PID listed twice is still known to have a filter, and so is not removed
+        process(&mut ctx, pmt_section(1, &[(0x1b, 0x201)]));
+        let mut changes = ctx.filter_changeset().updates.drain(..);
+        assert_matches!(changes.next(), Some(demultiplex::FilterChange::Insert(0x201, _)));
+        assert_matches!(changes.next(), Some(demultiplex::FilterChange::Remove(0x202)));
+        assert!(changes.next().is_none());
+    }
+
+    #[test]
+    fn program_map() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut pat_processor = demultiplex::PatProcessor::new();
+        let pat_section = vec!(
+            // common header
+            0, 0, 0,
+
+            // table syntax header
+            0x0D, 0x00, 0b00000001, 0xC1, 0x00,
+
+            0, 1,   // program_number
+            0, 101, // pid
+            0, 0, 0, 0  // CRC (incorrect!)
+        );
+        let header = psi::SectionCommonHeader::new(&pat_section[..psi::SectionCommonHeader::SIZE]);
+        let table_syntax_header = psi::TableSyntaxHeader::new(&pat_section[psi::SectionCommonHeader::SIZE..]);
+        pat_processor.section(&mut ctx, &header, &table_syntax_header, &pat_section[..]);
+        assert_eq!(ctx.program_map().unwrap().program(1).map(|p| p.pmt_pid), Some(101));
+        assert_eq!(ctx.program_map().unwrap().program(1).map(|p| p.pmt_version), Some(None));
+
+        let mut pmt_processor = demultiplex::PmtProcessor::new(101, 1);
+        let pmt_section = test_util::make_test_data(|mut w| {
+            // common section header,
+            w.write(8, 0x02)?;   // table_id
+            w.write_bit(true)?;  // section_syntax_indicator
+            w.write_bit(false)?; // private_indicator
+            w.write(2, 3)?;      // reserved
+            w.write(12, 25)?;    // section_length
+
+            // section syntax header,
+            w.write(16, 1)?;    // id
+            w.write(2, 3)?;     // reserved
+            w.write(5, 4)?;     // version
+            w.write(1, 1)?;     // current_next_indicator
+            w.write(8, 0)?;     // section_number
+            w.write(8, 0)?;     // last_section_number
+
+            // PMT section payload
+            w.write(3, 7)?;     // reserved
+            w.write(13, 201)?;  // pcr_pid
+            w.write(4, 15)?;    // reserved
+            w.write(12, 0)?;    // program_info_length
+            w.write(8, 0x1b)?;  // stream_type
+            w.write(3, 7)?;     // reserved
+            w.write(13, 201)?;  // elementary_pid
+            w.write(4, 15)?;    // reserved
+            w.write(12, 0)?;    // es_info_length
+            w.write(8, 0x0f)?;  // stream_type
+            w.write(3, 7)?;     // reserved
+            w.write(13, 202)?;  // elementary_pid
+            w.write(4, 15)?;    // reserved
+            w.write(12, 6)?;    // es_info_length
+            w.write(8, 0x0a)?;  // descriptor_tag (ISO_639_language_descriptor)
+            w.write(8, 4)?;     // descriptor_length
+            w.write_bytes(b"eng")?;
+            w.write(8, 0)?;     // audio_type
+            w.write(32, 0)      // CRC (incorrect)
+        });
+        let header = psi::SectionCommonHeader::new(&pmt_section[..psi::SectionCommonHeader::SIZE]);
+        let table_syntax_header = psi::TableSyntaxHeader::new(&pmt_section[psi::SectionCommonHeader::SIZE..]);
+        pmt_processor.section(&mut ctx, &header, &table_syntax_header, &pmt_section[..]);
+        {
+            let program = ctx.program_map().unwrap().program(1).unwrap();
+            assert_eq!(program.pmt_version, Some(4));
+            assert_eq!(program.pcr_pid, Some(201));
+            assert_eq!(program.streams.len(), 2);
+            assert_eq!(program.streams[0].stream_type, ::StreamType::H264);
+            assert_eq!(program.streams[0].language, None);
+            assert_eq!(program.streams[1].elementary_pid, 202);
+            assert_eq!(program.streams[1].language, Some("eng".to_string()));
+            assert_eq!(program.streams[1].es_info, vec!(0x0a, 4, b'e', b'n', b'g', 0));
+        }
+
+        // an updated PAT which no longer lists program 1,
+        let pat_section = vec!(
+            // common header
+            0, 0, 0,
+
+            // table syntax header
+            0x0D, 0x00, 0b00000011, 0xC1, 0x00,
+
+            0, 0, 0, 0  // CRC (incorrect!)
+        );
+        let header = psi::SectionCommonHeader::new(&pat_section[..psi::SectionCommonHeader::SIZE]);
+        let table_syntax_header = psi::TableSyntaxHeader::new(&pat_section[psi::SectionCommonHeader::SIZE..]);
+        pat_processor.section(&mut ctx, &header, &table_syntax_header, &pat_section[..]);
+        assert_eq!(ctx.program_map().unwrap().programs().count(), 0);
+    }
+
+    #[test]
+    fn program_bitrate() {
+        let pcr_packet = |cc: u8, millis: u64| test_util::pcr_packet(0x201, cc, millis * 27_000, false);
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::Demultiplex::new(&mut ctx);
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00])));
+        buf.extend(pcr_packet(0, 0));
+        buf.extend(test_util::packet(0x202, 0));
+        buf.extend(pcr_packet(1, 2));
+
+        // not measured unless enabled,
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(ctx.program_bitrate(1), None);
+
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .program_bitrate(true)
+            .build(&mut ctx);
+        deplex.push(&mut ctx, &buf[..]);
+        // two packets (the second carrying the PCR) in 2ms,
+        assert_eq!(ctx.program_bitrate(1), Some(2.0 * 188.0 * 8.0 / 0.002));
+        let mut more = test_util::packet(0x202, 1);
+        more.extend(test_util::packet(0x202, 2));
+        more.extend(pcr_packet(2, 5));
+        deplex.push(&mut ctx, &more[..]);
+        assert_eq!(ctx.program_bitrate(1), Some(3.0 * 188.0 * 8.0 / 0.003));
+        assert_eq!(ctx.program_bitrate(2), None);
+
+        deplex.reset(&mut ctx);
+        assert_eq!(ctx.program_bitrate(1), None);
+    }
+
+    #[test]
+    fn program_bitrate_fec() {
+        // each packet is followed by 16 bytes of Reed-Solomon parity data, which are not part of
+        // the Transport Stream, so are not counted
+        let fec = |pk: Vec<u8>| pk.chunks(packet::PACKET_SIZE).flat_map(|p| {
+            let mut p = p.to_vec();
+            p.resize(packet::FEC_PACKET_SIZE, 0);
+            p
+        }).collect::<Vec<u8>>();
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .packet_format(packet::PacketFormat::Fec)
+            .program_bitrate(true)
+            .build(&mut ctx);
+        let mut buf = fec(test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01])));
+        buf.extend(fec(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00]))));
+        buf.extend(fec(test_util::pcr_packet(0x201, 0, 0, false)));
+        buf.extend(fec(test_util::packet(0x202, 0)));
+        let mut errored = test_util::packet(0x202, 1);
+        errored[1] |= 0b1000_0000;  // transport_error_indicator
+        buf.extend(fec(errored));
+        buf.extend(fec(test_util::pcr_packet(0x201, 0, 2 * 27_000, false)));
+        deplex.push(&mut ctx, &buf[..]);
+        // three 188 byte packets counted in 2ms, including the errored packet
+        assert_eq!(ctx.program_bitrate(1), Some(3.0 * 188.0 * 8.0 / 0.002));
+    }
+
+    #[test]
+    fn has_dedicated_pcr_pid() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let stream = |elementary_pid| demultiplex::StreamEntry {
+            elementary_pid,
+            stream_type: ::StreamType::H264,
+            language: None,
+            es_info: vec!(),
+        };
+        assert_eq!(ctx.program_map().unwrap().has_dedicated_pcr_pid(1), None);
+        ctx.program_map_mut().unwrap().update_program(1, 101, 0, 201, vec!(stream(201), stream(202)));
+        ctx.program_map_mut().unwrap().update_program(2, 102, 0, 300, vec!(stream(301)));
+        ctx.program_map_mut().unwrap().update_program(3, 103, 0, packet::NULL_PID, vec!(stream(401)));
+        assert_eq!(ctx.program_map().unwrap().has_dedicated_pcr_pid(1), Some(false));
+        assert_eq!(ctx.program_map().unwrap().has_dedicated_pcr_pid(2), Some(true));
+        assert_eq!(ctx.program_map().unwrap().has_dedicated_pcr_pid(3), Some(false));
+    }
+
+    #[test]
+    fn pid_for_component_tag() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let stream = |elementary_pid, es_info: &str| demultiplex::StreamEntry {
+            elementary_pid,
+            stream_type: ::StreamType::H264,
+            language: None,
+            es_info: base16::decode(es_info.as_bytes()).unwrap(),
+        };
+        ctx.program_map_mut().unwrap().update_program(1, 101, 0, 201, vec!(
+            stream(201, "520101"),
+            // the stream_identifier_descriptor follows an ISO_639_language_descriptor
+            stream(202, "0A04656E6700520102"),
+            stream(203, ""),
+        ));
+        assert_eq!(ctx.pid_for_component_tag(1, 1), Some(201));
+        assert_eq!(ctx.pid_for_component_tag(1, 2), Some(202));
+        assert_eq!(ctx.pid_for_component_tag(1, 3), None);
+        assert_eq!(ctx.pid_for_component_tag(2, 1), None);
+        assert_eq!(ctx.program_map().unwrap().program(1).unwrap().streams[2].component_tag(), None);
+    }
+
+    #[test]
+    fn all_streams() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let stream = |elementary_pid, stream_type| demultiplex::StreamEntry {
+            elementary_pid,
+            stream_type,
+            language: None,
+            es_info: vec!(),
+        };
+        ctx.program_map_mut().unwrap().update_program(2, 102, 0, 301, vec!(
+            stream(301, ::StreamType::H264),
+        ));
+        ctx.program_map_mut().unwrap().update_program(1, 101, 0, 201, vec!(
+            stream(201, ::StreamType::H264),
+            stream(202, ::StreamType::Adts),
+        ));
+        let all: Vec<_> = ctx.all_streams()
+            .map(|(program_number, stream)| (program_number, stream.elementary_pid, stream.stream_type))
+            .collect();
+        assert_eq!(all, vec!(
+            (1, 201, ::StreamType::H264),
+            (1, 202, ::StreamType::Adts),
+            (2, 301, ::StreamType::H264),
+        ));
+    }
+
+    #[test]
+    fn subtitle_tracks() {
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let stream = |elementary_pid, es_info: &str| demultiplex::StreamEntry {
+            elementary_pid,
+            stream_type: ::StreamType::H2220PesPrivateData,
+            language: None,
+            es_info: base16::decode(es_info.as_bytes()).unwrap(),
+        };
+        ctx.program_map_mut().unwrap().update_program(1, 101, 0, 201, vec!(
+            stream(201, ""),
+            // teletext: an initial page, which is skipped, and a subtitle page on 888
+            stream(202, "560A656E6709006E6C641088"),
+            // DVB subtitles, following an unrelated descriptor
+            stream(203, "0A04656E670059086465751000010002"),
+        ));
+        assert_eq!(ctx.subtitle_tracks(), vec!(
+            demultiplex::SubtitleTrack {
+                program_number: 1,
+                pid: 202,
+                language: "nld".to_string(),
+                kind: demultiplex::SubtitleKind::Teletext { teletext_type: 2, magazine_number: 0, page_number: 0x88 },
+            },
+            demultiplex::SubtitleTrack {
+                program_number: 1,
+                pid: 203,
+                language: "deu".to_string(),
+                kind: demultiplex::SubtitleKind::Dvb { subtitling_type: 0x10, composition_page_id: 1, ancillary_page_id: 2 },
+            },
+        ));
+    }
+
+    packet_filter_switch!{
+        MinimalFilterSwitch<MinimalDemuxContext> {
+            Pat: demultiplex::PatPacketFilter<MinimalDemuxContext>,
+            Pmt: demultiplex::PmtPacketFilter<MinimalDemuxContext>,
+            Nul: demultiplex::NullPacketFilter<MinimalDemuxContext>,
+        }
+    }
+    pub struct MinimalStreamConstructor {
+        streams: Vec<u16>,
+    }
+    impl demultiplex::StreamConstructor for MinimalStreamConstructor {
+        type F = MinimalFilterSwitch;
+
+        fn construct(&mut self, req: demultiplex::FilterRequest) -> Self::F {
+            match req {
+                demultiplex::FilterRequest::ByPid(0) => MinimalFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
+                demultiplex::FilterRequest::Pmt{pid, program_number} => MinimalFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
+                demultiplex::FilterRequest::ByStream { stream_info, .. } => {
+                    self.streams.push(stream_info.elementary_pid());
+                    MinimalFilterSwitch::Nul(demultiplex::NullPacketFilter::new())
+                },
+                _ => MinimalFilterSwitch::Nul(demultiplex::NullPacketFilter::new()),
+            }
+        }
+    }
+    /// implements only the required methods of `DemuxContext`
+    pub struct MinimalDemuxContext {
+        changeset: demultiplex::FilterChangeset<MinimalFilterSwitch>,
+        constructor: MinimalStreamConstructor,
+    }
+    impl demultiplex::DemuxContext for MinimalDemuxContext {
+        type F = MinimalFilterSwitch;
+        type Ctor = MinimalStreamConstructor;
+
+        fn filter_changeset(&mut self) -> &mut demultiplex::FilterChangeset<Self::F> {
+            &mut self.changeset
+        }
+        fn filter_constructor(&mut self) -> &mut Self::Ctor {
+            &mut self.constructor
+        }
+    }
+
+    #[test]
+    fn minimal_context() {
+        let mut ctx = MinimalDemuxContext {
+            changeset: demultiplex::FilterChangeset::new(),
+            constructor: MinimalStreamConstructor { streams: vec!() },
+        };
+        let mut deplex = demultiplex::DemultiplexBuilder::new()
+            .program_bitrate(true)
+            .build(&mut ctx);
+        let mut buf = test_util::section_packets(0, 0, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01]));
+        buf.extend(test_util::section_packets(0x101, 0, &test_util::section(0x02, 1, 0, true, &[0xe2, 0x01, 0xf0, 0x00, 0x1b, 0xe2, 0x01, 0xf0, 0x00])));
+        buf.extend(test_util::pcr_packet(0x201, 0, 0, false));
+        buf.extend(test_util::pcr_packet(0x201, 0, 27_000, false));
+        deplex.push(&mut ctx, &buf[..]);
+        assert_eq!(ctx.constructor.streams, vec!(0x201));
+        assert!(ctx.program_map().is_none());
+        assert_eq!(ctx.pcr_pid(1), None);
+        assert_eq!(ctx.all_streams().count(), 0);
+        assert!(ctx.psi_stats(0).is_none());
+    }
 }
@@ -1,6 +1,9 @@
 use std::fmt;
 use hex_slice::AsHex;
 
+pub mod atsc;
+pub mod dvb;
+
 #[derive(Debug)]
 pub enum Descriptor<'buf> {
     Reserved { tag: u8, payload: &'buf[u8]},
@@ -42,7 +45,7 @@ impl<'buf> Descriptor<'buf> {
         let len = buf[1] as usize;
         let payload = &buf[2..2+len];
         match tag {
-            0|1|36...63 => Descriptor::Reserved { tag, payload },
+            0|1|36..=63 => Descriptor::Reserved { tag, payload },
             2 => Descriptor::VideoStream { payload },
             3 => Descriptor::AudioStream { payload },
             4 => Descriptor::Hierarchy { payload },
@@ -60,7 +63,7 @@ impl<'buf> Descriptor<'buf> {
             16 => Descriptor::SmoothingBuffer { payload },
             17 => Descriptor::STD { payload },
             18 => Descriptor::IBP { payload },
-            19...26 => Descriptor::IsoIec13818dash6 { tag, payload },
+            19..=26 => Descriptor::IsoIec13818dash6 { tag, payload },
             27 => Descriptor::MPEG4Video { payload },
             28 => Descriptor::MPEG4Audio { payload },
             29 => Descriptor::IOD { payload },
@@ -70,8 +73,7 @@ impl<'buf> Descriptor<'buf> {
             33 => Descriptor::MuxCode { payload },
             34 => Descriptor::FmxBufferSize { payload },
             35 => Descriptor::MultiplexBuffer { payload },
-            64...255 => Descriptor::UserPrivate { tag, payload },
-            _ => unimplemented!("tag {}", tag)  // TODO: why not exhaustive without this?
+            64..=255 => Descriptor::UserPrivate { tag, payload },
         }
     }
 }
@@ -83,14 +85,38 @@ impl<'buf> DescriptorIter<'buf> {
     pub fn new(buf: &'buf[u8]) -> DescriptorIter<'buf> {
         DescriptorIter { buf }
     }
+
+    /// Returns the first remaining descriptor with the given tag, skipping any before it.
+    /// Returns `None` if there is no such descriptor, or if a malformed descriptor is found
+    /// first.
+    pub fn find_tag(&mut self, tag: u8) -> Option<Descriptor<'buf>> {
+        while self.buf.len() >= 2 {
+            let len = self.buf[1] as usize + 2;
+            if len > self.buf.len() {
+                break;
+            }
+            let (desc, rest) = self.buf.split_at(len);
+            self.buf = rest;
+            if desc[0] == tag {
+                return Some(Descriptor::new(desc));
+            }
+        }
+        self.buf = &self.buf[0..0];
+        None
+    }
 }
 impl<'buf> Iterator for DescriptorIter<'buf> {
     type Item = Result<Descriptor<'buf>, ()>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.buf.len() == 0 {
+        if self.buf.is_empty() {
             return None;
         }
+        if self.buf.len() < 2 {
+            warn!("{} trailing bytes in descriptor loop, too few to hold a descriptor header", self.buf.len());
+            self.buf = &self.buf[0..0];
+            return Some(Err(()));
+        }
         let _tag = self.buf[0];
         let len = self.buf[1] as usize;
         if len > self.buf.len()-2 {
@@ -105,8 +131,12 @@ impl<'buf> Iterator for DescriptorIter<'buf> {
     }
 }
 
+#[derive(Debug,PartialEq)]
 pub enum DescriptorError  {
-    NotEnoughData { actual: usize, expected: usize }
+    NotEnoughData { actual: usize, expected: usize },
+    /// A field holding a Binary Coded Decimal value included a nibble outside the range `0` to
+    /// `9`
+    InvalidBcd { field: &'static str },
 }
 pub struct RegistrationDescriptor<'buf> {
     buf: &'buf[u8],
@@ -135,12 +165,24 @@ impl<'buf> fmt::Debug for RegistrationDescriptor<'buf> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
         f.debug_struct("RegistrationDescriptor")
             .field("format_identifier", &self.format_identifier())
-            .field("additional_identification_info", &format!("{:x}", self.additional_identification_info().as_hex()))
+            .field("additional_identification_info", &format_args!("{:x}", self.additional_identification_info().as_hex()))
             .finish()
     }
 }
 
-#[cfg(test)]
+/// Debug-formats a byte string (such as an _ISO 639_ language code) as text where it is valid
+/// UTF-8, and as hex otherwise, without needing to allocate.
+pub(crate) struct TextOrHex<'buf>(pub &'buf [u8]);
+impl<'buf> fmt::Debug for TextOrHex<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        match ::std::str::from_utf8(self.0) {
+            Ok(s) => fmt::Debug::fmt(s, f),
+            Err(_) => write!(f, "{:x}", self.0.as_hex()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
 mod test {
     use data_encoding::hex;
     use super::*;
@@ -151,4 +193,19 @@ mod test {
         let desc = Descriptor::new(&data);
         assert_matches!(desc, Descriptor::Registration{ payload: b"CUEI" });
     }
+
+    #[test]
+    fn truncated_descriptors() {
+        // a complete descriptor followed by a single trailing byte
+        let data = hex::decode(b"05044355454905").unwrap();
+        let mut iter = DescriptorIter::new(&data);
+        assert_matches!(iter.next(), Some(Ok(Descriptor::Registration{ payload: b"CUEI" })));
+        assert_matches!(iter.next(), Some(Err(())));
+        assert_matches!(iter.next(), None);
+        // descriptor_length exceeding the available data
+        let data = hex::decode(b"0505435545").unwrap();
+        let mut iter = DescriptorIter::new(&data);
+        assert_matches!(iter.next(), Some(Err(())));
+        assert_matches!(iter.next(), None);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,338 @@
+//! Descriptors defined by ATSC standards, such as _A/65_ (PSIP) and _A/52_ (AC-3).
+//!
+//! As with the [`dvb`](../dvb/index.html) descriptors, these use tag values which
+//! _ISO/IEC 13818-1_ leaves as 'user private', and so will be produced by
+//! [`DescriptorIter`](../struct.DescriptorIter.html) as `Descriptor::UserPrivate { tag, payload }`.
+
+use std::fmt;
+use super::DescriptorError;
+
+/// Lists the closed caption services (_CEA-608_ 'line 21' or _CEA-708_ digital captions) present
+/// in a video stream, normally found in the ES descriptor loop of the PMT for that stream.
+pub struct CaptionServiceDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> CaptionServiceDescriptor<'buf> {
+    pub const TAG: u8 = 0x86;
+
+    pub fn new(buf: &'buf[u8]) -> Result<CaptionServiceDescriptor<'buf>, DescriptorError> {
+        if buf.is_empty() {
+            Err(DescriptorError::NotEnoughData { actual: 0, expected: 1 })
+        } else {
+            Ok(CaptionServiceDescriptor { buf })
+        }
+    }
+
+    pub fn number_of_services(&self) -> u8 {
+        self.buf[0] & 0b0001_1111
+    }
+
+    /// Iterates over the `number_of_services()` entries in this descriptor.  Entries that would
+    /// extend past the end of the descriptor are not produced.
+    pub fn services(&self) -> impl Iterator<Item=CaptionService<'buf>> + 'buf {
+        self.buf[1..].chunks(CaptionService::SIZE)
+            .take(self.number_of_services() as usize)
+            .filter(|entry| entry.len() == CaptionService::SIZE)
+            .map(|buf| CaptionService { buf })
+    }
+}
+impl<'buf> fmt::Debug for CaptionServiceDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_list()
+            .entries(self.services())
+            .finish()
+    }
+}
+
+/// A single entry from a [`CaptionServiceDescriptor`](struct.CaptionServiceDescriptor.html).
+pub struct CaptionService<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> CaptionService<'buf> {
+    const SIZE: usize = 6;
+
+    /// The three byte _ISO 639-2_ language code, e.g. `b"eng"`.
+    pub fn language_code(&self) -> &'buf[u8] {
+        &self.buf[0..3]
+    }
+    /// `true` if this is a _CEA-708_ digital caption service, or `false` for a _CEA-608_
+    /// 'line 21' service.
+    pub fn digital_cc(&self) -> bool {
+        self.buf[3] & 0b1000_0000 != 0
+    }
+    /// The _CEA-708_ service number, or `None` if `digital_cc()` is `false`.
+    pub fn caption_service_number(&self) -> Option<u8> {
+        if self.digital_cc() {
+            Some(self.buf[3] & 0b0011_1111)
+        } else {
+            None
+        }
+    }
+    /// For _CEA-608_ services, `false` indicates field 1 of the line 21 data, and `true` field 2.
+    /// Returns `None` if `digital_cc()` is `true`.
+    pub fn line21_field(&self) -> Option<bool> {
+        if self.digital_cc() {
+            None
+        } else {
+            Some(self.buf[3] & 0b0000_0001 != 0)
+        }
+    }
+    /// Indicates that the service uses the 'easy reader' style, suited to beginning readers.
+    pub fn easy_reader(&self) -> bool {
+        self.buf[4] & 0b1000_0000 != 0
+    }
+    /// Indicates that the service is formatted for a 16:9 display, rather than 4:3.
+    pub fn wide_aspect_ratio(&self) -> bool {
+        self.buf[4] & 0b0100_0000 != 0
+    }
+}
+impl<'buf> fmt::Debug for CaptionService<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("CaptionService")
+            .field("language_code", &super::TextOrHex(self.language_code()))
+            .field("digital_cc", &self.digital_cc())
+            .field("caption_service_number", &self.caption_service_number())
+            .field("line21_field", &self.line21_field())
+            .field("easy_reader", &self.easy_reader())
+            .field("wide_aspect_ratio", &self.wide_aspect_ratio())
+            .finish()
+    }
+}
+
+/// Describes an AC-3 audio elementary stream, per _ATSC A/52, Annex A_ (ATSC uses this tag,
+/// `0x81`, where DVB uses its own `AC-3_descriptor`).  Normally found in the ES descriptor loop of
+/// the PMT for that stream.
+///
+/// Only the first three bytes are mandatory; the accessors for the later, optional fields return
+/// `None` where the descriptor is too short to hold them.
+pub struct AtscAc3Descriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> AtscAc3Descriptor<'buf> {
+    pub const TAG: u8 = 0x81;
+    const HEADER_SIZE: usize = 3;
+    /// Indexed by the low 5 bits of `bit_rate_code()`
+    const BIT_RATES_KBPS: [u32; 19] = [32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640];
+
+    pub fn new(buf: &'buf[u8]) -> Result<AtscAc3Descriptor<'buf>, DescriptorError> {
+        if buf.len() < Self::HEADER_SIZE {
+            Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: Self::HEADER_SIZE })
+        } else {
+            Ok(AtscAc3Descriptor { buf })
+        }
+    }
+
+    /// `0` for 48kHz, `1` for 44.1kHz and `2` for 32kHz, or values `4` to `7` indicating that the
+    /// sample rate may be one of several.
+    pub fn sample_rate_code(&self) -> u8 {
+        self.buf[0] >> 5
+    }
+    pub fn bsid(&self) -> u8 {
+        self.buf[0] & 0b0001_1111
+    }
+    /// The 6-bit code, of which the low 5 bits select the bitrate (see `bit_rate_kbps()`), and the
+    /// top bit indicates whether this is the exact bitrate (`0`) or an upper limit (`1`).
+    pub fn bit_rate_code(&self) -> u8 {
+        self.buf[1] >> 2
+    }
+    /// The bitrate, or upper limit on the bitrate, given by `bit_rate_code()`, in kilobits per
+    /// second.  Returns `None` for a reserved code.
+    pub fn bit_rate_kbps(&self) -> Option<u32> {
+        Self::BIT_RATES_KBPS.get((self.bit_rate_code() & 0b1_1111) as usize).cloned()
+    }
+    /// `true` if `bit_rate_kbps()` gives an upper limit, rather than the exact bitrate.
+    pub fn bit_rate_is_upper_limit(&self) -> bool {
+        self.bit_rate_code() & 0b10_0000 != 0
+    }
+    /// `0b10` indicates Dolby Surround encoded, `0b01` not encoded, and `0b00` not indicated.
+    pub fn surround_mode(&self) -> u8 {
+        self.buf[1] & 0b11
+    }
+    /// The bit stream mode, e.g. `0` for the main audio service, or `2` for a service for the
+    /// visually impaired.
+    pub fn bsmod(&self) -> u8 {
+        self.buf[2] >> 5
+    }
+    /// The audio coding mode, e.g. `0b0010` for stereo, or `0b0111` for 3/2 channels; values with
+    /// the top bit set indicate the maximum number of channels.  `0` indicates '1+1' (two
+    /// independent mono channels).
+    pub fn num_channels(&self) -> u8 {
+        self.buf[2] >> 1 & 0b1111
+    }
+    /// `true` if this stream is a full service suitable for presentation on its own.
+    pub fn full_svc(&self) -> bool {
+        self.buf[2] & 1 != 0
+    }
+    /// The legacy language code of the (first) audio channel.
+    pub fn langcod(&self) -> Option<u8> {
+        self.buf.get(3).cloned()
+    }
+    /// The legacy language code of the second channel, present only when `num_channels()` is `0`.
+    pub fn langcod2(&self) -> Option<u8> {
+        if self.num_channels() == 0 {
+            self.buf.get(4).cloned()
+        } else {
+            None
+        }
+    }
+    fn mainid_offset(&self) -> usize {
+        if self.num_channels() == 0 { 5 } else { 4 }
+    }
+    /// Identifies the main audio service, present only when `bsmod()` is less than `2`.
+    pub fn mainid(&self) -> Option<u8> {
+        if self.bsmod() < 2 {
+            self.buf.get(self.mainid_offset()).map(|b| b >> 5)
+        } else {
+            None
+        }
+    }
+    /// The priority of the main audio service, present only when `bsmod()` is less than `2`.
+    pub fn priority(&self) -> Option<u8> {
+        if self.bsmod() < 2 {
+            self.buf.get(self.mainid_offset()).map(|b| b >> 3 & 0b11)
+        } else {
+            None
+        }
+    }
+    /// Flags indicating the main audio services with which this associated service may be
+    /// combined, present only when `bsmod()` is `2` or more.
+    pub fn asvcflags(&self) -> Option<u8> {
+        if self.bsmod() >= 2 {
+            self.buf.get(self.mainid_offset()).cloned()
+        } else {
+            None
+        }
+    }
+    fn text_offset(&self) -> usize {
+        self.mainid_offset() + 2
+    }
+    fn textlen(&self) -> Option<usize> {
+        self.buf.get(self.text_offset() - 1).map(|b| (b >> 1) as usize)
+    }
+    /// `true` if `text()` is encoded as _ISO 8859-1_, or `false` if encoded as _UTF-16_.
+    pub fn text_code(&self) -> Option<bool> {
+        self.buf.get(self.text_offset() - 1).map(|b| b & 1 != 0)
+    }
+    /// A textual description of the audio service.
+    pub fn text(&self) -> Option<&'buf[u8]> {
+        let start = self.text_offset();
+        let end = start + self.textlen()?;
+        self.buf.get(start..end)
+    }
+    fn language_flags_offset(&self) -> Option<usize> {
+        Some(self.text_offset() + self.textlen()?)
+    }
+    /// The three byte _ISO 639-2_ language code of the (first) audio channel, e.g. `b"eng"`.
+    pub fn language(&self) -> Option<&'buf[u8]> {
+        let offset = self.language_flags_offset()?;
+        if self.buf.get(offset)? & 0b1000_0000 != 0 {
+            self.buf.get(offset + 1..offset + 4)
+        } else {
+            None
+        }
+    }
+    /// The three byte _ISO 639-2_ language code of the second channel, when `num_channels()` is
+    /// `0`.
+    pub fn language_2(&self) -> Option<&'buf[u8]> {
+        let offset = self.language_flags_offset()?;
+        let flags = *self.buf.get(offset)?;
+        if flags & 0b0100_0000 != 0 {
+            let start = if flags & 0b1000_0000 != 0 { offset + 4 } else { offset + 1 };
+            self.buf.get(start..start + 3)
+        } else {
+            None
+        }
+    }
+}
+impl<'buf> fmt::Debug for AtscAc3Descriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("AtscAc3Descriptor")
+            .field("sample_rate_code", &self.sample_rate_code())
+            .field("bsid", &self.bsid())
+            .field("bit_rate_code", &self.bit_rate_code())
+            .field("surround_mode", &self.surround_mode())
+            .field("bsmod", &self.bsmod())
+            .field("num_channels", &self.num_channels())
+            .field("full_svc", &self.full_svc())
+            .field("langcod", &self.langcod())
+            .field("langcod2", &self.langcod2())
+            .field("mainid", &self.mainid())
+            .field("priority", &self.priority())
+            .field("asvcflags", &self.asvcflags())
+            .field("text_code", &self.text_code())
+            .field("text", &self.text().map(super::TextOrHex))
+            .field("language", &self.language().map(super::TextOrHex))
+            .field("language_2", &self.language_2().map(super::TextOrHex))
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use data_encoding::hex;
+    use super::*;
+    use test_util;
+
+    #[test]
+    fn caption_service_descriptor() {
+        // an easy-reader CEA-608 service on field 1, and CEA-708 service number 1
+        let data = hex::decode(b"860DE2656E677EFFFF656E67C17FFF").unwrap();
+        let payload = test_util::user_private_payload(&data, CaptionServiceDescriptor::TAG);
+        let desc = CaptionServiceDescriptor::new(payload).unwrap();
+        assert_eq!(desc.number_of_services(), 2);
+        let services: Vec<_> = desc.services().collect();
+        assert_eq!(services.len(), 2);
+
+        assert_eq!(services[0].language_code(), b"eng");
+        assert!(!services[0].digital_cc());
+        assert_eq!(services[0].line21_field(), Some(false));
+        assert_eq!(services[0].caption_service_number(), None);
+        assert!(services[0].easy_reader());
+        assert!(services[0].wide_aspect_ratio());
+
+        assert!(services[1].digital_cc());
+        assert_eq!(services[1].caption_service_number(), Some(1));
+        assert_eq!(services[1].line21_field(), None);
+        assert!(!services[1].easy_reader());
+        assert!(services[1].wide_aspect_ratio());
+
+        // number_of_services larger than the data present
+        let desc = CaptionServiceDescriptor::new(&payload[..10]).unwrap();
+        assert_eq!(desc.services().count(), 1);
+    }
+
+    #[test]
+    fn atsc_ac3_descriptor() {
+        // 48kHz, bsid 8, 384kbps, 3/2 channels, main service, with text "Main" and language "eng"
+        let data = hex::decode(b"810E08380F00C0094D61696E80656E67").unwrap();
+        let payload = test_util::user_private_payload(&data, AtscAc3Descriptor::TAG);
+        let desc = AtscAc3Descriptor::new(payload).unwrap();
+        assert_eq!(desc.sample_rate_code(), 0);
+        assert_eq!(desc.bsid(), 8);
+        assert_eq!(desc.bit_rate_code(), 14);
+        assert_eq!(desc.bit_rate_kbps(), Some(384));
+        assert!(!desc.bit_rate_is_upper_limit());
+        assert_eq!(desc.surround_mode(), 0);
+        assert_eq!(desc.bsmod(), 0);
+        assert_eq!(desc.num_channels(), 7);
+        assert!(desc.full_svc());
+        assert_eq!(desc.langcod(), Some(0));
+        assert_eq!(desc.langcod2(), None);
+        assert_eq!(desc.mainid(), Some(6));
+        assert_eq!(desc.priority(), Some(0));
+        assert_eq!(desc.asvcflags(), None);
+        assert_eq!(desc.text_code(), Some(true));
+        assert_eq!(desc.text(), Some(&b"Main"[..]));
+        assert_eq!(desc.language(), Some(&b"eng"[..]));
+        assert_eq!(desc.language_2(), None);
+
+        // only the mandatory fields,
+        let desc = AtscAc3Descriptor::new(&payload[..3]).unwrap();
+        assert_eq!(desc.num_channels(), 7);
+        assert_eq!(desc.langcod(), None);
+        assert_eq!(desc.mainid(), None);
+        assert_eq!(desc.text(), None);
+        assert_eq!(desc.language(), None);
+        assert!(AtscAc3Descriptor::new(&payload[..2]).is_err());
+    }
+}
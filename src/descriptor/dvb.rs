@@ -0,0 +1,2049 @@
+//! Descriptors defined by _ETSI EN 300 468_ (DVB Service Information).
+//!
+//! These use descriptor tag values which _ISO/IEC 13818-1_ leaves as 'user private', and so will
+//! be produced by [`DescriptorIter`](../struct.DescriptorIter.html) as
+//! `Descriptor::UserPrivate { tag, payload }`.  Check the `tag` against the `TAG` constant of the
+//! type of interest, and then pass the `payload` to its `new()` function.
+
+use std::fmt;
+use hex_slice::AsHex;
+use super::DescriptorError;
+use mjd_bcd::bcd;
+
+/// Describes one component (e.g. a video, audio or subtitle stream) of an event or service.
+pub struct ComponentDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> ComponentDescriptor<'buf> {
+    pub const TAG: u8 = 0x50;
+    const HEADER_SIZE: usize = 6;
+
+    pub fn new(buf: &'buf[u8]) -> Result<ComponentDescriptor<'buf>, DescriptorError> {
+        if buf.len() < Self::HEADER_SIZE {
+            Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: Self::HEADER_SIZE })
+        } else {
+            Ok(ComponentDescriptor { buf })
+        }
+    }
+
+    /// Together with `stream_content()` and `component_type()`, identifies the kind of component
+    /// per _EN 300 468, table 26_.
+    pub fn stream_content_ext(&self) -> u8 {
+        self.buf[0] >> 4
+    }
+    pub fn stream_content(&self) -> u8 {
+        self.buf[0] & 0b1111
+    }
+    pub fn component_type(&self) -> u8 {
+        self.buf[1]
+    }
+    /// Matches the `component_tag` of the [`StreamIdentifierDescriptor`](struct.StreamIdentifierDescriptor.html)
+    /// of the elementary stream carrying this component (if any).
+    pub fn component_tag(&self) -> u8 {
+        self.buf[2]
+    }
+    /// The three byte _ISO 639-2_ language code, e.g. `b"eng"`.
+    pub fn language_code(&self) -> &'buf[u8] {
+        &self.buf[3..6]
+    }
+    /// Textual description of the component, in the DVB text encoding.
+    pub fn text(&self) -> &'buf[u8] {
+        &self.buf[Self::HEADER_SIZE..]
+    }
+}
+impl<'buf> fmt::Debug for ComponentDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("ComponentDescriptor")
+            .field("stream_content_ext", &self.stream_content_ext())
+            .field("stream_content", &self.stream_content())
+            .field("component_type", &self.component_type())
+            .field("component_tag", &self.component_tag())
+            .field("language_code", &super::TextOrHex(self.language_code()))
+            .field("text", &format_args!("{:x}", self.text().as_hex()))
+            .finish()
+    }
+}
+
+/// Labels an elementary stream in the PMT with a `component_tag`, by which the
+/// [`ComponentDescriptor`](struct.ComponentDescriptor.html) entries of the EIT and SDT refer to
+/// it.
+pub struct StreamIdentifierDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> StreamIdentifierDescriptor<'buf> {
+    pub const TAG: u8 = 0x52;
+
+    pub fn new(buf: &'buf[u8]) -> Result<StreamIdentifierDescriptor<'buf>, DescriptorError> {
+        if buf.is_empty() {
+            Err(DescriptorError::NotEnoughData { actual: 0, expected: 1 })
+        } else {
+            Ok(StreamIdentifierDescriptor { buf })
+        }
+    }
+
+    pub fn component_tag(&self) -> u8 {
+        self.buf[0]
+    }
+}
+impl<'buf> fmt::Debug for StreamIdentifierDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("StreamIdentifierDescriptor")
+            .field("component_tag", &self.component_tag())
+            .finish()
+    }
+}
+
+/// Carries the name of an event, and a short textual description.
+pub struct ShortEventDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> ShortEventDescriptor<'buf> {
+    pub const TAG: u8 = 0x4d;
+
+    /// Checks that the `event_name_length` and `text_length` fields are consistent with the
+    /// size of the given buffer, so that the accessor methods need not.
+    pub fn new(buf: &'buf[u8]) -> Result<ShortEventDescriptor<'buf>, DescriptorError> {
+        if buf.len() < 5 {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: 5 })
+        }
+        let text_length_offset = 4 + buf[3] as usize;
+        if buf.len() <= text_length_offset {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: text_length_offset + 1 })
+        }
+        let end = text_length_offset + 1 + buf[text_length_offset] as usize;
+        if buf.len() < end {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: end })
+        }
+        Ok(ShortEventDescriptor { buf })
+    }
+
+    /// The three byte _ISO 639-2_ language code, e.g. `b"eng"`.
+    pub fn language_code(&self) -> &'buf[u8] {
+        &self.buf[0..3]
+    }
+    fn event_name_length(&self) -> usize {
+        self.buf[3] as usize
+    }
+    /// The event name, in the DVB text encoding (see
+    /// [`dvb_text::decode()`](../../dvb_text/fn.decode.html)).
+    pub fn event_name(&self) -> &'buf[u8] {
+        &self.buf[4..4 + self.event_name_length()]
+    }
+    /// A short description of the event, in the DVB text encoding (see
+    /// [`dvb_text::decode()`](../../dvb_text/fn.decode.html)).
+    pub fn text(&self) -> &'buf[u8] {
+        let text_length_offset = 4 + self.event_name_length();
+        let text_length = self.buf[text_length_offset] as usize;
+        &self.buf[text_length_offset + 1..text_length_offset + 1 + text_length]
+    }
+}
+impl<'buf> fmt::Debug for ShortEventDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("ShortEventDescriptor")
+            .field("language_code", &super::TextOrHex(self.language_code()))
+            .field("event_name", &format_args!("{:x}", self.event_name().as_hex()))
+            .field("text", &format_args!("{:x}", self.text().as_hex()))
+            .finish()
+    }
+}
+
+/// Carries a detailed description of an event, optionally structured as a list of
+/// (item description, item) pairs (e.g. `"Director"`, `"Jane Doe"`).
+///
+/// A description too long for one descriptor is split across a sequence of
+/// `ExtendedEventDescriptor`s, numbered from `0` up to `last_descriptor_number()`.  This type
+/// describes only one part; callers wanting the whole description must collect every numbered
+/// part (for the same `language_code()`) and concatenate the `items()` and `text()` of each,
+/// in `descriptor_number()` order.  Note that a single item or text may be split mid-way between
+/// two parts.
+pub struct ExtendedEventDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> ExtendedEventDescriptor<'buf> {
+    pub const TAG: u8 = 0x4e;
+    const HEADER_SIZE: usize = 5;
+
+    /// Checks that the `length_of_items`, per-item lengths and `text_length` fields are
+    /// consistent with the size of the given buffer, so that the accessor methods need not.
+    pub fn new(buf: &'buf[u8]) -> Result<ExtendedEventDescriptor<'buf>, DescriptorError> {
+        if buf.len() < Self::HEADER_SIZE + 1 {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: Self::HEADER_SIZE + 1 })
+        }
+        let text_length_offset = Self::HEADER_SIZE + buf[4] as usize;
+        if buf.len() <= text_length_offset {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: text_length_offset + 1 })
+        }
+        let mut offset = Self::HEADER_SIZE;
+        while offset < text_length_offset {
+            // item_description_length, then item_length, must each lie within the items loop
+            for _ in 0..2 {
+                let end = offset + 1 + buf[offset] as usize;
+                if end > text_length_offset {
+                    return Err(DescriptorError::NotEnoughData { actual: text_length_offset, expected: end })
+                }
+                offset = end;
+            }
+        }
+        let end = text_length_offset + 1 + buf[text_length_offset] as usize;
+        if buf.len() < end {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: end })
+        }
+        Ok(ExtendedEventDescriptor { buf })
+    }
+
+    /// The position of this descriptor within the sequence of parts making up the whole
+    /// description, counting from `0`.
+    pub fn descriptor_number(&self) -> u8 {
+        self.buf[0] >> 4
+    }
+    /// The `descriptor_number()` of the final part of the sequence.
+    pub fn last_descriptor_number(&self) -> u8 {
+        self.buf[0] & 0b1111
+    }
+    /// The three byte _ISO 639-2_ language code, e.g. `b"eng"`.
+    pub fn language_code(&self) -> &'buf[u8] {
+        &self.buf[1..4]
+    }
+    fn text_length_offset(&self) -> usize {
+        Self::HEADER_SIZE + self.buf[4] as usize
+    }
+    /// Iterates over the `(item_description, item)` pairs in this part of the description, each
+    /// in the DVB text encoding (see [`dvb_text::decode()`](../../dvb_text/fn.decode.html)).
+    pub fn items(&self) -> ExtendedEventItemIter<'buf> {
+        ExtendedEventItemIter {
+            buf: &self.buf[Self::HEADER_SIZE..self.text_length_offset()],
+        }
+    }
+    /// This part of the free-text description of the event, in the DVB text encoding (see
+    /// [`dvb_text::decode()`](../../dvb_text/fn.decode.html)).
+    pub fn text(&self) -> &'buf[u8] {
+        let text_length_offset = self.text_length_offset();
+        let text_length = self.buf[text_length_offset] as usize;
+        &self.buf[text_length_offset + 1..text_length_offset + 1 + text_length]
+    }
+}
+impl<'buf> fmt::Debug for ExtendedEventDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        struct Items<'buf>(ExtendedEventItemIter<'buf>);
+        impl<'buf> fmt::Debug for Items<'buf> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+                let mut list = f.debug_list();
+                for (desc, item) in self.0.clone() {
+                    list.entry(&Item(desc, item));
+                }
+                list.finish()
+            }
+        }
+        struct Item<'buf>(&'buf[u8], &'buf[u8]);
+        impl<'buf> fmt::Debug for Item<'buf> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+                f.debug_tuple("")
+                    .field(&format_args!("{:x}", self.0.as_hex()))
+                    .field(&format_args!("{:x}", self.1.as_hex()))
+                    .finish()
+            }
+        }
+        f.debug_struct("ExtendedEventDescriptor")
+            .field("descriptor_number", &self.descriptor_number())
+            .field("last_descriptor_number", &self.last_descriptor_number())
+            .field("language_code", &super::TextOrHex(self.language_code()))
+            .field("items", &Items(self.items()))
+            .field("text", &format_args!("{:x}", self.text().as_hex()))
+            .finish()
+    }
+}
+
+/// Iterator over the `(item_description, item)` pairs of an
+/// [`ExtendedEventDescriptor`](struct.ExtendedEventDescriptor.html).
+#[derive(Clone)]
+pub struct ExtendedEventItemIter<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> ExtendedEventItemIter<'buf> {
+    fn take_field(&mut self) -> &'buf[u8] {
+        let len = self.buf[0] as usize;
+        let (field, rest) = self.buf[1..].split_at(len);
+        self.buf = rest;
+        field
+    }
+}
+impl<'buf> Iterator for ExtendedEventItemIter<'buf> {
+    type Item = (&'buf[u8], &'buf[u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        // lengths were already checked by ExtendedEventDescriptor::new()
+        let description = self.take_field();
+        let item = self.take_field();
+        Some((description, item))
+    }
+}
+
+/// Gives the name of a service and its provider, together with the type of the service.
+pub struct ServiceDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> ServiceDescriptor<'buf> {
+    pub const TAG: u8 = 0x48;
+
+    /// Checks that the `service_provider_name_length` and `service_name_length` fields are
+    /// consistent with the size of the given buffer, so that the accessor methods need not.
+    pub fn new(buf: &'buf[u8]) -> Result<ServiceDescriptor<'buf>, DescriptorError> {
+        if buf.len() < 3 {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: 3 })
+        }
+        let service_name_length_offset = 2 + buf[1] as usize;
+        if buf.len() <= service_name_length_offset {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: service_name_length_offset + 1 })
+        }
+        let end = service_name_length_offset + 1 + buf[service_name_length_offset] as usize;
+        if buf.len() < end {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: end })
+        }
+        Ok(ServiceDescriptor { buf })
+    }
+
+    /// The kind of service (e.g. `0x01` for 'digital television service', `0x02` for 'digital
+    /// radio sound service') per _EN 300 468, table 87_.
+    pub fn service_type(&self) -> u8 {
+        self.buf[0]
+    }
+    fn service_provider_name_length(&self) -> usize {
+        self.buf[1] as usize
+    }
+    /// The name of the service provider, in the DVB text encoding (see
+    /// [`dvb_text::decode()`](../../dvb_text/fn.decode.html)).
+    pub fn service_provider_name(&self) -> &'buf[u8] {
+        &self.buf[2..2 + self.service_provider_name_length()]
+    }
+    /// The name of the service, in the DVB text encoding (see
+    /// [`dvb_text::decode()`](../../dvb_text/fn.decode.html)).
+    pub fn service_name(&self) -> &'buf[u8] {
+        let service_name_length_offset = 2 + self.service_provider_name_length();
+        let service_name_length = self.buf[service_name_length_offset] as usize;
+        &self.buf[service_name_length_offset + 1..service_name_length_offset + 1 + service_name_length]
+    }
+}
+impl<'buf> fmt::Debug for ServiceDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("ServiceDescriptor")
+            .field("service_type", &self.service_type())
+            .field("service_provider_name", &format_args!("{:x}", self.service_provider_name().as_hex()))
+            .field("service_name", &format_args!("{:x}", self.service_name().as_hex()))
+            .finish()
+    }
+}
+
+/// Gives the name of the network, within the first descriptor loop of the NIT.
+pub struct NetworkNameDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> NetworkNameDescriptor<'buf> {
+    pub const TAG: u8 = 0x40;
+
+    pub fn new(buf: &'buf[u8]) -> Result<NetworkNameDescriptor<'buf>, DescriptorError> {
+        Ok(NetworkNameDescriptor { buf })
+    }
+
+    /// The name of the network, in the DVB text encoding (see
+    /// [`dvb_text::decode()`](../../dvb_text/fn.decode.html)).
+    pub fn name(&self) -> &'buf[u8] {
+        self.buf
+    }
+    /// The result of passing `name()` to [`dvb_text::decode()`](../../dvb_text/fn.decode.html).
+    #[cfg(feature = "std")]
+    pub fn name_text(&self) -> String {
+        ::dvb_text::decode(self.name())
+    }
+}
+impl<'buf> fmt::Debug for NetworkNameDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("NetworkNameDescriptor")
+            .field("name", &format_args!("{:x}", self.name().as_hex()))
+            .finish()
+    }
+}
+
+/// Gives the name of the bouquet, within the first descriptor loop of the BAT.
+pub struct BouquetNameDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> BouquetNameDescriptor<'buf> {
+    pub const TAG: u8 = 0x47;
+
+    pub fn new(buf: &'buf[u8]) -> Result<BouquetNameDescriptor<'buf>, DescriptorError> {
+        Ok(BouquetNameDescriptor { buf })
+    }
+
+    /// The name of the bouquet, in the DVB text encoding (see
+    /// [`dvb_text::decode()`](../../dvb_text/fn.decode.html)).
+    pub fn name(&self) -> &'buf[u8] {
+        self.buf
+    }
+    /// The result of passing `name()` to [`dvb_text::decode()`](../../dvb_text/fn.decode.html).
+    #[cfg(feature = "std")]
+    pub fn name_text(&self) -> String {
+        ::dvb_text::decode(self.name())
+    }
+}
+impl<'buf> fmt::Debug for BouquetNameDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("BouquetNameDescriptor")
+            .field("name", &format_args!("{:x}", self.name().as_hex()))
+            .finish()
+    }
+}
+
+/// Identifies a service related to the one being described (such as one giving more information
+/// about it, or replacing it), by the DVB triplet of `original_network_id`, `transport_stream_id`
+/// and `service_id`.  Found in the descriptor loops of the NIT, BAT, SDT and EIT.
+pub struct LinkageDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> LinkageDescriptor<'buf> {
+    pub const TAG: u8 = 0x4a;
+    const HEADER_SIZE: usize = 7;
+
+    pub fn new(buf: &'buf[u8]) -> Result<LinkageDescriptor<'buf>, DescriptorError> {
+        if buf.len() < Self::HEADER_SIZE {
+            Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: Self::HEADER_SIZE })
+        } else {
+            Ok(LinkageDescriptor { buf })
+        }
+    }
+
+    pub fn transport_stream_id(&self) -> u16 {
+        u16::from(self.buf[0]) << 8 | u16::from(self.buf[1])
+    }
+    pub fn original_network_id(&self) -> u16 {
+        u16::from(self.buf[2]) << 8 | u16::from(self.buf[3])
+    }
+    pub fn service_id(&self) -> u16 {
+        u16::from(self.buf[4]) << 8 | u16::from(self.buf[5])
+    }
+    pub fn linkage_type(&self) -> LinkageType {
+        self.buf[6].into()
+    }
+    /// The remaining bytes of the descriptor, whose structure depends on the `linkage_type()`
+    /// (for example, giving the hand-over type and network for `LinkageType::MobileHandOver`).
+    pub fn private_data(&self) -> &'buf[u8] {
+        &self.buf[Self::HEADER_SIZE..]
+    }
+}
+impl<'buf> fmt::Debug for LinkageDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("LinkageDescriptor")
+            .field("transport_stream_id", &self.transport_stream_id())
+            .field("original_network_id", &self.original_network_id())
+            .field("service_id", &self.service_id())
+            .field("linkage_type", &self.linkage_type())
+            .field("private_data", &format_args!("{:x}", self.private_data().as_hex()))
+            .finish()
+    }
+}
+
+/// The relationship between the service identified by a
+/// [`LinkageDescriptor`](struct.LinkageDescriptor.html) and the one it describes, per
+/// _EN 300 468, table 58_.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum LinkageType {
+    InformationService,
+    EpgService,
+    CaReplacementService,
+    /// a Transport Stream containing the complete network and bouquet SI
+    CompleteNetworkBouquetSi,
+    ServiceReplacementService,
+    DataBroadcastService,
+    RcsMap,
+    MobileHandOver,
+    SystemSoftwareUpdateService,
+    /// a Transport Stream containing the SSU BAT or NIT
+    SsuBatOrNit,
+    IpMacNotificationService,
+    /// a Transport Stream containing the INT BAT or NIT
+    IntBatOrNit,
+    EventLinkage,
+    /// values `0x0e` to `0x1f`
+    ExtendedEventLinkage(u8),
+    /// values `0x80` to `0xfe`
+    UserDefined(u8),
+    Reserved(u8),
+}
+impl From<u8> for LinkageType {
+    fn from(val: u8) -> LinkageType {
+        match val {
+            0x01 => LinkageType::InformationService,
+            0x02 => LinkageType::EpgService,
+            0x03 => LinkageType::CaReplacementService,
+            0x04 => LinkageType::CompleteNetworkBouquetSi,
+            0x05 => LinkageType::ServiceReplacementService,
+            0x06 => LinkageType::DataBroadcastService,
+            0x07 => LinkageType::RcsMap,
+            0x08 => LinkageType::MobileHandOver,
+            0x09 => LinkageType::SystemSoftwareUpdateService,
+            0x0a => LinkageType::SsuBatOrNit,
+            0x0b => LinkageType::IpMacNotificationService,
+            0x0c => LinkageType::IntBatOrNit,
+            0x0d => LinkageType::EventLinkage,
+            0x0e..=0x1f => LinkageType::ExtendedEventLinkage(val),
+            0x80..=0xfe => LinkageType::UserDefined(val),
+            v => LinkageType::Reserved(v),
+        }
+    }
+}
+
+/// Classifies the content of an event, as a sequence of
+/// [`ContentClassification`](struct.ContentClassification.html) entries.
+pub struct ContentDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> ContentDescriptor<'buf> {
+    pub const TAG: u8 = 0x54;
+    const ENTRY_SIZE: usize = 2;
+
+    pub fn new(buf: &'buf[u8]) -> Result<ContentDescriptor<'buf>, DescriptorError> {
+        Ok(ContentDescriptor { buf })
+    }
+
+    /// Iterates over the classification entries in this descriptor.  Any trailing byte, too
+    /// short to hold a complete entry, is ignored.
+    pub fn classifications(&self) -> impl Iterator<Item=ContentClassification> + 'buf {
+        self.buf.chunks(Self::ENTRY_SIZE)
+            .filter(|entry| entry.len() == Self::ENTRY_SIZE)
+            .map(|entry| ContentClassification {
+                content_nibble_level_1: entry[0] >> 4,
+                content_nibble_level_2: entry[0] & 0b1111,
+                user_nibble_1: entry[1] >> 4,
+                user_nibble_2: entry[1] & 0b1111,
+            })
+    }
+}
+impl<'buf> fmt::Debug for ContentDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_list()
+            .entries(self.classifications())
+            .finish()
+    }
+}
+
+/// A single entry from a [`ContentDescriptor`](struct.ContentDescriptor.html).
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub struct ContentClassification {
+    /// The broad genre of the content; see `genre()`
+    pub content_nibble_level_1: u8,
+    /// A more specific classification, interpreted per _EN 300 468, table 29_ in combination
+    /// with `content_nibble_level_1`
+    pub content_nibble_level_2: u8,
+    /// Defined by the broadcaster
+    pub user_nibble_1: u8,
+    /// Defined by the broadcaster
+    pub user_nibble_2: u8,
+}
+impl ContentClassification {
+    pub fn genre(&self) -> Genre {
+        Genre::from(self.content_nibble_level_1)
+    }
+}
+
+/// The coarse genres given by the values of `content_nibble_level_1`.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Genre {
+    Undefined,
+    MovieDrama,
+    NewsCurrentAffairs,
+    ShowGameShow,
+    Sports,
+    ChildrensYouth,
+    MusicBalletDance,
+    ArtsCulture,
+    SocialPoliticalEconomics,
+    EducationScienceFactual,
+    LeisureHobbies,
+    SpecialCharacteristics,
+    Reserved(u8),
+    UserDefined,
+}
+impl Genre {
+    /// A short English name for the genre, e.g. `"Movie/Drama"`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Genre::Undefined => "Undefined",
+            Genre::MovieDrama => "Movie/Drama",
+            Genre::NewsCurrentAffairs => "News/Current affairs",
+            Genre::ShowGameShow => "Show/Game show",
+            Genre::Sports => "Sports",
+            Genre::ChildrensYouth => "Children's/Youth",
+            Genre::MusicBalletDance => "Music/Ballet/Dance",
+            Genre::ArtsCulture => "Arts/Culture",
+            Genre::SocialPoliticalEconomics => "Social/Political issues/Economics",
+            Genre::EducationScienceFactual => "Education/Science/Factual topics",
+            Genre::LeisureHobbies => "Leisure hobbies",
+            Genre::SpecialCharacteristics => "Special characteristics",
+            Genre::Reserved(_) => "Reserved",
+            Genre::UserDefined => "User defined",
+        }
+    }
+}
+impl From<u8> for Genre {
+    fn from(content_nibble_level_1: u8) -> Genre {
+        match content_nibble_level_1 {
+            0x0 => Genre::Undefined,
+            0x1 => Genre::MovieDrama,
+            0x2 => Genre::NewsCurrentAffairs,
+            0x3 => Genre::ShowGameShow,
+            0x4 => Genre::Sports,
+            0x5 => Genre::ChildrensYouth,
+            0x6 => Genre::MusicBalletDance,
+            0x7 => Genre::ArtsCulture,
+            0x8 => Genre::SocialPoliticalEconomics,
+            0x9 => Genre::EducationScienceFactual,
+            0xa => Genre::LeisureHobbies,
+            0xb => Genre::SpecialCharacteristics,
+            0xf => Genre::UserDefined,
+            v => Genre::Reserved(v),
+        }
+    }
+}
+
+/// The 3-bit `running_status` of a service or event, as given in the SDT and EIT, per
+/// _EN 300 468, table 6_.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum RunningStatus {
+    Undefined,
+    NotRunning,
+    StartsInFewSeconds,
+    Pausing,
+    Running,
+    OffAir,
+    Reserved(u8),
+}
+impl RunningStatus {
+    /// `true` only for `RunningStatus::Running`.
+    pub fn is_running(&self) -> bool {
+        *self == RunningStatus::Running
+    }
+}
+impl From<u8> for RunningStatus {
+    fn from(val: u8) -> RunningStatus {
+        match val {
+            0 => RunningStatus::Undefined,
+            1 => RunningStatus::NotRunning,
+            2 => RunningStatus::StartsInFewSeconds,
+            3 => RunningStatus::Pausing,
+            4 => RunningStatus::Running,
+            5 => RunningStatus::OffAir,
+            v => RunningStatus::Reserved(v),
+        }
+    }
+}
+
+/// Gives the recommended minimum age of viewers of an event, possibly with a different rating
+/// per country.
+pub struct ParentalRatingDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> ParentalRatingDescriptor<'buf> {
+    pub const TAG: u8 = 0x55;
+    const ENTRY_SIZE: usize = 4;
+
+    pub fn new(buf: &'buf[u8]) -> Result<ParentalRatingDescriptor<'buf>, DescriptorError> {
+        Ok(ParentalRatingDescriptor { buf })
+    }
+
+    /// Iterates over the per-country ratings in this descriptor.  Any trailing bytes, too short
+    /// to hold a complete entry, are ignored.
+    pub fn ratings(&self) -> impl Iterator<Item=ParentalRating> + 'buf {
+        self.buf.chunks(Self::ENTRY_SIZE)
+            .filter(|entry| entry.len() == Self::ENTRY_SIZE)
+            .map(|entry| ParentalRating {
+                country_code: [entry[0], entry[1], entry[2]],
+                rating: entry[3],
+            })
+    }
+}
+impl<'buf> fmt::Debug for ParentalRatingDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_list()
+            .entries(self.ratings())
+            .finish()
+    }
+}
+
+/// A single entry from a [`ParentalRatingDescriptor`](struct.ParentalRatingDescriptor.html).
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub struct ParentalRating {
+    /// _ISO 3166_ country code, e.g. `*b"GBR"`
+    pub country_code: [u8; 3],
+    pub rating: u8,
+}
+impl ParentalRating {
+    /// The minimum recommended age in years, for `rating` values `0x01` to `0x0f`.  Returns
+    /// `None` for the value `0x00` ('undefined') and for the values `0x10` to `0xff`, whose
+    /// meaning is defined by the broadcaster.
+    pub fn minimum_age(&self) -> Option<u8> {
+        match self.rating {
+            0x01..=0x0f => Some(self.rating + 3),
+            _ => None,
+        }
+    }
+}
+
+/// Identifies the organisation defining any private descriptors that follow in the same
+/// descriptor loop.
+///
+/// Descriptors with tag values that _EN 300 468_ leaves as 'user defined' (`0x80` to `0xfe`)
+/// can't be interpreted in isolation; the `private_data_specifier` value of the most recent
+/// preceding `PrivateDataSpecifierDescriptor` in the same descriptor loop (until the loop ends,
+/// or another `PrivateDataSpecifierDescriptor` is encountered) indicates the vendor namespace in
+/// which their tags should be interpreted.  This applies to the descriptor loops of the PMT, as
+/// well as those of DVB tables like the SDT and NIT.
+pub struct PrivateDataSpecifierDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> PrivateDataSpecifierDescriptor<'buf> {
+    pub const TAG: u8 = 0x5f;
+
+    pub fn new(buf: &'buf[u8]) -> Result<PrivateDataSpecifierDescriptor<'buf>, DescriptorError> {
+        if buf.len() < 4 {
+            Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: 4 })
+        } else {
+            Ok(PrivateDataSpecifierDescriptor { buf })
+        }
+    }
+
+    /// The identifier registered with DVB for the organisation concerned (see _ETSI TS 101 162_).
+    pub fn private_data_specifier(&self) -> u32 {
+        u32::from(self.buf[0]) << 24
+        | u32::from(self.buf[1]) << 16
+        | u32::from(self.buf[2]) << 8
+        | u32::from(self.buf[3])
+    }
+}
+impl<'buf> fmt::Debug for PrivateDataSpecifierDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("PrivateDataSpecifierDescriptor")
+            .field("private_data_specifier", &format_args!("{:#010x}", self.private_data_specifier()))
+            .finish()
+    }
+}
+
+/// Announces the Teletext pages carried by an elementary stream, including any pages carrying
+/// subtitles.
+pub struct TeletextDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> TeletextDescriptor<'buf> {
+    pub const TAG: u8 = 0x56;
+    const ENTRY_SIZE: usize = 5;
+
+    pub fn new(buf: &'buf[u8]) -> Result<TeletextDescriptor<'buf>, DescriptorError> {
+        Ok(TeletextDescriptor { buf })
+    }
+
+    /// Iterates over the pages announced in this descriptor.  Any trailing bytes, too short to
+    /// hold a complete entry, are ignored.
+    pub fn pages(&self) -> impl Iterator<Item=TeletextPage> + 'buf {
+        self.buf.chunks(Self::ENTRY_SIZE)
+            .filter(|entry| entry.len() == Self::ENTRY_SIZE)
+            .map(|entry| TeletextPage {
+                language_code: [entry[0], entry[1], entry[2]],
+                teletext_type: entry[3] >> 3,
+                magazine_number: entry[3] & 0b111,
+                page_number: entry[4],
+            })
+    }
+}
+impl<'buf> fmt::Debug for TeletextDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_list()
+            .entries(self.pages())
+            .finish()
+    }
+}
+
+/// A single entry from a [`TeletextDescriptor`](struct.TeletextDescriptor.html).
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub struct TeletextPage {
+    /// _ISO 639-2_ language code, e.g. `*b"eng"`
+    pub language_code: [u8; 3],
+    /// The kind of page, per _EN 300 468, table 100_ (e.g. `0x02` 'Teletext subtitle page')
+    pub teletext_type: u8,
+    /// The magazine number, where the value `0` represents magazine 8
+    pub magazine_number: u8,
+    /// The page number within the magazine, as two BCD digits
+    pub page_number: u8,
+}
+impl TeletextPage {
+    /// `teletext_type` value for a subtitle page.
+    pub const SUBTITLE: u8 = 0x02;
+    /// `teletext_type` value for a subtitle page for the hearing impaired.
+    pub const HEARING_IMPAIRED_SUBTITLE: u8 = 0x05;
+
+    /// `true` if `teletext_type` indicates that this page carries subtitles.
+    pub fn is_subtitle(&self) -> bool {
+        self.teletext_type == Self::SUBTITLE || self.teletext_type == Self::HEARING_IMPAIRED_SUBTITLE
+    }
+
+    /// The page number as usually presented to viewers, e.g. `888` for magazine `0` page `0x88`.
+    /// Returns `None` if `page_number` is not valid BCD.
+    pub fn display_page(&self) -> Option<u16> {
+        let magazine = if self.magazine_number == 0 { 8 } else { u16::from(self.magazine_number) };
+        bcd(&[self.page_number], 2).map(|page| magazine * 100 + page as u16)
+    }
+}
+
+/// Announces the DVB subtitle services (_EN 300 743_) carried by an elementary stream.
+pub struct SubtitlingDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> SubtitlingDescriptor<'buf> {
+    pub const TAG: u8 = 0x59;
+    const ENTRY_SIZE: usize = 8;
+
+    pub fn new(buf: &'buf[u8]) -> Result<SubtitlingDescriptor<'buf>, DescriptorError> {
+        Ok(SubtitlingDescriptor { buf })
+    }
+
+    /// Iterates over the subtitle services announced in this descriptor.  Any trailing bytes, too
+    /// short to hold a complete entry, are ignored.
+    pub fn subtitles(&self) -> impl Iterator<Item=Subtitling> + 'buf {
+        self.buf.chunks(Self::ENTRY_SIZE)
+            .filter(|entry| entry.len() == Self::ENTRY_SIZE)
+            .map(|entry| Subtitling {
+                language_code: [entry[0], entry[1], entry[2]],
+                subtitling_type: entry[3],
+                composition_page_id: u16::from(entry[4]) << 8 | u16::from(entry[5]),
+                ancillary_page_id: u16::from(entry[6]) << 8 | u16::from(entry[7]),
+            })
+    }
+}
+impl<'buf> fmt::Debug for SubtitlingDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_list()
+            .entries(self.subtitles())
+            .finish()
+    }
+}
+
+/// A single entry from a [`SubtitlingDescriptor`](struct.SubtitlingDescriptor.html).
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub struct Subtitling {
+    /// _ISO 639-2_ language code, e.g. `*b"eng"`
+    pub language_code: [u8; 3],
+    /// The kind of subtitles, per the `component_type` values for `stream_content` `0x3` in
+    /// _EN 300 468, table 26_ (e.g. `0x10` 'DVB subtitles (normal) with no monitor aspect ratio
+    /// criticality')
+    pub subtitling_type: u8,
+    pub composition_page_id: u16,
+    pub ancillary_page_id: u16,
+}
+
+fn check_bcd(buf: &[u8], digits: usize, field: &'static str) -> Result<(), DescriptorError> {
+    match bcd(buf, digits) {
+        Some(_) => Ok(()),
+        None => Err(DescriptorError::InvalidBcd { field }),
+    }
+}
+
+/// Describes an elementary stream of MPEG-4 AAC, HE-AAC or HE-AAC v2 audio (_EN 300 468,
+/// annex H_).
+pub struct AacAudioDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> AacAudioDescriptor<'buf> {
+    pub const TAG: u8 = 0x7c;
+
+    pub fn new(buf: &'buf[u8]) -> Result<AacAudioDescriptor<'buf>, DescriptorError> {
+        if buf.is_empty() {
+            return Err(DescriptorError::NotEnoughData { actual: 0, expected: 1 });
+        }
+        let desc = AacAudioDescriptor { buf };
+        if desc.aac_type_flag() && buf.len() < 3 {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: 3 });
+        }
+        Ok(desc)
+    }
+
+    /// The MPEG-4 audio profile and level, coded per _ISO/IEC 14496-3_.
+    pub fn profile_and_level(&self) -> u8 {
+        self.buf[0]
+    }
+    fn aac_type_flag(&self) -> bool {
+        self.buf.len() > 1 && self.buf[1] & 0b1000_0000 != 0
+    }
+    /// Indicates that the stream carries SAOC-DE (dialogue enhancement) parametric data.
+    pub fn saoc_de_flag(&self) -> bool {
+        self.buf.len() > 1 && self.buf[1] & 0b0100_0000 != 0
+    }
+    /// The type of audio, coded in the same way as the `component_type` values for AAC in
+    /// _EN 300 468, table 26_, if the descriptor includes it.
+    pub fn aac_type(&self) -> Option<u8> {
+        if self.aac_type_flag() {
+            Some(self.buf[2])
+        } else {
+            None
+        }
+    }
+    /// Any bytes following the fields above, whose meaning is not defined by _EN 300 468_.
+    pub fn additional_info(&self) -> &'buf[u8] {
+        match self.buf.len() {
+            1 => &[],
+            _ if self.aac_type_flag() => &self.buf[3..],
+            _ => &self.buf[2..],
+        }
+    }
+}
+impl<'buf> fmt::Debug for AacAudioDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("AacAudioDescriptor")
+            .field("profile_and_level", &format_args!("{:#04x}", self.profile_and_level()))
+            .field("saoc_de_flag", &self.saoc_de_flag())
+            .field("aac_type", &self.aac_type())
+            .field("additional_info", &format_args!("{:x}", self.additional_info().as_hex()))
+            .finish()
+    }
+}
+
+/// The layout shared by the AC-3 and Enhanced AC-3 descriptors, where a flags byte is followed by
+/// a one byte field for each of `field_flags` that is set, in order from the most significant
+/// flag, and then by any additional bytes.
+struct FlagFields<'buf> {
+    buf: &'buf[u8],
+    field_flags: u8,
+}
+impl<'buf> FlagFields<'buf> {
+    fn new(buf: &'buf[u8], field_flags: u8) -> Result<FlagFields<'buf>, DescriptorError> {
+        if buf.is_empty() {
+            return Err(DescriptorError::NotEnoughData { actual: 0, expected: 1 });
+        }
+        let fields = FlagFields { buf, field_flags };
+        let expected = fields.fields_end();
+        if buf.len() < expected {
+            Err(DescriptorError::NotEnoughData { actual: buf.len(), expected })
+        } else {
+            Ok(fields)
+        }
+    }
+
+    fn flags(&self) -> u8 {
+        self.buf[0]
+    }
+
+    fn fields_end(&self) -> usize {
+        1 + (self.flags() & self.field_flags).count_ones() as usize
+    }
+
+    /// The field following the given flag, which appears after the fields of any of the flags
+    /// preceding it that are set
+    fn field(&self, flag: u8) -> Option<u8> {
+        let flags = self.flags() & self.field_flags;
+        if flags & flag == 0 {
+            return None;
+        }
+        let preceding = flags & !(flag | (flag - 1));
+        Some(self.buf[1 + preceding.count_ones() as usize])
+    }
+
+    fn additional_info(&self) -> &'buf[u8] {
+        &self.buf[self.fields_end()..]
+    }
+}
+
+/// Describes an elementary stream of AC-3 audio (_EN 300 468, annex D_).  Each of the optional
+/// fields is present only if the corresponding flag in the first byte is set.
+pub struct Ac3Descriptor<'buf> {
+    fields: FlagFields<'buf>,
+}
+impl<'buf> Ac3Descriptor<'buf> {
+    pub const TAG: u8 = 0x6a;
+    const COMPONENT_TYPE_FLAG: u8 = 0b1000_0000;
+    const BSID_FLAG: u8 = 0b0100_0000;
+    const MAINID_FLAG: u8 = 0b0010_0000;
+    const ASVC_FLAG: u8 = 0b0001_0000;
+    const FIELD_FLAGS: u8 = 0b1111_0000;
+
+    pub fn new(buf: &'buf[u8]) -> Result<Ac3Descriptor<'buf>, DescriptorError> {
+        FlagFields::new(buf, Self::FIELD_FLAGS).map(|fields| Ac3Descriptor { fields })
+    }
+
+    /// Describes the audio service, per _EN 300 468, table D.1_.
+    pub fn component_type(&self) -> Option<u8> {
+        self.fields.field(Self::COMPONENT_TYPE_FLAG)
+    }
+    /// The `bsid` value from the AC-3 bitstream.
+    pub fn bsid(&self) -> Option<u8> {
+        self.fields.field(Self::BSID_FLAG)
+    }
+    pub fn mainid(&self) -> Option<u8> {
+        self.fields.field(Self::MAINID_FLAG)
+    }
+    pub fn asvc(&self) -> Option<u8> {
+        self.fields.field(Self::ASVC_FLAG)
+    }
+    /// Any bytes following the fields above.
+    pub fn additional_info(&self) -> &'buf[u8] {
+        self.fields.additional_info()
+    }
+}
+impl<'buf> fmt::Debug for Ac3Descriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("Ac3Descriptor")
+            .field("component_type", &self.component_type())
+            .field("bsid", &self.bsid())
+            .field("mainid", &self.mainid())
+            .field("asvc", &self.asvc())
+            .field("additional_info", &format_args!("{:x}", self.additional_info().as_hex()))
+            .finish()
+    }
+}
+
+/// Describes an elementary stream of Enhanced AC-3 audio (_EN 300 468, annex D_).  Each of the
+/// optional fields is present only if the corresponding flag in the first byte is set.
+pub struct EnhancedAc3Descriptor<'buf> {
+    fields: FlagFields<'buf>,
+}
+impl<'buf> EnhancedAc3Descriptor<'buf> {
+    pub const TAG: u8 = 0x7a;
+    const COMPONENT_TYPE_FLAG: u8 = 0b1000_0000;
+    const BSID_FLAG: u8 = 0b0100_0000;
+    const MAINID_FLAG: u8 = 0b0010_0000;
+    const ASVC_FLAG: u8 = 0b0001_0000;
+    const MIXINFOEXISTS: u8 = 0b0000_1000;
+    const SUBSTREAM1_FLAG: u8 = 0b0000_0100;
+    const SUBSTREAM2_FLAG: u8 = 0b0000_0010;
+    const SUBSTREAM3_FLAG: u8 = 0b0000_0001;
+    /// the flags which are each followed by a single byte field
+    const FIELD_FLAGS: u8 = !Self::MIXINFOEXISTS;
+
+    pub fn new(buf: &'buf[u8]) -> Result<EnhancedAc3Descriptor<'buf>, DescriptorError> {
+        FlagFields::new(buf, Self::FIELD_FLAGS).map(|fields| EnhancedAc3Descriptor { fields })
+    }
+
+    /// Describes the audio service, per _EN 300 468, table D.1_.
+    pub fn component_type(&self) -> Option<u8> {
+        self.fields.field(Self::COMPONENT_TYPE_FLAG)
+    }
+    /// The `bsid` value from the Enhanced AC-3 bitstream (`16` for E-AC-3).
+    pub fn bsid(&self) -> Option<u8> {
+        self.fields.field(Self::BSID_FLAG)
+    }
+    pub fn mainid(&self) -> Option<u8> {
+        self.fields.field(Self::MAINID_FLAG)
+    }
+    /// For an associated service, indicates the main audio services (by `mainid`) it may be
+    /// combined with.
+    pub fn asvc(&self) -> Option<u8> {
+        self.fields.field(Self::ASVC_FLAG)
+    }
+    /// Indicates that the stream carries metadata for mixing it with a main audio service.
+    pub fn mixinfoexists(&self) -> bool {
+        self.fields.flags() & Self::MIXINFOEXISTS != 0
+    }
+    /// The `component_type` of the first additional substream, if present.
+    pub fn substream1(&self) -> Option<u8> {
+        self.fields.field(Self::SUBSTREAM1_FLAG)
+    }
+    pub fn substream2(&self) -> Option<u8> {
+        self.fields.field(Self::SUBSTREAM2_FLAG)
+    }
+    pub fn substream3(&self) -> Option<u8> {
+        self.fields.field(Self::SUBSTREAM3_FLAG)
+    }
+    /// Any bytes following the fields above.
+    pub fn additional_info(&self) -> &'buf[u8] {
+        self.fields.additional_info()
+    }
+}
+impl<'buf> fmt::Debug for EnhancedAc3Descriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("EnhancedAc3Descriptor")
+            .field("component_type", &self.component_type())
+            .field("bsid", &self.bsid())
+            .field("mainid", &self.mainid())
+            .field("asvc", &self.asvc())
+            .field("mixinfoexists", &self.mixinfoexists())
+            .field("substream1", &self.substream1())
+            .field("substream2", &self.substream2())
+            .field("substream3", &self.substream3())
+            .field("additional_info", &format_args!("{:x}", self.additional_info().as_hex()))
+            .finish()
+    }
+}
+
+/// Describes an elementary stream of DTS audio (_EN 300 468, annex G_).
+pub struct DtsDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> DtsDescriptor<'buf> {
+    pub const TAG: u8 = 0x7b;
+    const HEADER_SIZE: usize = 5;
+
+    pub fn new(buf: &'buf[u8]) -> Result<DtsDescriptor<'buf>, DescriptorError> {
+        if buf.len() < Self::HEADER_SIZE {
+            Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: Self::HEADER_SIZE })
+        } else {
+            Ok(DtsDescriptor { buf })
+        }
+    }
+
+    /// the 40 bits of fixed fields
+    fn bits(&self) -> u64 {
+        self.buf[..Self::HEADER_SIZE].iter().fold(0, |acc, &b| acc << 8 | u64::from(b))
+    }
+
+    /// The 4-bit code for the sampling frequency, per _EN 300 468, table G.1_.
+    pub fn sample_rate_code(&self) -> u8 {
+        (self.bits() >> 36) as u8 & 0b1111
+    }
+    /// The 6-bit code for the transmission bitrate, per _EN 300 468, table G.2_.
+    pub fn bit_rate_code(&self) -> u8 {
+        (self.bits() >> 30) as u8 & 0b11_1111
+    }
+    /// The number of PCM sample blocks in each frame, minus one.
+    pub fn nblks(&self) -> u8 {
+        (self.bits() >> 23) as u8 & 0b111_1111
+    }
+    /// The number of bytes in each frame, minus one.
+    pub fn fsize(&self) -> u16 {
+        (self.bits() >> 9) as u16 & 0b11_1111_1111_1111
+    }
+    /// The 6-bit code for the number of audio channels and their arrangement, per
+    /// _EN 300 468, table G.4_.
+    pub fn surround_mode(&self) -> u8 {
+        (self.bits() >> 3) as u8 & 0b11_1111
+    }
+    /// Indicates that a low frequency effects channel is present.
+    pub fn lfe_flag(&self) -> bool {
+        self.bits() & 0b100 != 0
+    }
+    /// The 2-bit code indicating any extended surround channels, per _EN 300 468, table G.5_.
+    pub fn extended_surround_flag(&self) -> u8 {
+        self.bits() as u8 & 0b11
+    }
+    /// Any bytes following the fields above.
+    pub fn additional_info(&self) -> &'buf[u8] {
+        &self.buf[Self::HEADER_SIZE..]
+    }
+}
+impl<'buf> fmt::Debug for DtsDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("DtsDescriptor")
+            .field("sample_rate_code", &self.sample_rate_code())
+            .field("bit_rate_code", &self.bit_rate_code())
+            .field("nblks", &self.nblks())
+            .field("fsize", &self.fsize())
+            .field("surround_mode", &self.surround_mode())
+            .field("lfe_flag", &self.lfe_flag())
+            .field("extended_surround_flag", &self.extended_surround_flag())
+            .field("additional_info", &format_args!("{:x}", self.additional_info().as_hex()))
+            .finish()
+    }
+}
+
+/// The inner Forward Error Correction scheme of a delivery system, per _EN 300 468, table 35_.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum FecInner {
+    NotDefined,
+    Rate1_2,
+    Rate2_3,
+    Rate3_4,
+    Rate5_6,
+    Rate7_8,
+    Rate8_9,
+    Rate3_5,
+    Rate4_5,
+    Rate9_10,
+    NoConvolutionalCoding,
+    Reserved(u8),
+}
+impl From<u8> for FecInner {
+    fn from(val: u8) -> FecInner {
+        match val {
+            0 => FecInner::NotDefined,
+            1 => FecInner::Rate1_2,
+            2 => FecInner::Rate2_3,
+            3 => FecInner::Rate3_4,
+            4 => FecInner::Rate5_6,
+            5 => FecInner::Rate7_8,
+            6 => FecInner::Rate8_9,
+            7 => FecInner::Rate3_5,
+            8 => FecInner::Rate4_5,
+            9 => FecInner::Rate9_10,
+            15 => FecInner::NoConvolutionalCoding,
+            v => FecInner::Reserved(v),
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum OrbitalDirection {
+    West,
+    East,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Polarization {
+    LinearHorizontal,
+    LinearVertical,
+    CircularLeft,
+    CircularRight,
+}
+
+/// The roll-off factor of a DVB-S2 transmission
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum RollOff {
+    Alpha0_35,
+    Alpha0_25,
+    Alpha0_20,
+    Reserved,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum SatelliteModulationSystem {
+    DvbS,
+    DvbS2,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum SatelliteModulationType {
+    Auto,
+    Qpsk,
+    Psk8,
+    Qam16,
+}
+
+/// Gives the tuning parameters of a DVB-S or DVB-S2 transport stream, within the transport
+/// stream loop of the NIT.
+pub struct SatelliteDeliverySystemDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> SatelliteDeliverySystemDescriptor<'buf> {
+    pub const TAG: u8 = 0x43;
+    const SIZE: usize = 11;
+
+    /// Checks the size of the descriptor, and that the BCD encoded fields are valid, so that the
+    /// accessor methods need not.
+    pub fn new(buf: &'buf[u8]) -> Result<SatelliteDeliverySystemDescriptor<'buf>, DescriptorError> {
+        if buf.len() < Self::SIZE {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: Self::SIZE });
+        }
+        check_bcd(&buf[0..4], 8, "frequency")?;
+        check_bcd(&buf[4..6], 4, "orbital_position")?;
+        check_bcd(&buf[7..11], 7, "symbol_rate")?;
+        Ok(SatelliteDeliverySystemDescriptor { buf })
+    }
+
+    /// The frequency in kHz (the descriptor itself has a resolution of 10kHz)
+    pub fn frequency(&self) -> u32 {
+        bcd(&self.buf[0..4], 8).unwrap() * 10
+    }
+    /// The orbital position of the satellite in tenths of a degree, e.g. `192` for 19.2°;
+    /// see also `west_east()`
+    pub fn orbital_position(&self) -> u16 {
+        bcd(&self.buf[4..6], 4).unwrap() as u16
+    }
+    pub fn west_east(&self) -> OrbitalDirection {
+        if self.buf[6] & 0b1000_0000 == 0 {
+            OrbitalDirection::West
+        } else {
+            OrbitalDirection::East
+        }
+    }
+    pub fn polarization(&self) -> Polarization {
+        match self.buf[6] >> 5 & 0b11 {
+            0b00 => Polarization::LinearHorizontal,
+            0b01 => Polarization::LinearVertical,
+            0b10 => Polarization::CircularLeft,
+            _ => Polarization::CircularRight,
+        }
+    }
+    /// The roll-off factor, which is only signalled when `modulation_system()` is DVB-S2
+    pub fn roll_off(&self) -> Option<RollOff> {
+        match self.modulation_system() {
+            SatelliteModulationSystem::DvbS => None,
+            SatelliteModulationSystem::DvbS2 => Some(match self.buf[6] >> 3 & 0b11 {
+                0b00 => RollOff::Alpha0_35,
+                0b01 => RollOff::Alpha0_25,
+                0b10 => RollOff::Alpha0_20,
+                _ => RollOff::Reserved,
+            }),
+        }
+    }
+    pub fn modulation_system(&self) -> SatelliteModulationSystem {
+        if self.buf[6] & 0b100 == 0 {
+            SatelliteModulationSystem::DvbS
+        } else {
+            SatelliteModulationSystem::DvbS2
+        }
+    }
+    pub fn modulation_type(&self) -> SatelliteModulationType {
+        match self.buf[6] & 0b11 {
+            0b00 => SatelliteModulationType::Auto,
+            0b01 => SatelliteModulationType::Qpsk,
+            0b10 => SatelliteModulationType::Psk8,
+            _ => SatelliteModulationType::Qam16,
+        }
+    }
+    /// The symbol rate in units of 100 symbols/second (e.g. `275000` for 27.5 Msymbol/s)
+    pub fn symbol_rate(&self) -> u32 {
+        bcd(&self.buf[7..11], 7).unwrap()
+    }
+    /// The symbol rate in ksymbol/s, rounded down
+    pub fn symbol_rate_ksps(&self) -> u32 {
+        self.symbol_rate() / 10
+    }
+    pub fn fec_inner(&self) -> FecInner {
+        FecInner::from(self.buf[10] & 0b1111)
+    }
+}
+impl<'buf> fmt::Debug for SatelliteDeliverySystemDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("SatelliteDeliverySystemDescriptor")
+            .field("frequency", &self.frequency())
+            .field("orbital_position", &self.orbital_position())
+            .field("west_east", &self.west_east())
+            .field("polarization", &self.polarization())
+            .field("roll_off", &self.roll_off())
+            .field("modulation_system", &self.modulation_system())
+            .field("modulation_type", &self.modulation_type())
+            .field("symbol_rate", &self.symbol_rate())
+            .field("fec_inner", &self.fec_inner())
+            .finish()
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum FecOuter {
+    NotDefined,
+    NoOuterFec,
+    /// Reed-Solomon RS(204/188)
+    Rs204_188,
+    Reserved(u8),
+}
+impl From<u8> for FecOuter {
+    fn from(val: u8) -> FecOuter {
+        match val {
+            0 => FecOuter::NotDefined,
+            1 => FecOuter::NoOuterFec,
+            2 => FecOuter::Rs204_188,
+            v => FecOuter::Reserved(v),
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum CableModulation {
+    NotDefined,
+    Qam16,
+    Qam32,
+    Qam64,
+    Qam128,
+    Qam256,
+    Reserved(u8),
+}
+impl From<u8> for CableModulation {
+    fn from(val: u8) -> CableModulation {
+        match val {
+            0 => CableModulation::NotDefined,
+            1 => CableModulation::Qam16,
+            2 => CableModulation::Qam32,
+            3 => CableModulation::Qam64,
+            4 => CableModulation::Qam128,
+            5 => CableModulation::Qam256,
+            v => CableModulation::Reserved(v),
+        }
+    }
+}
+
+/// Gives the tuning parameters of a DVB-C transport stream, within the transport stream loop of
+/// the NIT.
+pub struct CableDeliverySystemDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> CableDeliverySystemDescriptor<'buf> {
+    pub const TAG: u8 = 0x44;
+    const SIZE: usize = 11;
+
+    /// Checks the size of the descriptor, and that the BCD encoded fields are valid, so that the
+    /// accessor methods need not.
+    pub fn new(buf: &'buf[u8]) -> Result<CableDeliverySystemDescriptor<'buf>, DescriptorError> {
+        if buf.len() < Self::SIZE {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: Self::SIZE });
+        }
+        check_bcd(&buf[0..4], 8, "frequency")?;
+        check_bcd(&buf[7..11], 7, "symbol_rate")?;
+        Ok(CableDeliverySystemDescriptor { buf })
+    }
+
+    /// The frequency in units of 100Hz (e.g. `3_460_000` for 346MHz)
+    pub fn frequency(&self) -> u32 {
+        bcd(&self.buf[0..4], 8).unwrap()
+    }
+    /// The frequency in kHz, rounded down
+    pub fn frequency_khz(&self) -> u32 {
+        self.frequency() / 10
+    }
+    pub fn fec_outer(&self) -> FecOuter {
+        FecOuter::from(self.buf[5] & 0b1111)
+    }
+    pub fn modulation(&self) -> CableModulation {
+        CableModulation::from(self.buf[6])
+    }
+    /// The symbol rate in units of 100 symbols/second (e.g. `69_000` for 6.9 Msymbol/s)
+    pub fn symbol_rate(&self) -> u32 {
+        bcd(&self.buf[7..11], 7).unwrap()
+    }
+    /// The symbol rate in ksymbol/s, rounded down
+    pub fn symbol_rate_ksps(&self) -> u32 {
+        self.symbol_rate() / 10
+    }
+    pub fn fec_inner(&self) -> FecInner {
+        FecInner::from(self.buf[10] & 0b1111)
+    }
+}
+impl<'buf> fmt::Debug for CableDeliverySystemDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("CableDeliverySystemDescriptor")
+            .field("frequency", &self.frequency())
+            .field("fec_outer", &self.fec_outer())
+            .field("modulation", &self.modulation())
+            .field("symbol_rate", &self.symbol_rate())
+            .field("fec_inner", &self.fec_inner())
+            .finish()
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Bandwidth {
+    Mhz8,
+    Mhz7,
+    Mhz6,
+    Mhz5,
+    Reserved(u8),
+}
+
+/// Indicates which stream of a hierarchical transmission is described
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Priority {
+    /// High priority, also used for non-hierarchical transmissions
+    High,
+    Low,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Constellation {
+    Qpsk,
+    Qam16,
+    Qam64,
+    Reserved,
+}
+
+/// The `α` value of a hierarchical transmission
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Hierarchy {
+    NonHierarchical,
+    Alpha1,
+    Alpha2,
+    Alpha4,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Interleaver {
+    Native,
+    InDepth,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum CodeRate {
+    Rate1_2,
+    Rate2_3,
+    Rate3_4,
+    Rate5_6,
+    Rate7_8,
+    Reserved(u8),
+}
+impl From<u8> for CodeRate {
+    fn from(val: u8) -> CodeRate {
+        match val {
+            0 => CodeRate::Rate1_2,
+            1 => CodeRate::Rate2_3,
+            2 => CodeRate::Rate3_4,
+            3 => CodeRate::Rate5_6,
+            4 => CodeRate::Rate7_8,
+            v => CodeRate::Reserved(v),
+        }
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum GuardInterval {
+    Interval1_32,
+    Interval1_16,
+    Interval1_8,
+    Interval1_4,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum TransmissionMode {
+    Mode2k,
+    Mode8k,
+    Mode4k,
+    Reserved,
+}
+
+/// Gives the tuning parameters of a DVB-T transport stream, within the transport stream loop of
+/// the NIT.
+pub struct TerrestrialDeliverySystemDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> TerrestrialDeliverySystemDescriptor<'buf> {
+    pub const TAG: u8 = 0x5a;
+    const SIZE: usize = 11;
+
+    pub fn new(buf: &'buf[u8]) -> Result<TerrestrialDeliverySystemDescriptor<'buf>, DescriptorError> {
+        if buf.len() < Self::SIZE {
+            return Err(DescriptorError::NotEnoughData { actual: buf.len(), expected: Self::SIZE });
+        }
+        Ok(TerrestrialDeliverySystemDescriptor { buf })
+    }
+
+    /// The centre frequency in units of 10Hz (e.g. `51_400_000` for 514MHz)
+    pub fn centre_frequency(&self) -> u32 {
+        u32::from(self.buf[0]) << 24
+            | u32::from(self.buf[1]) << 16
+            | u32::from(self.buf[2]) << 8
+            | u32::from(self.buf[3])
+    }
+    /// The centre frequency in kHz, rounded down
+    pub fn centre_frequency_khz(&self) -> u32 {
+        self.centre_frequency() / 100
+    }
+    pub fn bandwidth(&self) -> Bandwidth {
+        match self.buf[4] >> 5 {
+            0b000 => Bandwidth::Mhz8,
+            0b001 => Bandwidth::Mhz7,
+            0b010 => Bandwidth::Mhz6,
+            0b011 => Bandwidth::Mhz5,
+            v => Bandwidth::Reserved(v),
+        }
+    }
+    pub fn priority(&self) -> Priority {
+        if self.buf[4] & 0b0001_0000 == 0 {
+            Priority::Low
+        } else {
+            Priority::High
+        }
+    }
+    /// `true` if at least one elementary stream of the transport stream uses Time Slicing (the
+    /// bit in the descriptor is set when Time Slicing is _not_ used)
+    pub fn time_slicing(&self) -> bool {
+        self.buf[4] & 0b0000_1000 == 0
+    }
+    /// `true` if at least one elementary stream of the transport stream uses MPE-FEC (the bit in
+    /// the descriptor is set when MPE-FEC is _not_ used)
+    pub fn mpe_fec(&self) -> bool {
+        self.buf[4] & 0b0000_0100 == 0
+    }
+    pub fn constellation(&self) -> Constellation {
+        match self.buf[5] >> 6 {
+            0b00 => Constellation::Qpsk,
+            0b01 => Constellation::Qam16,
+            0b10 => Constellation::Qam64,
+            _ => Constellation::Reserved,
+        }
+    }
+    pub fn hierarchy(&self) -> Hierarchy {
+        match self.buf[5] >> 3 & 0b11 {
+            0b00 => Hierarchy::NonHierarchical,
+            0b01 => Hierarchy::Alpha1,
+            0b10 => Hierarchy::Alpha2,
+            _ => Hierarchy::Alpha4,
+        }
+    }
+    /// The interleaver in use, signalled alongside `hierarchy()` in the `hierarchy_information`
+    /// field
+    pub fn interleaver(&self) -> Interleaver {
+        if self.buf[5] & 0b0010_0000 == 0 {
+            Interleaver::Native
+        } else {
+            Interleaver::InDepth
+        }
+    }
+    pub fn code_rate_hp_stream(&self) -> CodeRate {
+        CodeRate::from(self.buf[5] & 0b111)
+    }
+    /// The code rate of the low priority stream, which is only meaningful when `hierarchy()` is
+    /// not `NonHierarchical`
+    pub fn code_rate_lp_stream(&self) -> CodeRate {
+        CodeRate::from(self.buf[6] >> 5)
+    }
+    pub fn guard_interval(&self) -> GuardInterval {
+        match self.buf[6] >> 3 & 0b11 {
+            0b00 => GuardInterval::Interval1_32,
+            0b01 => GuardInterval::Interval1_16,
+            0b10 => GuardInterval::Interval1_8,
+            _ => GuardInterval::Interval1_4,
+        }
+    }
+    pub fn transmission_mode(&self) -> TransmissionMode {
+        match self.buf[6] >> 1 & 0b11 {
+            0b00 => TransmissionMode::Mode2k,
+            0b01 => TransmissionMode::Mode8k,
+            0b10 => TransmissionMode::Mode4k,
+            _ => TransmissionMode::Reserved,
+        }
+    }
+    /// `true` if other frequencies are in use for this transport stream (listed by a
+    /// frequency_list_descriptor, if present)
+    pub fn other_frequency_flag(&self) -> bool {
+        self.buf[6] & 1 != 0
+    }
+}
+impl<'buf> fmt::Debug for TerrestrialDeliverySystemDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(),fmt::Error> {
+        f.debug_struct("TerrestrialDeliverySystemDescriptor")
+            .field("centre_frequency", &self.centre_frequency())
+            .field("bandwidth", &self.bandwidth())
+            .field("priority", &self.priority())
+            .field("time_slicing", &self.time_slicing())
+            .field("mpe_fec", &self.mpe_fec())
+            .field("constellation", &self.constellation())
+            .field("hierarchy", &self.hierarchy())
+            .field("interleaver", &self.interleaver())
+            .field("code_rate_hp_stream", &self.code_rate_hp_stream())
+            .field("code_rate_lp_stream", &self.code_rate_lp_stream())
+            .field("guard_interval", &self.guard_interval())
+            .field("transmission_mode", &self.transmission_mode())
+            .field("other_frequency_flag", &self.other_frequency_flag())
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use data_encoding::hex;
+    use super::*;
+    use test_util;
+
+    #[test]
+    fn component_descriptor() {
+        let data = hex::decode(b"500BF10301656E67566964656F").unwrap();
+        let payload = test_util::user_private_payload(&data, ComponentDescriptor::TAG);
+        let desc = ComponentDescriptor::new(payload).unwrap();
+        assert_eq!(desc.stream_content_ext(), 0xf);
+        assert_eq!(desc.stream_content(), 1);
+        assert_eq!(desc.component_type(), 3);
+        assert_eq!(desc.component_tag(), 1);
+        assert_eq!(desc.language_code(), b"eng");
+        assert_eq!(desc.text(), b"Video");
+        assert_eq!(format!("{:?}", desc), "ComponentDescriptor { stream_content_ext: 15, stream_content: 1, component_type: 3, component_tag: 1, language_code: \"eng\", text: [56 69 64 65 6f] }");
+
+        assert_eq!(ComponentDescriptor::new(&payload[..5]).err(), Some(DescriptorError::NotEnoughData { actual: 5, expected: 6 }));
+    }
+
+    #[test]
+    fn stream_identifier_descriptor() {
+        let data = hex::decode(b"520105").unwrap();
+        let payload = test_util::user_private_payload(&data, StreamIdentifierDescriptor::TAG);
+        let desc = StreamIdentifierDescriptor::new(payload).unwrap();
+        assert_eq!(desc.component_tag(), 5);
+        assert_eq!(StreamIdentifierDescriptor::new(&[]).err(), Some(DescriptorError::NotEnoughData { actual: 0, expected: 1 }));
+    }
+
+    #[test]
+    fn short_event_descriptor() {
+        let data = b"eng\x05\x15News\x0cToday's news";
+        let desc = ShortEventDescriptor::new(&data[..]).unwrap();
+        assert_eq!(desc.language_code(), b"eng");
+        assert_eq!(desc.event_name(), b"\x15News");
+        assert_eq!(desc.text(), b"Today's news");
+        assert_eq!(::dvb_text::detect_encoding(desc.event_name()), (::dvb_text::Encoding::Utf8, &b"News"[..]));
+
+        // text_length extends beyond the end of the descriptor,
+        assert_eq!(ShortEventDescriptor::new(&data[..data.len()-1]).err(), Some(DescriptorError::NotEnoughData { actual: 21, expected: 22 }));
+        // event_name_length extends beyond the end of the descriptor,
+        assert!(ShortEventDescriptor::new(b"eng\x05\x15New").is_err());
+    }
+
+    #[test]
+    fn extended_event_descriptor() {
+        // a description split across two descriptors, with one item pair in each
+        let part0 = b"\x01eng\x12\x08Director\x08Jane Doe\x0aA long tal";
+        let part1 = b"\x11eng\x0a\x04Cast\x04Anon\x09e of woe.";
+        let parts: Vec<_> = [&part0[..], &part1[..]].iter()
+            .map(|buf| ExtendedEventDescriptor::new(buf).unwrap())
+            .collect();
+        assert_eq!(parts[0].descriptor_number(), 0);
+        assert_eq!(parts[0].last_descriptor_number(), 1);
+        assert_eq!(parts[1].descriptor_number(), 1);
+        assert_eq!(parts[1].last_descriptor_number(), 1);
+        assert_eq!(parts[0].language_code(), b"eng");
+        assert_eq!(
+            format!("{:?}", parts[0]),
+            "ExtendedEventDescriptor { descriptor_number: 0, last_descriptor_number: 1, language_code: \"eng\", items: [([44 69 72 65 63 74 6f 72], [4a 61 6e 65 20 44 6f 65])], text: [41 20 6c 6f 6e 67 20 74 61 6c] }"
+        );
+
+        let items: Vec<_> = parts.iter().flat_map(|p| p.items()).collect();
+        assert_eq!(items, vec![
+            (&b"Director"[..], &b"Jane Doe"[..]),
+            (&b"Cast"[..], &b"Anon"[..]),
+        ]);
+        let text: Vec<u8> = parts.iter().flat_map(|p| p.text().iter().cloned()).collect();
+        assert_eq!(&text[..], &b"A long tale of woe."[..]);
+
+        // no items and no text,
+        let desc = ExtendedEventDescriptor::new(b"\x00eng\x00\x00").unwrap();
+        assert_eq!(desc.items().count(), 0);
+        assert!(desc.text().is_empty());
+
+        // text_length extends beyond the end of the descriptor,
+        assert_eq!(ExtendedEventDescriptor::new(&part0[..part0.len()-1]).err(), Some(DescriptorError::NotEnoughData { actual: 33, expected: 34 }));
+        // item_length extends beyond length_of_items,
+        assert!(ExtendedEventDescriptor::new(b"\x00eng\x03\x01a\x01b\x00").is_err());
+    }
+
+    #[test]
+    fn service_descriptor() {
+        let data = b"\x01\x03BBC\x0aBBC One HD";
+        let desc = ServiceDescriptor::new(&data[..]).unwrap();
+        assert_eq!(desc.service_type(), 1);
+        assert_eq!(::dvb_text::decode(desc.service_provider_name()), "BBC");
+        assert_eq!(::dvb_text::decode(desc.service_name()), "BBC One HD");
+
+        // empty names,
+        let desc = ServiceDescriptor::new(b"\x02\x00\x00").unwrap();
+        assert!(desc.service_provider_name().is_empty());
+        assert!(desc.service_name().is_empty());
+
+        assert_eq!(ServiceDescriptor::new(&data[..data.len()-1]).err(), Some(DescriptorError::NotEnoughData { actual: 15, expected: 16 }));
+        assert!(ServiceDescriptor::new(b"\x01\x03BB").is_err());
+    }
+
+    #[test]
+    fn content_descriptor() {
+        // two entries, followed by a trailing partial entry
+        let data = hex::decode(b"5405104023F007").unwrap();
+        let payload = test_util::user_private_payload(&data, ContentDescriptor::TAG);
+        let desc = ContentDescriptor::new(payload).unwrap();
+        let classifications: Vec<_> = desc.classifications().collect();
+        assert_eq!(classifications, vec![
+            ContentClassification { content_nibble_level_1: 1, content_nibble_level_2: 0, user_nibble_1: 4, user_nibble_2: 0 },
+            ContentClassification { content_nibble_level_1: 2, content_nibble_level_2: 3, user_nibble_1: 0xf, user_nibble_2: 0 },
+        ]);
+        assert_eq!(classifications[0].genre(), Genre::MovieDrama);
+        assert_eq!(classifications[1].genre().name(), "News/Current affairs");
+        assert_eq!(Genre::from(0xd), Genre::Reserved(0xd));
+    }
+
+    #[test]
+    fn running_status() {
+        assert_eq!(RunningStatus::from(0), RunningStatus::Undefined);
+        assert_eq!(RunningStatus::from(3), RunningStatus::Pausing);
+        assert_eq!(RunningStatus::from(5), RunningStatus::OffAir);
+        assert_eq!(RunningStatus::from(7), RunningStatus::Reserved(7));
+        assert!(RunningStatus::from(4).is_running());
+        assert!(!RunningStatus::from(2).is_running());
+    }
+
+    #[test]
+    fn parental_rating_descriptor() {
+        let data = b"GBR\x0cFRA\x00DEU\x20ES";
+        let desc = ParentalRatingDescriptor::new(&data[..]).unwrap();
+        let ratings: Vec<_> = desc.ratings().collect();
+        assert_eq!(ratings, vec![
+            ParentalRating { country_code: *b"GBR", rating: 0x0c },
+            ParentalRating { country_code: *b"FRA", rating: 0x00 },
+            ParentalRating { country_code: *b"DEU", rating: 0x20 },
+        ]);
+        assert_eq!(ratings[0].minimum_age(), Some(15));
+        assert_eq!(ratings[1].minimum_age(), None);
+        assert_eq!(ratings[2].minimum_age(), None);
+    }
+
+    #[test]
+    fn private_data_specifier_descriptor() {
+        let data = hex::decode(b"5F0400000028").unwrap();
+        let payload = test_util::user_private_payload(&data, PrivateDataSpecifierDescriptor::TAG);
+        let desc = PrivateDataSpecifierDescriptor::new(payload).unwrap();
+        assert_eq!(desc.private_data_specifier(), 0x28);
+        assert!(PrivateDataSpecifierDescriptor::new(&payload[..3]).is_err());
+    }
+
+    #[test]
+    fn teletext_descriptor() {
+        // an initial page, and an english subtitle page on 888, followed by a trailing partial entry
+        let data = hex::decode(b"560C656E6709006E6C6412886765").unwrap();
+        let payload = test_util::user_private_payload(&data, TeletextDescriptor::TAG);
+        let desc = TeletextDescriptor::new(payload).unwrap();
+        let pages: Vec<_> = desc.pages().collect();
+        assert_eq!(pages, vec![
+            TeletextPage { language_code: *b"eng", teletext_type: 1, magazine_number: 1, page_number: 0x00 },
+            TeletextPage { language_code: *b"nld", teletext_type: 2, magazine_number: 2, page_number: 0x88 },
+        ]);
+        assert!(!pages[0].is_subtitle());
+        assert!(pages[1].is_subtitle());
+        assert_eq!(pages[0].display_page(), Some(100));
+        assert_eq!(pages[1].display_page(), Some(288));
+        let page = TeletextPage { magazine_number: 0, page_number: 0x8f, ..pages[1] };
+        assert_eq!(page.display_page(), None);
+        assert_eq!(TeletextPage { page_number: 0x88, ..page }.display_page(), Some(888));
+    }
+
+    #[test]
+    fn subtitling_descriptor() {
+        let data = hex::decode(b"5910656E671000010002646575200003FFFF").unwrap();
+        let payload = test_util::user_private_payload(&data, SubtitlingDescriptor::TAG);
+        let desc = SubtitlingDescriptor::new(payload).unwrap();
+        let subtitles: Vec<_> = desc.subtitles().collect();
+        assert_eq!(subtitles, vec![
+            Subtitling { language_code: *b"eng", subtitling_type: 0x10, composition_page_id: 1, ancillary_page_id: 2 },
+            Subtitling { language_code: *b"deu", subtitling_type: 0x20, composition_page_id: 3, ancillary_page_id: 0xffff },
+        ]);
+        assert_eq!(SubtitlingDescriptor::new(&payload[..7]).unwrap().subtitles().count(), 0);
+    }
+
+    #[test]
+    fn network_name_descriptor() {
+        let data = b"\x40\x09\x15Freeview";
+        let payload = test_util::user_private_payload(&data[..], NetworkNameDescriptor::TAG);
+        let desc = NetworkNameDescriptor::new(payload).unwrap();
+        assert_eq!(desc.name(), b"\x15Freeview");
+        assert_eq!(desc.name_text(), "Freeview");
+    }
+
+    #[test]
+    fn bouquet_name_descriptor() {
+        // in the default character table, with an accented character
+        let data = b"\x47\x08Caf\xc2e TV";
+        let payload = test_util::user_private_payload(&data[..], BouquetNameDescriptor::TAG);
+        let desc = BouquetNameDescriptor::new(payload).unwrap();
+        assert_eq!(desc.name(), b"Caf\xc2e TV");
+        assert_eq!(desc.name_text(), "Cafe\u{301} TV");
+        assert_eq!(BouquetNameDescriptor::new(&[]).unwrap().name_text(), "");
+    }
+
+    #[test]
+    fn linkage_descriptor() {
+        // mobile hand-over to an associated service in a neighbouring country, on network 0x3001
+        let data = hex::decode(b"4A0A10052233123408103001").unwrap();
+        let payload = test_util::user_private_payload(&data, LinkageDescriptor::TAG);
+        let desc = LinkageDescriptor::new(payload).unwrap();
+        assert_eq!(desc.transport_stream_id(), 0x1005);
+        assert_eq!(desc.original_network_id(), 0x2233);
+        assert_eq!(desc.service_id(), 0x1234);
+        assert_eq!(desc.linkage_type(), LinkageType::MobileHandOver);
+        assert_eq!(desc.private_data(), &[0x10, 0x30, 0x01][..]);
+
+        let desc = LinkageDescriptor::new(&payload[..7]).unwrap();
+        assert!(desc.private_data().is_empty());
+        assert_eq!(LinkageDescriptor::new(&payload[..6]).err(), Some(DescriptorError::NotEnoughData { actual: 6, expected: 7 }));
+
+        assert_eq!(LinkageType::from(0x03), LinkageType::CaReplacementService);
+        assert_eq!(LinkageType::from(0x0e), LinkageType::ExtendedEventLinkage(0x0e));
+        assert_eq!(LinkageType::from(0x80), LinkageType::UserDefined(0x80));
+        assert_eq!(LinkageType::from(0x00), LinkageType::Reserved(0x00));
+        assert_eq!(LinkageType::from(0xff), LinkageType::Reserved(0xff));
+    }
+
+    #[test]
+    fn aac_audio_descriptor() {
+        // the minimal form, with only profile_and_level,
+        let data = hex::decode(b"7C0158").unwrap();
+        let payload = test_util::user_private_payload(&data, AacAudioDescriptor::TAG);
+        let desc = AacAudioDescriptor::new(payload).unwrap();
+        assert_eq!(desc.profile_and_level(), 0x58);
+        assert!(!desc.saoc_de_flag());
+        assert_eq!(desc.aac_type(), None);
+        assert!(desc.additional_info().is_empty());
+
+        // with AAC_type, followed by additional_info_bytes,
+        let data = hex::decode(b"7C0551C0030102").unwrap();
+        let payload = test_util::user_private_payload(&data, AacAudioDescriptor::TAG);
+        let desc = AacAudioDescriptor::new(payload).unwrap();
+        assert_eq!(desc.profile_and_level(), 0x51);
+        assert!(desc.saoc_de_flag());
+        assert_eq!(desc.aac_type(), Some(0x03));
+        assert_eq!(desc.additional_info(), &[0x01, 0x02][..]);
+
+        // without AAC_type,
+        let desc = AacAudioDescriptor::new(&[0x51, 0x00, 0xff]).unwrap();
+        assert_eq!(desc.aac_type(), None);
+        assert_eq!(desc.additional_info(), &[0xff][..]);
+
+        assert_eq!(AacAudioDescriptor::new(&[]).err(), Some(DescriptorError::NotEnoughData { actual: 0, expected: 1 }));
+        assert_eq!(AacAudioDescriptor::new(&[0x51, 0x80]).err(), Some(DescriptorError::NotEnoughData { actual: 2, expected: 3 }));
+    }
+
+    #[test]
+    fn ac3_descriptor() {
+        let data = hex::decode(b"6A03C04408").unwrap();
+        let payload = test_util::user_private_payload(&data, Ac3Descriptor::TAG);
+        let desc = Ac3Descriptor::new(payload).unwrap();
+        assert_eq!(desc.component_type(), Some(0x44));
+        assert_eq!(desc.bsid(), Some(8));
+        assert_eq!(desc.mainid(), None);
+        assert_eq!(desc.asvc(), None);
+        assert!(desc.additional_info().is_empty());
+
+        // the reserved bits in the first byte do not indicate further fields
+        let desc = Ac3Descriptor::new(&[0b0001_1111, 2, 0xaa]).unwrap();
+        assert_eq!(desc.asvc(), Some(2));
+        assert_eq!(desc.additional_info(), &[0xaa][..]);
+        assert_eq!(Ac3Descriptor::new(&[]).err(), Some(DescriptorError::NotEnoughData { actual: 0, expected: 1 }));
+        assert_eq!(Ac3Descriptor::new(&[0b1100_0000, 0x44]).err(), Some(DescriptorError::NotEnoughData { actual: 2, expected: 3 }));
+    }
+
+    #[test]
+    fn enhanced_ac3_descriptor() {
+        // component_type and bsid, as commonly seen in broadcasts
+        let data = hex::decode(b"7A03C04410").unwrap();
+        let payload = test_util::user_private_payload(&data, EnhancedAc3Descriptor::TAG);
+        let desc = EnhancedAc3Descriptor::new(payload).unwrap();
+        assert_eq!(desc.component_type(), Some(0x44));
+        assert_eq!(desc.bsid(), Some(16));
+        assert_eq!(desc.mainid(), None);
+        assert_eq!(desc.asvc(), None);
+        assert!(!desc.mixinfoexists());
+        assert_eq!(desc.substream1(), None);
+        assert!(desc.additional_info().is_empty());
+
+        // mainid, asvc, mixinfoexists and substream2, followed by additional_info
+        let desc = EnhancedAc3Descriptor::new(&[0b0011_1010, 1, 2, 3, 0xaa]).unwrap();
+        assert_eq!(desc.component_type(), None);
+        assert_eq!(desc.bsid(), None);
+        assert_eq!(desc.mainid(), Some(1));
+        assert_eq!(desc.asvc(), Some(2));
+        assert!(desc.mixinfoexists());
+        assert_eq!(desc.substream1(), None);
+        assert_eq!(desc.substream2(), Some(3));
+        assert_eq!(desc.substream3(), None);
+        assert_eq!(desc.additional_info(), &[0xaa][..]);
+
+        let desc = EnhancedAc3Descriptor::new(&[0]).unwrap();
+        assert_eq!(desc.component_type(), None);
+        assert!(desc.additional_info().is_empty());
+        assert_eq!(EnhancedAc3Descriptor::new(&[]).err(), Some(DescriptorError::NotEnoughData { actual: 0, expected: 1 }));
+        assert_eq!(EnhancedAc3Descriptor::new(&[0b1000_0001, 0x44]).err(), Some(DescriptorError::NotEnoughData { actual: 2, expected: 3 }));
+    }
+
+    #[test]
+    fn dts_descriptor() {
+        // 48kHz, 16 blocks of 2013 bytes, 5 channels plus LFE
+        let data = hex::decode(b"7B05D3C78FB84C").unwrap();
+        let payload = test_util::user_private_payload(&data, DtsDescriptor::TAG);
+        let desc = DtsDescriptor::new(payload).unwrap();
+        assert_eq!(desc.sample_rate_code(), 13);
+        assert_eq!(desc.bit_rate_code(), 15);
+        assert_eq!(desc.nblks(), 15);
+        assert_eq!(desc.fsize(), 2012);
+        assert_eq!(desc.surround_mode(), 9);
+        assert!(desc.lfe_flag());
+        assert_eq!(desc.extended_surround_flag(), 0);
+        assert!(desc.additional_info().is_empty());
+        assert_eq!(DtsDescriptor::new(&payload[..4]).err(), Some(DescriptorError::NotEnoughData { actual: 4, expected: 5 }));
+    }
+
+    #[test]
+    fn satellite_delivery_system_descriptor() {
+        // 11.778GHz V, 27500 ksym/s, FEC 3/4, DVB-S QPSK from 19.2°E
+        let data = hex::decode(b"430B011778000192A102750003").unwrap();
+        let payload = test_util::user_private_payload(&data, SatelliteDeliverySystemDescriptor::TAG);
+        let desc = SatelliteDeliverySystemDescriptor::new(payload).unwrap();
+        assert_eq!(desc.frequency(), 11_778_000);
+        assert_eq!(desc.orbital_position(), 192);
+        assert_eq!(desc.west_east(), OrbitalDirection::East);
+        assert_eq!(desc.polarization(), Polarization::LinearVertical);
+        assert_eq!(desc.modulation_system(), SatelliteModulationSystem::DvbS);
+        assert_eq!(desc.roll_off(), None);
+        assert_eq!(desc.modulation_type(), SatelliteModulationType::Qpsk);
+        assert_eq!(desc.symbol_rate(), 275_000);
+        assert_eq!(desc.symbol_rate_ksps(), 27_500);
+        assert_eq!(desc.fec_inner(), FecInner::Rate3_4);
+
+        // DVB-S2 8PSK, roll-off 0.20, FEC 9/10, 0.8°W, circular-left
+        let data = hex::decode(b"0123456700085602200009").unwrap();
+        let desc = SatelliteDeliverySystemDescriptor::new(&data).unwrap();
+        assert_eq!(desc.frequency(), 12_345_670);
+        assert_eq!(desc.orbital_position(), 8);
+        assert_eq!(desc.west_east(), OrbitalDirection::West);
+        assert_eq!(desc.polarization(), Polarization::CircularLeft);
+        assert_eq!(desc.modulation_system(), SatelliteModulationSystem::DvbS2);
+        assert_eq!(desc.roll_off(), Some(RollOff::Alpha0_20));
+        assert_eq!(desc.modulation_type(), SatelliteModulationType::Psk8);
+        assert_eq!(desc.symbol_rate_ksps(), 22_000);
+        assert_eq!(desc.fec_inner(), FecInner::Rate9_10);
+
+        assert_eq!(SatelliteDeliverySystemDescriptor::new(&hex::decode(b"01A3456700085602200009").unwrap()).err(), Some(DescriptorError::InvalidBcd { field: "frequency" }));
+        assert!(SatelliteDeliverySystemDescriptor::new(&data[..10]).is_err());
+    }
+
+    #[test]
+    fn cable_delivery_system_descriptor() {
+        // 346MHz, 64-QAM, 6900 ksym/s, RS(204/188) outer FEC, inner FEC not defined
+        let data = hex::decode(b"440B03460000FFF20300690000").unwrap();
+        let payload = test_util::user_private_payload(&data, CableDeliverySystemDescriptor::TAG);
+        let desc = CableDeliverySystemDescriptor::new(payload).unwrap();
+        assert_eq!(desc.frequency(), 3_460_000);
+        assert_eq!(desc.frequency_khz(), 346_000);
+        assert_eq!(desc.fec_outer(), FecOuter::Rs204_188);
+        assert_eq!(desc.modulation(), CableModulation::Qam64);
+        assert_eq!(desc.symbol_rate(), 69_000);
+        assert_eq!(desc.symbol_rate_ksps(), 6_900);
+        assert_eq!(desc.fec_inner(), FecInner::NotDefined);
+
+        // 113.25MHz 256-QAM, 6.875 Msym/s
+        let data = hex::decode(b"01132500FFF20500687500").unwrap();
+        let desc = CableDeliverySystemDescriptor::new(&data).unwrap();
+        assert_eq!(desc.frequency_khz(), 113_250);
+        assert_eq!(desc.modulation(), CableModulation::Qam256);
+        assert_eq!(desc.symbol_rate_ksps(), 6_875);
+
+        assert_eq!(CableDeliverySystemDescriptor::new(&hex::decode(b"01132500FFF205006875F0").unwrap()).err(), Some(DescriptorError::InvalidBcd { field: "symbol_rate" }));
+        assert!(CableDeliverySystemDescriptor::new(&data[..10]).is_err());
+    }
+
+    #[test]
+    fn terrestrial_delivery_system_descriptor() {
+        // 514MHz, 8MHz bandwidth, 64-QAM, non-hierarchical, FEC 2/3, GI 1/32, 8k mode
+        let data = hex::decode(b"5A0B03104D401F8102FFFFFFFF").unwrap();
+        let payload = test_util::user_private_payload(&data, TerrestrialDeliverySystemDescriptor::TAG);
+        assert_eq!(payload.len(), 11);
+        let desc = TerrestrialDeliverySystemDescriptor::new(payload).unwrap();
+        assert_eq!(desc.centre_frequency(), 51_400_000);
+        assert_eq!(desc.centre_frequency_khz(), 514_000);
+        assert_eq!(desc.bandwidth(), Bandwidth::Mhz8);
+        assert_eq!(desc.priority(), Priority::High);
+        assert!(!desc.time_slicing());
+        assert!(!desc.mpe_fec());
+        assert_eq!(desc.constellation(), Constellation::Qam64);
+        assert_eq!(desc.hierarchy(), Hierarchy::NonHierarchical);
+        assert_eq!(desc.interleaver(), Interleaver::Native);
+        assert_eq!(desc.code_rate_hp_stream(), CodeRate::Rate2_3);
+        assert_eq!(desc.code_rate_lp_stream(), CodeRate::Rate1_2);
+        assert_eq!(desc.guard_interval(), GuardInterval::Interval1_32);
+        assert_eq!(desc.transmission_mode(), TransmissionMode::Mode8k);
+        assert!(!desc.other_frequency_flag());
+
+        // 698MHz, 7MHz bandwidth, low priority, time slicing & MPE-FEC, 16-QAM, α=2 with in-depth
+        // interleaver, HP 1/2, LP 7/8, GI 1/4, 4k mode, other frequencies in use
+        let data = hex::decode(b"0429104023709DFFFFFFFF").unwrap();
+        let desc = TerrestrialDeliverySystemDescriptor::new(&data).unwrap();
+        assert_eq!(desc.centre_frequency_khz(), 698_000);
+        assert_eq!(desc.bandwidth(), Bandwidth::Mhz7);
+        assert_eq!(desc.priority(), Priority::Low);
+        assert!(desc.time_slicing());
+        assert!(desc.mpe_fec());
+        assert_eq!(desc.constellation(), Constellation::Qam16);
+        assert_eq!(desc.hierarchy(), Hierarchy::Alpha2);
+        assert_eq!(desc.interleaver(), Interleaver::InDepth);
+        assert_eq!(desc.code_rate_hp_stream(), CodeRate::Rate1_2);
+        assert_eq!(desc.code_rate_lp_stream(), CodeRate::Rate7_8);
+        assert_eq!(desc.guard_interval(), GuardInterval::Interval1_4);
+        assert_eq!(desc.transmission_mode(), TransmissionMode::Mode4k);
+        assert!(desc.other_frequency_flag());
+
+        assert!(TerrestrialDeliverySystemDescriptor::new(&data[..10]).is_err());
+    }
+}
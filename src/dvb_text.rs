@@ -0,0 +1,317 @@
+//! Support for the text encoding used by strings within DVB Service Information descriptors, per
+//! _ETSI EN 300 468, Annex A_.
+//!
+//! Such strings may start with one or more bytes selecting the character table in which the rest
+//! of the text is encoded.  If no selector is present, the default table (based on _ISO/IEC 6937_)
+//! applies.
+
+/// The character table selected by the initial bytes of a DVB string.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Encoding {
+    /// The default character table, 'Latin alphabet' based on _ISO/IEC 6937_ (used when no
+    /// selector byte is present)
+    Iso6937,
+    /// _ISO/IEC 8859-n_, with the part number `n` given by the value
+    Iso8859(u8),
+    /// _ISO/IEC 10646_ Basic Multilingual Plane, encoded as 16-bit big-endian values
+    Iso10646,
+    /// _KS X 1001-2014_ (Korean)
+    KsX1001,
+    /// _GB-2312-1980_ (Simplified Chinese)
+    Gb2312,
+    /// _Big5_ subset of _ISO/IEC 10646_ (Traditional Chinese)
+    Big5,
+    /// UTF-8 encoding of _ISO/IEC 10646_
+    Utf8,
+    /// The encoding is identified by the given `encoding_type_id` value
+    EncodingTypeId(u8),
+    /// The given selector value is reserved for future use (or the selector was truncated)
+    Reserved(u8),
+}
+
+/// Identifies the character table selected by the start of the given DVB string, returning it
+/// together with the remaining bytes of the string following the selector.
+pub fn detect_encoding(buf: &[u8]) -> (Encoding, &[u8]) {
+    if buf.is_empty() {
+        return (Encoding::Iso6937, buf);
+    }
+    match buf[0] {
+        0x01..=0x07 => (Encoding::Iso8859(buf[0] + 4), &buf[1..]),
+        0x09..=0x0b => (Encoding::Iso8859(buf[0] + 4), &buf[1..]),
+        0x10 => {
+            if buf.len() < 3 {
+                (Encoding::Reserved(0x10), &buf[buf.len()..])
+            } else if buf[1] != 0x00 || buf[2] == 0 || buf[2] == 12 || buf[2] > 15 {
+                (Encoding::Reserved(0x10), &buf[3..])
+            } else {
+                (Encoding::Iso8859(buf[2]), &buf[3..])
+            }
+        },
+        0x11 => (Encoding::Iso10646, &buf[1..]),
+        0x12 => (Encoding::KsX1001, &buf[1..]),
+        0x13 => (Encoding::Gb2312, &buf[1..]),
+        0x14 => (Encoding::Big5, &buf[1..]),
+        0x15 => (Encoding::Utf8, &buf[1..]),
+        0x1f => {
+            if buf.len() < 2 {
+                (Encoding::Reserved(0x1f), &buf[buf.len()..])
+            } else {
+                (Encoding::EncodingTypeId(buf[1]), &buf[2..])
+            }
+        },
+        v @ 0x00..=0x1f => (Encoding::Reserved(v), &buf[1..]),
+        _ => (Encoding::Iso6937, buf),
+    }
+}
+
+/// Decodes the given DVB string into a Rust `String`, handling the initial character table
+/// selector (if any).
+///
+/// The default _ISO/IEC 6937_ based table, parts 1, 2, 5, 7, 9 and 15 of _ISO/IEC 8859_,
+/// _ISO/IEC 10646_ (UCS-2) and UTF-8 are supported.  Text in other character tables has any bytes
+/// outside the ASCII range replaced with `U+FFFD`.
+///
+/// The DVB control code for 'CR/LF' is mapped to `'\n'`, and the 'emphasis' and other control
+/// codes are discarded.  Accented characters from the _ISO/IEC 6937_ table, which are encoded as
+/// a non-spacing diacritical mark preceding the base character, are produced in Unicode
+/// decomposed form (i.e. the base character followed by a combining diacritical mark).
+pub fn decode(buf: &[u8]) -> String {
+    let (encoding, text) = detect_encoding(buf);
+    match encoding {
+        Encoding::Iso6937 => decode_iso6937(text),
+        Encoding::Iso8859(part) => {
+            match part {
+                1 | 2 | 5 | 7 | 9 | 15 => decode_single_byte(text, |b| iso8859(part, b)),
+                _ => {
+                    warn!("ISO/IEC 8859-{} character table not supported", part);
+                    decode_single_byte(text, |_| '\u{fffd}')
+                },
+            }
+        },
+        Encoding::Iso10646 => {
+            let mut result = String::with_capacity(text.len() / 2);
+            for pair in text.chunks(2) {
+                if pair.len() < 2 {
+                    result.push('\u{fffd}');
+                    break;
+                }
+                let c = u32::from(pair[0]) << 8 | u32::from(pair[1]);
+                push_unicode(&mut result, std::char::from_u32(c).unwrap_or('\u{fffd}'));
+            }
+            result
+        },
+        Encoding::Utf8 => {
+            let mut result = String::with_capacity(text.len());
+            for c in String::from_utf8_lossy(text).chars() {
+                push_unicode(&mut result, c);
+            }
+            result
+        },
+        _ => {
+            warn!("DVB text character table {:?} not supported", encoding);
+            decode_single_byte(text, |_| '\u{fffd}')
+        },
+    }
+}
+
+/// Handles the DVB control codes, which appear in the private use area when using the multi-byte
+/// character tables
+fn push_unicode(result: &mut String, c: char) {
+    match c {
+        '\u{e08a}' => result.push('\n'),
+        '\u{e080}'..='\u{e09f}' => (),
+        _ => result.push(c),
+    }
+}
+
+/// Decodes text where bytes `0x20` to `0x7f` are ASCII, `0x80` to `0x9f` are DVB control codes,
+/// and `0xa0` to `0xff` are mapped by the given function
+fn decode_single_byte<F>(text: &[u8], upper: F) -> String
+    where F: Fn(u8) -> char
+{
+    let mut result = String::with_capacity(text.len());
+    for &b in text {
+        if let Some(c) = control_or_ascii(b) {
+            result.extend(c);
+        } else {
+            result.push(upper(b));
+        }
+    }
+    result
+}
+
+/// Returns `Some` if the given byte is not from the upper half of the character table, with the
+/// resulting character (if any)
+fn control_or_ascii(b: u8) -> Option<Option<char>> {
+    match b {
+        0x8a => Some(Some('\n')),
+        0x00..=0x1f | 0x80..=0x9f => Some(None),
+        0x20..=0x7f => Some(Some(char::from(b))),
+        _ => None,
+    }
+}
+
+fn decode_iso6937(text: &[u8]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut iter = text.iter();
+    while let Some(&b) = iter.next() {
+        if let Some(c) = control_or_ascii(b) {
+            result.extend(c);
+        } else if let Some(mark) = iso6937_diacritic(b) {
+            // the diacritical mark precedes the character it modifies
+            match iter.next() {
+                Some(&base) => {
+                    match control_or_ascii(base) {
+                        Some(c) => result.extend(c),
+                        None => result.push(ISO_6937_UPPER[(base - 0xa0) as usize]),
+                    }
+                    result.push(mark);
+                },
+                None => result.push('\u{fffd}'),
+            }
+        } else {
+            result.push(ISO_6937_UPPER[(b - 0xa0) as usize]);
+        }
+    }
+    result
+}
+
+/// Maps the _ISO/IEC 6937_ non-spacing diacritical marks to the equivalent Unicode combining
+/// character
+fn iso6937_diacritic(b: u8) -> Option<char> {
+    match b {
+        0xc1 => Some('\u{0300}'),  // grave
+        0xc2 => Some('\u{0301}'),  // acute
+        0xc3 => Some('\u{0302}'),  // circumflex
+        0xc4 => Some('\u{0303}'),  // tilde
+        0xc5 => Some('\u{0304}'),  // macron
+        0xc6 => Some('\u{0306}'),  // breve
+        0xc7 => Some('\u{0307}'),  // dot above
+        0xc8 => Some('\u{0308}'),  // diaeresis
+        0xca => Some('\u{030a}'),  // ring
+        0xcb => Some('\u{0327}'),  // cedilla
+        0xcd => Some('\u{030b}'),  // double acute
+        0xce => Some('\u{0328}'),  // ogonek
+        0xcf => Some('\u{030c}'),  // caron
+        _ => None,
+    }
+}
+
+/// The characters `0xa0` to `0xff` of the DVB default character table (with the Euro sign at
+/// `0xa4`).  Unused positions, and the diacritical marks handled by `iso6937_diacritic()`, map
+/// to `U+FFFD`.
+const ISO_6937_UPPER: [char; 96] = [
+    '\u{a0}', '¡', '¢', '£', '€', '¥', '\u{fffd}', '§', '¤', '‘', '“', '«', '←', '↑', '→', '↓',
+    '°', '±', '²', '³', '×', 'µ', '¶', '·', '÷', '’', '”', '»', '¼', '½', '¾', '¿',
+    '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}',
+    '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}',
+    '―', '¹', '®', '©', '™', '♪', '¬', '¦', '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}', '⅛', '⅜', '⅝', '⅞',
+    'Ω', 'Æ', 'Đ', 'ª', 'Ħ', '\u{fffd}', 'Ĳ', 'Ŀ', 'Ł', 'Ø', 'Œ', 'º', 'Þ', 'Ŧ', 'Ŋ', 'ŉ',
+    'ĸ', 'æ', 'đ', 'ð', 'ħ', 'ı', 'ĳ', 'ŀ', 'ł', 'ø', 'œ', 'ß', 'þ', 'ŧ', 'ŋ', '\u{ad}',
+];
+
+/// The characters `0xa0` to `0xff` of _ISO/IEC 8859-2_
+const ISO_8859_2_UPPER: [char; 96] = [
+    '\u{a0}', 'Ą', '˘', 'Ł', '¤', 'Ľ', 'Ś', '§', '¨', 'Š', 'Ş', 'Ť', 'Ź', '\u{ad}', 'Ž', 'Ż',
+    '°', 'ą', '˛', 'ł', '´', 'ľ', 'ś', 'ˇ', '¸', 'š', 'ş', 'ť', 'ź', '˝', 'ž', 'ż',
+    'Ŕ', 'Á', 'Â', 'Ă', 'Ä', 'Ĺ', 'Ć', 'Ç', 'Č', 'É', 'Ę', 'Ë', 'Ě', 'Í', 'Î', 'Ď',
+    'Đ', 'Ń', 'Ň', 'Ó', 'Ô', 'Ő', 'Ö', '×', 'Ř', 'Ů', 'Ú', 'Ű', 'Ü', 'Ý', 'Ţ', 'ß',
+    'ŕ', 'á', 'â', 'ă', 'ä', 'ĺ', 'ć', 'ç', 'č', 'é', 'ę', 'ë', 'ě', 'í', 'î', 'ď',
+    'đ', 'ń', 'ň', 'ó', 'ô', 'ő', 'ö', '÷', 'ř', 'ů', 'ú', 'ű', 'ü', 'ý', 'ţ', '˙',
+];
+
+/// The characters `0xa0` to `0xbf` of _ISO/IEC 8859-7_ (the remainder are computed)
+const ISO_8859_7_UPPER: [char; 32] = [
+    '\u{a0}', '‘', '’', '£', '€', '₯', '¦', '§', '¨', '©', 'ͺ', '«', '¬', '\u{ad}', '\u{fffd}', '―',
+    '°', '±', '²', '³', '΄', '΅', 'Ά', '·', 'Έ', 'Ή', 'Ί', '»', 'Ό', '½', 'Ύ', 'Ώ',
+];
+
+/// Maps a byte in the range `0xa0` to `0xff` from the given part of _ISO/IEC 8859_
+fn iso8859(part: u8, b: u8) -> char {
+    let latin1 = char::from(b);
+    match part {
+        1 => latin1,
+        2 => ISO_8859_2_UPPER[(b - 0xa0) as usize],
+        5 => match b {
+            0xa0 => '\u{a0}',
+            0xad => '\u{ad}',
+            0xf0 => '№',
+            0xfd => '§',
+            _ => std::char::from_u32(0x0400 + u32::from(b - 0xa0)).unwrap(),
+        },
+        7 => match b {
+            0xa0..=0xbf => ISO_8859_7_UPPER[(b - 0xa0) as usize],
+            0xd2 | 0xff => '\u{fffd}',
+            _ => std::char::from_u32(0x0390 + u32::from(b - 0xc0)).unwrap(),
+        },
+        9 => match b {
+            0xd0 => 'Ğ',
+            0xdd => 'İ',
+            0xde => 'Ş',
+            0xf0 => 'ğ',
+            0xfd => 'ı',
+            0xfe => 'ş',
+            _ => latin1,
+        },
+        15 => match b {
+            0xa4 => '€',
+            0xa6 => 'Š',
+            0xa8 => 'š',
+            0xb4 => 'Ž',
+            0xb8 => 'ž',
+            0xbc => 'Œ',
+            0xbd => 'œ',
+            0xbe => 'Ÿ',
+            _ => latin1,
+        },
+        _ => '\u{fffd}',
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encoding_selector() {
+        assert_eq!(detect_encoding(b""), (Encoding::Iso6937, &b""[..]));
+        assert_eq!(detect_encoding(b"News"), (Encoding::Iso6937, &b"News"[..]));
+        assert_eq!(detect_encoding(b"\x01News"), (Encoding::Iso8859(5), &b"News"[..]));
+        assert_eq!(detect_encoding(b"\x0bNews"), (Encoding::Iso8859(15), &b"News"[..]));
+        assert_eq!(detect_encoding(b"\x08News"), (Encoding::Reserved(8), &b"News"[..]));
+        assert_eq!(detect_encoding(b"\x10\x00\x02News"), (Encoding::Iso8859(2), &b"News"[..]));
+        assert_eq!(detect_encoding(b"\x10\x00\x0cNews"), (Encoding::Reserved(0x10), &b"News"[..]));
+        assert_eq!(detect_encoding(b"\x10\x00"), (Encoding::Reserved(0x10), &b""[..]));
+        assert_eq!(detect_encoding(b"\x15News"), (Encoding::Utf8, &b"News"[..]));
+        assert_eq!(detect_encoding(b"\x1f\x01News"), (Encoding::EncodingTypeId(1), &b"News"[..]));
+    }
+
+    #[test]
+    fn decode_default_table() {
+        assert_eq!(decode(b"News at Ten"), "News at Ten");
+        assert_eq!(decode(b"Line one\x8aLine \x86two\x87"), "Line one\nLine two");
+        assert_eq!(decode(b"Caf\xc2e \xa4 5"), "Cafe\u{0301} € 5");
+        assert_eq!(decode(b"\xe9l \xfb"), "Øl ß");
+        // trailing diacritic without base character
+        assert_eq!(decode(b"a\xc8"), "a\u{fffd}");
+    }
+
+    #[test]
+    fn decode_iso8859() {
+        assert_eq!(decode(b"\x10\x00\x01Caf\xe9"), "Café");
+        assert_eq!(decode(b"\x10\x00\x02\xa3\xf3d\xbc"), "Łódź");
+        assert_eq!(decode(b"\x01\xbd\xde\xd2\xde\xe1\xe2\xd8"), "Новости");
+        assert_eq!(decode(b"\x03\xc5\xe9\xe4\xde\xf3\xe5\xe9\xf2"), "Ειδήσεις");
+        assert_eq!(decode(b"\x05Haberler \xdd\xfeler"), "Haberler İşler");
+        assert_eq!(decode(b"\x0b\xa4 \xbd"), "€ œ");
+        assert_eq!(decode(b"\x10\x00\x04A\xe0"), "A\u{fffd}");
+    }
+
+    #[test]
+    fn decode_multibyte() {
+        assert_eq!(decode(b"\x15Caf\xc3\xa9\xee\x82\x8ax"), "Café\nx");
+        assert_eq!(decode(b"\x11\x00C\x00a\x00f\x00\xe9\xe0\x8a\x00x"), "Café\nx");
+        assert_eq!(decode(b"\x11\x00C\x00"), "C\u{fffd}");
+        assert_eq!(decode(b"\x13ab\xc4\xe3"), "ab\u{fffd}\u{fffd}");
+    }
+}
@@ -30,26 +30,58 @@
 //! - General
 //!   - lots of places return `Option` but should return `Result` and a descriptive error
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate core as std;
+
+#[cfg(feature = "std")]
 extern crate hexdump;
 extern crate hex_slice;
+#[cfg(feature = "std")]
 extern crate byteorder;
+#[cfg(feature = "std")]
 extern crate data_encoding;
+#[cfg(feature = "std")]
 extern crate bitreader;
 #[cfg(test)]
 #[macro_use]
 extern crate matches;
 #[cfg(test)]
 extern crate bitstream_io;
+#[cfg(feature = "std")]
 extern crate fixedbitset;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[macro_use]
+extern crate log;
 
 pub mod packet;
+#[cfg(feature = "std")]
 #[macro_use]
 pub mod demultiplex;
 pub mod psi;
 pub mod pes;
+#[cfg(feature = "std")]
+pub mod pcr;
+#[cfg(feature = "std")]
+pub mod psip;
+#[cfg(feature = "std")]
+pub mod conformance;
+#[cfg(feature = "std")]
+pub mod scte35;
 pub mod descriptor;
+#[cfg(feature = "std")]
+pub mod dvb_text;
+pub mod mjd_bcd;
 mod mpegts_crc;
+#[cfg(all(test, feature = "std"))]
+mod test_util;
 
+/// The `stream_type` of an elementary stream, as given in the PMT.
+///
+/// Converts from and to the underlying `u8` value; values without a named variant are kept as
+/// `Private` or `Reserved`, so `u8::from(StreamType::from(v)) == v` for every `v`.
 #[derive(Debug,PartialEq,Eq,Hash,Clone,Copy)]
 pub enum StreamType {
 	// 0x00 reserved
@@ -141,6 +173,70 @@ impl From<u8> for StreamType {
     }
 }
 
+/// A coarse classification of the content carried by an elementary stream, as returned by
+/// [`StreamType::pes_kind()`](enum.StreamType.html#method.pes_kind), allowing a
+/// `StreamConstructor` to choose a suitable filter without matching every `StreamType` value.
+#[derive(Debug,PartialEq,Eq,Hash,Clone,Copy)]
+pub enum PesKind {
+    Video,
+    Audio,
+    Subtitle,
+    /// Some other kind of data carried in PES packets
+    Data,
+    /// The stream carries PSI-style sections, rather than PES packets
+    Sections,
+    /// The content can't be determined from the `stream_type` alone, as with
+    /// `H2220PesPrivateData` and the privately defined values
+    Unknown,
+}
+
+impl StreamType {
+    /// Classifies the stream by the standard meaning of its `stream_type` value.
+    ///
+    /// No `stream_type` value identifies subtitles by itself, so `PesKind::Subtitle` is never
+    /// returned here; DVB subtitles and teletext, for instance, are `H2220PesPrivateData` and
+    /// can be recognised only by the descriptors in the PMT, which
+    /// [`StreamInfo::pes_kind()`](demultiplex/struct.StreamInfo.html#method.pes_kind) examines.
+    pub fn pes_kind(self) -> PesKind {
+        match self {
+            StreamType::Iso11172Video
+            | StreamType::H262
+            | StreamType::Iso144962Visual
+            | StreamType::H264
+            | StreamType::H265
+            | StreamType::ChineseVideoStandard => PesKind::Video,
+            StreamType::Iso11172Audio
+            | StreamType::Iso138183Audio
+            | StreamType::Adts
+            | StreamType::Latm
+            | StreamType::AtscDolbyDigitalAudio => PesKind::Audio,
+            StreamType::H2220PrivateSections
+            | StreamType::Iso138186MultiprotocolEncapsulation
+            | StreamType::DsmccUnMessages
+            | StreamType::DsmccStreamDescriptors
+            | StreamType::DsmccSections
+            | StreamType::FlexMuxIso14496Sections
+            | StreamType::MetadataInMetadataSections
+            | StreamType::AtscDsmccNetworkResourcesTable => PesKind::Sections,
+            StreamType::Mheg
+            | StreamType::H2220DsmCc
+            | StreamType::H2221
+            | StreamType::H2220Auxiliary
+            | StreamType::FlexMuxPes
+            | StreamType::SynchronizedDownloadProtocol
+            | StreamType::MetadataInPes
+            | StreamType::DsmccDataCarouselMetadata
+            | StreamType::DsmccObjectCarouselMetadata
+            | StreamType::SynchronizedDownloadProtocolMetadata
+            | StreamType::Ipmp
+            | StreamType::AtscDsmccSynchronousData => PesKind::Data,
+            StreamType::H2220PesPrivateData
+            | StreamType::Private(_)
+            | StreamType::Reserved(_) => PesKind::Unknown,
+        }
+    }
+}
+
 impl From<StreamType> for u8 {
     fn from(val: StreamType) -> Self {
         match val {
@@ -181,3 +277,17 @@ impl From<StreamType> for u8 {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::StreamType;
+
+    #[test]
+    fn stream_type_roundtrip() {
+        for val in 0..=255u8 {
+            assert_eq!(u8::from(StreamType::from(val)), val);
+        }
+        assert_eq!(StreamType::from(0x1c), StreamType::Reserved(0x1c));
+        assert_eq!(StreamType::from(0x82), StreamType::Private(0x82));
+    }
+}
@@ -0,0 +1,132 @@
+//! Decoding of the date and time encoding used by DVB tables such as the _Time and Date Table_
+//! and _Event Information Table_, per _ETSI EN 300 468, Annex C_.
+//!
+//! The value is 40 bits long, consisting of the 16-bit _Modified Julian Date_ followed by six
+//! Binary Coded Decimal digits giving the UTC time of day as hours, minutes and seconds.
+
+/// A UTC date and time decoded by [`decode()`](fn.decode.html).
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub struct DateTime {
+    pub year: u16,
+    /// month of the year, from `1` for January to `12` for December
+    pub month: u8,
+    /// day of the month, from `1`
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+#[derive(Debug,PartialEq,Eq)]
+pub enum DateTimeError {
+    /// All 40 bits are set, which the standard uses to indicate that the time is undefined (for
+    /// example, the start time of an event which has not yet been scheduled)
+    Undefined,
+    /// The named field does not hold valid Binary Coded Decimal digits, or holds a value out of
+    /// range for that field
+    InvalidBcd { field: &'static str },
+    /// The Modified Julian Date is before 1900-03-01, the earliest date for which the conversion
+    /// given in the standard is valid
+    DateOutOfRange { mjd: u16 },
+}
+
+/// Decodes the given 40-bit _MJD_ and _BCD_ encoded value.
+pub fn decode(buf: &[u8; 5]) -> Result<DateTime, DateTimeError> {
+    if buf.iter().all(|&b| b == 0xff) {
+        return Err(DateTimeError::Undefined);
+    }
+    let mjd = u16::from(buf[0]) << 8 | u16::from(buf[1]);
+    let (year, month, day) = mjd_to_date(mjd)?;
+    let hour = time_field(buf[2], 23, "hour")?;
+    let minute = time_field(buf[3], 59, "minute")?;
+    let second = time_field(buf[4], 59, "second")?;
+    Ok(DateTime { year, month, day, hour, minute, second })
+}
+
+fn time_field(b: u8, max: u8, field: &'static str) -> Result<u8, DateTimeError> {
+    match bcd(&[b], 2) {
+        Some(v) if v <= u32::from(max) => Ok(v as u8),
+        _ => Err(DateTimeError::InvalidBcd { field }),
+    }
+}
+
+/// Converts a Modified Julian Date to a `(year, month, day)` in the Gregorian calendar, using the
+/// formula given in the standard, scaled so as to use integer arithmetic.
+fn mjd_to_date(mjd: u16) -> Result<(u16, u8, u8), DateTimeError> {
+    // 1900-03-01
+    if mjd < 15079 {
+        return Err(DateTimeError::DateOutOfRange { mjd });
+    }
+    let mjd = i64::from(mjd);
+    // Y' = int((MJD - 15078.2) / 365.25)
+    let y_ = (mjd * 100 - 1_507_820) / 36525;
+    let y_days = y_ * 36525 / 100;
+    // M' = int((MJD - 14956.1 - int(Y' × 365.25)) / 30.6001)
+    let m_ = ((mjd - 14956 - y_days) * 10000 - 1000) / 306_001;
+    // D = MJD - 14956 - int(Y' × 365.25) - int(M' × 30.6001)
+    let day = mjd - 14956 - y_days - m_ * 306_001 / 10000;
+    let k = if m_ == 14 || m_ == 15 { 1 } else { 0 };
+    let year = 1900 + y_ + k;
+    let month = m_ - 1 - k * 12;
+    Ok((year as u16, month as u8, day as u8))
+}
+
+/// Decodes the given number of Binary Coded Decimal digits from the start of the buffer, or
+/// returns `None` if a nibble is not a valid decimal digit
+pub(crate) fn bcd(buf: &[u8], digits: usize) -> Option<u32> {
+    let mut result = 0;
+    for i in 0..digits {
+        let nibble = if i % 2 == 0 {
+            buf[i / 2] >> 4
+        } else {
+            buf[i / 2] & 0b1111
+        };
+        if nibble > 9 {
+            return None;
+        }
+        result = result * 10 + u32::from(nibble);
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn example_from_standard() {
+        // the example given in EN 300 468 Annex C, '93/10/13 12:45:00'
+        assert_eq!(decode(&[0xc0, 0x79, 0x12, 0x45, 0x00]), Ok(DateTime {
+            year: 1993,
+            month: 10,
+            day: 13,
+            hour: 12,
+            minute: 45,
+            second: 0,
+        }));
+    }
+
+    #[test]
+    fn dates() {
+        let date = |mjd: u16| {
+            let dt = decode(&[(mjd >> 8) as u8, mjd as u8, 0, 0, 0]).unwrap();
+            (dt.year, dt.month, dt.day)
+        };
+        assert_eq!(date(15079), (1900, 3, 1));
+        assert_eq!(date(51544), (2000, 1, 1));
+        assert_eq!(date(51603), (2000, 2, 29));
+        assert_eq!(date(51604), (2000, 3, 1));
+        assert_eq!(date(60309), (2023, 12, 31));
+        assert_eq!(date(60310), (2024, 1, 1));
+        assert_eq!(date(0xffff), (2038, 4, 22));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(decode(&[0xff; 5]), Err(DateTimeError::Undefined));
+        assert_eq!(decode(&[0xc0, 0x79, 0x24, 0x00, 0x00]), Err(DateTimeError::InvalidBcd { field: "hour" }));
+        assert_eq!(decode(&[0xc0, 0x79, 0x12, 0x4a, 0x00]), Err(DateTimeError::InvalidBcd { field: "minute" }));
+        assert_eq!(decode(&[0xc0, 0x79, 0x12, 0x45, 0x60]), Err(DateTimeError::InvalidBcd { field: "second" }));
+        assert_eq!(decode(&[0, 1, 0, 0, 0]), Err(DateTimeError::DateOutOfRange { mjd: 1 }));
+    }
+}
@@ -50,7 +50,7 @@ impl TransportScramblingControl {
     fn from(val: u8) -> TransportScramblingControl {
         match val {
             0 => TransportScramblingControl::NotScrambled,
-            1...3 => TransportScramblingControl::Undefined(val),
+            1..=3 => TransportScramblingControl::Undefined(val),
             _ => panic!("invalid value {}", val),
         }
     }
@@ -109,6 +109,16 @@ pub enum AdaptationFieldError {
     SpliceTimestampError(pes::TimestampError)
 }
 
+/// Reasons that [`Packet::try_new()`](struct.Packet.html#method.try_new) or
+/// [`Packet::try_from_m2ts()`](struct.Packet.html#method.try_from_m2ts) may reject a buffer.
+#[derive(Debug,PartialEq)]
+pub enum PacketError {
+    /// The buffer was `actual` bytes long, rather than the `expected` packet size
+    BadLength { expected: usize, actual: usize },
+    /// The byte where the sync byte should appear held the value `actual`, rather than `0x47`
+    NoSyncByte { actual: u8 },
+}
+
 /// A collection of fields that may optionally appear within the header of a transport stream
 /// `Packet`.
 pub struct AdaptationField<'buf> {
@@ -116,7 +126,7 @@ pub struct AdaptationField<'buf> {
 }
 
 impl<'buf> AdaptationField<'buf> {
-    pub fn new(buf: &'buf [u8]) -> AdaptationField {
+    pub fn new(buf: &'buf [u8]) -> AdaptationField<'buf> {
         AdaptationField { buf }
     }
 
@@ -135,21 +145,23 @@ impl<'buf> AdaptationField<'buf> {
     fn opcr_flag(&self) -> bool {
         self.buf[0] & 0b1000 != 0
     }
-    fn splicing_point_flag(&self) -> bool {
+    /// Indicates if a `splice_countdown()` value is present in this adaptation field.
+    pub fn splicing_point_flag(&self) -> bool {
         self.buf[0] & 0b100 != 0
     }
-    fn transport_private_data_flag(&self) -> bool {
+    /// Indicates if `transport_private_data()` is present in this adaptation field.
+    pub fn transport_private_data_flag(&self) -> bool {
         self.buf[0] & 0b10 != 0
     }
     fn adaptation_field_extension_flag(&self) -> bool {
         self.buf[0] & 0b1 != 0
     }
     fn slice(&self, from: usize, to: usize) -> Result<&'buf[u8],AdaptationFieldError> {
-        //if to > self.buf.len() {
-        //    Err(AdaptationFieldError::NotEnoughData)
-        //} else {
+        if to > self.buf.len() {
+            Err(AdaptationFieldError::NotEnoughData)
+        } else {
             Ok(&self.buf[from..to])
-        //}
+        }
     }
     const PCR_SIZE: usize = 6;
     pub fn pcr(&self) -> Result<PCR, AdaptationFieldError> {
@@ -182,10 +194,15 @@ impl<'buf> AdaptationField<'buf> {
             0
         }
     }
-    pub fn splice_countdown(&self) -> Result<u8, AdaptationFieldError> {
+    /// The number of packets with the same PID remaining until a splicing point is reached.
+    ///
+    /// When the value is `0`, this is the last packet before the splicing point; the _next_
+    /// packet with the same PID is the first following the splice.  Negative values count the
+    /// packets since the splicing point was passed.
+    pub fn splice_countdown(&self) -> Result<i8, AdaptationFieldError> {
         if self.splicing_point_flag() {
             let off = self.splice_countdown_offset();
-            Ok(self.slice(off, off + 1)?[0])
+            Ok(self.slice(off, off + 1)?[0] as i8)
         } else {
             Err(AdaptationFieldError::FieldNotPresent)
         }
@@ -197,7 +214,10 @@ impl<'buf> AdaptationField<'buf> {
             0
         }
     }
-    pub fn transport_private_data(&self) -> Result<&[u8], AdaptationFieldError> {
+    /// The private data bytes following the `transport_private_data_length` field, if
+    /// `transport_private_data_flag()` is set.  Any PCR, OPCR and splice_countdown fields present
+    /// are skipped to locate the data.
+    pub fn transport_private_data(&self) -> Result<&'buf[u8], AdaptationFieldError> {
         if self.transport_private_data_flag() {
             let off = self.transport_private_data_offset();
             let len = self.slice(off, off + 1)?[0] as usize;
@@ -363,8 +383,10 @@ impl ContinuityCounter {
 
 /// A transport stream `Packet` is a wrapper around a byte slice which allows the bytes to be
 /// interpreted as a packet structure per _ISO/IEC 13818-1, Section 2.4.3.3_.
+#[derive(Clone)]
 pub struct Packet<'buf> {
     buf: &'buf [u8],
+    tp_extra_header: Option<u32>,
 }
 
 /// The value `0x47`, which must appear in the first byte of every transport stream packet.
@@ -373,6 +395,61 @@ pub const SYNC_BYTE: u8 = 0x47;
 /// The fixed 188 byte size of a transport stream packet.
 pub const PACKET_SIZE: usize = 188;
 
+/// The 192 byte size of a packet in the 'm2ts' format (as used by Blu-ray and AVCHD), where each
+/// transport stream packet is preceded by a 4 byte `TP_extra_header`.
+pub const M2TS_PACKET_SIZE: usize = 192;
+
+/// The 204 byte size of a packet as carried by some DVB interfaces (e.g. DVB-ASI), where each
+/// transport stream packet is followed by 16 bytes of Reed-Solomon parity data.
+pub const FEC_PACKET_SIZE: usize = 204;
+
+/// The framing of each transport stream packet within a buffer of packets, as configured by
+/// [`DemultiplexBuilder::packet_format()`](../demultiplex/struct.DemultiplexBuilder.html#method.packet_format).
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Default)]
+pub enum PacketFormat {
+    /// Plain 188 byte transport stream packets
+    #[default]
+    Ts,
+    /// 192 byte 'm2ts' packets, each having a 4 byte `TP_extra_header` (whose
+    /// `arrival_time_stamp()` is available from the resulting `Packet`) before the transport
+    /// stream packet
+    M2ts,
+    /// 204 byte packets, each having 16 bytes of Reed-Solomon parity data after the transport
+    /// stream packet, which are ignored
+    Fec,
+}
+impl PacketFormat {
+    /// The number of bytes each packet occupies in this format.
+    pub fn packet_size(self) -> usize {
+        match self {
+            PacketFormat::Ts => PACKET_SIZE,
+            PacketFormat::M2ts => M2TS_PACKET_SIZE,
+            PacketFormat::Fec => FEC_PACKET_SIZE,
+        }
+    }
+
+    /// The offset of the transport stream packet (and so its sync byte) within each packet of
+    /// this format.
+    pub fn sync_offset(self) -> usize {
+        match self {
+            PacketFormat::M2ts => M2TS_PACKET_SIZE - PACKET_SIZE,
+            PacketFormat::Ts | PacketFormat::Fec => 0,
+        }
+    }
+}
+
+/// The PID value `0x1fff`, used by 'null packets' which exist only to pad the transport stream
+/// to a constant bitrate.
+pub const NULL_PID: u16 = 0x1fff;
+
+/// Extracts a 13-bit PID value from the given pair of bytes, discarding the top 3 bits of `hi`
+/// (which belong to other fields).  This is the layout used for PID values in the packet header,
+/// and in PSI tables such as the PAT and PMT.
+#[inline(always)]
+pub fn pid_from_bytes(hi: u8, lo: u8) -> u16 {
+    u16::from(hi & 0b00011111) << 8 | u16::from(lo)
+}
+
 const FIXED_HEADER_SIZE: usize = 4;
 // when AF present, a 1-byte 'length' field precedes the content,
 const ADAPTATION_FIELD_OFFSET: usize = FIXED_HEADER_SIZE + 1;
@@ -389,10 +466,62 @@ impl<'buf> Packet<'buf> {
     /// have the correct value (`0x47`).  Calling code is expected to have already checked those
     /// conditions.
     #[inline(always)]
-    pub fn new(buf: &'buf [u8]) -> Packet {
+    pub fn new(buf: &'buf [u8]) -> Packet<'buf> {
         assert_eq!(buf.len(),  PACKET_SIZE);
         assert!(Packet::is_sync_byte(buf[0]));
-        Packet { buf }
+        Packet { buf, tp_extra_header: None }
+    }
+
+    /// As for `new()`, but returns an error rather than panicking if the buffer is not 188 bytes
+    /// long, or does not start with the sync byte.  Use this for buffers from a source which
+    /// may not already have checked these conditions.
+    pub fn try_new(buf: &'buf [u8]) -> Result<Packet<'buf>, PacketError> {
+        if buf.len() != PACKET_SIZE {
+            return Err(PacketError::BadLength { expected: PACKET_SIZE, actual: buf.len() });
+        }
+        if !Packet::is_sync_byte(buf[0]) {
+            return Err(PacketError::NoSyncByte { actual: buf[0] });
+        }
+        Ok(Packet::new(buf))
+    }
+
+    /// As for `from_m2ts()`, but returns an error rather than panicking if the buffer is not 192
+    /// bytes long, or if the transport stream packet following the `TP_extra_header` does not
+    /// start with the sync byte.
+    pub fn try_from_m2ts(buf: &'buf [u8]) -> Result<Packet<'buf>, PacketError> {
+        if buf.len() != M2TS_PACKET_SIZE {
+            return Err(PacketError::BadLength { expected: M2TS_PACKET_SIZE, actual: buf.len() });
+        }
+        let sync = buf[M2TS_PACKET_SIZE - PACKET_SIZE];
+        if !Packet::is_sync_byte(sync) {
+            return Err(PacketError::NoSyncByte { actual: sync });
+        }
+        Ok(Packet::from_m2ts(buf))
+    }
+
+    /// Creates a packet from a 192 byte 'm2ts' packet, consisting of the 4 byte
+    /// `TP_extra_header` followed by the 188 byte transport stream packet.  Panics under the same
+    /// conditions as `new()`, with respect to the transport stream packet.
+    #[inline(always)]
+    pub fn from_m2ts(buf: &'buf [u8]) -> Packet<'buf> {
+        assert_eq!(buf.len(), M2TS_PACKET_SIZE);
+        let mut pk = Packet::new(&buf[M2TS_PACKET_SIZE - PACKET_SIZE..]);
+        pk.tp_extra_header = Some(u32::from(buf[0]) << 24 | u32::from(buf[1]) << 16 | u32::from(buf[2]) << 8 | u32::from(buf[3]));
+        pk
+    }
+
+    /// The 30-bit `arrival_time_stamp` from the `TP_extra_header` of an 'm2ts' packet, giving the
+    /// time the packet arrived at the recorder in units of the 27MHz system clock (modulo
+    /// 2<sup>30</sup>).  This is always `None` for a packet created by `new()`, i.e. other than
+    /// by `from_m2ts()`.
+    pub fn arrival_time_stamp(&self) -> Option<u32> {
+        self.tp_extra_header.map(|h| h & 0x3fff_ffff)
+    }
+
+    /// The 2-bit `copy_permission_indicator` from the `TP_extra_header` of an 'm2ts' packet, or
+    /// `None` for a packet created by `new()`.
+    pub fn copy_permission_indicator(&self) -> Option<u8> {
+        self.tp_extra_header.map(|h| (h >> 30) as u8)
     }
 
     pub fn transport_error_indicator(&self) -> bool {
@@ -416,7 +545,7 @@ impl<'buf> Packet<'buf> {
     /// value.
     #[inline]
     pub fn pid(&self) -> u16 {
-        u16::from(self.buf[1] & 0b00011111) << 8 | u16::from(self.buf[2])
+        pid_from_bytes(self.buf[1], self.buf[2])
     }
 
     pub fn transport_scrambling_control(&self) -> TransportScramblingControl {
@@ -430,6 +559,22 @@ impl<'buf> Packet<'buf> {
         AdaptationControl::from(self.buf[3] >> 4 & 0b11)
     }
 
+    /// Equivalent to `adaptation_control()`, but named after the `adaptation_field_control`
+    /// field of _ISO/IEC 13818-1_.
+    #[inline]
+    pub fn adaptation_field_control(&self) -> AdaptationControl {
+        self.adaptation_control()
+    }
+
+    /// `true` if `adaptation_control()` indicates that this packet carries a payload, `false` for
+    /// packets carrying only an adaptation field (for example, to deliver a PCR).  This is
+    /// cheaper than checking whether `payload()` returns `Some`, but does not check that the
+    /// payload is non-empty.
+    #[inline]
+    pub fn has_payload(&self) -> bool {
+        self.adaptation_control().has_payload()
+    }
+
     /// Each packet with a given `pid()` value within a transport stream should have a continuity
     /// counter value which increases by 1 from the last counter value seen.  Unexpected continuity
     /// counter values allow the receiver of the transport stream to detect discontinuities in the
@@ -444,13 +589,13 @@ impl<'buf> Packet<'buf> {
     }
 
     /// An `AdaptationField` contains additional packet headers that may be present in the packet.
-    pub fn adaptation_field(&self) -> Option<AdaptationField> {
+    pub fn adaptation_field(&self) -> Option<AdaptationField<'_>> {
         match self.adaptation_control() {
             AdaptationControl::Reserved | AdaptationControl::PayloadOnly => None,
             AdaptationControl::AdaptationFieldOnly => {
                 let len = self.adaptation_field_length();
                 if len != (PACKET_SIZE - ADAPTATION_FIELD_OFFSET) {
-                    println!(
+                    warn!(
                         "invalid adaptation_field_length for AdaptationFieldOnly: {}",
                         len
                     );
@@ -462,7 +607,7 @@ impl<'buf> Packet<'buf> {
             AdaptationControl::AdaptationFieldAndPayload => {
                 let len = self.adaptation_field_length();
                 if len > 182 {
-                    println!(
+                    warn!(
                         "invalid adaptation_field_length for AdaptationFieldAndPayload: {}",
                         len
                     );
@@ -474,7 +619,7 @@ impl<'buf> Packet<'buf> {
         }
     }
 
-    fn mk_af(&self, len: usize) -> AdaptationField {
+    fn mk_af(&self, len: usize) -> AdaptationField<'_> {
         AdaptationField::new(
             &self.buf[ADAPTATION_FIELD_OFFSET..ADAPTATION_FIELD_OFFSET + len],
         )
@@ -496,17 +641,20 @@ impl<'buf> Packet<'buf> {
     fn mk_payload(&self) -> Option<&'buf [u8]> {
         let offset = self.content_offset();
         if offset == self.buf.len() {
-            println!("no payload data present");
+            warn!("no payload data present");
             None
         } else if offset > self.buf.len() {
-            println!("adaptation_field_length {} too large", self.adaptation_field_length());
+            warn!("adaptation_field_length {} too large", self.adaptation_field_length());
             None
         } else {
             Some(&self.buf[offset..])
         }
     }
 
-    // borrow a reference to the underlying buffer of this packet
+    /// The complete 188 bytes of this packet, borrowed from the buffer it was created from,
+    /// allowing the packet to be passed through (e.g. recorded to a file) without copying.
+    ///
+    /// For a packet created by `from_m2ts()`, the `TP_extra_header` is not included.
     pub fn buffer(&self) -> &'buf[u8] {
         self.buf
     }
@@ -523,15 +671,155 @@ impl<'buf> Packet<'buf> {
     }
 }
 
+/// Iterates over the `Packet`s within a byte slice, for simple tools that don't need the full
+/// [`Demultiplex`](../demultiplex/struct.Demultiplex.html) machinery.
+///
+/// By default iteration stops at the first position not holding a sync-byte, or when fewer than
+/// 188 bytes remain.  If `resync(true)` has been called, the iterator will instead skip forward
+/// to the next position that appears to hold the start of a packet.
+///
+/// ```
+/// # use mpeg2ts_reader::packet;
+/// let mut buf = vec![0xff; packet::PACKET_SIZE * 2];
+/// buf[0] = packet::SYNC_BYTE;
+/// buf[packet::PACKET_SIZE] = packet::SYNC_BYTE;
+/// for pk in packet::PacketIter::new(&buf[..]) {
+///     println!("PID {}", pk.pid());
+/// }
+/// ```
+pub struct PacketIter<'buf> {
+    buf: &'buf [u8],
+    resync: bool,
+}
+impl<'buf> PacketIter<'buf> {
+    pub fn new(buf: &'buf [u8]) -> PacketIter<'buf> {
+        PacketIter {
+            buf,
+            resync: false,
+        }
+    }
+
+    /// If `true`, skip over data that does not start with a sync-byte, rather than ending
+    /// iteration.  Defaults to `false`.
+    pub fn resync(mut self, resync: bool) -> PacketIter<'buf> {
+        self.resync = resync;
+        self
+    }
+
+    /// The data not yet consumed by the iterator
+    pub fn remaining(&self) -> &'buf [u8] {
+        self.buf
+    }
+
+    /// Finds the next offset holding a sync-byte which is followed (if the data is long enough
+    /// to tell) by another sync-byte one packet later.
+    fn find_sync(&self) -> Option<usize> {
+        (1..self.buf.len()).find(|&i| {
+            Packet::is_sync_byte(self.buf[i])
+                && (i + PACKET_SIZE >= self.buf.len() || Packet::is_sync_byte(self.buf[i + PACKET_SIZE]))
+        })
+    }
+}
+impl<'buf> Iterator for PacketIter<'buf> {
+    type Item = Packet<'buf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.len() < PACKET_SIZE {
+            return None;
+        }
+        if !Packet::is_sync_byte(self.buf[0]) {
+            if !self.resync {
+                return None;
+            }
+            match self.find_sync() {
+                Some(offset) => {
+                    warn!("skipping {} bytes to resynchronise with packet boundary", offset);
+                    self.buf = &self.buf[offset..];
+                    if self.buf.len() < PACKET_SIZE {
+                        return None;
+                    }
+                },
+                None => {
+                    self.buf = &self.buf[self.buf.len()..];
+                    return None;
+                },
+            }
+        }
+        let (pk, rest) = self.buf.split_at(PACKET_SIZE);
+        self.buf = rest;
+        Some(Packet::new(pk))
+    }
+}
+
 /// trait for objects which process transport stream packets
 pub trait PacketConsumer<Ret> {
     fn consume(&mut self, pk: Packet) -> Option<Ret>;
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod test {
     use packet::*;
     use pes;
+    use test_util;
+
+    #[test]
+    fn packet_iter() {
+        let mut buf = test_util::packet(100, 0);
+        buf.extend(test_util::packet(200, 0));
+        buf.extend_from_slice(&[0x00, 0x47, 0x00]); // garbage, including a false sync-byte
+        buf.extend(test_util::packet(300, 0));
+        buf.extend_from_slice(&[0x47; 10]);  // trailing partial packet
+
+        let pids: Vec<u16> = PacketIter::new(&buf[..]).map(|pk| pk.pid()).collect();
+        assert_eq!(pids, vec![100, 200]);
+
+        let pids: Vec<u16> = PacketIter::new(&buf[..]).resync(true).map(|pk| pk.pid()).collect();
+        assert_eq!(pids, vec![100, 200, 300]);
+
+        let mut iter = PacketIter::new(&buf[..]).resync(true);
+        assert_eq!(iter.by_ref().count(), 3);
+        assert_eq!(iter.remaining().len(), 10);
+    }
+
+    #[test]
+    fn buffer() {
+        let buf = test_util::packet(100, 0);
+        let pk = Packet::new(&buf[..]);
+        assert_eq!(pk.buffer().as_ptr(), buf.as_ptr());
+        assert_eq!(pk.buffer().len(), PACKET_SIZE);
+
+        let mut m2ts = vec![0x12, 0x34, 0x56, 0x78];
+        m2ts.extend(test_util::packet(200, 0));
+        let pk = Packet::from_m2ts(&m2ts[..]);
+        assert_eq!(pk.buffer(), &m2ts[4..]);
+    }
+
+    #[test]
+    fn pid_masked() {
+        assert_eq!(pid_from_bytes(0xff, 0xff), 0x1fff);
+        assert_eq!(pid_from_bytes(0b1110_0000, 0x01), 0x0001);
+        let mut buf = test_util::packet(0x1234, 0);
+        buf[1] |= 0b1110_0000;  // transport_error_indicator, payload_unit_start_indicator, transport_priority
+        assert_eq!(Packet::new(&buf[..]).pid(), 0x1234);
+    }
+
+    #[test]
+    fn try_new() {
+        let mut buf = test_util::packet(100, 0);
+        assert_eq!(Packet::try_new(&buf[..]).map(|pk| pk.pid()), Ok(100));
+        assert_eq!(Packet::try_new(&buf[..187]).err(), Some(PacketError::BadLength { expected: PACKET_SIZE, actual: 187 }));
+        assert_eq!(Packet::try_new(&[][..]).err(), Some(PacketError::BadLength { expected: PACKET_SIZE, actual: 0 }));
+
+        let mut m2ts = vec![0x12, 0x34, 0x56, 0x78];
+        m2ts.extend_from_slice(&buf[..]);
+        assert_eq!(Packet::try_from_m2ts(&m2ts[..]).map(|pk| pk.pid()), Ok(100));
+        assert_eq!(Packet::try_from_m2ts(&buf[..]).err(), Some(PacketError::BadLength { expected: M2TS_PACKET_SIZE, actual: PACKET_SIZE }));
+        m2ts[4] = 0x48;
+        assert_eq!(Packet::try_from_m2ts(&m2ts[..]).err(), Some(PacketError::NoSyncByte { actual: 0x48 }));
+
+        buf[0] = 0;
+        assert_eq!(Packet::try_new(&buf[..]).err(), Some(PacketError::NoSyncByte { actual: 0 }));
+    }
 
     #[test]
     #[should_panic]
@@ -540,6 +828,75 @@ mod test {
         Packet::new(&buf[..]);
     }
 
+    #[test]
+    fn payload_offset() {
+        let mut buf = [0u8; self::PACKET_SIZE];
+        buf[0] = self::SYNC_BYTE;
+
+        buf[3] = 0b00010000; // PayloadOnly
+        assert_eq!(Packet::new(&buf[..]).adaptation_field_control(), AdaptationControl::PayloadOnly);
+        assert_eq!(Packet::new(&buf[..]).payload().map(|p| p.len()), Some(184));
+
+        buf[3] = 0b00110000; // AdaptationFieldAndPayload
+        buf[4] = 7;          // adaptation_field_length
+        assert_eq!(Packet::new(&buf[..]).adaptation_field_control(), AdaptationControl::AdaptationFieldAndPayload);
+        assert_eq!(Packet::new(&buf[..]).payload().map(|p| p.len()), Some(176));
+
+        buf[3] = 0b00100000; // AdaptationFieldOnly
+        buf[4] = 183;        // adaptation_field_length
+        assert_eq!(Packet::new(&buf[..]).adaptation_field_control(), AdaptationControl::AdaptationFieldOnly);
+        assert_eq!(Packet::new(&buf[..]).payload(), None);
+
+        buf[3] = 0b00000000; // Reserved
+        assert_eq!(Packet::new(&buf[..]).payload(), None);
+    }
+
+    #[test]
+    fn splice_countdown() {
+        let mut buf = [0u8; self::PACKET_SIZE];
+        buf[0] = self::SYNC_BYTE;
+        buf[3] = 0b00110000; // AdaptationFieldAndPayload
+        buf[4] = 2;          // adaptation_field_length
+        let pk = Packet::new(&buf[..]);
+        assert!(!pk.adaptation_field().unwrap().splicing_point_flag());
+        assert_eq!(pk.adaptation_field().unwrap().splice_countdown(), Err(AdaptationFieldError::FieldNotPresent));
+
+        buf[5] = 0b00000100; // splicing_point_flag
+        buf[6] = -3i8 as u8; // splice_countdown
+        let pk = Packet::new(&buf[..]);
+        let ad = pk.adaptation_field().unwrap();
+        assert!(ad.splicing_point_flag());
+        assert_eq!(ad.splice_countdown(), Ok(-3));
+    }
+
+    #[test]
+    fn transport_private_data() {
+        let mut buf = [0u8; self::PACKET_SIZE];
+        buf[0] = self::SYNC_BYTE;
+        buf[3] = 0b00110000; // AdaptationFieldAndPayload
+        buf[4] = 18;         // adaptation_field_length
+        buf[5] = 0b00011110; // PCR_flag, OPCR_flag, splicing_point_flag, transport_private_data_flag
+        // PCR
+        buf[6..12].copy_from_slice(&[0, 0, 0, 1, 0b1000_0000, 2]);
+        // OPCR
+        buf[12..18].copy_from_slice(&[0, 0, 0, 0, 0, 3]);
+        buf[18] = 4;         // splice_countdown
+        buf[19] = 3;         // transport_private_data_length
+        buf[20..23].copy_from_slice(b"abc");
+        let pk = Packet::new(&buf[..]);
+        let ad = pk.adaptation_field().unwrap();
+        assert!(ad.transport_private_data_flag());
+        assert_eq!(ad.pcr(), Ok(PCR::from_parts(3, 2)));
+        assert_eq!(ad.opcr(), Ok(PCR::from_parts(0, 3)));
+        assert_eq!(ad.splice_countdown(), Ok(4));
+        assert_eq!(ad.transport_private_data(), Ok(&b"abc"[..]));
+
+        // transport_private_data_length extending beyond the end of the adaptation field,
+        buf[19] = 10;
+        let pk = Packet::new(&buf[..]);
+        assert_eq!(pk.adaptation_field().unwrap().transport_private_data(), Err(AdaptationFieldError::NotEnoughData));
+    }
+
     #[test]
     fn test_xmas_tree() {
         let mut buf = [0xffu8; self::PACKET_SIZE];
@@ -567,7 +924,7 @@ mod test {
         assert_eq!(ad.pcr(), Ok(PCR::from_parts(0b1_1111_1111_1111_1111_1111_1111_1111_1111, 0b1_1111_1111)));
         assert_eq!(1234 * 300 + 56, u64::from(PCR::from_parts(1234, 56)));
         assert_eq!(ad.opcr(), Ok(PCR::from_parts(0b1_1111_1111_1111_1111_1111_1111_1111_1111, 0b1_1111_1111)));
-        assert_eq!(ad.splice_countdown(), Ok(0b11111111));
+        assert_eq!(ad.splice_countdown(), Ok(-1));
         let expected_data = [0xff];
         assert_eq!(ad.transport_private_data(), Ok(&expected_data[..]));
         let ext = ad.adaptation_field_extension().unwrap();
@@ -0,0 +1,267 @@
+//! Checks on the sequence of _Program Clock Reference_ values within a Transport Stream.
+//!
+//! The PCR is carried in the adaptation field of packets on each program's PCR PID, and
+//! (for a constant bitrate stream) should advance in proportion to the number of bytes
+//! transmitted between one PCR and the next.  A PCR value departing from that relationship,
+//! without the `discontinuity_indicator` having been set, usually points to an encoder or
+//! remultiplexer fault.
+
+use packet;
+use std::collections::HashMap;
+
+/// The number of 27MHz PCR clock ticks in one second
+pub const PCR_HZ: u64 = 27_000_000;
+/// PCR values wrap back to zero after reaching this many ticks (`2^33 * 300`)
+pub(crate) const PCR_WRAP: u64 = (1 << 33) * 300;
+
+/// The outcome of checking a single PCR value with [`PcrWatch`](struct.PcrWatch.html).
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum PcrResult {
+    /// The PCR was plausible given the previous value, or there was no previous value to compare
+    /// against
+    Ok,
+    /// The PCR differed from the previous value on the same PID by `actual` ticks of the 27MHz
+    /// clock, but `expected` ticks would have been consistent with the stream bitrate.
+    Discontinuity { expected: u64, actual: u64 },
+}
+
+struct LastPcr {
+    pcr: u64,
+    packet_index: u64,
+}
+
+/// Tracks the last PCR seen on each PCR PID, and flags any later PCR value which is implausible
+/// given the stream bitrate and the number of packets transmitted in between.
+///
+/// Jumps in PCR signalled by the `discontinuity_indicator` of the adaptation field are expected,
+/// and are not reported.
+///
+/// ```
+/// # use mpeg2ts_reader::pcr::{PcrWatch,PcrResult};
+/// # use mpeg2ts_reader::packet::PacketIter;
+/// # let buf = vec![];
+/// let mut watch = PcrWatch::new(15_000_000);
+/// for pk in PacketIter::new(&buf[..]) {
+///     if let Some(PcrResult::Discontinuity { expected, actual }) = watch.packet(&pk) {
+///         println!("PID {}: PCR advanced by {} ticks, expected {}", pk.pid(), actual, expected);
+///     }
+/// }
+/// ```
+pub struct PcrWatch {
+    bitrate: u64,
+    tolerance: u64,
+    packet_count: u64,
+    last_by_pid: HashMap<u16, LastPcr>,
+}
+impl PcrWatch {
+    /// 100 milliseconds, the maximum interval between PCRs permitted by _ISO/IEC 13818-1_
+    pub const DEFAULT_TOLERANCE: u64 = PCR_HZ / 10;
+
+    /// Create a `PcrWatch` for a Transport Stream with the given (constant) bitrate, in bits per
+    /// second.
+    ///
+    /// Panics if `bitrate` is zero.
+    pub fn new(bitrate: u64) -> PcrWatch {
+        assert!(bitrate > 0);
+        PcrWatch {
+            bitrate,
+            tolerance: Self::DEFAULT_TOLERANCE,
+            packet_count: 0,
+            last_by_pid: HashMap::new(),
+        }
+    }
+
+    /// The number of 27MHz ticks by which a PCR may differ from the expected value before being
+    /// reported as a discontinuity.  Defaults to `DEFAULT_TOLERANCE`.
+    pub fn tolerance(mut self, ticks: u64) -> PcrWatch {
+        self.tolerance = ticks;
+        self
+    }
+
+    /// Changes the bitrate used to calculate subsequent expected PCR values.
+    ///
+    /// Panics if `bitrate` is zero.
+    pub fn set_bitrate(&mut self, bitrate: u64) {
+        assert!(bitrate > 0);
+        self.bitrate = bitrate;
+    }
+
+    /// To be called for _every_ packet in the Transport Stream (not only those on PCR PIDs), so
+    /// that the packets transmitted between PCR values can be counted.  Returns `None` if the
+    /// packet carries no PCR.
+    pub fn packet(&mut self, pk: &packet::Packet) -> Option<PcrResult> {
+        let index = self.packet_count;
+        self.packet_count += 1;
+        let af = pk.adaptation_field()?;
+        let pcr = af.pcr().ok()?;
+        if af.discontinuity_indicator() {
+            self.last_by_pid.remove(&pk.pid());
+        }
+        let packets_since_last = self.last_by_pid.get(&pk.pid()).map_or(0, |last| index - last.packet_index);
+        let result = self.check(pk.pid(), pcr, packets_since_last);
+        self.last_by_pid.get_mut(&pk.pid()).unwrap().packet_index = index;
+        Some(result)
+    }
+
+    /// Checks the given PCR against the last value seen for the same PID, where the PCR was
+    /// carried `packets_since_last` packets after the previous one (so `1` for adjacent packets).
+    /// The given value then becomes the one that the next PCR on this PID is compared against.
+    ///
+    /// Use this instead of `packet()` if the application is counting packets itself.
+    pub fn check(&mut self, pid: u16, pcr: packet::PCR, packets_since_last: u64) -> PcrResult {
+        let pcr = u64::from(pcr);
+        let bitrate = self.bitrate;
+        let tolerance = self.tolerance;
+        match self.last_by_pid.insert(pid, LastPcr { pcr, packet_index: 0 }) {
+            None => PcrResult::Ok,
+            Some(last) => {
+                let bits = u128::from(packets_since_last) * packet::PACKET_SIZE as u128 * 8;
+                let expected = (bits * u128::from(PCR_HZ) / u128::from(bitrate)) as u64;
+                let actual = (pcr + PCR_WRAP - last.pcr) % PCR_WRAP;
+                if actual.abs_diff(expected) > tolerance {
+                    PcrResult::Discontinuity { expected, actual }
+                } else {
+                    PcrResult::Ok
+                }
+            },
+        }
+    }
+
+    /// Forget the last PCR seen on the given PID, so that the next value is not checked (for
+    /// example, when the application knows of a discontinuity in the input).
+    pub fn reset(&mut self, pid: u16) {
+        self.last_by_pid.remove(&pid);
+    }
+}
+
+/// The number of 27MHz ticks by which `later` is ahead of `earlier`, allowing for the PCR having
+/// wrapped back to zero in between.
+pub fn pcr_delta(earlier: packet::PCR, later: packet::PCR) -> u64 {
+    (u64::from(later) + PCR_WRAP - u64::from(earlier)) % PCR_WRAP
+}
+
+/// The bitrate, in bits per second, implied by `bytes` having been transmitted while the PCR
+/// advanced by `pcr_delta` ticks of the 27MHz clock.  Returns `None` if `pcr_delta` is zero.
+pub fn bitrate(bytes: u64, pcr_delta: u64) -> Option<f64> {
+    if pcr_delta == 0 {
+        None
+    } else {
+        Some(bytes as f64 * 8.0 * PCR_HZ as f64 / pcr_delta as f64)
+    }
+}
+
+/// Measures the Transport Stream bitrate from the number of bytes transmitted between one PCR and
+/// the next on each PCR PID.
+///
+/// The value is recalculated for every PCR after the first, so reflects the rate over the last
+/// PCR interval only (typically no more than 100 milliseconds).  A PCR with the
+/// `discontinuity_indicator` set starts measurement afresh.
+///
+/// This is used by [`DemultiplexBuilder::program_bitrate()`](../demultiplex/struct.DemultiplexBuilder.html#method.program_bitrate).
+#[derive(Default)]
+pub struct PcrBitrate {
+    packet_count: u64,
+    last_by_pid: HashMap<u16, LastPcr>,
+}
+impl PcrBitrate {
+    pub fn new() -> PcrBitrate {
+        PcrBitrate::default()
+    }
+
+    /// To be called for _every_ packet in the Transport Stream (not only those on PCR PIDs), so
+    /// that the bytes transmitted between PCR values can be counted.  Returns the bitrate if this
+    /// packet carried a PCR, and an earlier PCR on the same PID is available to measure from.
+    ///
+    /// Each packet counts as `packet::PACKET_SIZE` bytes, whatever extra bytes the container adds
+    /// to it (such as the m2ts `TP_extra_header`, or Reed-Solomon parity bytes), since those are
+    /// not part of the Transport Stream.  Packets with the `transport_error_indicator` set are
+    /// counted, but any PCR they appear to carry is not used.
+    pub fn packet(&mut self, pk: &packet::Packet) -> Option<f64> {
+        let index = self.packet_count;
+        self.packet_count += 1;
+        if pk.transport_error_indicator() {
+            return None;
+        }
+        let af = pk.adaptation_field()?;
+        let pcr = u64::from(af.pcr().ok()?);
+        let last = self.last_by_pid.insert(pk.pid(), LastPcr { pcr, packet_index: index });
+        if af.discontinuity_indicator() {
+            return None;
+        }
+        let last = last?;
+        let bytes = (index - last.packet_index) * packet::PACKET_SIZE as u64;
+        bitrate(bytes, (pcr + PCR_WRAP - last.pcr) % PCR_WRAP)
+    }
+
+    /// Forget the PCRs seen so far, so that measurement starts afresh.
+    pub fn clear(&mut self) {
+        self.last_by_pid.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_util;
+
+    #[test]
+    fn pcr_watch() {
+        // at this bitrate, each packet takes exactly 1ms (27000 ticks) to transmit
+        let mut watch = PcrWatch::new(1_504_000).tolerance(1000);
+        let check = |watch: &mut PcrWatch, buf: Vec<u8>| watch.packet(&packet::Packet::new(&buf[..]));
+
+        assert_eq!(check(&mut watch, test_util::pcr_packet(0x100, 0, 1_000_000, false)), Some(PcrResult::Ok));
+        assert_eq!(check(&mut watch, test_util::packet(0x101, 0)), None);
+        assert_eq!(check(&mut watch, test_util::packet(0x101, 0)), None);
+        assert_eq!(check(&mut watch, test_util::pcr_packet(0x100, 0, 1_000_000 + 3 * 27_000 + 500, false)), Some(PcrResult::Ok));
+        // a PCR on another PID is tracked independently,
+        assert_eq!(check(&mut watch, test_util::pcr_packet(0x200, 0, 5, false)), Some(PcrResult::Ok));
+        assert_eq!(
+            check(&mut watch, test_util::pcr_packet(0x100, 0, 5_000_000, false)),
+            Some(PcrResult::Discontinuity { expected: 2 * 27_000, actual: 5_000_000 - 1_081_500 })
+        );
+        // the discontinuous value becomes the new reference point,
+        assert_eq!(check(&mut watch, test_util::pcr_packet(0x100, 0, 5_027_000, false)), Some(PcrResult::Ok));
+        // signalled discontinuities are not reported,
+        assert_eq!(check(&mut watch, test_util::pcr_packet(0x100, 0, 7, true)), Some(PcrResult::Ok));
+        assert_eq!(check(&mut watch, test_util::pcr_packet(0x100, 0, 27_007, false)), Some(PcrResult::Ok));
+    }
+
+    #[test]
+    fn bitrate_from_pcr() {
+        assert_eq!(pcr_delta(packet::PCR::from_parts((1 << 33) - 1, 299), packet::PCR::from_parts(0, 199)), 200);
+        assert_eq!(bitrate(188, 27_000), Some(1_504_000.0));
+        assert_eq!(bitrate(188, 0), None);
+
+        let mut rate = PcrBitrate::new();
+        let check = |rate: &mut PcrBitrate, buf: Vec<u8>| rate.packet(&packet::Packet::new(&buf[..]));
+        assert_eq!(check(&mut rate, test_util::pcr_packet(0x100, 0, 1_000_000, false)), None);
+        assert_eq!(check(&mut rate, test_util::packet(0x101, 0)), None);
+        assert_eq!(check(&mut rate, test_util::packet(0x101, 0)), None);
+        assert_eq!(check(&mut rate, test_util::pcr_packet(0x100, 0, 1_000_000 + 3 * 27_000, false)), Some(1_504_000.0));
+        assert_eq!(check(&mut rate, test_util::pcr_packet(0x100, 0, 1_000_000 + 3 * 27_000 + 13_500, false)), Some(3_008_000.0));
+        // measurement restarts following a signalled discontinuity,
+        assert_eq!(check(&mut rate, test_util::pcr_packet(0x100, 0, 7, true)), None);
+        assert_eq!(check(&mut rate, test_util::pcr_packet(0x100, 0, 27_007, false)), Some(1_504_000.0));
+        rate.clear();
+        assert_eq!(check(&mut rate, test_util::pcr_packet(0x100, 0, 54_007, false)), None);
+
+        // errored packets still occupy their place in the Transport Stream, so are counted,
+        let mut rate = PcrBitrate::new();
+        let mut errored = test_util::packet(0x101, 0);
+        errored[1] |= 0b1000_0000;  // transport_error_indicator
+        assert_eq!(check(&mut rate, test_util::pcr_packet(0x100, 0, 1_000_000, false)), None);
+        assert_eq!(check(&mut rate, errored), None);
+        assert_eq!(check(&mut rate, test_util::pcr_packet(0x100, 0, 1_000_000 + 2 * 27_000, false)), Some(1_504_000.0));
+    }
+
+    #[test]
+    fn pcr_wraps() {
+        let mut watch = PcrWatch::new(1_504_000).tolerance(0);
+        assert_eq!(watch.check(0x100, packet::PCR::from_parts((1 << 33) - 1, 299), 1), PcrResult::Ok);
+        assert_eq!(watch.check(0x100, packet::PCR::from_parts(0, 199), 1), PcrResult::Discontinuity { expected: 27_000, actual: 200 });
+        watch.reset(0x100);
+        assert_eq!(watch.check(0x100, packet::PCR::from_parts((1 << 33) - 1, 299), 1), PcrResult::Ok);
+        assert_eq!(watch.check(0x100, packet::PCR::from_parts(26_999 / 300, 26_999 % 300), 1), PcrResult::Ok);
+    }
+}
@@ -12,7 +12,9 @@
 //! [`Demultiplex`](../demultiplex/struct.Demultiplex.html) instance.
 
 use packet;
+#[cfg(feature = "std")]
 use demultiplex;
+#[cfg(feature = "std")]
 use std::marker;
 
 /// Trait for types that will receive call-backs as pieces of a specific elementary stream are
@@ -23,6 +25,9 @@ use std::marker;
 /// extracting elementary stream data from transport stream packets.
 pub trait ElementaryStreamConsumer {
     fn start_stream(&mut self);
+    /// Called at the start of each PES packet.  A consumer that needs to know whether the
+    /// payload begins at an access unit boundary can check
+    /// [`header.is_aligned()`](struct.PesHeader.html#method.is_aligned).
     fn begin_packet(&mut self, header: PesHeader);
     fn continue_packet(&mut self, data: &[u8]);
     fn end_packet(&mut self);
@@ -32,7 +37,10 @@ pub trait ElementaryStreamConsumer {
 #[derive(Debug,PartialEq)]
 enum PesState {
     Begin,
-    Started,
+    /// `remaining` is the number of bytes of the current PES packet yet to be received, or `None`
+    /// if the packet has an 'unbounded' length (`PES_packet_length` of `0`), in which case the
+    /// packet continues until the next packet with `payload_unit_start_indicator` set.
+    Started { remaining: Option<usize> },
     IgnoreRest,
 }
 
@@ -41,6 +49,9 @@ enum PesState {
 ///
 /// A `PesPacketConsumer` is registered with a
 /// [`Demultiplex`](../demultiplex/struct.Demultiplex.html) instance
+///
+/// PES packets with the `padding_stream` `stream_id` carry only stuffing, and are discarded
+/// without being passed to the `ElementaryStreamConsumer`.
 pub struct PesPacketConsumer<C>
 where
     C: ElementaryStreamConsumer
@@ -64,13 +75,13 @@ where
     pub fn is_continuous(&self, packet: &packet::Packet) -> bool {
         if let Some(cc) = self.ccounter {
             // counter only increases if the packet has a payload,
-            let result = if packet.adaptation_control().has_payload() {
+            let result = if packet.has_payload() {
                 packet.continuity_counter().follows(cc)
             } else {
                 packet.continuity_counter().count() == cc.count()
             };
             if !result {
-                println!("discontinuity at packet with PID={} last={} this={} ({:?})", packet.pid(), cc.count(), packet.continuity_counter().count(), packet.adaptation_control());
+                warn!("discontinuity at packet with PID={} last={} this={} ({:?})", packet.pid(), cc.count(), packet.continuity_counter().count(), packet.adaptation_control());
             }
             result
         } else {
@@ -85,28 +96,63 @@ where
             self.state = PesState::IgnoreRest;
         }
         self.ccounter = Some(packet.continuity_counter());
+        if !packet.has_payload() {
+            // e.g. a packet carrying only a PCR; any payload_unit_start_indicator is meaningless
+            return;
+        }
         if packet.payload_unit_start_indicator() {
-            if self.state == PesState::Started {
+            if let PesState::Started { .. } = self.state {
                 self.stream_consumer.end_packet();
-            } else {
-                self.state = PesState::Started;
             }
+            self.state = PesState::Started { remaining: None };
             if let Some(payload) = packet.payload() {
                 if let Some(header) = PesHeader::from_bytes(payload) {
-                    self.stream_consumer.begin_packet(header);
+                    let pes_packet_length = header.pes_packet_length() as usize;
+                    if header.is_padding() {
+                        // the next PES packet will begin in a packet with payload_unit_start_indicator
+                        // set, so ignoring everything up to that point skips the
+                        // PES_packet_length bytes of padding
+                        self.state = PesState::IgnoreRest;
+                    } else if pes_packet_length == 0 {
+                        // unbounded; the packet will end at the next payload_unit_start_indicator
+                        self.stream_consumer.begin_packet(header);
+                    } else {
+                        let total = PesHeader::FIXED_HEADER_SIZE + pes_packet_length;
+                        if payload.len() >= total {
+                            let header = PesHeader::from_bytes(&payload[..total]).unwrap();
+                            self.stream_consumer.begin_packet(header);
+                            self.stream_consumer.end_packet();
+                            self.state = PesState::IgnoreRest;
+                        } else {
+                            self.stream_consumer.begin_packet(header);
+                            self.state = PesState::Started { remaining: Some(total - payload.len()) };
+                        }
+                    }
                 }
             }
         } else {
             match self.state {
-                PesState::Started => {
+                PesState::Started { remaining: None } => {
                     if let Some(payload) = packet.payload() {
-                        if payload.len() > 0 {
+                        if !payload.is_empty() {
                             self.stream_consumer.continue_packet(payload);
                         }
                     }
                 },
+                PesState::Started { remaining: Some(remaining) } => {
+                    if let Some(payload) = packet.payload() {
+                        if payload.len() >= remaining {
+                            self.stream_consumer.continue_packet(&payload[..remaining]);
+                            self.stream_consumer.end_packet();
+                            self.state = PesState::IgnoreRest;
+                        } else {
+                            self.stream_consumer.continue_packet(payload);
+                            self.state = PesState::Started { remaining: Some(remaining - payload.len()) };
+                        }
+                    }
+                },
                 PesState::Begin => {
-                    println!("pid={}: Ignoring elementary stream content without a payload_start_indicator", packet.pid());
+                    warn!("pid={}: Ignoring elementary stream content without a payload_start_indicator", packet.pid());
                     self.state = PesState::IgnoreRest;
                 },
                 PesState::IgnoreRest => ()
@@ -115,6 +161,7 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 pub struct PesPacketFilter<Ctx,E>
 where
     Ctx: demultiplex::DemuxContext,
@@ -123,6 +170,7 @@ where
     consumer: PesPacketConsumer<E>,
     phantom: marker::PhantomData<Ctx>,
 }
+#[cfg(feature = "std")]
 impl<Ctx,E> PesPacketFilter<Ctx,E>
     where
         Ctx: demultiplex::DemuxContext,
@@ -135,6 +183,7 @@ impl<Ctx,E> PesPacketFilter<Ctx,E>
         }
     }
 }
+#[cfg(feature = "std")]
 impl<Ctx,E> demultiplex::PacketFilter for PesPacketFilter<Ctx,E>
 where
     Ctx: demultiplex::DemuxContext,
@@ -146,6 +195,145 @@ where
     fn consume(&mut self, _ctx: &mut Self::Ctx, pk: packet::Packet) {
         self.consumer.consume(pk);
     }
+    #[inline(always)]
+    fn needs_payload(&self) -> bool {
+        true
+    }
+}
+
+/// Accumulates the pieces of each PES packet for a `BufferedPesFilter`.
+#[cfg(feature = "std")]
+struct PesCollector {
+    buf: Vec<u8>,
+    max_size: usize,
+    /// `false` once the current packet is found to be incomplete or too large, so that it is not
+    /// delivered
+    in_packet: bool,
+    complete: Vec<Vec<u8>>,
+}
+#[cfg(feature = "std")]
+impl PesCollector {
+    fn append(&mut self, data: &[u8]) {
+        if !self.in_packet {
+            return;
+        }
+        if self.buf.len() + data.len() > self.max_size {
+            warn!("discarding PES packet larger than the limit of {} bytes", self.max_size);
+            self.in_packet = false;
+            self.buf.clear();
+            return;
+        }
+        self.buf.extend_from_slice(data);
+    }
+}
+#[cfg(feature = "std")]
+impl ElementaryStreamConsumer for PesCollector {
+    fn start_stream(&mut self) { }
+    fn begin_packet(&mut self, header: PesHeader) {
+        self.buf.clear();
+        self.in_packet = true;
+        self.append(header.buf);
+    }
+    fn continue_packet(&mut self, data: &[u8]) {
+        self.append(data);
+    }
+    fn end_packet(&mut self) {
+        if self.in_packet {
+            self.in_packet = false;
+            self.complete.push(std::mem::take(&mut self.buf));
+        }
+    }
+    fn continuity_error(&mut self) {
+        self.in_packet = false;
+        self.buf.clear();
+    }
+}
+
+/// A complete PES packet, as delivered by a [`BufferedPesFilter`](struct.BufferedPesFilter.html).
+#[cfg(feature = "std")]
+pub struct BufferedPesPacket {
+    buf: Vec<u8>,
+}
+#[cfg(feature = "std")]
+impl BufferedPesPacket {
+    pub fn header(&self) -> PesHeader<'_> {
+        // the collector only begins a packet given a valid header
+        PesHeader { buf: &self.buf[..] }
+    }
+    /// The PTS and DTS values from the optional header fields, or `PtsDts::None` if the
+    /// `stream_id` does not have the optional header.
+    pub fn pts_dts(&self) -> PtsDts {
+        match self.header().contents() {
+            PesContents::Parsed(Some(parsed)) => parsed.pts_dts(),
+            _ => PtsDts::None,
+        }
+    }
+    /// The `PES_packet_data_byte`s following the header (and any optional header fields).
+    pub fn payload(&self) -> &[u8] {
+        match self.header().contents() {
+            PesContents::Parsed(Some(parsed)) => parsed.payload(),
+            PesContents::Parsed(None) => &[],
+            PesContents::Payload(buf) => buf,
+        }
+    }
+    /// The bytes of the whole PES packet, starting with the `packet_start_code_prefix`.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// A `PacketFilter` which reassembles each PES packet into a single buffer, and passes it to the
+/// given callback once complete.
+///
+/// This is simpler to use than implementing
+/// [`ElementaryStreamConsumer`](trait.ElementaryStreamConsumer.html) (at the cost of copying the
+/// data) for streams whose PES packets are small, such as audio or subtitles.  A PES packet whose
+/// length is 'unbounded' is delivered when the next PES packet starts.
+///
+/// A PES packet larger than `max_size` bytes is discarded with a warning, rather than being
+/// buffered without limit, as is a PES packet affected by a continuity error.
+#[cfg(feature = "std")]
+pub struct BufferedPesFilter<Ctx: demultiplex::DemuxContext> {
+    consumer: PesPacketConsumer<PesCollector>,
+    callback: Box<BufferedPesCallback<Ctx>>,
+}
+#[cfg(feature = "std")]
+type BufferedPesCallback<Ctx> = dyn FnMut(&mut Ctx, BufferedPesPacket);
+#[cfg(feature = "std")]
+impl<Ctx: demultiplex::DemuxContext> BufferedPesFilter<Ctx> {
+    /// A `max_size` sufficient for any PES packet with a bounded length, i.e. `6` bytes of fixed
+    /// header plus the largest possible `PES_packet_length`.
+    pub const DEFAULT_MAX_SIZE: usize = PesHeader::FIXED_HEADER_SIZE + 0xffff;
+
+    pub fn new<F>(max_size: usize, callback: F) -> BufferedPesFilter<Ctx>
+    where
+        F: FnMut(&mut Ctx, BufferedPesPacket) + 'static
+    {
+        BufferedPesFilter {
+            consumer: PesPacketConsumer::new(PesCollector {
+                buf: Vec::new(),
+                max_size,
+                in_packet: false,
+                complete: Vec::new(),
+            }),
+            callback: Box::new(callback),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl<Ctx: demultiplex::DemuxContext> demultiplex::PacketFilter for BufferedPesFilter<Ctx> {
+    type Ctx = Ctx;
+
+    fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet) {
+        self.consumer.consume(pk);
+        // a single TS packet may both end one PES packet and contain the whole of the next
+        for buf in self.consumer.stream_consumer.complete.drain(..) {
+            (self.callback)(ctx, BufferedPesPacket { buf });
+        }
+    }
+    fn needs_payload(&self) -> bool {
+        true
+    }
 }
 
 /// Header at the start of every PES packet.
@@ -165,14 +353,18 @@ pub struct PesHeader<'buf> {
     buf: &'buf[u8],
 }
 impl<'buf> PesHeader<'buf> {
-    pub fn from_bytes(buf: &'buf[u8]) -> Option<PesHeader> {
+    /// The size of the `packet_start_code_prefix`, `stream_id` and `PES_packet_length` fields
+    /// which begin every PES packet.
+    const FIXED_HEADER_SIZE: usize = 6;
+
+    pub fn from_bytes(buf: &'buf[u8]) -> Option<PesHeader<'buf>> {
         if buf.len() < 6 {
-            println!("Buffer size {} too small to hold PES header", buf.len());
+            warn!("Buffer size {} too small to hold PES header", buf.len());
             return None;
         }
         let packet_start_code_prefix = u32::from(buf[0]) << 16 | u32::from(buf[1]) << 8 | u32::from(buf[2]);
         if packet_start_code_prefix != 1 {
-            println!("invalid packet_start_code_prefix {:#x}, expected 0x000001", packet_start_code_prefix);
+            warn!("invalid packet_start_code_prefix {:#x}, expected 0x000001", packet_start_code_prefix);
             return None
         }
         Some(PesHeader {
@@ -184,10 +376,56 @@ impl<'buf> PesHeader<'buf> {
         self.buf[3]
     }
 
+    /// `true` if `stream_id()` is in the range `0xC0` to `0xDF`, indicating an
+    /// _ISO/IEC 13818-3_ or _ISO/IEC 11172-3_ (or similar) audio stream.
+    pub fn is_audio(&self) -> bool {
+        self.stream_id() & 0b1110_0000 == 0b1100_0000
+    }
+
+    /// `true` if `stream_id()` is in the range `0xE0` to `0xEF`, indicating an
+    /// _ITU-T Rec. H.262_ / _ISO/IEC 11172-2_ (or similar) video stream.
+    pub fn is_video(&self) -> bool {
+        self.stream_id() & 0b1111_0000 == 0b1110_0000
+    }
+
+    /// `true` if `stream_id()` is that of a `padding_stream`, whose PES packets contain only
+    /// stuffing bytes.
+    pub fn is_padding(&self) -> bool {
+        self.stream_id() == PADDING_STREAM
+    }
+
+    /// `false` for those `stream_id()` values (e.g. `padding_stream` or `private_stream_2`) where
+    /// the PES packet header is immediately followed by payload data, with no optional header
+    /// fields such as PTS/DTS.  When this returns `true`, `contents()` will produce
+    /// `PesContents::Parsed`.
+    pub fn has_optional_header(&self) -> bool {
+        is_parsed(self.stream_id())
+    }
+
+    /// The number of bytes in the PES packet following this field, or `0` if the length is
+    /// 'unbounded' (which is allowed for video streams carried in transport streams).
+    ///
+    /// `PesPacketConsumer` uses this value to find the end of the PES packet; an unbounded packet
+    /// ends when the next packet with `payload_unit_start_indicator` set arrives.
     pub fn pes_packet_length(&self) -> u16 {
         u16::from(self.buf[4]) << 8 | u16::from(self.buf[5])
     }
 
+    /// `true` if the header's `data_alignment_indicator` is set, meaning that the payload begins
+    /// with the start of an access unit (or of whatever other syntax element the stream's
+    /// `data_stream_alignment_descriptor` specifies), so that a decoder may begin cleanly from
+    /// this packet.
+    ///
+    /// When `false` (including where the `stream_id()` has no optional header fields, or they
+    /// fail to parse), nothing is known about where access units begin, and the consumer must
+    /// search for start codes itself.
+    pub fn is_aligned(&self) -> bool {
+        match self.contents() {
+            PesContents::Parsed(Some(contents)) => contents.data_alignment_indicator() == DataAlignment::Aligned,
+            _ => false,
+        }
+    }
+
     // maaaaaybe just have parsed_contents(&self) + payload(&self)
     pub fn contents(&self) -> PesContents<'buf> {
         let header_len = 6;
@@ -200,17 +438,29 @@ impl<'buf> PesHeader<'buf> {
     }
 }
 
+const PROGRAM_STREAM_MAP: u8 = 0b1011_1100;
+const PADDING_STREAM: u8 = 0b1011_1110;
+const PRIVATE_STREAM_2: u8 = 0b1011_1111;
+const ECM_STREAM: u8 = 0b1111_0000;
+const EMM_STREAM: u8 = 0b1111_0001;
+const PROGRAM_STREAM_DIRECTORY: u8 = 0b1111_1111;
+const DSMCC_STREAM: u8 = 0b1111_0010;
+const H222_1_TYPE_E_STREAM: u8 = 0b1111_1000;
+
+/// The `stream_id` values for which the standard specifies that the PES packet header is
+/// followed directly by `PES_packet_data_byte`s, rather than by the flags and optional fields
+/// which `PesParsedContents` decodes.
 fn is_parsed(stream_id: u8) -> bool {
-    match stream_id {
-        0b1011_1100 |
-        0b1011_1111 |
-        0b1111_0000 |
-        0b1111_0001 |
-        0b1111_1111 |
-        0b1111_0010 |
-        0b1111_1000 => false,
-        _ => true,
-    }
+    !matches!(stream_id,
+        PROGRAM_STREAM_MAP |
+        PADDING_STREAM |
+        PRIVATE_STREAM_2 |
+        ECM_STREAM |
+        EMM_STREAM |
+        PROGRAM_STREAM_DIRECTORY |
+        DSMCC_STREAM |
+        H222_1_TYPE_E_STREAM
+    )
 }
 
 /// Either `PesContents::Payload`, when the `PesHeader` has no extra fields, or
@@ -248,12 +498,12 @@ pub struct PesParsedContents<'buf> {
 impl<'buf> PesParsedContents<'buf> {
     pub fn from_bytes(buf: &'buf[u8]) -> Option<PesParsedContents<'buf>> {
         if buf.len() < 3 {
-            println!("buf not large enough to hold PES parsed header: {} bytes", buf.len());
+            warn!("buf not large enough to hold PES parsed header: {} bytes", buf.len());
             return None;
         }
         let check_bits = buf[0] >> 6;
         if check_bits != 0b10 {
-            println!("unexpected check-bits value {:#b}, expected 0b10", check_bits);
+            warn!("unexpected check-bits value {:#b}, expected 0b10", check_bits);
             return None;
         }
         Some(PesParsedContents{
@@ -289,7 +539,6 @@ impl<'buf> PesParsedContents<'buf> {
     fn pts_dts_flags(&self) -> u8 {
         self.buf[1] >> 6
     }
-    /*
     fn escr_flag(&self) -> bool {
         self.buf[1] >> 5 & 1 != 0
     }
@@ -308,7 +557,6 @@ impl<'buf> PesParsedContents<'buf> {
     fn pes_extension_flag(&self) -> bool {
         self.buf[1] & 1 != 0
     }
-    */
     fn pes_header_data_len(&self) -> usize {
         self.buf[2] as usize
     }
@@ -320,7 +568,7 @@ impl<'buf> PesParsedContents<'buf> {
             0b01 => PtsDts::Invalid,
             0b10 => {
                 if self.buf.len() < header_size+timestamp_size {
-                    println!("PES packet buffer not long enough to hold of PTS, {}", self.buf.len());
+                    warn!("PES packet buffer not long enough to hold of PTS, {}", self.buf.len());
                     return PtsDts::None;
                 }
                 PtsDts::PtsOnly(
@@ -329,7 +577,7 @@ impl<'buf> PesParsedContents<'buf> {
             },
             0b11 => {
                 if self.buf.len() < header_size+timestamp_size*2 {
-                    println!("PES packet buffer not long enough to hold of PTS+DTS, {}", self.buf.len());
+                    warn!("PES packet buffer not long enough to hold of PTS+DTS, {}", self.buf.len());
                     return PtsDts::None;
                 }
                 PtsDts::Both {
@@ -340,12 +588,397 @@ impl<'buf> PesParsedContents<'buf> {
             v => panic!("unexpected value {}", v),
         }
     }
+    fn pts_dts_size(&self) -> usize {
+        match self.pts_dts_flags() {
+            0b10 => 5,
+            0b11 => 10,
+            _ => 0,
+        }
+    }
+    /// Returns a slice of the optional header fields, checking that the requested range lies
+    /// within both the `PES_header_data_length` and the available data.
+    fn slice(&self, from: usize, to: usize) -> Result<&'buf[u8], PesError> {
+        let fixed_header_len = 3;
+        if to > fixed_header_len + self.pes_header_data_len() || to > self.buf.len() {
+            Err(PesError::NotEnoughData)
+        } else {
+            Ok(&self.buf[from..to])
+        }
+    }
+    const ESCR_SIZE: usize = 6;
+    fn escr_offset(&self) -> usize {
+        3 + self.pts_dts_size()
+    }
+    /// Returns the 'Elementary Stream Clock Reference' value, if present.  The value has the same
+    /// 27MHz, base + extension structure as a PCR, and so is represented with the same type.
+    pub fn escr(&self) -> Result<packet::PCR, PesError> {
+        if self.escr_flag() {
+            let off = self.escr_offset();
+            let dat = self.slice(off, off + Self::ESCR_SIZE)?;
+            let val = dat.iter().fold(0u64, |acc, &b| acc << 8 | u64::from(b));
+            // marker bits
+            for &bit in &[42, 26, 10, 0] {
+                if val >> bit & 1 == 0 {
+                    return Err(PesError::MarkerBitNotSet);
+                }
+            }
+            let base = (val >> 43 & 0b111) << 30
+                | (val >> 27 & 0x7fff) << 15
+                | (val >> 11 & 0x7fff);
+            let extension = (val >> 1 & 0x1ff) as u16;
+            Ok(packet::PCR::from_parts(base, extension))
+        } else {
+            Err(PesError::FieldNotPresent)
+        }
+    }
+    fn es_rate_offset(&self) -> usize {
+        self.escr_offset() + if self.escr_flag() {
+            Self::ESCR_SIZE
+        } else {
+            0
+        }
+    }
+    /// Returns the `ES_rate` field, if present, which gives the rate at which the decoder
+    /// receives bytes of the PES packet, in units of 50 bytes/second.
+    pub fn es_rate(&self) -> Result<u32, PesError> {
+        if self.esrate_flag() {
+            let off = self.es_rate_offset();
+            let dat = self.slice(off, off + 3)?;
+            if dat[0] & 0b1000_0000 == 0 || dat[2] & 1 == 0 {
+                return Err(PesError::MarkerBitNotSet);
+            }
+            Ok(u32::from(dat[0] & 0b0111_1111) << 15
+                | u32::from(dat[1]) << 7
+                | u32::from(dat[2]) >> 1)
+        } else {
+            Err(PesError::FieldNotPresent)
+        }
+    }
+    fn dsm_trick_mode_offset(&self) -> usize {
+        self.es_rate_offset() + if self.esrate_flag() {
+            3
+        } else {
+            0
+        }
+    }
+    /// Returns the `DSM_trick_mode` field, if present, describing how the video in this PES
+    /// packet relates to trick-mode (fast-forward, slow-motion etc.) playback.
+    pub fn dsm_trick_mode(&self) -> Result<TrickMode, PesError> {
+        if self.dsm_trick_mode_flag() {
+            let off = self.dsm_trick_mode_offset();
+            let dat = self.slice(off, off + 1)?;
+            Ok(TrickMode::from_byte(dat[0]))
+        } else {
+            Err(PesError::FieldNotPresent)
+        }
+    }
+    fn additional_copy_info_offset(&self) -> usize {
+        self.dsm_trick_mode_offset() + if self.dsm_trick_mode_flag() {
+            1
+        } else {
+            0
+        }
+    }
+    /// Returns the 7-bit `additional_copy_info` field, if present, holding private data relating
+    /// to copyright information.
+    pub fn additional_copy_info(&self) -> Result<u8, PesError> {
+        if self.additional_copy_info_flag() {
+            let off = self.additional_copy_info_offset();
+            let dat = self.slice(off, off + 1)?;
+            if dat[0] & 0b1000_0000 == 0 {
+                return Err(PesError::MarkerBitNotSet);
+            }
+            Ok(dat[0] & 0b0111_1111)
+        } else {
+            Err(PesError::FieldNotPresent)
+        }
+    }
+    fn previous_packet_crc_offset(&self) -> usize {
+        self.additional_copy_info_offset() + if self.additional_copy_info_flag() {
+            1
+        } else {
+            0
+        }
+    }
+    /// Returns the `previous_PES_packet_CRC` field, if present, which holds the _CRC-16_ of the
+    /// data bytes of the _previous_ PES packet (excluding its header) on this stream.
+    pub fn previous_packet_crc(&self) -> Result<u16, PesError> {
+        if self.pes_crc_flag() {
+            let off = self.previous_packet_crc_offset();
+            let dat = self.slice(off, off + 2)?;
+            Ok(u16::from(dat[0]) << 8 | u16::from(dat[1]))
+        } else {
+            Err(PesError::FieldNotPresent)
+        }
+    }
+    fn pes_extension_offset(&self) -> usize {
+        self.previous_packet_crc_offset() + if self.pes_crc_flag() {
+            2
+        } else {
+            0
+        }
+    }
+    /// Returns the `PES_extension` fields, if present.
+    pub fn pes_extension(&self) -> Result<PesExtension<'buf>, PesError> {
+        if self.pes_extension_flag() {
+            let fixed_header_len = 3;
+            let off = self.pes_extension_offset();
+            let end = (fixed_header_len + self.pes_header_data_len()).min(self.buf.len());
+            if off >= end {
+                return Err(PesError::NotEnoughData);
+            }
+            Ok(PesExtension { buf: &self.buf[off..end] })
+        } else {
+            Err(PesError::FieldNotPresent)
+        }
+    }
+    /// The PES packet payload data following the optional header fields (or at least, that part
+    /// of the payload present in the buffer).  If the buffer is not large enough to hold the
+    /// number of header bytes indicated by `PES_header_data_length`, the result is empty.
     pub fn payload(&self) -> &'buf[u8] {
         let fixed_header_len = 3;
-        &self.buf[fixed_header_len+self.pes_header_data_len()..]
+        let payload_start = fixed_header_len + self.pes_header_data_len();
+        if payload_start > self.buf.len() {
+            warn!("PES_header_data_length={} extends beyond the end of the {} bytes of available data", self.pes_header_data_len(), self.buf.len());
+            return &self.buf[0..0];
+        }
+        &self.buf[payload_start..]
     }
 }
 
+/// The `PES_extension` fields of the PES header, returned by
+/// [`PesParsedContents::pes_extension()`](struct.PesParsedContents.html#method.pes_extension).
+///
+/// The fields following `P-STD_buffer` (signalled by `PES_extension_flag_2`) are not yet
+/// interpreted.
+pub struct PesExtension<'buf> {
+    /// the extension data, up to the end of the `PES_header_data_length`
+    buf: &'buf[u8],
+}
+impl<'buf> PesExtension<'buf> {
+    const PES_PRIVATE_DATA_SIZE: usize = 16;
+
+    fn pes_private_data_flag(&self) -> bool {
+        self.buf[0] >> 7 & 1 != 0
+    }
+    fn pack_header_field_flag(&self) -> bool {
+        self.buf[0] >> 6 & 1 != 0
+    }
+    fn program_packet_sequence_counter_flag(&self) -> bool {
+        self.buf[0] >> 5 & 1 != 0
+    }
+    fn p_std_buffer_flag(&self) -> bool {
+        self.buf[0] >> 4 & 1 != 0
+    }
+    fn slice(&self, from: usize, to: usize) -> Result<&'buf[u8], PesError> {
+        if to > self.buf.len() {
+            Err(PesError::NotEnoughData)
+        } else {
+            Ok(&self.buf[from..to])
+        }
+    }
+
+    fn pes_private_data_offset(&self) -> usize {
+        1
+    }
+    /// Returns the 16 bytes of `PES_private_data`, if present.
+    pub fn pes_private_data(&self) -> Result<&'buf[u8], PesError> {
+        if self.pes_private_data_flag() {
+            let off = self.pes_private_data_offset();
+            self.slice(off, off + Self::PES_PRIVATE_DATA_SIZE)
+        } else {
+            Err(PesError::FieldNotPresent)
+        }
+    }
+    fn pack_header_offset(&self) -> usize {
+        self.pes_private_data_offset() + if self.pes_private_data_flag() {
+            Self::PES_PRIVATE_DATA_SIZE
+        } else {
+            0
+        }
+    }
+    /// Returns the `pack_header()` of a Program Stream, if present (following the
+    /// `pack_field_length` byte, which is not included).
+    pub fn pack_header(&self) -> Result<&'buf[u8], PesError> {
+        if self.pack_header_field_flag() {
+            let off = self.pack_header_offset();
+            let len = self.slice(off, off + 1)?[0] as usize;
+            self.slice(off + 1, off + 1 + len)
+        } else {
+            Err(PesError::FieldNotPresent)
+        }
+    }
+    fn program_packet_sequence_counter_offset(&self) -> Result<usize, PesError> {
+        let off = self.pack_header_offset();
+        Ok(off + if self.pack_header_field_flag() {
+            1 + self.slice(off, off + 1)?[0] as usize
+        } else {
+            0
+        })
+    }
+    /// Returns the `program_packet_sequence_counter` fields, if present.
+    pub fn program_packet_sequence_counter(&self) -> Result<ProgramPacketSequenceCounter, PesError> {
+        if self.program_packet_sequence_counter_flag() {
+            let off = self.program_packet_sequence_counter_offset()?;
+            let dat = self.slice(off, off + 2)?;
+            if dat[0] & 0b1000_0000 == 0 || dat[1] & 0b1000_0000 == 0 {
+                return Err(PesError::MarkerBitNotSet);
+            }
+            Ok(ProgramPacketSequenceCounter {
+                counter: dat[0] & 0b0111_1111,
+                mpeg1_mpeg2_identifier: dat[1] >> 6 & 1 != 0,
+                original_stuff_length: dat[1] & 0b0011_1111,
+            })
+        } else {
+            Err(PesError::FieldNotPresent)
+        }
+    }
+    fn p_std_buffer_offset(&self) -> Result<usize, PesError> {
+        Ok(self.program_packet_sequence_counter_offset()? + if self.program_packet_sequence_counter_flag() {
+            2
+        } else {
+            0
+        })
+    }
+    /// Returns the `P-STD_buffer` fields, if present.
+    pub fn p_std_buffer(&self) -> Result<PStdBuffer, PesError> {
+        if self.p_std_buffer_flag() {
+            let off = self.p_std_buffer_offset()?;
+            let dat = self.slice(off, off + 2)?;
+            if dat[0] >> 6 != 0b01 {
+                return Err(PesError::MarkerBitNotSet);
+            }
+            Ok(PStdBuffer {
+                scale: dat[0] >> 5 & 1 != 0,
+                size: u16::from(dat[0] & 0b1_1111) << 8 | u16::from(dat[1]),
+            })
+        } else {
+            Err(PesError::FieldNotPresent)
+        }
+    }
+}
+
+/// The `program_packet_sequence_counter` fields of a [`PesExtension`](struct.PesExtension.html).
+#[derive(PartialEq,Debug,Clone,Copy)]
+pub struct ProgramPacketSequenceCounter {
+    /// A 7-bit counter, incremented with each successive PES packet of the originating Program
+    /// Stream (or _ISO/IEC 11172-1_ System Stream)
+    pub counter: u8,
+    /// `true` if the originating stream was _ISO/IEC 11172-1_, or `false` if it was an
+    /// _ISO/IEC 13818-1_ Program Stream
+    pub mpeg1_mpeg2_identifier: bool,
+    /// The number of stuffing bytes used in the original PES packet header
+    pub original_stuff_length: u8,
+}
+
+/// The `P-STD_buffer` fields of a [`PesExtension`](struct.PesExtension.html), giving the size of
+/// the Program Stream decoder's input buffer for this stream.
+#[derive(PartialEq,Debug,Clone,Copy)]
+pub struct PStdBuffer {
+    /// `P-STD_buffer_scale`; if `false` the size is in units of 128 bytes, if `true` 1024 bytes
+    pub scale: bool,
+    /// `P-STD_buffer_size`, in units given by `scale`
+    pub size: u16,
+}
+impl PStdBuffer {
+    /// The buffer size in bytes
+    pub fn size_bytes(&self) -> u32 {
+        u32::from(self.size) * if self.scale { 1024 } else { 128 }
+    }
+}
+
+/// Which field(s) of an interlaced frame are to be displayed during trick-mode playback.
+#[derive(PartialEq,Debug,Clone,Copy)]
+pub enum FieldId {
+    /// Display from the top field only
+    TopOnly,
+    /// Display from the bottom field only
+    BottomOnly,
+    /// Display the complete frame
+    CompleteFrame,
+    /// The value `0b11`, reserved by the spec
+    Reserved,
+}
+impl FieldId {
+    fn from_bits(val: u8) -> FieldId {
+        match val & 0b11 {
+            0b00 => FieldId::TopOnly,
+            0b01 => FieldId::BottomOnly,
+            0b10 => FieldId::CompleteFrame,
+            _ => FieldId::Reserved,
+        }
+    }
+}
+
+/// The decoded `DSM_trick_mode` field of the PES header.
+#[derive(PartialEq,Debug,Clone,Copy)]
+pub enum TrickMode {
+    FastForward {
+        field_id: FieldId,
+        /// If true, there may be missing macroblocks between coded slices
+        intra_slice_refresh: bool,
+        /// A restricted set of coefficients may have been used in coding the video (the 2-bit
+        /// value is not interpreted further by this crate)
+        frequency_truncation: u8,
+    },
+    SlowMotion {
+        /// The number of times each field (or frame, for progressive video) should be displayed
+        rep_cntrl: u8,
+    },
+    FreezeFrame {
+        field_id: FieldId,
+    },
+    FastReverse {
+        field_id: FieldId,
+        /// If true, there may be missing macroblocks between coded slices
+        intra_slice_refresh: bool,
+        /// A restricted set of coefficients may have been used in coding the video (the 2-bit
+        /// value is not interpreted further by this crate)
+        frequency_truncation: u8,
+    },
+    SlowReverse {
+        /// The number of times each field (or frame, for progressive video) should be displayed
+        rep_cntrl: u8,
+    },
+    /// A `trick_mode_control` value reserved by the spec.  The first value is the
+    /// `trick_mode_control` value, and the second the following 5 bits.
+    Reserved(u8, u8),
+}
+impl TrickMode {
+    fn from_byte(b: u8) -> TrickMode {
+        let trick_mode_control = b >> 5;
+        let rest = b & 0b1_1111;
+        match trick_mode_control {
+            0b000 => TrickMode::FastForward {
+                field_id: FieldId::from_bits(rest >> 3),
+                intra_slice_refresh: rest >> 2 & 1 != 0,
+                frequency_truncation: rest & 0b11,
+            },
+            0b001 => TrickMode::SlowMotion { rep_cntrl: rest },
+            0b010 => TrickMode::FreezeFrame { field_id: FieldId::from_bits(rest >> 3) },
+            0b011 => TrickMode::FastReverse {
+                field_id: FieldId::from_bits(rest >> 3),
+                intra_slice_refresh: rest >> 2 & 1 != 0,
+                frequency_truncation: rest & 0b11,
+            },
+            0b100 => TrickMode::SlowReverse { rep_cntrl: rest },
+            _ => TrickMode::Reserved(trick_mode_control, rest),
+        }
+    }
+}
+
+/// Problems which may prevent one of the optional fields of
+/// [`PesParsedContents`](struct.PesParsedContents.html) being returned.
+#[derive(PartialEq,Debug)]
+pub enum PesError {
+    /// The flag indicating the presence of the requested field was not set
+    FieldNotPresent,
+    /// The field extends beyond the end of the `PES_header_data_length`, or of the available data
+    NotEnoughData,
+    /// One of the marker bits within the field, which are required to be `1`, was `0`
+    MarkerBitNotSet,
+}
+
 
 /// Detail about the formatting problem which prevented a [`Timestamp`](struct.Timestamp.html)
 /// value being parsed.
@@ -458,7 +1091,7 @@ pub enum OriginalOrCopy {
     Copy,
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod test {
     use std::io;
     use std;
@@ -466,15 +1099,9 @@ mod test {
     use data_encoding::base16;
     use pes;
     use packet;
-
-    fn make_test_data<F>(builder: F) -> Vec<u8>
-    where
-        F: Fn(BitWriter<BE>)->Result<(), io::Error>
-    {
-        let mut data: Vec<u8> = Vec::new();
-        builder(BitWriter::<BE>::new(&mut data)).unwrap();
-        data
-    }
+    use demultiplex;
+    use demultiplex::PacketFilter;
+    use test_util;
 
     /// `ts` is a 33-bit timestamp value
     fn write_ts(w: &mut BitWriter<BE>, ts: u64, prefix: u8) -> Result<(), io::Error> {
@@ -490,7 +1117,7 @@ mod test {
 
     #[test]
     fn parse_header() {
-        let data = make_test_data(|mut w| {
+        let data = test_util::make_test_data(|mut w| {
             w.write(24, 1)?; // packet_start_code_prefix
             w.write(8, 7)?;  // stream_id
             w.write(16, 7)?; // PES_packet_length
@@ -536,10 +1163,191 @@ mod test {
         }
     }
 
+    #[test]
+    fn stream_id_predicates() {
+        let header_for = |stream_id: u8| vec!(0, 0, 1, stream_id, 0, 0);
+        let data = header_for(0xE0);
+        let header = pes::PesHeader::from_bytes(&data[..]).unwrap();
+        assert!(header.is_video());
+        assert!(!header.is_audio());
+        assert!(header.has_optional_header());
+
+        let data = header_for(0xDF);
+        let header = pes::PesHeader::from_bytes(&data[..]).unwrap();
+        assert!(!header.is_video());
+        assert!(header.is_audio());
+        assert!(header.has_optional_header());
+
+        let data = header_for(0xBD);  // private_stream_1
+        let header = pes::PesHeader::from_bytes(&data[..]).unwrap();
+        assert!(!header.is_video());
+        assert!(!header.is_audio());
+        assert!(header.has_optional_header());
+
+        for &stream_id in &[0xBE, 0xBF] {  // padding_stream, private_stream_2
+            let data = header_for(stream_id);
+            let header = pes::PesHeader::from_bytes(&data[..]).unwrap();
+            assert!(!header.has_optional_header());
+        }
+    }
+
+    #[test]
+    fn escr_and_es_rate() {
+        let data = test_util::make_test_data(|mut w| {
+            w.write(2, 0b10)?;  // check-bits
+            w.write(2, 0)?;     // PES_scrambling_control
+            w.write(4, 0)?;     // pes_priority, data_alignment_indicator, copyright, original_or_copy
+            w.write(2, 0b10)?;  // PTS_DTS_flags
+            w.write(1, 1)?;     // ESCR_flag
+            w.write(1, 1)?;     // ES_rate_flag
+            w.write(4, 0)?;     // DSM_trick_mode_flag .. PES_extension_flag
+            w.write(8, 14)?;    // PES_header_data_length
+            write_ts(&mut w, 1234, 0b0010)?;  // PTS
+            // ESCR
+            let escr_base: u64 = 0b1_0101_0101_0101_0101_0101_0101_0101_0101;
+            w.write(2, 0b11)?;  // reserved
+            w.write(3, escr_base >> 30)?;
+            w.write(1, 1)?;     // marker_bit
+            w.write(15, escr_base >> 15 & 0x7fff)?;
+            w.write(1, 1)?;     // marker_bit
+            w.write(15, escr_base & 0x7fff)?;
+            w.write(1, 1)?;     // marker_bit
+            w.write(9, 299)?;   // ESCR_extension
+            w.write(1, 1)?;     // marker_bit
+            // ES_rate
+            w.write(1, 1)?;     // marker_bit
+            w.write(22, 0b10_1010_1010_1010_1010_1010)?;
+            w.write(1, 1)       // marker_bit
+        });
+        let parsed = pes::PesParsedContents::from_bytes(&data[..]).unwrap();
+        assert_eq!(parsed.escr(), Ok(packet::PCR::from_parts(0b1_0101_0101_0101_0101_0101_0101_0101_0101, 299)));
+        assert_eq!(parsed.es_rate(), Ok(0b10_1010_1010_1010_1010_1010));
+        assert_matches!(parsed.pts_dts(), pes::PtsDts::PtsOnly(Ok(_)));
+
+        // with PES_header_data_length too small to include the ES_rate,
+        let mut short = data.clone();
+        short[2] = 12;
+        let parsed = pes::PesParsedContents::from_bytes(&short[..]).unwrap();
+        assert!(parsed.escr().is_ok());
+        assert_eq!(parsed.es_rate(), Err(pes::PesError::NotEnoughData));
+    }
+
+    #[test]
+    fn dsm_trick_mode() {
+        let make = |es_rate: bool, trick_mode: u8| test_util::make_test_data(|mut w| {
+            w.write(2, 0b10)?;  // check-bits
+            w.write(2, 0)?;     // PES_scrambling_control
+            w.write(4, 0)?;     // pes_priority, data_alignment_indicator, copyright, original_or_copy
+            w.write(2, 0b00)?;  // PTS_DTS_flags
+            w.write(1, 0)?;     // ESCR_flag
+            w.write_bit(es_rate)?;  // ES_rate_flag
+            w.write(1, 1)?;     // DSM_trick_mode_flag
+            w.write(3, 0)?;     // additional_copy_info_flag, PES_CRC_flag, PES_extension_flag
+            w.write(8, if es_rate { 4 } else { 1 })?;  // PES_header_data_length
+            if es_rate {
+                w.write(1, 1)?;     // marker_bit
+                w.write(22, 1234)?;
+                w.write(1, 1)?;     // marker_bit
+            }
+            w.write(8, trick_mode)
+        });
+        let data = make(false, 0b0001_0101);  // fast-forward, complete frame, intra_slice_refresh, frequency_truncation=1
+        let parsed = pes::PesParsedContents::from_bytes(&data[..]).unwrap();
+        assert_eq!(parsed.dsm_trick_mode(), Ok(pes::TrickMode::FastForward {
+            field_id: pes::FieldId::CompleteFrame,
+            intra_slice_refresh: true,
+            frequency_truncation: 1,
+        }));
+        let data = make(true, 0b0010_0111);  // slow-motion, rep_cntrl=7
+        let parsed = pes::PesParsedContents::from_bytes(&data[..]).unwrap();
+        assert_eq!(parsed.es_rate(), Ok(1234));
+        assert_eq!(parsed.dsm_trick_mode(), Ok(pes::TrickMode::SlowMotion { rep_cntrl: 7 }));
+        let data = make(false, 0b0100_1000);  // freeze-frame, bottom field
+        let parsed = pes::PesParsedContents::from_bytes(&data[..]).unwrap();
+        assert_eq!(parsed.dsm_trick_mode(), Ok(pes::TrickMode::FreezeFrame { field_id: pes::FieldId::BottomOnly }));
+        let data = make(false, 0b1110_0011);  // reserved
+        let parsed = pes::PesParsedContents::from_bytes(&data[..]).unwrap();
+        assert_eq!(parsed.dsm_trick_mode(), Ok(pes::TrickMode::Reserved(0b111, 0b11)));
+        assert_eq!(parsed.escr(), Err(pes::PesError::FieldNotPresent));
+    }
+
+    #[test]
+    fn crc_and_extension() {
+        let make = |header_data_length: u8| test_util::make_test_data(|mut w| {
+            w.write(2, 0b10)?;  // check-bits
+            w.write(6, 0)?;     // PES_scrambling_control .. original_or_copy
+            w.write(2, 0)?;     // PTS_DTS_flags
+            w.write(3, 0)?;     // ESCR_flag, ES_rate_flag, DSM_trick_mode_flag
+            w.write_bit(true)?; // additional_copy_info_flag
+            w.write_bit(true)?; // PES_CRC_flag
+            w.write_bit(true)?; // PES_extension_flag
+            w.write(8, header_data_length)?;
+            w.write_bit(true)?; // marker_bit
+            w.write(7, 0x55)?;  // additional_copy_info
+            w.write(16, 0xbeef)?;  // previous_PES_packet_CRC
+            // PES_extension,
+            w.write_bit(true)?;  // PES_private_data_flag
+            w.write_bit(true)?;  // pack_header_field_flag
+            w.write_bit(true)?;  // program_packet_sequence_counter_flag
+            w.write_bit(true)?;  // P-STD_buffer_flag
+            w.write(3, 0b111)?;  // reserved
+            w.write_bit(false)?; // PES_extension_flag_2
+            for i in 0..16 {
+                w.write(8, i)?;  // PES_private_data
+            }
+            w.write(8, 2)?;     // pack_field_length
+            w.write(16, 0xabcd)?;  // pack_header
+            w.write_bit(true)?; // marker_bit
+            w.write(7, 99)?;    // program_packet_sequence_counter
+            w.write_bit(true)?; // marker_bit
+            w.write_bit(false)?;  // MPEG1_MPEG2_identifier
+            w.write(6, 3)?;     // original_stuff_length
+            w.write(2, 0b01)?;
+            w.write_bit(true)?; // P-STD_buffer_scale
+            w.write(13, 224)    // P-STD_buffer_size
+        });
+        let data = make(28);
+        let parsed = pes::PesParsedContents::from_bytes(&data[..]).unwrap();
+        assert_eq!(parsed.additional_copy_info(), Ok(0x55));
+        assert_eq!(parsed.previous_packet_crc(), Ok(0xbeef));
+        let ext = parsed.pes_extension().unwrap();
+        assert_eq!(ext.pes_private_data(), Ok(&(0..16).collect::<Vec<u8>>()[..]));
+        assert_eq!(ext.pack_header(), Ok(&[0xab, 0xcd][..]));
+        assert_eq!(ext.program_packet_sequence_counter(), Ok(pes::ProgramPacketSequenceCounter {
+            counter: 99,
+            mpeg1_mpeg2_identifier: false,
+            original_stuff_length: 3,
+        }));
+        let p_std_buffer = ext.p_std_buffer().unwrap();
+        assert_eq!(p_std_buffer, pes::PStdBuffer { scale: true, size: 224 });
+        assert_eq!(p_std_buffer.size_bytes(), 224 * 1024);
+        assert!(parsed.payload().is_empty());
+
+        // a PES_header_data_length which excludes the last field,
+        let data = make(26);
+        let parsed = pes::PesParsedContents::from_bytes(&data[..]).unwrap();
+        let ext = parsed.pes_extension().unwrap();
+        assert!(ext.program_packet_sequence_counter().is_ok());
+        assert_eq!(ext.p_std_buffer(), Err(pes::PesError::NotEnoughData));
+        assert_eq!(parsed.escr(), Err(pes::PesError::FieldNotPresent));
+    }
+
+    #[test]
+    fn header_data_length_too_long() {
+        let data = test_util::make_test_data(|mut w| {
+            w.write(2, 0b10)?;  // check-bits
+            w.write(14, 0)?;    // various flags
+            w.write(8, 200)?;   // PES_header_data_length
+            w.write(8, 0xff)    // stuffing_byte
+        });
+        let parsed = pes::PesParsedContents::from_bytes(&data[..]).unwrap();
+        assert!(parsed.payload().is_empty());
+    }
+
     #[test]
     fn pts() {
         let pts_prefix = 0b0010;
-        let pts = make_test_data(|mut w| {
+        let pts = test_util::make_test_data(|mut w| {
             write_ts(&mut w, 0b1_0101_0101_0101_0101_0101_0101_0101_0101, pts_prefix)
         });
         let a = pes::Timestamp::from_pts_bytes(&pts[..]).unwrap().value();
@@ -550,7 +1358,7 @@ mod test {
     #[test]
     fn dts() {
         let pts_prefix = 0b0001;
-        let pts = make_test_data(|mut w| {
+        let pts = test_util::make_test_data(|mut w| {
             write_ts(&mut w, 0b0_1010_1010_1010_1010_1010_1010_1010_1010, pts_prefix)
         });
         let a = pes::Timestamp::from_dts_bytes(&pts[..]).unwrap().value();
@@ -561,7 +1369,7 @@ mod test {
     #[test]
     fn timestamp_ones() {
         let pts_prefix = 0b0010;
-        let pts = make_test_data(|mut w| {
+        let pts = test_util::make_test_data(|mut w| {
             write_ts(&mut w, 0b1_1111_1111_1111_1111_1111_1111_1111_1111, pts_prefix)
         });
         let a = pes::Timestamp::from_pts_bytes(&pts[..]).unwrap().value();
@@ -572,7 +1380,7 @@ mod test {
     #[test]
     fn timestamp_zeros() {
         let pts_prefix = 0b0010;
-        let pts = make_test_data(|mut w| {
+        let pts = test_util::make_test_data(|mut w| {
             write_ts(&mut w, 0b0_0000_0000_0000_0000_0000_0000_0000_0000, pts_prefix)
         });
         let a = pes::Timestamp::from_pts_bytes(&pts[..]).unwrap().value();
@@ -583,7 +1391,7 @@ mod test {
     #[test]
     fn timestamp_bad_prefix() {
         let pts_prefix = 0b0010;
-        let mut pts = make_test_data(|mut w| {
+        let mut pts = test_util::make_test_data(|mut w| {
             write_ts(&mut w, 1234, pts_prefix)
         });
         // make the prefix bits invalid by flipping a 0 to a 1,
@@ -594,7 +1402,7 @@ mod test {
     #[test]
     fn timestamp_bad_marker() {
         let pts_prefix = 0b0010;
-        let mut pts = make_test_data(|mut w| {
+        let mut pts = test_util::make_test_data(|mut w| {
             write_ts(&mut w, 1234, pts_prefix)
         });
         // make the first maker_bit (at index 7) invalid, by flipping a 1 to a 0,
@@ -606,6 +1414,13 @@ mod test {
         start_stream_called: bool,
         begin_packet_called: bool,
         continuity_error_called: bool,
+        packet_lengths: Vec<usize>,
+        current_length: Option<usize>,
+        /// for each packet, the payload bytes given with the header, where there are no optional
+        /// header fields
+        unparsed_payloads: Vec<Vec<u8>>,
+        /// `header.is_aligned()` for each packet
+        aligned: Vec<bool>,
     }
     impl MockState {
         fn new() -> MockState {
@@ -613,6 +1428,10 @@ mod test {
                 start_stream_called: false,
                 begin_packet_called: false,
                 continuity_error_called: false,
+                packet_lengths: vec!(),
+                current_length: None,
+                unparsed_payloads: vec!(),
+                aligned: vec!(),
             }
         }
     }
@@ -630,12 +1449,23 @@ mod test {
         fn start_stream(&mut self) {
             self.state.borrow_mut().start_stream_called = true;
         }
-        fn begin_packet(&mut self, _header: pes::PesHeader) {
-            self.state.borrow_mut().begin_packet_called = true;
+        fn begin_packet(&mut self, header: pes::PesHeader) {
+            let mut state = self.state.borrow_mut();
+            state.begin_packet_called = true;
+            state.current_length = Some(header.buf.len());
+            state.aligned.push(header.is_aligned());
+            if let pes::PesContents::Payload(data) = header.contents() {
+                state.unparsed_payloads.push(data.to_vec());
+            }
         }
-        fn continue_packet(&mut self, _data: &[u8]) {
+        fn continue_packet(&mut self, data: &[u8]) {
+            let mut state = self.state.borrow_mut();
+            state.current_length = state.current_length.map(|l| l + data.len());
         }
         fn end_packet(&mut self) {
+            let mut state = self.state.borrow_mut();
+            let len = state.current_length.take().expect("end_packet() without begin_packet()");
+            state.packet_lengths.push(len);
         }
         fn continuity_error(&mut self) {
             self.state.borrow_mut().continuity_error_called = true;
@@ -664,4 +1494,164 @@ mod test {
             assert!(state.continuity_error_called);
         }
     }
+
+    #[test]
+    fn unbounded_pes_packets() {
+        let state = std::rc::Rc::new(std::cell::RefCell::new(MockState::new()));
+        let mut pes_consumer = pes::PesPacketConsumer::new(MockElementaryStreamConsumer::new(state.clone()));
+        let first = test_util::pes(0, 175);
+        let second = test_util::pes(0, 10);
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 0, &first[..])[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, false, 1, &[0xbb; 184][..])[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, false, 2, &[0xbb; 100][..])[..]));
+        assert!(state.borrow().packet_lengths.is_empty());
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 3, &second[..])[..]));
+        assert_eq!(state.borrow().packet_lengths, vec!(184 + 184 + 100));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, false, 4, &[0xbb; 50][..])[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 5, &test_util::pes(0, 0)[..])[..]));
+        assert_eq!(state.borrow().packet_lengths, vec!(184 + 184 + 100, 19 + 50));
+    }
+
+    #[test]
+    fn bounded_pes_packet() {
+        let state = std::rc::Rc::new(std::cell::RefCell::new(MockState::new()));
+        let mut pes_consumer = pes::PesPacketConsumer::new(MockElementaryStreamConsumer::new(state.clone()));
+        // PES_packet_length covers 3 bytes of optional header, plus 200 bytes of data
+        let pes = test_util::pes(203, 200);
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 0, &pes[..184])[..]));
+        assert!(state.borrow().packet_lengths.is_empty());
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, false, 1, &pes[184..])[..]));
+        // the packet is complete, without waiting for the next payload_unit_start_indicator
+        assert_eq!(state.borrow().packet_lengths, vec!(209));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 2, &test_util::pes(3, 0)[..])[..]));
+        assert_eq!(state.borrow().packet_lengths, vec!(209, 9));
+    }
+
+    #[test]
+    fn padding_stream_skipped() {
+        let state = std::rc::Rc::new(std::cell::RefCell::new(MockState::new()));
+        let mut pes_consumer = pes::PesPacketConsumer::new(MockElementaryStreamConsumer::new(state.clone()));
+        let mut audio = test_util::pes(13, 10);
+        audio[3] = 0xC0;  // stream_id
+        // a padding_stream PES packet of 300 bytes, spanning two TS packets
+        let mut padding = vec!(0, 0, 1, 0xBE, 0x01, 0x2c);
+        padding.resize(6 + 300, 0xff);
+        assert!(pes::PesHeader::from_bytes(&padding[..]).unwrap().is_padding());
+        assert!(!pes::PesHeader::from_bytes(&audio[..]).unwrap().is_padding());
+
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 0, &audio[..])[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 1, &padding[..184])[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, false, 2, &padding[184..])[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 3, &audio[..])[..]));
+        assert_eq!(state.borrow().packet_lengths, vec!(19, 19));
+        assert!(state.borrow().unparsed_payloads.is_empty());
+        assert!(!state.borrow().continuity_error_called);
+    }
+
+    #[test]
+    fn is_aligned() {
+        let state = std::rc::Rc::new(std::cell::RefCell::new(MockState::new()));
+        let mut pes_consumer = pes::PesPacketConsumer::new(MockElementaryStreamConsumer::new(state.clone()));
+        let mut aligned = test_util::pes(0, 10);
+        aligned[6] |= 0b0000_0100;  // data_alignment_indicator
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 0, &aligned[..])[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 1, &test_util::pes(0, 10)[..])[..]));
+        // bounded, and complete within one TS packet
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 2, &test_util::pes(13, 10)[..])[..]));
+        assert_eq!(state.borrow().aligned, vec!(true, false, false));
+        assert_eq!(state.borrow().packet_lengths.len(), 3);
+    }
+
+    packet_filter_switch!{
+        PesFilterSwitch<PesDemuxContext> {
+            Pes: pes::BufferedPesFilter<PesDemuxContext>,
+            Nul: demultiplex::NullPacketFilter<PesDemuxContext>,
+        }
+    }
+    demux_context!(PesDemuxContext, PesStreamConstructor);
+    pub struct PesStreamConstructor;
+    impl demultiplex::StreamConstructor for PesStreamConstructor {
+        type F = PesFilterSwitch;
+
+        fn construct(&mut self, _req: demultiplex::FilterRequest) -> Self::F {
+            PesFilterSwitch::Nul(demultiplex::NullPacketFilter::new())
+        }
+    }
+
+    #[test]
+    fn buffered_pes_filter() {
+        let mut ctx = PesDemuxContext::new(PesStreamConstructor);
+        let delivered = std::rc::Rc::new(std::cell::RefCell::new(vec!()));
+        let d = delivered.clone();
+        // being free of closure type parameters, the filter can be named in a filter switch
+        let mut filter = PesFilterSwitch::Pes(pes::BufferedPesFilter::new(pes::BufferedPesFilter::<PesDemuxContext>::DEFAULT_MAX_SIZE, move |_ctx: &mut PesDemuxContext, pes: pes::BufferedPesPacket| {
+            let pts = match pes.pts_dts() {
+                pes::PtsDts::PtsOnly(Ok(pts)) => Some(pts.value()),
+                _ => None,
+            };
+            d.borrow_mut().push((pes.header().stream_id(), pts, pes.payload().to_vec()));
+        }));
+        // an audio PES packet with a PTS of 1, and 300 bytes of payload
+        let mut audio = vec!(
+            0, 0, 1, 0xc0, 0x01, 0x34,   // PES_packet_length of 308
+            0x80, 0x80, 5,               // PTS only
+            0x21, 0x00, 0x01, 0x00, 0x03,
+        );
+        audio.resize(audio.len() + 300, 0xaa);
+        filter.consume(&mut ctx, packet::Packet::new(&test_util::stuffed_packet(0x101, true, 0, &audio[..184])[..]));
+        assert!(delivered.borrow().is_empty());
+        filter.consume(&mut ctx, packet::Packet::new(&test_util::stuffed_packet(0x101, false, 1, &audio[184..])[..]));
+        assert_eq!(*delivered.borrow(), vec!((0xc0, Some(1), vec![0xaa; 300])));
+
+        // an unbounded PES packet is delivered when the next begins
+        filter.consume(&mut ctx, packet::Packet::new(&test_util::stuffed_packet(0x101, true, 2, &test_util::pes(0, 10)[..])[..]));
+        assert_eq!(delivered.borrow().len(), 1);
+        filter.consume(&mut ctx, packet::Packet::new(&test_util::stuffed_packet(0x101, true, 3, &test_util::pes(3, 0)[..])[..]));
+        assert_eq!(delivered.borrow()[1], (0xe0, None, vec![0xaa; 10]));
+        assert_eq!(delivered.borrow()[2], (0xe0, None, vec![]));
+
+        // PES packets larger than the limit are discarded
+        let count = std::rc::Rc::new(std::cell::Cell::new(0));
+        let c = count.clone();
+        let mut filter = pes::BufferedPesFilter::new(200, move |_ctx: &mut PesDemuxContext, pes: pes::BufferedPesPacket| {
+            c.set(c.get() + 1);
+            assert_eq!(pes.into_vec().len(), 9 + 20);
+        });
+        filter.consume(&mut ctx, packet::Packet::new(&test_util::stuffed_packet(0x101, true, 0, &audio[..184])[..]));
+        filter.consume(&mut ctx, packet::Packet::new(&test_util::stuffed_packet(0x101, false, 1, &audio[184..])[..]));
+        filter.consume(&mut ctx, packet::Packet::new(&test_util::stuffed_packet(0x101, true, 2, &test_util::pes(23, 20)[..])[..]));
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn adaptation_field_only_packets() {
+        let state = std::rc::Rc::new(std::cell::RefCell::new(MockState::new()));
+        let mut pes_consumer = pes::PesPacketConsumer::new(MockElementaryStreamConsumer::new(state.clone()));
+        // before the first PES packet, and then within one; the continuity_counter does not
+        // advance for packets without payload
+        pes_consumer.consume(packet::Packet::new(&test_util::af_only_packet(0x101, false, 0)[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 1, &test_util::pes(0, 175)[..])[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::af_only_packet(0x101, false, 1)[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::af_only_packet(0x101, true, 1)[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, false, 2, &[0xbb; 100][..])[..]));
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 3, &test_util::pes(0, 0)[..])[..]));
+        let state = state.borrow();
+        assert!(!state.continuity_error_called);
+        assert_eq!(state.packet_lengths, vec!(184 + 100));
+    }
+
+    #[test]
+    fn private_stream_2_payload() {
+        let state = std::rc::Rc::new(std::cell::RefCell::new(MockState::new()));
+        let mut pes_consumer = pes::PesPacketConsumer::new(MockElementaryStreamConsumer::new(state.clone()));
+        // the data following PES_packet_length would look like the flags of a header having PTS
+        // and DTS, with a PES_header_data_length longer than the packet, if it were parsed
+        let data = [0x80, 0xc0, 0xff, 1, 2, 3, 4, 5];
+        let mut pes = vec!(0, 0, 1, 0xBF, 0, data.len() as u8);
+        pes.extend_from_slice(&data[..]);
+        pes_consumer.consume(packet::Packet::new(&test_util::stuffed_packet(0x101, true, 0, &pes[..])[..]));
+        let state = state.borrow();
+        assert_eq!(state.packet_lengths, vec!(pes.len()));
+        assert_eq!(state.unparsed_payloads, vec!(data.to_vec()));
+    }
 }
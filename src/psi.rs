@@ -19,9 +19,12 @@
 //! Note that the specific types of table such as Program Association Table are defined elsewhere
 //! with only the generic functionality in this module.
 
+pub mod pat;
+pub mod pmt;
+
 use packet;
-use hexdump;
 use mpegts_crc;
+use hex_slice::AsHex;
 
 
 /// Trait for types which process the data within a PSI section following the 12-byte
@@ -39,12 +42,12 @@ pub trait SectionProcessor {
     /// Note that the first 3 bytes of `section_data` contain the header fields that have also
     /// been supplied to this call in the `header` parameter.  This is to allow implementers to
     /// calculate a CRC over the whole section if required.
-    fn start_section<'a>(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, section_data: &'a[u8]);
-    fn continue_section<'a>(&mut self, ctx: &mut Self::Context, section_data: &'a[u8]);
+    fn start_section(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, section_data: &[u8]);
+    fn continue_section(&mut self, ctx: &mut Self::Context, section_data: &[u8]);
     fn reset(&mut self);
 }
 
-#[derive(Debug,PartialEq)]
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
 pub enum CurrentNext {
     Current,
     Next,
@@ -74,7 +77,7 @@ pub struct TableSyntaxHeader<'buf> {
 impl<'buf> TableSyntaxHeader<'buf> {
     pub const SIZE: usize = 5;
 
-    pub fn new(buf: &'buf[u8]) -> TableSyntaxHeader {
+    pub fn new(buf: &'buf[u8]) -> TableSyntaxHeader<'buf> {
         assert!(buf.len() >= Self::SIZE);
         TableSyntaxHeader {
             buf
@@ -96,8 +99,9 @@ impl<'buf> TableSyntaxHeader<'buf> {
         (self.buf[2] >> 1) & 0b00011111
     }
     /// Is this table applicable now, or will it become applicable at some future time.
-    /// NB I've not seen sample data that uses anything other than `CurrentNext::Current`, so
-    /// handling of tables with 'future' applicability may not actually work properly.
+    ///
+    /// The PAT and PMT handling in the `demultiplex` module ignores sections which are not yet
+    /// applicable, and acts on the table only once it is transmitted as `CurrentNext::Current`.
     pub fn current_next_indicator(&self) -> CurrentNext {
         CurrentNext::from(self.buf[2] & 1)
     }
@@ -140,28 +144,126 @@ where
 {
     type Context = P::Context;
 
-    fn section<'a>(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, table_syntax_header: &TableSyntaxHeader, data: &'a [u8]) {
+    fn section(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, table_syntax_header: &TableSyntaxHeader, data: &[u8]) {
         assert!(header.section_syntax_indicator);
-        if CRC_CHECK && mpegts_crc::sum32(data) != 0 {
-            println!(
-                "section crc check failed for table_id {}",
-                header.table_id,
-            );
-            hexdump::hexdump(data);
-            return;
+        if CRC_CHECK {
+            if let Some((computed, expected)) = crc_mismatch(data) {
+                warn!(
+                    "section CRC check failed for table_id {}: computed {:#010x}, but CRC_32 field holds {:#010x} (section_length {})",
+                    header.table_id,
+                    computed,
+                    expected,
+                    header.section_length,
+                );
+                debug!("section data: {:02x}", data.as_hex());
+                self.inner.crc_error(ctx, header, table_syntax_header);
+                return;
+            }
         }
         self.inner.section(ctx, header, table_syntax_header, data);
     }
 }
 
+/// Trait for types which handle complete sections passed on by
+/// [`CrcFlagWholeSectionSyntaxPayloadParser`](struct.CrcFlagWholeSectionSyntaxPayloadParser.html),
+/// which are told the result of the CRC check rather than having damaged sections withheld.
+pub trait FlaggedWholeSectionSyntaxPayloadParser {
+    type Context;
+
+    /// `crc_valid` is `false` if the section failed its CRC check, in which case any of the
+    /// section's content (including the headers) may be corrupt.
+    fn section(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, table_syntax_header: &TableSyntaxHeader, data: &[u8], crc_valid: bool);
+}
+
+/// An alternative to `CrcCheckWholeSectionSyntaxPayloadParser`, for tolerant applications such as
+/// stream monitoring tools, which passes every section through to the wrapped parser together
+/// with a flag indicating whether the CRC check passed, and counts the failures.
+pub struct CrcFlagWholeSectionSyntaxPayloadParser<P>
+where
+    P: FlaggedWholeSectionSyntaxPayloadParser
+{
+    inner: P,
+    crc_failures: u64,
+}
+impl<P> CrcFlagWholeSectionSyntaxPayloadParser<P>
+    where
+        P: FlaggedWholeSectionSyntaxPayloadParser
+{
+    pub fn new(inner: P) -> CrcFlagWholeSectionSyntaxPayloadParser<P> {
+        CrcFlagWholeSectionSyntaxPayloadParser {
+            inner,
+            crc_failures: 0,
+        }
+    }
+
+    /// The number of sections which have failed the CRC check so far.
+    pub fn crc_failures(&self) -> u64 {
+        self.crc_failures
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+    pub fn inner_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P> WholeSectionSyntaxPayloadParser for CrcFlagWholeSectionSyntaxPayloadParser<P>
+where
+    P: FlaggedWholeSectionSyntaxPayloadParser
+{
+    type Context = P::Context;
+
+    fn section(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, table_syntax_header: &TableSyntaxHeader, data: &[u8]) {
+        let crc_valid = !CRC_CHECK || crc_mismatch(data).is_none();
+        if !crc_valid {
+            debug!("section CRC check failed for table_id {} (section_length {})", header.table_id, header.section_length);
+            self.crc_failures += 1;
+        }
+        self.inner.section(ctx, header, table_syntax_header, data, crc_valid);
+    }
+}
+
+/// If the CRC of the given section is incorrect, returns the CRC computed over the section body
+/// together with the value of the trailing `CRC_32` field, in that order.
+fn crc_mismatch(data: &[u8]) -> Option<(u32, u32)> {
+    if mpeg2_crc32(data) == 0 {
+        return None;
+    }
+    if data.len() < 4 {
+        return Some((mpeg2_crc32(data), 0));
+    }
+    let (body, crc) = data.split_at(data.len() - 4);
+    let expected = u32::from(crc[0]) << 24 | u32::from(crc[1]) << 16 | u32::from(crc[2]) << 8 | u32::from(crc[3]);
+    Some((mpeg2_crc32(body), expected))
+}
+
 pub trait WholeSectionSyntaxPayloadParser {
     type Context;
 
-    fn section<'a>(&mut self, &mut Self::Context, header: &SectionCommonHeader, table_syntax_header: &TableSyntaxHeader, data: &'a [u8]);
+    fn section(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, table_syntax_header: &TableSyntaxHeader, data: &[u8]);
+
+    /// Called by `CrcCheckWholeSectionSyntaxPayloadParser` in place of `section()`, when the
+    /// section fails its CRC check.  The default implementation does nothing.
+    fn crc_error(&mut self, _ctx: &mut Self::Context, _header: &SectionCommonHeader, _table_syntax_header: &TableSyntaxHeader) {
+    }
+}
+
+/// Computes the _CRC-32/MPEG-2_ value of the given data, as used by the `CRC_32` field at the end
+/// of PSI sections.
+///
+/// To check a section, compute the value over the complete section _including_ the trailing
+/// `CRC_32` field, and the result will be `0` if the section is intact.  To generate the
+/// `CRC_32` field for a new section, compute the value over the section up to (but excluding)
+/// the `CRC_32` field, and append the result in big-endian byte order.
+pub fn mpeg2_crc32(data: &[u8]) -> u32 {
+    mpegts_crc::sum32(data)
 }
 
 pub fn section_syntax_payload(buf: &[u8]) -> &[u8] { &buf[SectionCommonHeader::SIZE+TableSyntaxHeader::SIZE..] }
 
+#[cfg(feature = "std")]
 enum BufferSectionState {
     Buffering(usize),
     Complete,
@@ -170,6 +272,7 @@ enum BufferSectionState {
 /// Implements `BufferSectionSyntaxParser` so that any sections that cross TS-packet boundaries
 /// are collected into a single byte-buffer for easier parsing.  In the common case that the
 /// section fits entirely in a single TS packet, the implementation is zero-copy.
+#[cfg(feature = "std")]
 pub struct BufferSectionSyntaxParser<P>
 where
     P: WholeSectionSyntaxPayloadParser
@@ -178,6 +281,7 @@ where
     state: BufferSectionState,
     parser: P,
 }
+#[cfg(feature = "std")]
 impl<P> BufferSectionSyntaxParser<P>
     where
         P: WholeSectionSyntaxPayloadParser
@@ -190,42 +294,43 @@ impl<P> BufferSectionSyntaxParser<P>
         }
     }
 }
+#[cfg(feature = "std")]
 impl<P> SectionSyntaxPayloadParser for BufferSectionSyntaxParser<P>
 where
     P: WholeSectionSyntaxPayloadParser
 {
     type Context = P::Context;
 
-    fn start_syntax_section<'a>(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, table_syntax_header: &TableSyntaxHeader, data: &'a [u8]) {
+    fn start_syntax_section(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, table_syntax_header: &TableSyntaxHeader, data: &[u8]) {
         if header.section_length <=  data.len() - SectionCommonHeader::SIZE {
             self.state = BufferSectionState::Complete;
             self.parser.section(ctx, header, table_syntax_header, &data[..header.section_length + SectionCommonHeader::SIZE])
         } else {
-            let to_read = if data.len() > header.section_length {
-                header.section_length
-            } else {
-                header.section_length - data.len()
-            };
+            let to_read = header.section_length + SectionCommonHeader::SIZE - data.len();
             self.state = BufferSectionState::Buffering(to_read);
             self.buf.clear();
             self.buf.extend_from_slice(data);
         }
     }
 
-    fn continue_syntax_section<'a>(&mut self, ctx: &mut Self::Context, data: &'a [u8]) {
+    fn continue_syntax_section(&mut self, ctx: &mut Self::Context, data: &[u8]) {
         match self.state {
             BufferSectionState::Complete => {
-                println!("attempt to add extra data when section already complete");
+                warn!("attempt to add extra data when section already complete");
             },
             BufferSectionState::Buffering(remaining) => {
-                assert!(remaining >= data.len());
-                let new_remaining = remaining - data.len();
+                // the final packet of the section may also contain stuffing bytes (or the start of
+                // the next section), which we don't want to buffer
+                let len = remaining.min(data.len());
+                self.buf.extend_from_slice(&data[..len]);
+                let new_remaining = remaining - len;
                 if new_remaining == 0 {
-                    let payload = section_syntax_payload(&self.buf[..]);
                     self.state = BufferSectionState::Complete;
-                    let header = SectionCommonHeader::new(&self.buf[..]);
+                    let header = SectionCommonHeader::new(&self.buf[..SectionCommonHeader::SIZE]);
                     let table_syntax_header = TableSyntaxHeader::new(&self.buf[SectionCommonHeader::SIZE..]);
-                    self.parser.section(ctx, &header, &table_syntax_header, payload);
+                    self.parser.section(ctx, &header, &table_syntax_header, &self.buf[..]);
+                } else {
+                    self.state = BufferSectionState::Buffering(new_remaining);
                 }
             }
         }
@@ -238,18 +343,26 @@ where
 
 /// A wrapper around some other implementation of `SectionSyntaxPayloadParser` that passes-through
 /// section data, unless the `TableSyntaxHeader` indicates a version_number which is the same as
-/// the last data that was passed though.
+/// the last data with the same `table_id` that was passed though.
 ///
 /// This avoids the underlying code needing to re-parse duplicate copies of the section, which are
 /// usually inserted periodically in the Transport Stream.
+///
+/// Sections having `current_next_indicator` of `CurrentNext::Next` are always passed through,
+/// and do not update the remembered version, so that the same version will not be discarded when
+/// it is later transmitted as the current table.
 pub struct DedupSectionSyntaxPayloadParser<SSPP>
 where
     SSPP: SectionSyntaxPayloadParser
 {
     inner: SSPP,
-    last_version: Option<u8>,
+    /// `(table_id, version)` of the last section seen for each of the most recent few
+    /// `table_id` values, so that a PID carrying more than one kind of table does not have one
+    /// table's sections discarded as duplicates of another's
+    last_versions: [Option<(u8, u8)>; DEDUP_TABLE_IDS],
     ignore_rest: bool,
 }
+const DEDUP_TABLE_IDS: usize = 4;
 impl<SSPP> DedupSectionSyntaxPayloadParser<SSPP>
     where
         SSPP: SectionSyntaxPayloadParser
@@ -257,7 +370,7 @@ impl<SSPP> DedupSectionSyntaxPayloadParser<SSPP>
     pub fn new(inner: SSPP) -> DedupSectionSyntaxPayloadParser<SSPP> {
         DedupSectionSyntaxPayloadParser {
             inner,
-            last_version: None,
+            last_versions: [None; DEDUP_TABLE_IDS],
             ignore_rest: false,
         }
     }
@@ -268,26 +381,39 @@ where
 {
     type Context = SSPP::Context;
 
-    fn start_syntax_section<'a>(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, table_syntax_header: &TableSyntaxHeader, data: &'a [u8]) {
-        if let Some(last) = self.last_version {
-            if last == table_syntax_header.version() {
-                self.ignore_rest = true;
-                return;
+    fn start_syntax_section(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, table_syntax_header: &TableSyntaxHeader, data: &[u8]) {
+        if table_syntax_header.current_next_indicator() == CurrentNext::Next {
+            self.ignore_rest = false;
+            self.inner.start_syntax_section(ctx, header, table_syntax_header, data);
+            return;
+        }
+        let version = table_syntax_header.version();
+        match self.last_versions.iter().position(|v| v.map(|(table_id, _)| table_id) == Some(header.table_id)) {
+            Some(i) => {
+                if self.last_versions[i] == Some((header.table_id, version)) {
+                    self.ignore_rest = true;
+                    return;
+                }
+                self.last_versions[i] = Some((header.table_id, version));
+            },
+            None => {
+                // forget the least recently added table_id, if all slots are in use
+                self.last_versions.rotate_right(1);
+                self.last_versions[0] = Some((header.table_id, version));
             }
         }
         self.ignore_rest = false;
-        self.last_version = Some(table_syntax_header.version());
         self.inner.start_syntax_section(ctx, header, table_syntax_header, data);
     }
 
-    fn continue_syntax_section<'a>(&mut self, ctx: &mut Self::Context, data: &'a [u8]) {
+    fn continue_syntax_section(&mut self, ctx: &mut Self::Context, data: &[u8]) {
         if !self.ignore_rest {
             self.inner.continue_syntax_section(ctx, data)
         }
     }
     fn reset(&mut self) {
         self.inner.reset();
-        self.last_version = None;
+        self.last_versions = [None; DEDUP_TABLE_IDS];
         self.ignore_rest = false;
     }
 }
@@ -299,12 +425,12 @@ pub trait SectionSyntaxPayloadParser {
     /// NB the `data` buffer passed to _will_ include the bytes which are represented by `header`
     /// and `table_syntax_header` (in order that the called code can check any CRC that covers the
     /// whole section).
-    fn start_syntax_section<'a>(&mut self,
+    fn start_syntax_section(&mut self,
                             ctx: &mut Self::Context,
                             header: &SectionCommonHeader,
-                            table_syntax_header: &TableSyntaxHeader, data: &'a [u8]);
+                            table_syntax_header: &TableSyntaxHeader, data: &[u8]);
 
-    fn continue_syntax_section<'a>(&mut self, ctx: &mut Self::Context, data: &'a [u8]);
+    fn continue_syntax_section(&mut self, ctx: &mut Self::Context, data: &[u8]);
 
     fn reset(&mut self);
 }
@@ -335,21 +461,21 @@ where
 {
     type Context = SP::Context;
 
-    fn start_section<'a>(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, data: &'a [u8]) {
+    fn start_section(&mut self, ctx: &mut Self::Context, header: &SectionCommonHeader, data: &[u8]) {
         if !header.section_syntax_indicator {
-            println!(
+            warn!(
                 "SectionSyntaxSectionProcessor requires that section_syntax_indicator be set in the section header"
             );
             self.ignore_rest = true;
             return;
         }
         if data.len() < SectionCommonHeader::SIZE + TableSyntaxHeader::SIZE {
-            println!("data too short for header (TODO: implement buffering)");
+            warn!("data too short for header (TODO: implement buffering)");
             self.ignore_rest = true;
             return;
         }
         if header.section_length > Self::SECTION_LIMIT {
-            println!("section_length={} is too large (limit {})", header.section_length, Self::SECTION_LIMIT);
+            warn!("table_id {:#x}: section_length={} is too large (limit {})", header.table_id, header.section_length, Self::SECTION_LIMIT);
             self.ignore_rest = true;
             return;
         }
@@ -358,7 +484,7 @@ where
         self.payload_parser.start_syntax_section(ctx, header, &table_syntax_header, data)
     }
 
-    fn continue_section<'a>(&mut self, ctx: &mut Self::Context, data: &'a [u8]) {
+    fn continue_section(&mut self, ctx: &mut Self::Context, data: &[u8]) {
         if !self.ignore_rest {
             self.payload_parser.continue_syntax_section(ctx, data)
         }
@@ -368,11 +494,18 @@ where
     }
 }
 
+/// The three byte header common to every section, whether or not it uses the 'section syntax'.
 #[derive(Debug)]
 pub struct SectionCommonHeader {
     pub table_id: u8,
+    /// If `true`, a [`TableSyntaxHeader`](struct.TableSyntaxHeader.html) follows this header,
+    /// and the section ends with a CRC.
     pub section_syntax_indicator: bool,
+    /// Always `false` for the tables defined by _ISO/IEC 13818-1_, but may be used by the
+    /// definition of a `private_section`.
     pub private_indicator: bool,
+    /// The number of bytes in the section following this header (including any
+    /// `TableSyntaxHeader` and CRC).
     pub section_length: usize,
 }
 
@@ -392,13 +525,27 @@ impl SectionCommonHeader {
 /// A `PacketConsumer` for buffering Program Specific Information, which may be split across
 /// multiple TS packets, and passing a complete PSI table to the given `SectionProcessor` when a
 /// complete, valid section has been received.
+///
+/// If the `continuity_counter` shows that a packet has been lost, any partially received section
+/// is abandoned (by calling `reset()` on the `SectionProcessor`) rather than being passed on with
+/// data missing.  Duplicate packets (which repeat the previous `continuity_counter` value) are
+/// ignored.
 pub struct SectionPacketConsumer<P>
 where
     P: SectionProcessor,
 {
     parser: P,
+    last_cc: Option<packet::ContinuityCounter>,
+    /// set when the current section was rejected, so that its continuation is not passed on
+    skip_section: bool,
 }
 
+/// The largest `section_length` permitted for any kind of section (that of a `private_section`;
+/// the PSI tables defined by _ISO/IEC 13818-1_ are limited further, to `1021`).
+const MAX_SECTION_LENGTH: usize = 4093;
+/// The size of the `CRC_32` field that ends every section using the section syntax.
+const CRC_SIZE: usize = 4;
+
 
 #[cfg(not(fuzz))]
 const CRC_CHECK: bool = true;
@@ -412,80 +559,105 @@ where
     pub fn new(parser: P) -> SectionPacketConsumer<P> {
         SectionPacketConsumer {
             parser,
+            last_cc: None,
+            skip_section: false,
+        }
+    }
+
+    /// Returns `None` if the given packet is a duplicate which should be ignored, and otherwise
+    /// whether it follows on from the previous packet.  If it does not, any incomplete section is
+    /// dropped.
+    fn check_continuity(&mut self, pk: &packet::Packet) -> Option<bool> {
+        let cc = pk.continuity_counter();
+        let continuous = match self.last_cc {
+            None => true,
+            Some(last) if cc == last => return None,
+            Some(last) => cc.follows(last),
+        };
+        if !continuous {
+            let signalled = pk.adaptation_field().is_some_and(|af| af.discontinuity_indicator());
+            if !signalled {
+                warn!("PID {}: PSI continuity error, last={} this={}", pk.pid(), self.last_cc.unwrap().count(), cc.count());
+            }
+            self.parser.reset();
         }
+        self.last_cc = Some(cc);
+        Some(continuous)
     }
 
     pub fn consume(&mut self, ctx: &mut Ctx, pk: packet::Packet) {
         match pk.payload() {
             Some(pk_buf) => {
+                let continuous = match self.check_continuity(&pk) {
+                    Some(continuous) => continuous,
+                    None => return,
+                };
                 if pk.payload_unit_start_indicator() {
                     // this packet payload contains the start of a new PSI section
                     let pointer = pk_buf[0] as usize;
                     let section_data = &pk_buf[1..];
                     if pointer > 0 {
                         if pointer >= section_data.len() {
-                            println!("PSI pointer beyond end of packet payload");
+                            warn!("PSI pointer beyond end of packet payload");
                             self.parser.reset();
                             return;
                         }
-                        let remainder = &section_data[..pointer];
-                        self.parser.continue_section(ctx, remainder);
+                        // the bytes before the pointed-to position complete the preceding
+                        // section, unless it was lost or rejected
+                        if continuous && !self.skip_section {
+                            let remainder = &section_data[..pointer];
+                            self.parser.continue_section(ctx, remainder);
+                        }
                         // the following call to begin_new_section() will assert that
                         // append_to_current() just finalised the preceding section
                     }
                     let next_sect = &section_data[pointer..];
                     if next_sect.len() < SectionCommonHeader::SIZE {
-                        println!("TODO: not enough bytes to read section header - implement buffering");
+                        warn!("TODO: not enough bytes to read section header - implement buffering");
                         self.parser.reset();
                         return;
                     }
                     let header = SectionCommonHeader::new(&next_sect[..SectionCommonHeader::SIZE]);
+                    let min_length = if header.section_syntax_indicator {
+                        TableSyntaxHeader::SIZE + CRC_SIZE
+                    } else {
+                        0
+                    };
+                    if header.section_length > MAX_SECTION_LENGTH || header.section_length < min_length {
+                        warn!("PID {}: ignoring section with table_id {:#x}, having invalid section_length {}", pk.pid(), header.table_id, header.section_length);
+                        self.parser.reset();
+                        self.skip_section = true;
+                        return;
+                    }
+                    self.skip_section = false;
                     self.parser.start_section(ctx, &header, next_sect);
-                } else {
+                } else if continuous && !self.skip_section {
                     // this packet is a continuation of an existing PSI section
                     self.parser.continue_section(ctx, pk_buf);
                 }
             }
             None => {
-                println!("no payload present in PSI packet");
+                // packets carrying only an adaptation field are expected, and do not advance the
+                // continuity_counter
+                if pk.has_payload() {
+                    warn!("no payload present in PSI packet");
+                }
             }
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod test {
     use super::*;
+    use test_util::{NullDemuxContext, NullStreamConstructor};
     use packet::Packet;
-    use demultiplex;
-
-    packet_filter_switch!{
-        NullFilterSwitch<NullDemuxContext> {
-            Pat: demultiplex::PatPacketFilter<NullDemuxContext>,
-            Pmt: demultiplex::PmtPacketFilter<NullDemuxContext>,
-            Nul: demultiplex::NullPacketFilter<NullDemuxContext>,
-        }
-    }
-    demux_context!(NullDemuxContext, NullStreamConstructor);
-    pub struct NullStreamConstructor;
-    impl demultiplex::StreamConstructor for NullStreamConstructor {
-        type F = NullFilterSwitch;
-
-        fn construct(&mut self, req: demultiplex::FilterRequest) -> Self::F {
-            match req {
-                demultiplex::FilterRequest::ByPid(0) => NullFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
-                demultiplex::FilterRequest::ByPid(_) => NullFilterSwitch::Nul(demultiplex::NullPacketFilter::new()),
-                demultiplex::FilterRequest::ByStream(_stype, _pmt_section, _stream_info) => NullFilterSwitch::Nul(demultiplex::NullPacketFilter::new()),
-                demultiplex::FilterRequest::Pmt{pid, program_number} => NullFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
-            }
-        }
-    }
 
     struct NullSectionProcessor;
     impl SectionProcessor for NullSectionProcessor {
         type Context = NullDemuxContext;
-        fn start_section<'a>(&mut self, _ctx: &mut Self::Context, _header: &SectionCommonHeader, _section_data: &'a [u8]) { }
-        fn continue_section<'a>(&mut self, _ctx: &mut Self::Context, _section_data: &'a [u8]) { }
+        fn start_section(&mut self, _ctx: &mut Self::Context, _header: &SectionCommonHeader, _section_data: &[u8]) { }
+        fn continue_section(&mut self, _ctx: &mut Self::Context, _section_data: &[u8]) { }
         fn reset(&mut self) { }
     }
 
@@ -500,6 +672,21 @@ mod test {
         psi_buf.consume(&mut ctx, pk);
     }
 
+    #[test]
+    fn common_header() {
+        // a private_section without section syntax,
+        let header = SectionCommonHeader::new(&[0x80, 0b0111_0001, 0x02]);
+        assert_eq!(header.table_id, 0x80);
+        assert!(!header.section_syntax_indicator);
+        assert!(header.private_indicator);
+        assert_eq!(header.section_length, 0x102);
+
+        let header = SectionCommonHeader::new(&[0x02, 0b1011_0000, 0x0d]);
+        assert!(header.section_syntax_indicator);
+        assert!(!header.private_indicator);
+        assert_eq!(header.section_length, 13);
+    }
+
     #[test]
     fn small_section() {
         let mut buf = [0u8; 188];
@@ -512,4 +699,139 @@ mod test {
         let mut ctx = NullDemuxContext::new(NullStreamConstructor);
         psi_buf.consume(&mut ctx, pk);
     }
+
+    #[derive(Default)]
+    struct CountingSectionProcessor {
+        starts: usize,
+        continues: usize,
+    }
+    impl SectionProcessor for CountingSectionProcessor {
+        type Context = NullDemuxContext;
+        fn start_section(&mut self, _ctx: &mut Self::Context, _header: &SectionCommonHeader, _section_data: &[u8]) {
+            self.starts += 1;
+        }
+        fn continue_section(&mut self, _ctx: &mut Self::Context, _section_data: &[u8]) {
+            self.continues += 1;
+        }
+        fn reset(&mut self) { }
+    }
+
+    #[test]
+    fn invalid_section_length() {
+        let make_packet = |pusi: bool, cc: u8, section_length: u16| {
+            let mut buf = [0xffu8; 188];
+            buf[0] = 0x47;
+            buf[1] = if pusi { 0b01000000 } else { 0 };
+            buf[3] = 0b00010000 | cc; // PayloadOnly
+            if pusi {
+                buf[4] = 0;  // pointer_field
+                buf[5] = 0x02;  // table_id
+                buf[6] = 0b1011_0000 | (section_length >> 8) as u8;
+                buf[7] = section_length as u8;
+            }
+            buf
+        };
+        let mut psi_buf = SectionPacketConsumer::new(CountingSectionProcessor::default());
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        // longer than any section may be, followed by an apparent continuation,
+        psi_buf.consume(&mut ctx, Packet::new(&make_packet(true, 0, 0xfff)[..]));
+        psi_buf.consume(&mut ctx, Packet::new(&make_packet(false, 1, 0)[..]));
+        // too short to hold the table syntax header and CRC,
+        psi_buf.consume(&mut ctx, Packet::new(&make_packet(true, 2, 8)[..]));
+        assert_eq!(psi_buf.parser.starts, 0);
+        assert_eq!(psi_buf.parser.continues, 0);
+        // the bytes preceding the next section, per its pointer_field, belong to the rejected
+        // section and so are not passed on either
+        let mut pk = make_packet(true, 3, 8);
+        pk[4..8].copy_from_slice(&[3, 0xff, 0xff, 0xff]);
+        psi_buf.consume(&mut ctx, Packet::new(&pk[..]));
+        assert_eq!(psi_buf.parser.continues, 0);
+        // a valid section_length is processed as normal
+        psi_buf.consume(&mut ctx, Packet::new(&make_packet(true, 4, 300)[..]));
+        psi_buf.consume(&mut ctx, Packet::new(&make_packet(false, 5, 0)[..]));
+        assert_eq!(psi_buf.parser.starts, 1);
+        assert_eq!(psi_buf.parser.continues, 1);
+    }
+
+    struct CollectingParser {
+        sections: Vec<Vec<u8>>,
+    }
+    impl WholeSectionSyntaxPayloadParser for CollectingParser {
+        type Context = NullDemuxContext;
+        fn section(&mut self, _ctx: &mut Self::Context, _header: &SectionCommonHeader, _table_syntax_header: &TableSyntaxHeader, data: &[u8]) {
+            self.sections.push(data.to_vec());
+        }
+    }
+
+    #[test]
+    fn crc32() {
+        // PAT from the demultiplex::test::pat() test data
+        let section = data_encoding::base16::decode(b"00B00D0001C100000001E1E02D507804").unwrap();
+        assert_eq!(mpeg2_crc32(&section[..]), 0);
+        let (body, crc) = section.split_at(section.len() - 4);
+        assert_eq!(mpeg2_crc32(body), u32::from(crc[0]) << 24 | u32::from(crc[1]) << 16 | u32::from(crc[2]) << 8 | u32::from(crc[3]));
+        assert_eq!(mpeg2_crc32(b"123456789"), 0x0376_e6e7);
+    }
+
+    #[test]
+    fn crc_check() {
+        let mut section = data_encoding::base16::decode(b"00B00D0001C100000001E1E02D507804").unwrap();
+        assert_eq!(crc_mismatch(&section[..]), None);
+        let mut parser = CrcCheckWholeSectionSyntaxPayloadParser::new(CollectingParser { sections: vec![] });
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        section[11] = 0xe2;  // corrupt the PID
+        assert_eq!(crc_mismatch(&section[..]), Some((mpeg2_crc32(&section[..12]), 0x2d50_7804)));
+        {
+            let header = SectionCommonHeader::new(&section[..SectionCommonHeader::SIZE]);
+            let table_syntax_header = TableSyntaxHeader::new(&section[SectionCommonHeader::SIZE..]);
+            parser.section(&mut ctx, &header, &table_syntax_header, &section[..]);
+        }
+        assert!(parser.inner.sections.is_empty());
+    }
+
+    struct FlagCollectingParser {
+        sections: Vec<(Vec<u8>, bool)>,
+    }
+    impl FlaggedWholeSectionSyntaxPayloadParser for FlagCollectingParser {
+        type Context = NullDemuxContext;
+        fn section(&mut self, _ctx: &mut Self::Context, _header: &SectionCommonHeader, _table_syntax_header: &TableSyntaxHeader, data: &[u8], crc_valid: bool) {
+            self.sections.push((data.to_vec(), crc_valid));
+        }
+    }
+
+    #[test]
+    fn crc_flag() {
+        let good = data_encoding::base16::decode(b"00B00D0001C100000001E1E02D507804").unwrap();
+        let mut bad = good.clone();
+        bad[11] = 0xe2;  // corrupt the PID
+        let mut parser = CrcFlagWholeSectionSyntaxPayloadParser::new(FlagCollectingParser { sections: vec![] });
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        for section in &[&good, &bad] {
+            let header = SectionCommonHeader::new(&section[..SectionCommonHeader::SIZE]);
+            let table_syntax_header = TableSyntaxHeader::new(&section[SectionCommonHeader::SIZE..]);
+            parser.section(&mut ctx, &header, &table_syntax_header, &section[..]);
+        }
+        assert_eq!(parser.inner().sections, vec![(good, true), (bad, false)]);
+        assert_eq!(parser.crc_failures(), 1);
+    }
+
+    #[test]
+    fn buffer_section_across_packets() {
+        // a section of 300 bytes in total (section_length of 297), delivered in three pieces,
+        // the last of which is followed by stuffing
+        let mut section = vec![0x02, 0b1011_0001, 0x29];
+        section.extend((0..297).map(|i| i as u8));
+        let mut parser = BufferSectionSyntaxParser::new(CollectingParser { sections: vec![] });
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+        let header = SectionCommonHeader::new(&section[..SectionCommonHeader::SIZE]);
+        assert_eq!(297, header.section_length);
+        let table_syntax_header = TableSyntaxHeader::new(&section[SectionCommonHeader::SIZE..]);
+        parser.start_syntax_section(&mut ctx, &header, &table_syntax_header, &section[..183]);
+        parser.continue_syntax_section(&mut ctx, &section[183..250]);
+        assert!(parser.parser.sections.is_empty());
+        let mut last = section[250..].to_vec();
+        last.extend_from_slice(&[0xff; 20]);
+        parser.continue_syntax_section(&mut ctx, &last[..]);
+        assert_eq!(parser.parser.sections, vec![section]);
+    }
 }
@@ -0,0 +1,81 @@
+//! Views over the sections of the _Program Association Table_ (PAT), which lists the programs
+//! within the Transport Stream, and the PID of the PMT for each.
+
+use packet;
+
+/// A single entry in the PAT, giving the PID of the PMT for one program.
+#[derive(Clone,Debug)]
+pub struct ProgramDescriptor<'buf> {
+    data: &'buf[u8],
+}
+
+impl<'buf> ProgramDescriptor<'buf> {
+    const SIZE: usize = 4;
+
+    /// panics if fewer than 4 bytes are provided
+    pub fn from_bytes(data: &'buf[u8]) -> ProgramDescriptor<'buf> {
+        ProgramDescriptor {
+            data,
+        }
+    }
+
+    pub fn program_number(&self) -> u16 {
+        (u16::from(self.data[0]) << 8) | u16::from(self.data[1])
+
+    }
+
+    pub fn pid(&self) -> u16 {
+        packet::pid_from_bytes(self.data[2], self.data[3])
+    }
+}
+
+#[derive(Clone,Debug)]
+pub struct PatSection<'buf> {
+    data: &'buf[u8],
+}
+impl<'buf> PatSection<'buf> {
+    /// The `table_id` value identifying sections of this table
+    pub const TABLE_ID: u8 = 0x00;
+
+    /// Wraps the `program_association_section` payload following the table syntax header (and
+    /// excluding the trailing CRC).
+    pub fn new(data: &'buf[u8]) -> PatSection<'buf> {
+        PatSection {
+            data,
+        }
+    }
+    pub fn programs(&self) -> ProgramIter<'_> {
+        ProgramIter { buf: self.data }
+    }
+}
+pub struct ProgramIter<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> Iterator for ProgramIter<'buf> {
+    type Item = ProgramDescriptor<'buf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        if self.buf.len() < ProgramDescriptor::SIZE {
+            warn!("ignoring {} trailing bytes in PAT section, too few to hold a program entry", self.buf.len());
+            return None;
+        }
+        let (head, tail) = self.buf.split_at(ProgramDescriptor::SIZE);
+        self.buf = tail;
+        Some(ProgramDescriptor::from_bytes(head))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reserved_bits_masked() {
+        let data = [0x00, 0x01, 0xff, 0xff, 0x00, 0x02, 0xe0, 0x10];
+        let pids: Vec<_> = PatSection::new(&data[..]).programs().map(|p| p.pid()).collect();
+        assert_eq!(pids, vec!(0x1fff, 0x0010));
+    }
+}
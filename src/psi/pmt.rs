@@ -0,0 +1,364 @@
+//! Views over the sections of the _Program Map Table_ (PMT), which describes the elementary
+//! streams making up each program.
+
+use std::fmt;
+use descriptor;
+use descriptor::dvb::{AacAudioDescriptor, Ac3Descriptor, DtsDescriptor, EnhancedAc3Descriptor, SubtitlingDescriptor, TeletextDescriptor};
+use packet;
+use StreamType;
+use PesKind;
+
+const REGISTRATION_TAG: u8 = 5;
+const ISO_639_LANGUAGE_TAG: u8 = 10;
+
+pub struct StreamInfo<'buf> {
+    data: &'buf[u8],
+}
+
+impl<'buf> StreamInfo<'buf> {
+    const HEADER_SIZE: usize = 5;
+
+    /// Parses the stream information entry at the start of the given buffer (which may be
+    /// followed by further entries), returning the `StreamInfo` together with the number of bytes
+    /// it occupies.
+    ///
+    /// Returns `None` if the buffer is too short to hold the header, or the descriptors indicated
+    /// by `es_info_length`.  This is the only way to construct a `StreamInfo`, so the accessor
+    /// methods can rely on these checks having been made.
+    pub fn from_bytes(data: &'buf[u8]) -> Option<(StreamInfo<'buf>, usize)> {
+        if data.len() < Self::HEADER_SIZE {
+            warn!("only {} bytes remaining for stream info, at least {} required {:?}", data.len(), Self::HEADER_SIZE, data);
+            return None;
+        }
+        let es_info_length = u16::from(data[3] & 0b00001111) << 8 | u16::from(data[4]);
+        let descriptor_end = Self::HEADER_SIZE + es_info_length as usize;
+        if descriptor_end > data.len() {
+            warn!("PMT section of size {} is not large enough to contain es_info_length of {}", data.len(), es_info_length);
+            return None;
+        }
+        let result = StreamInfo {
+            data: &data[..descriptor_end],
+        };
+        Some((result, descriptor_end))
+    }
+
+    pub fn stream_type(&self) -> StreamType {
+        self.data[0].into()
+    }
+    pub fn reserved1(&self) -> u8 {
+        self.data[1] >> 5
+    }
+    pub fn elementary_pid(&self) -> u16 {
+       packet::pid_from_bytes(self.data[1], self.data[2])
+    }
+    pub fn reserved2(&self) -> u8 {
+        self.data[3] >> 4
+    }
+    pub fn es_info_length(&self) -> u16 {
+        u16::from(self.data[3] & 0b00001111) << 8 | u16::from(self.data[4])
+    }
+
+    /// Iterates over the descriptors within this entry's `es_info` block.  If `es_info_length`
+    /// is inconsistent with the available data, the returned iterator will be empty.
+    pub fn descriptors(&self) -> descriptor::DescriptorIter<'_> {
+        let descriptor_end = Self::HEADER_SIZE + self.es_info_length() as usize;
+        if descriptor_end > self.data.len() {
+            return descriptor::DescriptorIter::new(&self.data[0..0]);
+        }
+        descriptor::DescriptorIter::new(&self.data[Self::HEADER_SIZE..descriptor_end])
+    }
+
+    /// The first descriptor in this entry's `es_info` block with the given tag, if any.
+    pub fn find_descriptor(&self, tag: u8) -> Option<descriptor::Descriptor<'buf>> {
+        let descriptor_end = Self::HEADER_SIZE + self.es_info_length() as usize;
+        if descriptor_end > self.data.len() {
+            return None;
+        }
+        descriptor::DescriptorIter::new(&self.data[Self::HEADER_SIZE..descriptor_end]).find_tag(tag)
+    }
+
+    /// The language code from the first entry of the first `ISO_639_language_descriptor` for
+    /// this stream, if any (for example `*b"eng"`).
+    pub fn language(&self) -> Option<[u8; 3]> {
+        match self.find_descriptor(ISO_639_LANGUAGE_TAG) {
+            Some(descriptor::Descriptor::ISO639Language { payload }) if payload.len() >= 3 => {
+                Some([payload[0], payload[1], payload[2]])
+            },
+            _ => None,
+        }
+    }
+
+    /// The `format_identifier` from the first `registration_descriptor` for this stream, if any
+    /// (for example `*b"AC-3"`).
+    pub fn registration_format(&self) -> Option<[u8; 4]> {
+        match self.find_descriptor(REGISTRATION_TAG) {
+            Some(descriptor::Descriptor::Registration { payload }) if payload.len() >= 4 => {
+                Some([payload[0], payload[1], payload[2], payload[3]])
+            },
+            _ => None,
+        }
+    }
+
+    /// Classifies the stream as for
+    /// [`StreamType::pes_kind()`](../enum.StreamType.html#method.pes_kind), except that where
+    /// the `stream_type` alone is inconclusive, the stream's descriptors are also examined.  DVB
+    /// `subtitling_descriptor` and `teletext_descriptor` give `PesKind::Subtitle`, and the DVB
+    /// audio descriptors (AC-3, Enhanced AC-3, DTS and AAC) or an `"AC-3"` / `"EAC3"`
+    /// `registration_descriptor` give `PesKind::Audio`.
+    pub fn pes_kind(&self) -> PesKind {
+        let kind = self.stream_type().pes_kind();
+        if kind != PesKind::Unknown {
+            return kind;
+        }
+        for desc in self.descriptors() {
+            if let Ok(descriptor::Descriptor::UserPrivate { tag, .. }) = desc {
+                match tag {
+                    SubtitlingDescriptor::TAG | TeletextDescriptor::TAG => return PesKind::Subtitle,
+                    Ac3Descriptor::TAG
+                    | EnhancedAc3Descriptor::TAG
+                    | DtsDescriptor::TAG
+                    | AacAudioDescriptor::TAG => return PesKind::Audio,
+                    _ => (),
+                }
+            }
+        }
+        match self.registration_format() {
+            Some(ref f) if f == b"AC-3" || f == b"EAC3" => PesKind::Audio,
+            _ => PesKind::Unknown,
+        }
+    }
+
+    /// The raw bytes of this entry's `es_info` block, i.e. the descriptor loop exactly as it
+    /// appears in the section, for applications that need to reproduce descriptors verbatim
+    /// (for instance when rebuilding a PMT).  Use `descriptors()` to interpret them.
+    pub fn es_info_bytes(&self) -> &'buf[u8] {
+        &self.data[Self::HEADER_SIZE..]
+    }
+}
+impl<'buf> fmt::Debug for StreamInfo<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("StreamInfo")
+            .field("stream_type", &self.stream_type())
+            .field("elementry_pid", &self.elementary_pid())
+            .field("es_info_length", &self.es_info_length())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct PmtSection<'buf> {
+    data: &'buf[u8],
+}
+
+impl<'buf> PmtSection<'buf> {
+    /// The `table_id` value identifying sections of this table
+    pub const TABLE_ID: u8 = 0x02;
+
+    /// Wraps the `program_map_section` payload following the table syntax header (and excluding
+    /// the trailing CRC).
+    pub fn new(data: &'buf[u8]) -> PmtSection<'buf> {
+        PmtSection {
+            data,
+        }
+    }
+}
+
+impl<'buf> PmtSection<'buf> {
+    pub(crate) const HEADER_SIZE: usize = 4;
+
+    pub fn reserved1(&self) -> u8 {
+        self.data[0] >> 5
+    }
+    pub fn pcr_pid(&self) -> u16 {
+        packet::pid_from_bytes(self.data[0], self.data[1])
+    }
+    /// `true` if the PCR is carried on a PID of its own, rather than in the adaptation field of
+    /// one of the elementary streams this section lists (i.e. if `pcr_pid()` matches none of
+    /// their `elementary_pid()` values).  A `StreamConstructor` can use this to decide whether a
+    /// separate filter is needed to receive the PCR.  Returns `false` if the program has no PCR
+    /// (indicated by a `pcr_pid()` of `0x1fff`).
+    pub fn has_dedicated_pcr_pid(&self) -> bool {
+        let pcr_pid = self.pcr_pid();
+        pcr_pid != packet::NULL_PID && !self.streams().any(|s| s.elementary_pid() == pcr_pid)
+    }
+    pub fn reserved2(&self) -> u8 {
+        self.data[2] >> 4
+    }
+    pub fn program_info_length(&self) -> u16 {
+        u16::from(self.data[2] & 0b00001111) << 8 | u16::from(self.data[3])
+    }
+    /// Iterates over the descriptors in the `program_info` block of this PMT section.  If
+    /// `program_info_length` extends beyond the end of the section, the returned iterator will
+    /// be empty.
+    pub fn descriptors(&self) -> descriptor::DescriptorIter<'_> {
+        descriptor::DescriptorIter::new(self.program_info_bytes())
+    }
+    /// The raw bytes of the `program_info` block of this PMT section, i.e. the descriptor loop
+    /// exactly as it appears in the section.  If `program_info_length` extends beyond the end of
+    /// the section, the returned slice will be empty.
+    pub fn program_info_bytes(&self) -> &'buf[u8] {
+        let descriptor_end = Self::HEADER_SIZE + self.program_info_length() as usize;
+        if descriptor_end > self.data.len() {
+            warn!("program_info_length={} extends beyond end of PMT section (section_length={})", self.program_info_length(), self.data.len());
+            return &self.data[0..0];
+        }
+        &self.data[Self::HEADER_SIZE..descriptor_end]
+    }
+    /// Iterates over the elementary streams described by this PMT section.
+    ///
+    /// If the section is malformed, such that `program_info_length` extends beyond the end of the
+    /// section, the returned iterator will produce no items.
+    pub fn streams(&self) -> StreamInfoIter<'_> {
+        if self.data.len() < Self::HEADER_SIZE {
+            warn!("PMT section of size {} is too small to hold the {} byte header", self.data.len(), Self::HEADER_SIZE);
+            return StreamInfoIter::new(&self.data[0..0]);
+        }
+        let descriptor_end = Self::HEADER_SIZE + self.program_info_length() as usize;
+        if descriptor_end > self.data.len() {
+            warn!("program_info_length={} extends beyond end of PMT section (section_length={})", self.program_info_length(), self.data.len());
+            return StreamInfoIter::new(&self.data[0..0]);
+        }
+        StreamInfoIter::new(&self.data[descriptor_end..])
+    }
+}
+pub struct StreamInfoIter<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> StreamInfoIter<'buf> {
+   fn new(buf: &'buf[u8]) -> StreamInfoIter<'buf> {
+       StreamInfoIter { buf }
+   }
+}
+/// Iteration ends when fewer bytes remain than are needed for the header of a further entry
+/// (treating any such trailing bytes as padding), or at the first entry whose `es_info_length`
+/// extends beyond the end of the section.  Once `None` is returned, it will be returned for every
+/// subsequent call.
+impl<'buf> Iterator for StreamInfoIter<'buf> {
+    type Item = StreamInfo<'buf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.len() < StreamInfo::HEADER_SIZE {
+            if !self.buf.is_empty() {
+                debug!("ignoring {} trailing bytes after last PMT stream entry", self.buf.len());
+                self.buf = &[];
+            }
+            return None;
+        }
+        if let Some((stream_info, info_len)) = StreamInfo::from_bytes(self.buf) {
+            self.buf = &self.buf[info_len..];
+            Some(stream_info)
+        } else {
+            // from_bytes() will have logged the problem
+            self.buf = &[];
+            None
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stream_info_descriptors() {
+        let data = [
+            0x81, 0xe1, 0x01, 0xf0, 17,
+            0x0a, 4, b'e', b'n', b'g', 0,   // ISO_639_language_descriptor
+            0x05, 4, b'A', b'C', b'-', b'3',  // registration_descriptor
+            0x0a, 4, b'f', b'r', b'a', 0,   // second ISO_639_language_descriptor is ignored
+        ];
+        let (info, _) = StreamInfo::from_bytes(&data[..]).unwrap();
+        assert_eq!(info.language(), Some(*b"eng"));
+        assert_eq!(info.registration_format(), Some(*b"AC-3"));
+        assert!(matches!(info.find_descriptor(5), Some(descriptor::Descriptor::Registration { .. })));
+        assert!(info.find_descriptor(0x52).is_none());
+
+        assert_eq!(info.es_info_bytes(), &data[5..22]);
+
+        let data = [0x1b, 0xe1, 0x01, 0xf0, 2, 0x0a, 0];
+        let (info, _) = StreamInfo::from_bytes(&data[..]).unwrap();
+        assert_eq!(info.es_info_bytes(), &[0x0a, 0][..]);
+        assert_eq!(info.language(), None);
+        assert_eq!(info.registration_format(), None);
+    }
+
+    #[test]
+    fn reserved_bits_masked() {
+        let data = [
+            0xff, 0xff, 0xf0, 0,
+            0x1b, 0xe1, 0x01, 0xf0, 0,
+        ];
+        let pmt = PmtSection::new(&data[..]);
+        assert_eq!(pmt.pcr_pid(), 0x1fff);
+        assert_eq!(pmt.streams().next().unwrap().elementary_pid(), 0x0101);
+    }
+
+    #[test]
+    fn dedicated_pcr_pid() {
+        let data = [
+            0xe1, 0x02, 0xf0, 0,
+            0x1b, 0xe1, 0x01, 0xf0, 0,
+            0x0f, 0xe1, 0x02, 0xf0, 0,
+        ];
+        assert!(!PmtSection::new(&data[..]).has_dedicated_pcr_pid());
+        let mut data = data;
+        data[1] = 0x03;
+        assert!(PmtSection::new(&data[..]).has_dedicated_pcr_pid());
+        data[0] = 0xff;
+        data[1] = 0xff;
+        assert!(!PmtSection::new(&data[..]).has_dedicated_pcr_pid());
+    }
+
+    #[test]
+    fn pes_kind() {
+        let kind = |data: &[u8]| StreamInfo::from_bytes(data).unwrap().0.pes_kind();
+        assert_eq!(kind(&[0x1b, 0xe1, 0x01, 0xf0, 0]), PesKind::Video);
+        assert_eq!(kind(&[0x0f, 0xe1, 0x01, 0xf0, 0]), PesKind::Audio);
+        assert_eq!(kind(&[0x05, 0xe1, 0x01, 0xf0, 0]), PesKind::Sections);
+        assert_eq!(kind(&[0x15, 0xe1, 0x01, 0xf0, 0]), PesKind::Data);
+        assert_eq!(kind(&[0x06, 0xe1, 0x01, 0xf0, 0]), PesKind::Unknown);
+        assert_eq!(kind(&[0x06, 0xe1, 0x01, 0xf0, 10, 0x59, 8, b'e', b'n', b'g', 0x10, 0, 1, 0, 1]), PesKind::Subtitle);
+        assert_eq!(kind(&[0x06, 0xe1, 0x01, 0xf0, 3, 0x6a, 1, 0]), PesKind::Audio);
+        assert_eq!(kind(&[0x87, 0xe1, 0x01, 0xf0, 6, 0x05, 4, b'E', b'A', b'C', b'3']), PesKind::Audio);
+    }
+
+    #[test]
+    fn program_info_bytes() {
+        let data = [
+            0xe1, 0x00, 0xf0, 6,
+            0x05, 4, b'H', b'D', b'M', b'V',  // registration_descriptor
+            0x1b, 0xe1, 0x01, 0xf0, 0,
+        ];
+        let pmt = PmtSection::new(&data[..]);
+        assert_eq!(pmt.program_info_bytes(), &data[4..10]);
+        assert_eq!(pmt.descriptors().count(), 1);
+        assert_eq!(pmt.streams().count(), 1);
+
+        // program_info_length extends beyond the end of the section,
+        let pmt = PmtSection::new(&data[..9]);
+        assert!(pmt.program_info_bytes().is_empty());
+    }
+
+    #[test]
+    fn streams_trailing_bytes() {
+        let data = [
+            0xe1, 0x00, 0xf0, 0,
+            0x1b, 0xe1, 0x01, 0xf0, 0,
+            0x0f, 0xe1, 0x02, 0xf0, 2, 0x0a, 0,
+            0xff, 0xff, 0xff,  // stuffing
+        ];
+        let pmt = PmtSection::new(&data[..]);
+        let pids: Vec<_> = pmt.streams().map(|s| s.elementary_pid()).collect();
+        assert_eq!(pids, vec!(0x101, 0x102));
+        let mut streams = pmt.streams();
+        assert_eq!(streams.by_ref().count(), 2);
+        assert!(streams.next().is_none());
+
+        // es_info_length of the last entry extends beyond the end of the section
+        let pmt = PmtSection::new(&data[..15]);
+        let mut streams = pmt.streams();
+        assert_eq!(streams.next().map(|s| s.elementary_pid()), Some(0x101));
+        assert!(streams.next().is_none());
+        assert!(streams.next().is_none());
+    }
+}
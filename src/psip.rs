@@ -0,0 +1,590 @@
+//! Support for the base tables of the ATSC _Program and System Information Protocol_
+//! (_ATSC A/65_), as used in North American broadcasts.
+//!
+//! The base tables are carried on PID `0x1ffb` (see `PSIP_BASE_PID`), which will not be
+//! announced in the PAT.  To process them, have the application's `StreamConstructor` create a
+//! `PsipPacketFilter` in response to `FilterRequest::ByPid(PSIP_BASE_PID)`, supplying an
+//! implementation of `PsipConsumer` to receive the decoded tables.
+
+use demultiplex;
+use descriptor;
+use packet;
+use psi;
+use std::marker;
+
+/// The PID carrying the MGT, VCT and other PSIP base tables
+pub const PSIP_BASE_PID: u16 = 0x1ffb;
+
+/// Receives the PSIP tables decoded by a [`PsipPacketFilter`](struct.PsipPacketFilter.html).
+///
+/// Every section is passed on as received, including repeated copies of an unchanged table, so
+/// the implementation may want to compare `table_syntax_header.version()` with the last version
+/// processed.
+pub trait PsipConsumer {
+    fn mgt(&mut self, table_syntax_header: &psi::TableSyntaxHeader, mgt: &MgtSection);
+    fn vct(&mut self, table_syntax_header: &psi::TableSyntaxHeader, vct: &VctSection);
+}
+
+/// The kind of table that an entry of the Master Guide Table describes.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum TableType {
+    /// Terrestrial VCT, with `current_next_indicator` of `1`
+    TvctCurrent,
+    /// Terrestrial VCT, with `current_next_indicator` of `0`
+    TvctNext,
+    /// Cable VCT, with `current_next_indicator` of `1`
+    CvctCurrent,
+    /// Cable VCT, with `current_next_indicator` of `0`
+    CvctNext,
+    ChannelEtt,
+    Dccsct,
+    /// Event Information Table, with the given index (`EIT-0` to `EIT-127`)
+    Eit(u8),
+    /// Event Extended Text Table, with the given index (`ETT-0` to `ETT-127`)
+    EventEtt(u8),
+    /// Rating Region Table for the given `rating_region`
+    Rrt(u8),
+    /// Directed Channel Change Table, with the given `dcc_id`
+    Dcct(u8),
+    Reserved(u16),
+}
+impl From<u16> for TableType {
+    fn from(val: u16) -> TableType {
+        match val {
+            0x0000 => TableType::TvctCurrent,
+            0x0001 => TableType::TvctNext,
+            0x0002 => TableType::CvctCurrent,
+            0x0003 => TableType::CvctNext,
+            0x0004 => TableType::ChannelEtt,
+            0x0005 => TableType::Dccsct,
+            0x0100..=0x017f => TableType::Eit((val - 0x0100) as u8),
+            0x0200..=0x027f => TableType::EventEtt((val - 0x0200) as u8),
+            0x0301..=0x03ff => TableType::Rrt((val - 0x0300) as u8),
+            0x1400..=0x14ff => TableType::Dcct((val - 0x1400) as u8),
+            v => TableType::Reserved(v),
+        }
+    }
+}
+
+/// A section of the Master Guide Table (`table_id` `0xc7`), which lists the PIDs and versions of
+/// the other PSIP tables present in the Transport Stream.
+pub struct MgtSection<'buf> {
+    data: &'buf[u8],
+}
+impl<'buf> MgtSection<'buf> {
+    pub const TABLE_ID: u8 = 0xc7;
+    const HEADER_SIZE: usize = 3;
+
+    fn new(data: &'buf[u8]) -> MgtSection<'buf> {
+        MgtSection { data }
+    }
+
+    pub fn protocol_version(&self) -> u8 {
+        self.data[0]
+    }
+    pub fn tables_defined(&self) -> u16 {
+        u16::from(self.data[1]) << 8 | u16::from(self.data[2])
+    }
+    /// Iterates over the `tables_defined()` entries of this section, stopping early if an entry
+    /// would extend beyond the end of the section.
+    pub fn tables(&self) -> MgtTableIter<'buf> {
+        MgtTableIter {
+            buf: &self.data[Self::HEADER_SIZE..],
+            remaining: self.tables_defined(),
+        }
+    }
+    /// Iterates over the descriptors that follow the list of tables.  If the section is
+    /// malformed, the returned iterator will be empty.
+    pub fn descriptors(&self) -> descriptor::DescriptorIter<'buf> {
+        let mut tables = self.tables();
+        while tables.next().is_some() { }
+        if tables.remaining > 0 {
+            return descriptor::DescriptorIter::new(&[]);
+        }
+        trailing_descriptors(tables.buf, 0b0000_1111)
+    }
+}
+
+/// Locates the descriptor loop introduced by the 2-byte length field at the start of `buf`,
+/// where `len_mask` selects the bits of the first byte which belong to the length.
+fn trailing_descriptors(buf: &[u8], len_mask: u8) -> descriptor::DescriptorIter<'_> {
+    if buf.len() < 2 {
+        warn!("PSIP section too short to hold the final descriptors_length field");
+        return descriptor::DescriptorIter::new(&[]);
+    }
+    let len = (usize::from(buf[0] & len_mask) << 8) | usize::from(buf[1]);
+    if 2 + len > buf.len() {
+        warn!("descriptors_length={} extends beyond end of PSIP section", len);
+        return descriptor::DescriptorIter::new(&[]);
+    }
+    descriptor::DescriptorIter::new(&buf[2..2 + len])
+}
+
+/// An entry in the list of tables of an [`MgtSection`](struct.MgtSection.html).
+pub struct MgtTable<'buf> {
+    data: &'buf[u8],
+}
+impl<'buf> MgtTable<'buf> {
+    const FIXED_SIZE: usize = 11;
+
+    pub fn table_type(&self) -> TableType {
+        TableType::from(u16::from(self.data[0]) << 8 | u16::from(self.data[1]))
+    }
+    /// The PID on which the table is carried
+    pub fn pid(&self) -> u16 {
+        packet::pid_from_bytes(self.data[2], self.data[3])
+    }
+    /// The current `version_number` of the table
+    pub fn version(&self) -> u8 {
+        self.data[4] & 0b0001_1111
+    }
+    /// The total size in bytes of all the sections of the table
+    pub fn number_bytes(&self) -> u32 {
+        u32::from(self.data[5]) << 24
+            | u32::from(self.data[6]) << 16
+            | u32::from(self.data[7]) << 8
+            | u32::from(self.data[8])
+    }
+    pub fn descriptors(&self) -> descriptor::DescriptorIter<'buf> {
+        descriptor::DescriptorIter::new(&self.data[Self::FIXED_SIZE..])
+    }
+}
+
+pub struct MgtTableIter<'buf> {
+    buf: &'buf[u8],
+    remaining: u16,
+}
+impl<'buf> Iterator for MgtTableIter<'buf> {
+    type Item = MgtTable<'buf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let data = split_entry(&mut self.buf, MgtTable::FIXED_SIZE, 0b0000_1111)?;
+        self.remaining -= 1;
+        Some(MgtTable { data })
+    }
+}
+
+/// Splits off an entry consisting of `fixed_size` bytes (the last two of which give the length
+/// of the descriptors that follow) plus its descriptors, from the front of `buf`.
+fn split_entry<'buf>(buf: &mut &'buf[u8], fixed_size: usize, len_mask: u8) -> Option<&'buf[u8]> {
+    if buf.len() < fixed_size {
+        if !buf.is_empty() {
+            warn!("{} trailing bytes in PSIP section, too few to hold a {} byte entry", buf.len(), fixed_size);
+        }
+        return None;
+    }
+    let len = (usize::from(buf[fixed_size - 2] & len_mask) << 8) | usize::from(buf[fixed_size - 1]);
+    if fixed_size + len > buf.len() {
+        warn!("descriptors_length={} of PSIP entry extends beyond end of section", len);
+        return None;
+    }
+    let (head, tail) = buf.split_at(fixed_size + len);
+    *buf = tail;
+    Some(head)
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum VctKind {
+    /// Terrestrial Virtual Channel Table (`table_id` `0xc8`)
+    Terrestrial,
+    /// Cable Virtual Channel Table (`table_id` `0xc9`)
+    Cable,
+}
+
+/// A section of a Terrestrial or Cable Virtual Channel Table, which lists the channels carried
+/// in the Transport Stream.
+pub struct VctSection<'buf> {
+    kind: VctKind,
+    transport_stream_id: u16,
+    data: &'buf[u8],
+}
+impl<'buf> VctSection<'buf> {
+    pub const TVCT_TABLE_ID: u8 = 0xc8;
+    pub const CVCT_TABLE_ID: u8 = 0xc9;
+    const HEADER_SIZE: usize = 2;
+
+    fn new(kind: VctKind, transport_stream_id: u16, data: &'buf[u8]) -> VctSection<'buf> {
+        VctSection { kind, transport_stream_id, data }
+    }
+
+    pub fn kind(&self) -> VctKind {
+        self.kind
+    }
+    pub fn transport_stream_id(&self) -> u16 {
+        self.transport_stream_id
+    }
+    pub fn protocol_version(&self) -> u8 {
+        self.data[0]
+    }
+    pub fn num_channels_in_section(&self) -> u8 {
+        self.data[1]
+    }
+    /// Iterates over the `num_channels_in_section()` channel entries of this section, stopping
+    /// early if an entry would extend beyond the end of the section.
+    pub fn channels(&self) -> VctChannelIter<'buf> {
+        VctChannelIter {
+            kind: self.kind,
+            buf: &self.data[Self::HEADER_SIZE..],
+            remaining: self.num_channels_in_section(),
+        }
+    }
+    /// Iterates over the `additional_descriptors` that follow the list of channels.  If the
+    /// section is malformed, the returned iterator will be empty.
+    pub fn descriptors(&self) -> descriptor::DescriptorIter<'buf> {
+        let mut channels = self.channels();
+        while channels.next().is_some() { }
+        if channels.remaining > 0 {
+            return descriptor::DescriptorIter::new(&[]);
+        }
+        trailing_descriptors(channels.buf, 0b0000_0011)
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum ServiceType {
+    AnalogTelevision,
+    DigitalTelevision,
+    Audio,
+    DataOnly,
+    SoftwareDownload,
+    Reserved(u8),
+}
+impl From<u8> for ServiceType {
+    fn from(val: u8) -> ServiceType {
+        match val {
+            0x01 => ServiceType::AnalogTelevision,
+            0x02 => ServiceType::DigitalTelevision,
+            0x03 => ServiceType::Audio,
+            0x04 => ServiceType::DataOnly,
+            0x05 => ServiceType::SoftwareDownload,
+            v => ServiceType::Reserved(v),
+        }
+    }
+}
+
+/// A channel entry of a [`VctSection`](struct.VctSection.html).
+pub struct VctChannel<'buf> {
+    kind: VctKind,
+    data: &'buf[u8],
+}
+impl<'buf> VctChannel<'buf> {
+    const FIXED_SIZE: usize = 32;
+    const SHORT_NAME_SIZE: usize = 14;
+
+    /// The channel name, of up to 7 UTF-16 code units, with any trailing NUL padding removed
+    pub fn short_name(&self) -> String {
+        let units: Vec<u16> = self.data[..Self::SHORT_NAME_SIZE]
+            .chunks(2)
+            .map(|c| u16::from(c[0]) << 8 | u16::from(c[1]))
+            .collect();
+        String::from_utf16_lossy(&units).trim_end_matches('\0').to_string()
+    }
+    pub fn major_channel_number(&self) -> u16 {
+        u16::from(self.data[14] & 0b0000_1111) << 6 | u16::from(self.data[15]) >> 2
+    }
+    pub fn minor_channel_number(&self) -> u16 {
+        u16::from(self.data[15] & 0b0000_0011) << 8 | u16::from(self.data[16])
+    }
+    pub fn modulation_mode(&self) -> u8 {
+        self.data[17]
+    }
+    /// Deprecated by _A/65_, and usually `0`
+    pub fn carrier_frequency(&self) -> u32 {
+        u32::from(self.data[18]) << 24
+            | u32::from(self.data[19]) << 16
+            | u32::from(self.data[20]) << 8
+            | u32::from(self.data[21])
+    }
+    /// The `transport_stream_id` of the Transport Stream carrying this channel
+    pub fn channel_tsid(&self) -> u16 {
+        u16::from(self.data[22]) << 8 | u16::from(self.data[23])
+    }
+    /// The `program_number` of the PAT entry for this channel
+    pub fn program_number(&self) -> u16 {
+        u16::from(self.data[24]) << 8 | u16::from(self.data[25])
+    }
+    pub fn etm_location(&self) -> u8 {
+        self.data[26] >> 6
+    }
+    pub fn access_controlled(&self) -> bool {
+        self.data[26] & 0b0010_0000 != 0
+    }
+    pub fn hidden(&self) -> bool {
+        self.data[26] & 0b0001_0000 != 0
+    }
+    /// Only signalled in the Cable VCT, so always `None` for a Terrestrial VCT
+    pub fn path_select(&self) -> Option<bool> {
+        match self.kind {
+            VctKind::Terrestrial => None,
+            VctKind::Cable => Some(self.data[26] & 0b0000_1000 != 0),
+        }
+    }
+    /// Only signalled in the Cable VCT, so always `None` for a Terrestrial VCT
+    pub fn out_of_band(&self) -> Option<bool> {
+        match self.kind {
+            VctKind::Terrestrial => None,
+            VctKind::Cable => Some(self.data[26] & 0b0000_0100 != 0),
+        }
+    }
+    pub fn hide_guide(&self) -> bool {
+        self.data[26] & 0b0000_0010 != 0
+    }
+    pub fn service_type(&self) -> ServiceType {
+        ServiceType::from(self.data[27] & 0b0011_1111)
+    }
+    /// Identifies the programming source of this channel, and links the channel to entries in
+    /// the EIT
+    pub fn source_id(&self) -> u16 {
+        u16::from(self.data[28]) << 8 | u16::from(self.data[29])
+    }
+    pub fn descriptors(&self) -> descriptor::DescriptorIter<'buf> {
+        descriptor::DescriptorIter::new(&self.data[Self::FIXED_SIZE..])
+    }
+}
+
+pub struct VctChannelIter<'buf> {
+    kind: VctKind,
+    buf: &'buf[u8],
+    remaining: u8,
+}
+impl<'buf> Iterator for VctChannelIter<'buf> {
+    type Item = VctChannel<'buf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let data = split_entry(&mut self.buf, VctChannel::FIXED_SIZE, 0b0000_0011)?;
+        self.remaining -= 1;
+        Some(VctChannel { kind: self.kind, data })
+    }
+}
+
+/// Decodes sections of the MGT and VCT, passing them on to the given `PsipConsumer`.  Sections
+/// of other PSIP tables are ignored.
+pub struct PsipProcessor<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: PsipConsumer
+{
+    consumer: C,
+    phantom: marker::PhantomData<Ctx>,
+}
+impl<Ctx, C> PsipProcessor<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: PsipConsumer
+{
+    pub fn new(consumer: C) -> PsipProcessor<Ctx, C> {
+        PsipProcessor {
+            consumer,
+            phantom: marker::PhantomData,
+        }
+    }
+}
+impl<Ctx, C> psi::WholeSectionSyntaxPayloadParser for PsipProcessor<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: PsipConsumer
+{
+    type Context = Ctx;
+
+    fn section(&mut self, _ctx: &mut Self::Context, header: &psi::SectionCommonHeader, table_syntax_header: &psi::TableSyntaxHeader, data: &[u8]) {
+        let start = psi::SectionCommonHeader::SIZE + psi::TableSyntaxHeader::SIZE;
+        let header_size = match header.table_id {
+            MgtSection::TABLE_ID => MgtSection::HEADER_SIZE,
+            VctSection::TVCT_TABLE_ID | VctSection::CVCT_TABLE_ID => VctSection::HEADER_SIZE,
+            _ => return,
+        };
+        if data.len() < start + header_size + 4 {
+            warn!("PSIP section (table_id {:#x}) of size {} is too small to hold the header and CRC", header.table_id, data.len());
+            return;
+        }
+        let payload = &data[start..data.len() - 4];  // remove CRC bytes
+        match header.table_id {
+            MgtSection::TABLE_ID => {
+                self.consumer.mgt(table_syntax_header, &MgtSection::new(payload));
+            },
+            table_id => {
+                let kind = if table_id == VctSection::TVCT_TABLE_ID {
+                    VctKind::Terrestrial
+                } else {
+                    VctKind::Cable
+                };
+                self.consumer.vct(table_syntax_header, &VctSection::new(kind, table_syntax_header.id(), payload));
+            },
+        }
+    }
+}
+
+// DedupSectionSyntaxPayloadParser is not used here, since it remembers one version_number per
+// table_id, ignoring section_number and table_id_extension.  A VCT may be split over several
+// sections which all carry the same version_number, and every section after the first would be
+// discarded as a duplicate.
+type PsipSectionPacketConsumer<Ctx, C> = psi::SectionPacketConsumer<
+    psi::SectionSyntaxSectionProcessor<
+        psi::BufferSectionSyntaxParser<
+            psi::CrcCheckWholeSectionSyntaxPayloadParser<
+                PsipProcessor<Ctx, C>
+            >
+        >
+    >
+>;
+
+/// A `PacketFilter` for the PSIP base PID, decoding the MGT and VCT and passing them to the
+/// given `PsipConsumer`.
+pub struct PsipPacketFilter<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: PsipConsumer
+{
+    psip_section_packet_consumer: PsipSectionPacketConsumer<Ctx, C>,
+}
+impl<Ctx, C> PsipPacketFilter<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: PsipConsumer
+{
+    pub fn new(consumer: C) -> PsipPacketFilter<Ctx, C> {
+        PsipPacketFilter {
+            psip_section_packet_consumer: psi::SectionPacketConsumer::new(
+                psi::SectionSyntaxSectionProcessor::new(
+                    psi::BufferSectionSyntaxParser::new(
+                        psi::CrcCheckWholeSectionSyntaxPayloadParser::new(
+                            PsipProcessor::new(consumer)
+                        )
+                    )
+                )
+            ),
+        }
+    }
+}
+impl<Ctx, C> demultiplex::PacketFilter for PsipPacketFilter<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: PsipConsumer
+{
+    type Ctx = Ctx;
+
+    fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet) {
+        self.psip_section_packet_consumer.consume(ctx, pk);
+    }
+    fn needs_payload(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_util::{NullDemuxContext, NullStreamConstructor};
+    use test_util;
+    use demultiplex::PacketFilter;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Debug,PartialEq)]
+    enum Table {
+        Mgt { version: u8, tables: Vec<(TableType, u16, u8, u32)>, descriptor_count: usize },
+        Vct { kind: VctKind, transport_stream_id: u16, channels: Vec<(String, u16, u16, ServiceType, u16, Option<bool>)> },
+    }
+    struct Recorder(Rc<RefCell<Vec<Table>>>);
+    impl PsipConsumer for Recorder {
+        fn mgt(&mut self, table_syntax_header: &psi::TableSyntaxHeader, mgt: &MgtSection) {
+            self.0.borrow_mut().push(Table::Mgt {
+                version: table_syntax_header.version(),
+                tables: mgt.tables().map(|t| (t.table_type(), t.pid(), t.version(), t.number_bytes())).collect(),
+                descriptor_count: mgt.descriptors().count(),
+            });
+        }
+        fn vct(&mut self, _table_syntax_header: &psi::TableSyntaxHeader, vct: &VctSection) {
+            self.0.borrow_mut().push(Table::Vct {
+                kind: vct.kind(),
+                transport_stream_id: vct.transport_stream_id(),
+                channels: vct.channels().map(|c| (c.short_name(), c.major_channel_number(), c.minor_channel_number(), c.service_type(), c.source_id(), c.path_select())).collect(),
+            });
+        }
+    }
+
+    fn channel(name: &str, major: u16, minor: u16, service_type: u8, source_id: u16) -> Vec<u8> {
+        let mut data: Vec<u8> = name.encode_utf16().flat_map(|u| vec![(u >> 8) as u8, u as u8]).collect();
+        data.resize(14, 0);
+        data.extend_from_slice(&[
+            0b1111_0000 | (major >> 6) as u8,
+            (major << 2) as u8 | (minor >> 8) as u8,
+            minor as u8,
+            0x04,  // modulation_mode: 8VSB
+            0, 0, 0, 0,  // carrier_frequency
+            0x01, 0x23,  // channel_TSID
+            0x00, minor as u8,  // program_number
+            0b0000_1101,  // ETM_location=0, hidden=0, path_select=1, hide_guide=0
+            0b1100_0000 | service_type,
+            (source_id >> 8) as u8, source_id as u8,
+            0b1111_1100, 3,  // descriptors_length
+            0xa0, 1, 0x00,  // an unknown descriptor
+        ]);
+        data
+    }
+
+    #[test]
+    fn mgt_and_vct() {
+        let tables = Rc::new(RefCell::new(vec![]));
+        let mut filter: PsipPacketFilter<NullDemuxContext, _> = PsipPacketFilter::new(Recorder(tables.clone()));
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+
+        let mgt = test_util::section(MgtSection::TABLE_ID, 0, 1, true, &[
+            0,  // protocol_version
+            0, 2,  // tables_defined
+            0x00, 0x00, 0xff, 0xfb, 0xe1, 0x00, 0x00, 0x01, 0x00, 0xf0, 0x00,
+            0x01, 0x00, 0xfd, 0x00, 0xe5, 0x00, 0x00, 0x10, 0x00, 0xf0, 0x02, 0x80, 0x00,
+            0xf0, 0x00,  // descriptors_length
+        ]);
+        let mut body = vec![0, 2];  // protocol_version, num_channels_in_section
+        body.extend(channel("KQED", 9, 1, 0x02, 0x0010));
+        body.extend(channel("KQED-HD", 9, 2, 0x02, 0x0011));
+        body.extend_from_slice(&[0xfc, 0x00]);  // additional_descriptors_length
+        let tvct = test_util::section(VctSection::TVCT_TABLE_ID, 0x0123, 1, true, &body);
+        let cvct = test_util::section(VctSection::CVCT_TABLE_ID, 0x0123, 1, true, &body);
+        let mut cc = 0;
+        for sect in &[mgt, tvct, cvct] {
+            for pk in test_util::section_packets(PSIP_BASE_PID, cc, sect).chunks(packet::PACKET_SIZE) {
+                filter.consume(&mut ctx, packet::Packet::new(pk));
+                cc += 1;
+            }
+        }
+
+        let tables = tables.borrow();
+        assert_eq!(tables.len(), 3);
+        assert_eq!(tables[0], Table::Mgt {
+            version: 1,
+            tables: vec![(TableType::TvctCurrent, 0x1ffb, 1, 0x100), (TableType::Eit(0), 0x1d00, 5, 0x1000)],
+            descriptor_count: 0,
+        });
+        let channels = vec![
+            ("KQED".to_string(), 9, 1, ServiceType::DigitalTelevision, 0x0010, None),
+            ("KQED-HD".to_string(), 9, 2, ServiceType::DigitalTelevision, 0x0011, None),
+        ];
+        assert_eq!(tables[1], Table::Vct { kind: VctKind::Terrestrial, transport_stream_id: 0x0123, channels: channels.clone() });
+        let cable_channels = channels.into_iter().map(|(a, b, c, d, e, _)| (a, b, c, d, e, Some(true))).collect();
+        assert_eq!(tables[2], Table::Vct { kind: VctKind::Cable, transport_stream_id: 0x0123, channels: cable_channels });
+    }
+
+    #[test]
+    fn truncated_vct() {
+        let mut body = vec![0, 3];  // claims 3 channels,
+        body.extend(channel("ABC", 7, 1, 0x02, 1));  // but only holds 1
+        let vct = VctSection::new(VctKind::Terrestrial, 1, &body[..]);
+        assert_eq!(vct.channels().count(), 1);
+        assert_eq!(vct.descriptors().count(), 0);
+        let chan = vct.channels().next().unwrap();
+        assert_eq!(chan.short_name(), "ABC");
+        assert_eq!(chan.modulation_mode(), 0x04);
+        assert_eq!(chan.channel_tsid(), 0x0123);
+        assert_eq!(chan.program_number(), 1);
+        assert!(!chan.hidden());
+        assert_eq!(chan.descriptors().count(), 1);
+    }
+}
@@ -0,0 +1,727 @@
+//! Support for the `splice_info_section` of _ANSI/SCTE 35_, which signals the points in a
+//! program at which ads or other content may be inserted.
+//!
+//! Splice information is carried on an elementary stream announced in the PMT with `stream_type`
+//! `0x86` (see `SCTE35_STREAM_TYPE`).  To process it, have the application's
+//! `StreamConstructor` create a `SpliceInfoPacketFilter` in response to a
+//! `FilterRequest::ByStream` for that `stream_type`, supplying an implementation of
+//! `SpliceInfoConsumer` to receive the decoded sections.
+
+use demultiplex;
+use descriptor;
+use packet;
+use psi;
+use std::fmt;
+use std::marker;
+use hex_slice::AsHex;
+
+/// The `stream_type` value announcing an elementary stream of SCTE-35 splice information
+pub const SCTE35_STREAM_TYPE: u8 = 0x86;
+
+/// Receives the sections decoded by a [`SpliceInfoPacketFilter`](struct.SpliceInfoPacketFilter.html).
+///
+/// Every section is passed on as received, including the `splice_null()` commands which some
+/// encoders send periodically as a heartbeat.
+pub trait SpliceInfoConsumer {
+    fn splice_info(&mut self, section: &SpliceInfoSection);
+}
+
+/// A `splice_info_section` (`table_id` `0xfc`).
+///
+/// The `pts_time` values given by a `splice_command()` are relative to the stream's encoding, and
+/// need to have `pts_adjustment()` applied (see `adjust_pts()`) to give a PTS value comparable to
+/// those of the program's other elementary streams.
+pub struct SpliceInfoSection<'buf> {
+    data: &'buf[u8],
+    command_len: usize,
+}
+impl<'buf> SpliceInfoSection<'buf> {
+    pub const TABLE_ID: u8 = 0xfc;
+    const HEADER_SIZE: usize = 11;
+    const SPLICE_NULL: u8 = 0x00;
+    const TIME_SIGNAL: u8 = 0x06;
+
+    /// Wraps the section data following the common section header (and excluding the trailing
+    /// `CRC_32`).
+    ///
+    /// Returns `None` if the data is too short to hold the header, the splice command and the
+    /// descriptor loop.  A legacy `splice_command_length` of `0xfff` is accepted for the
+    /// `splice_null()` and `time_signal()` commands, whose length can be determined from their
+    /// content.
+    pub fn from_bytes(data: &'buf[u8]) -> Option<SpliceInfoSection<'buf>> {
+        if data.len() < Self::HEADER_SIZE {
+            warn!("splice_info_section of size {} is too small to hold the {} byte header", data.len(), Self::HEADER_SIZE);
+            return None;
+        }
+        let splice_command_length = (u16::from(data[8] & 0b1111) << 8 | u16::from(data[9])) as usize;
+        let command_len = if splice_command_length == 0xfff {
+            match data[10] {
+                Self::SPLICE_NULL => 0,
+                Self::TIME_SIGNAL if data.len() > Self::HEADER_SIZE => SpliceTime::len(data[Self::HEADER_SIZE]),
+                t => {
+                    warn!("can't determine the length of splice_command_type {:#x} having legacy splice_command_length 0xfff", t);
+                    return None;
+                },
+            }
+        } else {
+            splice_command_length
+        };
+        let loop_length_offset = Self::HEADER_SIZE + command_len;
+        if data.len() < loop_length_offset + 2 {
+            warn!("splice_info_section of size {} is too small to hold splice_command_length {}", data.len(), command_len);
+            return None;
+        }
+        let section = SpliceInfoSection { data, command_len };
+        if data.len() < loop_length_offset + 2 + section.descriptor_loop_length() as usize {
+            warn!("splice_info_section of size {} is too small to hold descriptor_loop_length {}", data.len(), section.descriptor_loop_length());
+            return None;
+        }
+        Some(section)
+    }
+
+    pub fn protocol_version(&self) -> u8 {
+        self.data[0]
+    }
+    /// If `true`, the splice command and descriptors are encrypted, and can't be interpreted by
+    /// this crate
+    pub fn encrypted_packet(&self) -> bool {
+        self.data[1] & 0b1000_0000 != 0
+    }
+    pub fn encryption_algorithm(&self) -> u8 {
+        (self.data[1] >> 1) & 0b11_1111
+    }
+    /// A 33-bit offset, in 90kHz units, to be added to the `pts_time` values of this section.
+    pub fn pts_adjustment(&self) -> u64 {
+        u64::from(self.data[1] & 1) << 32
+        | u64::from(self.data[2]) << 24
+        | u64::from(self.data[3]) << 16
+        | u64::from(self.data[4]) << 8
+        | u64::from(self.data[5])
+    }
+    /// Applies `pts_adjustment()` to the given `pts_time`, wrapping at 33 bits as the PTS does.
+    pub fn adjust_pts(&self, pts_time: u64) -> u64 {
+        (pts_time + self.pts_adjustment()) & 0x1_ffff_ffff
+    }
+    pub fn cw_index(&self) -> u8 {
+        self.data[6]
+    }
+    pub fn tier(&self) -> u16 {
+        u16::from(self.data[7]) << 4 | u16::from(self.data[8] >> 4)
+    }
+    pub fn splice_command_type(&self) -> u8 {
+        self.data[10]
+    }
+    /// Decodes the `splice_null()` and `time_signal()` commands; other commands (including
+    /// `splice_insert()`) are given as `SpliceCommand::Other`, holding the raw command bytes.
+    pub fn splice_command(&self) -> SpliceCommand<'buf> {
+        let data = &self.data[Self::HEADER_SIZE..Self::HEADER_SIZE + self.command_len];
+        match self.splice_command_type() {
+            Self::SPLICE_NULL => SpliceCommand::SpliceNull,
+            Self::TIME_SIGNAL if !data.is_empty() && data.len() >= SpliceTime::len(data[0]) => {
+                SpliceCommand::TimeSignal { pts_time: SpliceTime::pts_time(data) }
+            },
+            splice_command_type => SpliceCommand::Other { splice_command_type, data },
+        }
+    }
+    fn descriptor_loop_length(&self) -> u16 {
+        let off = Self::HEADER_SIZE + self.command_len;
+        u16::from(self.data[off]) << 8 | u16::from(self.data[off + 1])
+    }
+    /// Iterates over the `splice_descriptor()` entries following the splice command.
+    pub fn descriptors(&self) -> SpliceDescriptorIter<'buf> {
+        let start = Self::HEADER_SIZE + self.command_len + 2;
+        SpliceDescriptorIter {
+            buf: &self.data[start..start + self.descriptor_loop_length() as usize],
+        }
+    }
+}
+impl<'buf> fmt::Debug for SpliceInfoSection<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SpliceInfoSection")
+            .field("protocol_version", &self.protocol_version())
+            .field("encrypted_packet", &self.encrypted_packet())
+            .field("pts_adjustment", &self.pts_adjustment())
+            .field("tier", &self.tier())
+            .field("splice_command", &self.splice_command())
+            .finish()
+    }
+}
+
+/// The command carried by a [`SpliceInfoSection`](struct.SpliceInfoSection.html).
+#[derive(Debug,PartialEq,Eq)]
+pub enum SpliceCommand<'buf> {
+    /// Carries no information, but may be sent as a heartbeat, or to carry descriptors
+    SpliceNull,
+    /// Marks the given time, which is described by the section's descriptors (typically a
+    /// `SegmentationDescriptor`).  A `pts_time` of `None` means 'immediately'.
+    TimeSignal { pts_time: Option<u64> },
+    /// A command not decoded by this crate
+    Other { splice_command_type: u8, data: &'buf[u8] },
+}
+
+struct SpliceTime;
+impl SpliceTime {
+    fn len(first: u8) -> usize {
+        if first & 0b1000_0000 != 0 { 5 } else { 1 }
+    }
+    fn pts_time(data: &[u8]) -> Option<u64> {
+        if data[0] & 0b1000_0000 != 0 {
+            Some(u64::from(data[0] & 1) << 32
+                | u64::from(data[1]) << 24
+                | u64::from(data[2]) << 16
+                | u64::from(data[3]) << 8
+                | u64::from(data[4]))
+        } else {
+            None
+        }
+    }
+}
+
+/// A `splice_descriptor()` from the descriptor loop of a
+/// [`SpliceInfoSection`](struct.SpliceInfoSection.html).
+#[derive(PartialEq,Eq,Clone,Copy)]
+pub struct SpliceDescriptor<'buf> {
+    pub splice_descriptor_tag: u8,
+    /// Identifies the owner of the descriptor; `0x43554549` (`"CUEI"`) for the descriptors
+    /// defined by SCTE-35 itself
+    pub identifier: u32,
+    /// The descriptor data following the `identifier` field
+    pub data: &'buf[u8],
+}
+impl<'buf> SpliceDescriptor<'buf> {
+    /// The `identifier` value of descriptors defined by SCTE-35
+    pub const CUEI: u32 = 0x4355_4549;
+}
+impl<'buf> fmt::Debug for SpliceDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SpliceDescriptor")
+            .field("splice_descriptor_tag", &self.splice_descriptor_tag)
+            .field("identifier", &format_args!("{:#010x}", self.identifier))
+            .field("data", &format_args!("{:x}", self.data.as_hex()))
+            .finish()
+    }
+}
+
+/// Iterator over the descriptors of a [`SpliceInfoSection`](struct.SpliceInfoSection.html).
+/// Stops at the first descriptor that does not fit within the descriptor loop.
+pub struct SpliceDescriptorIter<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> Iterator for SpliceDescriptorIter<'buf> {
+    type Item = SpliceDescriptor<'buf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        if self.buf.len() < 2 {
+            warn!("{} trailing bytes in splice_info_section descriptor loop", self.buf.len());
+            return None;
+        }
+        let descriptor_length = self.buf[1] as usize;
+        if descriptor_length < 4 || self.buf.len() < 2 + descriptor_length {
+            warn!("splice_descriptor_tag {:#x} has invalid descriptor_length {} ({} bytes remaining)", self.buf[0], descriptor_length, self.buf.len() - 2);
+            self.buf = &self.buf[0..0];
+            return None;
+        }
+        let desc = SpliceDescriptor {
+            splice_descriptor_tag: self.buf[0],
+            identifier: u32::from(self.buf[2]) << 24
+                | u32::from(self.buf[3]) << 16
+                | u32::from(self.buf[4]) << 8
+                | u32::from(self.buf[5]),
+            data: &self.buf[6..2 + descriptor_length],
+        };
+        self.buf = &self.buf[2 + descriptor_length..];
+        Some(desc)
+    }
+}
+
+/// Describes a segment of the program (for example, the start or end of a program, chapter or
+/// ad break), as used by `time_signal()` based signalling.
+///
+/// Construct from the `data` of a `SpliceDescriptor` having `splice_descriptor_tag` equal to
+/// `SegmentationDescriptor::TAG` (and `identifier` equal to `SpliceDescriptor::CUEI`).
+pub struct SegmentationDescriptor<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> SegmentationDescriptor<'buf> {
+    pub const TAG: u8 = 0x02;
+    const HEADER_SIZE: usize = 5;
+
+    pub fn new(buf: &'buf[u8]) -> Result<SegmentationDescriptor<'buf>, descriptor::DescriptorError> {
+        if buf.len() < Self::HEADER_SIZE {
+            return Err(descriptor::DescriptorError::NotEnoughData { actual: buf.len(), expected: Self::HEADER_SIZE });
+        }
+        let desc = SegmentationDescriptor { buf };
+        if !desc.segmentation_event_cancel_indicator() {
+            SegmentationEvent::check(&buf[Self::HEADER_SIZE..])
+                .map_err(|expected| descriptor::DescriptorError::NotEnoughData { actual: buf.len(), expected: Self::HEADER_SIZE + expected })?;
+        }
+        Ok(desc)
+    }
+
+    pub fn segmentation_event_id(&self) -> u32 {
+        u32::from(self.buf[0]) << 24
+        | u32::from(self.buf[1]) << 16
+        | u32::from(self.buf[2]) << 8
+        | u32::from(self.buf[3])
+    }
+    /// If `true`, a previously signalled segmentation event having the same
+    /// `segmentation_event_id` has been cancelled, and `event()` will return `None`.
+    pub fn segmentation_event_cancel_indicator(&self) -> bool {
+        self.buf[4] & 0b1000_0000 != 0
+    }
+    /// The details of the segmentation event, unless it has been cancelled.
+    pub fn event(&self) -> Option<SegmentationEvent<'buf>> {
+        if self.segmentation_event_cancel_indicator() {
+            None
+        } else {
+            Some(SegmentationEvent { buf: &self.buf[Self::HEADER_SIZE..] })
+        }
+    }
+}
+impl<'buf> fmt::Debug for SegmentationDescriptor<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SegmentationDescriptor")
+            .field("segmentation_event_id", &self.segmentation_event_id())
+            .field("segmentation_event_cancel_indicator", &self.segmentation_event_cancel_indicator())
+            .field("event", &self.event())
+            .finish()
+    }
+}
+
+/// The details of a segmentation event which has not been cancelled, from a
+/// [`SegmentationDescriptor`](struct.SegmentationDescriptor.html).
+pub struct SegmentationEvent<'buf> {
+    buf: &'buf[u8],
+}
+impl<'buf> SegmentationEvent<'buf> {
+    const COMPONENT_SIZE: usize = 6;
+
+    /// Returns `Err` with the number of bytes required if the given buffer is too short to hold
+    /// the fields it indicates are present.
+    fn check(buf: &[u8]) -> Result<(), usize> {
+        let mut required = 1;
+        if buf.len() < required {
+            return Err(required);
+        }
+        let event = SegmentationEvent { buf };
+        if !event.program_segmentation_flag() {
+            required += 1;
+            if buf.len() < required {
+                return Err(required);
+            }
+            required += buf[1] as usize * Self::COMPONENT_SIZE;
+        }
+        if event.segmentation_duration_flag() {
+            required += 5;
+        }
+        required += 2;
+        if buf.len() < required {
+            return Err(required);
+        }
+        required += buf[required - 1] as usize + 3;
+        if buf.len() < required {
+            return Err(required);
+        }
+        Ok(())
+    }
+
+    /// If `true`, the segment applies to all of the program's components; otherwise, see
+    /// `components()`.
+    pub fn program_segmentation_flag(&self) -> bool {
+        self.buf[0] & 0b1000_0000 != 0
+    }
+    fn segmentation_duration_flag(&self) -> bool {
+        self.buf[0] & 0b0100_0000 != 0
+    }
+    /// If `false`, the `web_delivery_allowed_flag`, `no_regional_blackout_flag`,
+    /// `archive_allowed_flag` and `device_restrictions` fields (see `restrictions()`) apply.
+    pub fn delivery_not_restricted_flag(&self) -> bool {
+        self.buf[0] & 0b0010_0000 != 0
+    }
+    /// The low 5 bits of the flags byte, holding `web_delivery_allowed_flag`,
+    /// `no_regional_blackout_flag`, `archive_allowed_flag` and the 2-bit `device_restrictions`.
+    /// Only meaningful if `delivery_not_restricted_flag()` is `false`.
+    pub fn restrictions(&self) -> u8 {
+        self.buf[0] & 0b1_1111
+    }
+    fn component_count(&self) -> usize {
+        if self.program_segmentation_flag() {
+            0
+        } else {
+            self.buf[1] as usize
+        }
+    }
+    fn duration_offset(&self) -> usize {
+        if self.program_segmentation_flag() {
+            1
+        } else {
+            2 + self.component_count() * Self::COMPONENT_SIZE
+        }
+    }
+    /// The components to which the segment applies, together with their `pts_offset`.  Empty
+    /// when `program_segmentation_flag()` is `true`.
+    pub fn components(&self) -> impl Iterator<Item=SegmentationComponent> + 'buf {
+        let end = self.duration_offset();
+        let start = end - self.component_count() * Self::COMPONENT_SIZE;
+        self.buf[start..end].chunks(Self::COMPONENT_SIZE)
+            .map(|c| SegmentationComponent {
+                component_tag: c[0],
+                pts_offset: u64::from(c[1] & 1) << 32
+                    | u64::from(c[2]) << 24
+                    | u64::from(c[3]) << 16
+                    | u64::from(c[4]) << 8
+                    | u64::from(c[5]),
+            })
+    }
+    /// The duration of the segment in 90kHz units, if given.
+    pub fn segmentation_duration(&self) -> Option<u64> {
+        if self.segmentation_duration_flag() {
+            let d = &self.buf[self.duration_offset()..];
+            Some(u64::from(d[0]) << 32
+                | u64::from(d[1]) << 24
+                | u64::from(d[2]) << 16
+                | u64::from(d[3]) << 8
+                | u64::from(d[4]))
+        } else {
+            None
+        }
+    }
+    fn upid_offset(&self) -> usize {
+        self.duration_offset() + if self.segmentation_duration_flag() { 5 } else { 0 }
+    }
+    pub fn segmentation_upid_type(&self) -> u8 {
+        self.buf[self.upid_offset()]
+    }
+    /// The unique program identifier, whose format is given by `segmentation_upid_type()`
+    pub fn segmentation_upid(&self) -> &'buf[u8] {
+        let off = self.upid_offset();
+        let len = self.buf[off + 1] as usize;
+        &self.buf[off + 2..off + 2 + len]
+    }
+    fn type_offset(&self) -> usize {
+        let off = self.upid_offset();
+        off + 2 + self.buf[off + 1] as usize
+    }
+    /// The kind of segment, per _SCTE 35, table 22_ (e.g. `0x30` 'Provider Advertisement Start',
+    /// `0x31` 'Provider Advertisement End').
+    pub fn segmentation_type_id(&self) -> u8 {
+        self.buf[self.type_offset()]
+    }
+    pub fn segment_num(&self) -> u8 {
+        self.buf[self.type_offset() + 1]
+    }
+    pub fn segments_expected(&self) -> u8 {
+        self.buf[self.type_offset() + 2]
+    }
+    /// The `sub_segment_num` and `sub_segments_expected` fields, present for some values of
+    /// `segmentation_type_id()`.
+    pub fn sub_segments(&self) -> Option<(u8, u8)> {
+        let off = self.type_offset() + 3;
+        if self.buf.len() >= off + 2 {
+            Some((self.buf[off], self.buf[off + 1]))
+        } else {
+            None
+        }
+    }
+}
+impl<'buf> fmt::Debug for SegmentationEvent<'buf> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SegmentationEvent")
+            .field("program_segmentation_flag", &self.program_segmentation_flag())
+            .field("segmentation_duration", &self.segmentation_duration())
+            .field("segmentation_upid_type", &self.segmentation_upid_type())
+            .field("segmentation_upid", &format_args!("{:x}", self.segmentation_upid().as_hex()))
+            .field("segmentation_type_id", &format_args!("{:#04x}", self.segmentation_type_id()))
+            .field("segment_num", &self.segment_num())
+            .field("segments_expected", &self.segments_expected())
+            .finish()
+    }
+}
+
+/// A single entry from [`SegmentationEvent::components()`](struct.SegmentationEvent.html#method.components).
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub struct SegmentationComponent {
+    pub component_tag: u8,
+    /// A 33-bit offset, in 90kHz units, from the `pts_time` of the `time_signal()` command
+    pub pts_offset: u64,
+}
+
+enum BufferState {
+    Complete,
+    Buffering(usize),
+}
+
+/// Collects `splice_info_section` data which may span several packets, checks its CRC, and
+/// passes it to the given `SpliceInfoConsumer`.  Sections with `encrypted_packet` set are
+/// ignored.
+pub struct SpliceInfoProcessor<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: SpliceInfoConsumer
+{
+    consumer: C,
+    buf: Vec<u8>,
+    state: BufferState,
+    phantom: marker::PhantomData<Ctx>,
+}
+impl<Ctx, C> SpliceInfoProcessor<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: SpliceInfoConsumer
+{
+    const CRC_SIZE: usize = 4;
+
+    pub fn new(consumer: C) -> SpliceInfoProcessor<Ctx, C> {
+        SpliceInfoProcessor {
+            consumer,
+            buf: vec!(),
+            state: BufferState::Complete,
+            phantom: marker::PhantomData,
+        }
+    }
+
+    fn section(&mut self, data: &[u8]) {
+        if data.len() < psi::SectionCommonHeader::SIZE + Self::CRC_SIZE {
+            warn!("splice_info_section of size {} is too small to hold the CRC", data.len());
+            return;
+        }
+        if psi::mpeg2_crc32(data) != 0 {
+            warn!("splice_info_section CRC check failed");
+            return;
+        }
+        let body = &data[psi::SectionCommonHeader::SIZE..data.len() - Self::CRC_SIZE];
+        if let Some(section) = SpliceInfoSection::from_bytes(body) {
+            if section.encrypted_packet() {
+                debug!("ignoring encrypted splice_info_section");
+                return;
+            }
+            self.consumer.splice_info(&section);
+        }
+    }
+}
+impl<Ctx, C> psi::SectionProcessor for SpliceInfoProcessor<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: SpliceInfoConsumer
+{
+    type Context = Ctx;
+
+    fn start_section(&mut self, _ctx: &mut Self::Context, header: &psi::SectionCommonHeader, section_data: &[u8]) {
+        if header.table_id != SpliceInfoSection::TABLE_ID {
+            warn!("expected splice_info_section table_id {:#x}, got {:#x}", SpliceInfoSection::TABLE_ID, header.table_id);
+            self.reset();
+            return;
+        }
+        let len = psi::SectionCommonHeader::SIZE + header.section_length;
+        if section_data.len() >= len {
+            self.state = BufferState::Complete;
+            self.section(&section_data[..len]);
+        } else {
+            self.buf.clear();
+            self.buf.extend_from_slice(section_data);
+            self.state = BufferState::Buffering(len - section_data.len());
+        }
+    }
+
+    fn continue_section(&mut self, _ctx: &mut Self::Context, section_data: &[u8]) {
+        if let BufferState::Buffering(remaining) = self.state {
+            let len = remaining.min(section_data.len());
+            self.buf.extend_from_slice(&section_data[..len]);
+            if len == remaining {
+                self.state = BufferState::Complete;
+                let buf = ::std::mem::take(&mut self.buf);
+                self.section(&buf[..]);
+                self.buf = buf;
+            } else {
+                self.state = BufferState::Buffering(remaining - len);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.state = BufferState::Complete;
+    }
+}
+
+/// A `PacketFilter` for an SCTE-35 elementary stream, decoding each `splice_info_section` and
+/// passing it to the given `SpliceInfoConsumer`.
+pub struct SpliceInfoPacketFilter<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: SpliceInfoConsumer
+{
+    section_packet_consumer: psi::SectionPacketConsumer<SpliceInfoProcessor<Ctx, C>>,
+}
+impl<Ctx, C> SpliceInfoPacketFilter<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: SpliceInfoConsumer
+{
+    pub fn new(consumer: C) -> SpliceInfoPacketFilter<Ctx, C> {
+        SpliceInfoPacketFilter {
+            section_packet_consumer: psi::SectionPacketConsumer::new(SpliceInfoProcessor::new(consumer)),
+        }
+    }
+}
+impl<Ctx, C> demultiplex::PacketFilter for SpliceInfoPacketFilter<Ctx, C>
+where
+    Ctx: demultiplex::DemuxContext,
+    C: SpliceInfoConsumer
+{
+    type Ctx = Ctx;
+
+    fn consume(&mut self, ctx: &mut Self::Ctx, pk: packet::Packet) {
+        self.section_packet_consumer.consume(ctx, pk);
+    }
+    fn needs_payload(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_util::{NullDemuxContext, NullStreamConstructor};
+    use data_encoding::hex;
+    use test_util;
+    use demultiplex::PacketFilter;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Debug,PartialEq)]
+    struct Splice {
+        splice_command_type: u8,
+        pts: Option<u64>,
+        descriptors: Vec<(u8, u32, usize)>,
+    }
+    struct Recorder(Rc<RefCell<Vec<Splice>>>);
+    impl SpliceInfoConsumer for Recorder {
+        fn splice_info(&mut self, section: &SpliceInfoSection) {
+            let pts = match section.splice_command() {
+                SpliceCommand::TimeSignal { pts_time } => pts_time.map(|t| section.adjust_pts(t)),
+                _ => None,
+            };
+            self.0.borrow_mut().push(Splice {
+                splice_command_type: section.splice_command_type(),
+                pts,
+                descriptors: section.descriptors().map(|d| (d.splice_descriptor_tag, d.identifier, d.data.len())).collect(),
+            });
+        }
+    }
+
+    // segmentation_event_id 1: a 30 second 'Provider Advertisement Start', with an ad-ID upid
+    const SEGMENTATION_START: &[u8] = b"\x02\x18CUEI\x00\x00\x00\x01\x7f\xff\x00\x00\x29\x32\xe0\x03\x04ABCD\x30\x01\x01";
+    // segmentation_event_id 2 is cancelled
+    const SEGMENTATION_CANCEL: &[u8] = b"\x02\x09CUEI\x00\x00\x00\x02\xff";
+
+    #[test]
+    fn time_signal_and_splice_null() {
+        let splices = Rc::new(RefCell::new(vec![]));
+        let mut filter: SpliceInfoPacketFilter<NullDemuxContext, _> = SpliceInfoPacketFilter::new(Recorder(splices.clone()));
+        let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+
+        let mut body = vec![
+            0x00,  // protocol_version
+            0x00, 0x00, 0x00, 0x00, 0x64,  // encrypted_packet=0, pts_adjustment=100
+            0xff,  // cw_index
+            0xff, 0xf0, 0x05,  // tier, splice_command_length
+            0x06,  // time_signal()
+            0xff, 0xff, 0xff, 0xff, 0xf0,  // time_specified_flag=1, pts_time=0x1fffffff0
+            0x00, (SEGMENTATION_START.len() + SEGMENTATION_CANCEL.len()) as u8,
+        ];
+        body.extend_from_slice(SEGMENTATION_START);
+        body.extend_from_slice(SEGMENTATION_CANCEL);
+        let time_signal = test_util::short_section(SpliceInfoSection::TABLE_ID, &body);
+
+        // a splice_null() with the legacy splice_command_length, and descriptors from some other
+        // organisation filling several packets
+        let mut body = vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x01, 0x90];
+        for tag in 0..2 {
+            body.extend_from_slice(&[tag, 198, b'A', b'B', b'C', b'D']);
+            body.extend_from_slice(&[0u8; 194]);
+        }
+        let splice_null = test_util::short_section(SpliceInfoSection::TABLE_ID, &body);
+        let mut cc = 0;
+        for sect in &[time_signal, splice_null] {
+            for pk in test_util::section_packets(0x50, cc, sect).chunks(packet::PACKET_SIZE) {
+                filter.consume(&mut ctx, packet::Packet::new(pk));
+                cc += 1;
+            }
+        }
+        assert_eq!(cc, 4);
+
+        let splices = splices.borrow();
+        assert_eq!(*splices, vec![
+            Splice {
+                splice_command_type: 0x06,
+                pts: Some(100 - 0x10),
+                descriptors: vec![(SegmentationDescriptor::TAG, SpliceDescriptor::CUEI, 20), (SegmentationDescriptor::TAG, SpliceDescriptor::CUEI, 5)],
+            },
+            Splice {
+                splice_command_type: 0x00,
+                pts: None,
+                descriptors: vec![(0, 0x4142_4344, 194), (1, 0x4142_4344, 194)],
+            },
+        ]);
+    }
+
+    #[test]
+    fn segmentation_descriptor() {
+        let desc = SegmentationDescriptor::new(&SEGMENTATION_START[6..]).unwrap();
+        assert_eq!(desc.segmentation_event_id(), 1);
+        assert!(!desc.segmentation_event_cancel_indicator());
+        let event = desc.event().unwrap();
+        assert!(event.program_segmentation_flag());
+        assert!(event.delivery_not_restricted_flag());
+        assert_eq!(event.components().count(), 0);
+        assert_eq!(event.segmentation_duration(), Some(30 * 90_000));
+        assert_eq!(event.segmentation_upid_type(), 0x03);
+        assert_eq!(event.segmentation_upid(), b"ABCD");
+        assert_eq!(event.segmentation_type_id(), 0x30);
+        assert_eq!(event.segment_num(), 1);
+        assert_eq!(event.segments_expected(), 1);
+        assert_eq!(event.sub_segments(), None);
+        assert!(!format!("{:?}", desc).is_empty());
+
+        let desc = SegmentationDescriptor::new(&SEGMENTATION_CANCEL[6..]).unwrap();
+        assert_eq!(desc.segmentation_event_id(), 2);
+        assert!(desc.event().is_none());
+
+        // component mode, without a duration, and with sub-segments
+        let data = hex::decode(b"000000037F3F0201FE000000640201000000C800003402030102").unwrap();
+        let desc = SegmentationDescriptor::new(&data).unwrap();
+        let event = desc.event().unwrap();
+        assert!(!event.program_segmentation_flag());
+        assert_eq!(event.components().collect::<Vec<_>>(), vec![
+            SegmentationComponent { component_tag: 1, pts_offset: 0x64 },
+            SegmentationComponent { component_tag: 2, pts_offset: 0x1_0000_00c8 },
+        ]);
+        assert_eq!(event.segmentation_duration(), None);
+        assert!(event.segmentation_upid().is_empty());
+        assert_eq!(event.segmentation_type_id(), 0x34);
+        assert_eq!(event.sub_segments(), Some((1, 2)));
+
+        assert_eq!(
+            SegmentationDescriptor::new(&SEGMENTATION_START[6..SEGMENTATION_START.len() - 1]).err(),
+            Some(descriptor::DescriptorError::NotEnoughData { actual: 19, expected: 20 })
+        );
+        assert!(SegmentationDescriptor::new(&data[..8]).is_err());
+    }
+
+    #[test]
+    fn truncated_section() {
+        // splice_command_length extends beyond the end of the section,
+        assert!(SpliceInfoSection::from_bytes(&[0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xf0, 0x05, 0x06, 0xfe, 0, 0]).is_none());
+        // a legacy splice_command_length for a command whose length can't be determined,
+        assert!(SpliceInfoSection::from_bytes(&[0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0x05, 0, 0]).is_none());
+        // time_signal() with no time specified, and a legacy splice_command_length
+        let section = SpliceInfoSection::from_bytes(&[0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0x06, 0x7f, 0, 0]).unwrap();
+        assert_eq!(section.splice_command(), SpliceCommand::TimeSignal { pts_time: None });
+        assert_eq!(section.descriptors().count(), 0);
+        assert!(!format!("{:?}", section).is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+//! Builders for the Transport Stream packets, PSI sections and PES packets used by the unit tests
+//! of several modules, and a `DemuxContext` for tests which need no application-specific filters.
+//!
+//! Also included by the integration tests, which is why paths here are relative to the crate root
+//! (which in an integration test must `use` the modules of `mpeg2ts_reader` named below).
+
+use bitstream_io::{BE, BitWriter};
+use demultiplex;
+use descriptor;
+use packet;
+use psi;
+use std::cell::Cell;
+use std::io;
+use std::rc::Rc;
+
+packet_filter_switch!{
+    NullFilterSwitch<NullDemuxContext> {
+        Pat: demultiplex::PatPacketFilter<NullDemuxContext>,
+        Pmt: demultiplex::PmtPacketFilter<NullDemuxContext>,
+        Nul: demultiplex::NullPacketFilter<NullDemuxContext>,
+        Cnt: CountingFilter,
+        Lim: demultiplex::LimitPacketFilter<CountingFilter>,
+    }
+}
+demux_context!(NullDemuxContext, NullStreamConstructor);
+
+/// Creates filters for the PAT and each PMT, and a `NullPacketFilter` for every other PID.
+pub struct NullStreamConstructor;
+impl demultiplex::StreamConstructor for NullStreamConstructor {
+    type F = NullFilterSwitch;
+
+    fn construct(&mut self, req: demultiplex::FilterRequest) -> Self::F {
+        match req {
+            demultiplex::FilterRequest::ByPid(0) => NullFilterSwitch::Pat(demultiplex::PatPacketFilter::new()),
+            demultiplex::FilterRequest::Pmt{pid, program_number} => NullFilterSwitch::Pmt(demultiplex::PmtPacketFilter::new(pid, program_number)),
+            _ => NullFilterSwitch::Nul(demultiplex::NullPacketFilter::new()),
+        }
+    }
+}
+
+/// Counts the packets passed to it
+pub struct CountingFilter {
+    pub needs_payload: bool,
+    pub count: Rc<Cell<usize>>,
+}
+impl demultiplex::PacketFilter for CountingFilter {
+    type Ctx = NullDemuxContext;
+    fn consume(&mut self, _ctx: &mut Self::Ctx, _pk: packet::Packet) {
+        self.count.set(self.count.get() + 1);
+    }
+    fn needs_payload(&self) -> bool {
+        self.needs_payload
+    }
+}
+
+/// The bytes written by the given function
+pub fn make_test_data<F>(builder: F) -> Vec<u8>
+where
+    F: Fn(BitWriter<BE>)->Result<(), io::Error>
+{
+    let mut data: Vec<u8> = Vec::new();
+    builder(BitWriter::<BE>::new(&mut data)).unwrap();
+    data
+}
+
+/// A packet carrying only payload (filled with `0xff`).
+pub fn packet(pid: u16, cc: u8) -> Vec<u8> {
+    let mut buf = vec![0xffu8; packet::PACKET_SIZE];
+    buf[0] = packet::SYNC_BYTE;
+    buf[1] = (pid >> 8) as u8;
+    buf[2] = pid as u8;
+    buf[3] = 0b0001_0000 | (cc & 0xf);  // PayloadOnly
+    buf
+}
+
+/// Wraps the given payload in a packet, using adaptation field stuffing if the payload is smaller
+/// than 184 bytes.
+pub fn stuffed_packet(pid: u16, pusi: bool, cc: u8, payload: &[u8]) -> Vec<u8> {
+    assert!(payload.len() <= packet::PACKET_SIZE - 4);
+    let mut buf = packet(pid, cc);
+    buf.truncate(4);
+    if pusi {
+        buf[1] |= 0b0100_0000;
+    }
+    if payload.len() < packet::PACKET_SIZE - 4 {
+        buf[3] = 0b0011_0000 | (cc & 0xf);  // AdaptationFieldAndPayload
+        let af_len = packet::PACKET_SIZE - 5 - payload.len();
+        buf.push(af_len as u8);
+        if af_len > 0 {
+            buf.push(0);  // flags
+            let stuffing_end = buf.len() + af_len - 1;
+            buf.resize(stuffing_end, 0xff);
+        }
+    }
+    buf.extend_from_slice(payload);
+    assert_eq!(buf.len(), packet::PACKET_SIZE);
+    buf
+}
+
+/// A packet with an adaptation field (holding no optional fields), but no payload
+pub fn af_only_packet(pid: u16, pusi: bool, cc: u8) -> Vec<u8> {
+    let mut buf = packet(pid, cc);
+    if pusi {
+        buf[1] |= 0b0100_0000;
+    }
+    buf[3] = 0b0010_0000 | (cc & 0xf);  // AdaptationFieldOnly
+    buf[4] = 183;  // adaptation_field_length
+    buf[5] = 0;  // flags
+    buf
+}
+
+/// A packet with only an adaptation field, holding the given PCR value (in units of the 27MHz
+/// clock).  Having no payload, the packet does not advance the `continuity_counter`.
+pub fn pcr_packet(pid: u16, cc: u8, pcr: u64, discontinuity: bool) -> Vec<u8> {
+    let base = pcr / 300;
+    let ext = pcr % 300;
+    let mut buf = packet(pid, cc);
+    buf[3] = 0b0010_0000 | (cc & 0xf);  // AdaptationFieldOnly
+    buf[4] = 183;  // adaptation_field_length
+    buf[5] = 0b0001_0000 | if discontinuity { 0b1000_0000 } else { 0 };  // PCR_flag
+    buf[6] = (base >> 25) as u8;
+    buf[7] = (base >> 17) as u8;
+    buf[8] = (base >> 9) as u8;
+    buf[9] = (base >> 1) as u8;
+    buf[10] = (base << 7) as u8 | 0b0111_1110 | (ext >> 8) as u8;
+    buf[11] = ext as u8;
+    buf
+}
+
+/// A complete section with section syntax, from `table_id` up to and including the `CRC_32`,
+/// where `body` is everything following the `last_section_number` field.
+pub fn section(table_id: u8, table_id_extension: u16, version: u8, current: bool, body: &[u8]) -> Vec<u8> {
+    let section_length = psi::TableSyntaxHeader::SIZE + body.len() + 4;
+    let mut sect = vec![
+        table_id,
+        0b1011_0000 | (section_length >> 8) as u8,
+        section_length as u8,
+        (table_id_extension >> 8) as u8,
+        table_id_extension as u8,
+        0b1100_0000 | version << 1 | current as u8,
+        0,  // section_number
+        0,  // last_section_number
+    ];
+    sect.extend_from_slice(body);
+    append_crc(&mut sect);
+    sect
+}
+
+/// A complete section without section syntax (as used by SCTE-35), where `body` is everything
+/// following the `section_length` field, apart from the `CRC_32`.
+pub fn short_section(table_id: u8, body: &[u8]) -> Vec<u8> {
+    let section_length = body.len() + 4;
+    let mut sect = vec![
+        table_id,
+        0b0011_0000 | (section_length >> 8) as u8,
+        section_length as u8,
+    ];
+    sect.extend_from_slice(body);
+    append_crc(&mut sect);
+    sect
+}
+
+fn append_crc(sect: &mut Vec<u8>) {
+    let crc = psi::mpeg2_crc32(&sect[..]);
+    sect.extend_from_slice(&[(crc >> 24) as u8, (crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+}
+
+/// The packets carrying the given section, which starts in the first packet (following a
+/// `pointer_field` of `0`).  The packets' `continuity_counter` values count up from `first_cc`.
+pub fn section_packets(pid: u16, first_cc: u8, sect: &[u8]) -> Vec<u8> {
+    let mut payload = vec![0];  // pointer_field
+    payload.extend_from_slice(sect);
+    let mut buf = vec![];
+    for (i, chunk) in payload.chunks(packet::PACKET_SIZE - 4).enumerate() {
+        let mut pk = packet(pid, first_cc.wrapping_add(i as u8));
+        if i == 0 {
+            pk[1] |= 0b0100_0000;  // payload_unit_start_indicator
+        }
+        pk[4..4 + chunk.len()].copy_from_slice(chunk);
+        buf.extend(pk);
+    }
+    buf
+}
+
+/// A video PES packet with no optional header fields, followed by `data_len` bytes of `0xaa`.  A
+/// `pes_packet_length` of `0` leaves the length unbounded.
+pub fn pes(pes_packet_length: u16, data_len: usize) -> Vec<u8> {
+    let mut pes = vec!(
+        0, 0, 1, 0xE0,  // packet_start_code_prefix, stream_id
+        (pes_packet_length >> 8) as u8, pes_packet_length as u8,
+        0x80, 0, 0,     // no optional fields
+    );
+    let data_end = pes.len() + data_len;
+    pes.resize(data_end, 0xaa);
+    pes
+}
+
+/// The payload of the first descriptor in `data`, panicking unless it is a
+/// `Descriptor::UserPrivate` with the given `tag`.
+pub fn user_private_payload(data: &[u8], tag: u8) -> &[u8] {
+    match descriptor::DescriptorIter::new(data).next() {
+        Some(Ok(descriptor::Descriptor::UserPrivate { tag: t, payload })) if t == tag => payload,
+        other => panic!("expected UserPrivate descriptor with tag {:#x}, got {:?}", tag, other),
+    }
+}
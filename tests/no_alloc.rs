@@ -0,0 +1,67 @@
+//! Checks that `Demultiplex::push()` makes no heap allocation per packet, once the filters for
+//! the stream's PIDs exist.
+//!
+//! This lives in its own test binary, since counting allocations requires replacing the global
+//! allocator for every test in the binary.
+
+#[macro_use]
+extern crate mpeg2ts_reader;
+extern crate bitstream_io;
+
+use mpeg2ts_reader::demultiplex;
+use mpeg2ts_reader::demultiplex::DemuxContext;
+use mpeg2ts_reader::descriptor;
+use mpeg2ts_reader::packet;
+use mpeg2ts_reader::psi;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use test_util::{NullDemuxContext, NullStreamConstructor};
+
+#[path = "../src/test_util.rs"]
+#[allow(dead_code)]
+mod test_util;
+
+/// Counts heap allocations made by the current thread, so that tests running in parallel do not
+/// interfere
+struct CountingAlloc;
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+#[test]
+fn push_steady_state_does_not_allocate() {
+    // a PMT long enough to need two packets
+    let mut pmt = vec!(0xe2, 0x01, 0xf0, 0x00);
+    for i in 0..40 {
+        pmt.extend_from_slice(&[0x1b, 0xe2, 0x01 + i, 0xf0, 0x00]);
+    }
+    let mut buf = vec!();
+    for cc in 0..16 {
+        buf.extend(test_util::section_packets(0, cc, &test_util::section(0x00, 1, 0, true, &[0, 1, 0xe1, 0x01])));
+        buf.extend(test_util::section_packets(0x101, cc * 2, &test_util::section(0x02, 1, 0, true, &pmt[..])));
+        buf.extend(test_util::packet(0x201, cc));
+        buf.extend(test_util::packet(packet::NULL_PID, 0));
+    }
+
+    let mut ctx = NullDemuxContext::new(NullStreamConstructor);
+    let mut deplex = demultiplex::DemultiplexBuilder::new()
+        .pid_stats(true)
+        .build(&mut ctx);
+    // the first pass creates the filters and the program map,
+    deplex.push(&mut ctx, &buf[..]);
+    let before = ALLOCATIONS.with(|a| a.get());
+    deplex.push(&mut ctx, &buf[..]);
+    assert_eq!(ALLOCATIONS.with(|a| a.get()), before);
+    assert_eq!(ctx.psi_stats(0x101).unwrap().sections_seen(), 32);
+}